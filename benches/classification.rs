@@ -0,0 +1,120 @@
+use std::convert::TryFrom;
+use std::iter::FromIterator;
+use std::path::Path;
+use std::process::Command;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use git2::Repository;
+use tempfile::TempDir;
+
+use git_trim::args::{DeleteFilter, DeleteRange, Scope};
+use git_trim::{get_trim_plan, Git, PlanParam};
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .env("GIT_AUTHOR_NAME", "Bench")
+        .env("GIT_AUTHOR_EMAIL", "bench@test")
+        .env("GIT_COMMITTER_NAME", "Bench")
+        .env("GIT_COMMITTER_EMAIL", "bench@test")
+        .status()
+        .expect("git");
+    assert!(status.success(), "git {}", args.join(" "));
+}
+
+/// A clone with `branches` tracking branches. Every other one is merged into `master`.
+fn synthetic_repo(branches: usize) -> TempDir {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let origin = dir.path().join("origin");
+    let local = dir.path().join("local");
+
+    git(dir.path(), &["init", "-q", "origin"]);
+    git(
+        &origin,
+        &["commit", "-q", "--allow-empty", "-m", "Initial commit"],
+    );
+    for i in 0..branches {
+        let name = format!("feature-{}", i);
+        git(&origin, &["checkout", "-q", "-b", &name, "master"]);
+        git(&origin, &["commit", "-q", "--allow-empty", "-m", &name]);
+    }
+    git(&origin, &["checkout", "-q", "master"]);
+    for i in (0..branches).step_by(2) {
+        let name = format!("feature-{}", i);
+        git(&origin, &["merge", "-q", "--no-ff", "-m", &name, &name]);
+    }
+
+    git(dir.path(), &["clone", "-q", "origin", "local"]);
+    for i in 0..branches {
+        let name = format!("feature-{}", i);
+        let upstream = format!("origin/{}", name);
+        git(&local, &["branch", "-q", "--track", &name, &upstream]);
+    }
+    dir
+}
+
+fn param() -> PlanParam<'static> {
+    use DeleteRange::*;
+    PlanParam {
+        bases: vec!["master"],
+        merged_into_tags: Vec::new(),
+        protected_patterns: Vec::new(),
+        protected_commits: Vec::new(),
+        protect_merged_into: Vec::new(),
+        exclude_patterns: Vec::new(),
+        delete: DeleteFilter::from_iter(vec![
+            MergedLocal,
+            MergedRemote(Scope::All),
+            Stray,
+            Diverged(Scope::All),
+        ]),
+        detach: true,
+        head: None,
+        pull_refs: false,
+        gerrit: false,
+        keep_if_protected_upstream: false,
+        cautious_push_only: false,
+        no_stray_remote_delete: false,
+        keep_remote_ahead: false,
+        keep_stashed: false,
+        keep_ancestors: false,
+        keep_recent: None,
+        keep_recent_checkouts: None,
+        merged_older_than: None,
+        stack_prefix: false,
+        offline: false,
+        skip_defaults: false,
+        limit_to_tracked: false,
+        assume_unmerged_safe: false,
+        cross_check_gone: false,
+        remote_default_only: false,
+        timeout: None,
+        squash_detect: true,
+        strict_squash: false,
+        cache: false,
+        incremental: false,
+        detect_reverted: false,
+        detect_renamed_upstream: false,
+        remote_ahead_guard: None,
+        base_resolver: None,
+        merged_branches: Vec::new(),
+    }
+}
+
+fn classification(c: &mut Criterion) {
+    let mut group = c.benchmark_group("classification");
+    group.sample_size(10);
+    for branches in &[10, 100] {
+        let dir = synthetic_repo(*branches);
+        let git =
+            Git::try_from(Repository::open(dir.path().join("local")).expect("repo")).expect("git");
+        group.bench_with_input(BenchmarkId::from_parameter(branches), branches, |b, _| {
+            b.iter(|| get_trim_plan(&git, &param()).expect("plan"))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, classification);
+criterion_main!(benches);
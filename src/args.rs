@@ -3,8 +3,10 @@ use std::fmt::Debug;
 use std::hash::Hash;
 use std::iter::FromIterator;
 use std::mem::discriminant;
+use std::path::PathBuf;
 use std::process::exit;
 use std::str::FromStr;
+use std::time::Duration;
 
 use clap::Clap;
 use thiserror::Error;
@@ -19,20 +21,44 @@ use thiserror::Error;
 pub struct Args {
     /// Comma separated multiple names of branches.
     /// All the other branches are compared with the upstream branches of those branches.
-    /// [default: branches that tracks `git symbolic-ref refs/remotes/*/HEAD`] [config: trim.bases]
+    /// A base can be given as `main`, `origin/main`, `refs/heads/main` or `refs/remotes/origin/main`.
+    /// `merge-base:<base>` compares with the merge base of HEAD and the base instead of its tip.
+    /// [default: branches that tracks `git symbolic-ref refs/remotes/*/HEAD`] [config: trim.bases, trim.base] [env: GIT_TRIM_BASE]
     ///
     /// The default value is a branch that tracks `git symbolic-ref refs/remotes/*/HEAD`.
     /// They might not be reflected correctly when the HEAD branch of your remote repository is changed.
     /// You can see the changed HEAD branch name with `git remote show <remote>`
     /// and apply it to your local repository with `git remote set-head <remote> --auto`.
+    /// When there is no `refs/remotes/*/HEAD`, the first existing one of `git config init.defaultBranch`,
+    /// `main` and `master` is used.
     #[clap(short, long, value_delimiter = ",", aliases=&["base"])]
     pub bases: Vec<String>,
 
+    /// A glob pattern of tags (e.g. `v2.*`) of a maintenance line. Branches in any of the matching tags
+    /// are merged, as well as the ones merged into the bases. It can be given multiple times.
+    #[clap(long, number_of_values = 1, value_name = "GLOB")]
+    pub merged_into_tags: Vec<String>,
+
     /// Comma separated multiple glob patterns (e.g. `release-*`, `feature/*`) of branches that should never be deleted.
-    /// [config: trim.protected]
+    /// [config: trim.protected] [env: GIT_TRIM_PROTECT]
     #[clap(short, long, value_delimiter = ",")]
     pub protected: Vec<String>,
 
+    /// A commit (e.g. a deployed one) whose local and remote branches should never be deleted, whatever their names are.
+    /// Abbreviated commits are accepted. It can be given multiple times.
+    #[clap(long, number_of_values = 1, value_name = "OID")]
+    pub protect_commit: Vec<String>,
+
+    /// A ref (e.g. `staging`) whose merged branches are kept back, since they are still in use downstream.
+    /// It can be given multiple times.
+    #[clap(long, number_of_values = 1, value_name = "REF")]
+    pub protect_merged_into: Vec<String>,
+
+    /// A glob pattern (e.g. `keep/*`) of branches that are kept regardless of their merge status and `--delete`.
+    /// It can be given multiple times.
+    #[clap(long, number_of_values = 1)]
+    pub exclude: Vec<String>,
+
     /// Do not update remotes
     /// [config: trim.update]
     #[clap(long)]
@@ -41,7 +67,14 @@ pub struct Args {
     pub update: bool,
 
     /// Output for scripting. Options are "json" for full structured output or "local" or "remote" for a list of branches to be deleted.
-    #[clap(long)]
+    /// "json" has `diagnostics` with the warnings met while planning, only when there is any.
+    /// It only prints, and never deletes or prunes anything.
+    /// "jsonl" prints a JSON object per line for each action on a branch, for streaming consumers.
+    /// "xml" prints the branches to delete and the kept back branches as XML.
+    /// "markdown" prints them as a Markdown table and a collapsible list with the reasons, to paste in pull requests.
+    /// "ndjson-events" is the exception which deletes as usual. It prints a JSON object per line for each event
+    /// of the run as it happens: `classified`, `kept_back`, `deleted` and `summary` at last.
+    #[clap(long, alias = "output")]
     pub porcelain: Option<PorcelainFormat>,
 
     /// Prevents too frequent updates. Seconds between updates in seconds. 0 to disable.
@@ -63,11 +96,18 @@ pub struct Args {
     #[clap(long, hidden(true))]
     pub detach: bool,
 
+    /// Keep back the local branch as if it is HEAD, even when HEAD is detached or another branch is checked out.
+    /// It is for the automated contexts like CI, which often checks out a commit of a branch with a detached HEAD.
+    /// The branch is kept back regardless of `--no-detach`, and so is the checked out branch unless `--detach`.
+    #[clap(long, value_name = "REF")]
+    pub head: Option<String>,
+
     /// Comma separated values of `<delete range>[:<remote name>]`.
-    /// Delete range is one of the `merged, merged-local, merged-remote, stray, diverged, local, remote`.
+    /// Delete range is one of the `merged, merged-local, merged-remote, stray, diverged, local, remote, stale-tracking`,
+    /// or one of the presets `safe, standard, aggressive`.
     /// `:<remote name>` is only necessary to a `<delete range>` when the range is applied to remote branches.
     /// You can use `*` as `<remote name>` to delete a range of branches from all remotes.
-    /// [default : `merged:origin`] [config: trim.delete]
+    /// [default : `merged:origin`] [config: trim.delete] [env: GIT_TRIM_DELETE]
     ///
     /// `merged` implies `merged-local,merged-remote`.
     ///
@@ -75,15 +115,381 @@ pub struct Args {
     /// `merged-remote:<remote>` will delete merged upstream branches from `<remote>`.
     /// `stray` will delete tracking local branches, which is not merged, but the upstream is gone.
     /// `diverged:<remote>` will delete merged tracking local branches, and their upstreams from `<remote>` even if the upstreams are not merged and diverged from local ones.
+    /// Upstreams that have commits in neither the local branches nor the bases are still kept.
     /// `local` will delete non-tracking merged local branches.
     /// `remote:<remote>` will delete non-upstream merged remote tracking branches.
+    /// `stale-tracking[:<remote>]` will delete remote tracking branches whose remote branches are gone.
+    /// They are deleted only from the local repository. `:<remote>` defaults to `*`.
+    ///
+    /// There are presets for common combinations. `:<remote>` of presets defaults to `origin`.
+    /// `safe` implies `merged-local`.
+    /// `standard[:<remote>]` implies `merged-local,merged-remote:<remote>`.
+    /// `aggressive[:<remote>]` implies `merged-local,merged-remote:<remote>,stray,diverged:<remote>`.
     /// Use with caution when you are using other than `merged`. It might lose changes, and even nuke repositories.
     #[clap(short, long, value_delimiter = ",")]
     pub delete: Vec<DeleteRange>,
 
+    /// Delete only merged branches. It keeps the merged ranges of `--delete` and adds `merged-local`
+    /// and `merged-remote`, while `stray`, `diverged` and `stale-tracking` are dropped.
+    /// `merged-remote` is scoped to the remotes of the dropped ranges, or `origin`.
+    #[clap(long, conflicts_with = "only-stray")]
+    pub only_merged: bool,
+
+    /// Delete only stray local branches, like `--delete stray`.
+    #[clap(long)]
+    pub only_stray: bool,
+
+    /// Also delete the merged remote branches that aren't the upstream of any local branch, on every remote.
+    /// It is an alias of adding `remote:*` to `--delete`. Only the remote tracking branches are scanned,
+    /// so remote branches that aren't fetched are never deleted.
+    #[clap(long)]
+    pub all_remotes: bool,
+
     /// Do not delete branches, show what branches will be deleted.
+    /// This is the safe way to preview. Nothing is deleted regardless of other flags.
     #[clap(long)]
     pub dry_run: bool,
+
+    /// Use GitHub style pull request heads fetched to `refs/pull/*/head` to detect merged branches.
+    /// A branch is merged when it is contained in a pull request head that is merged.
+    #[clap(long)]
+    pub pull_refs: bool,
+
+    /// Trim the remote tracking refs of the merged Gerrit changes, fetched from `refs/changes/*`.
+    /// Gerrit doesn't let anyone delete them on the server, so they are only deleted locally.
+    /// Other non-heads remote refs are kept back as always.
+    /// Abandoned changes aren't recognized, since only the Gerrit server knows about them.
+    #[clap(long)]
+    pub gerrit: bool,
+
+    /// Do not delete local branches whose fetch or push upstream is protected by `--protected`.
+    #[clap(long)]
+    pub keep_if_protected_upstream: bool,
+
+    /// Do not delete the push upstreams of the branches whose fetch upstreams are gone.
+    /// Whether they are merged is inferred from the push upstreams only, which might be pushed to forks.
+    /// Their local branches are still deleted.
+    #[clap(long)]
+    pub cautious_push_only: bool,
+
+    /// Never push the deletions of the remote branches that aren't merged, the diverged upstreams of merged locals.
+    /// Their local branches are still deleted, and so are the merged remote branches.
+    #[clap(long)]
+    pub no_stray_remote_delete: bool,
+
+    /// Do not delete the upstreams of merged local branches when someone pushed commits to them directly,
+    /// which are in neither the local branches nor the bases, even with `--delete diverged:<remote>`.
+    /// Their local branches are still deleted.
+    #[clap(long)]
+    pub keep_remote_ahead: bool,
+
+    /// Do not delete local branches that have stashes created on them.
+    #[clap(long)]
+    pub keep_stashed: bool,
+
+    /// Do not delete merged local branches that unmerged local branches are built on, like `feature/base` of `feature/child`.
+    #[clap(long)]
+    pub keep_ancestors: bool,
+
+    /// Always keep the N local branches to delete with the newest commits, whether they are merged or not.
+    /// The HEAD branch doesn't count.
+    #[clap(long, value_name = "N")]
+    pub keep_recent: Option<usize>,
+
+    /// Do not delete the local branches of the last N distinct checkouts in the HEAD reflog.
+    /// They are likely still relevant even if they are merged.
+    #[clap(long, value_name = "N")]
+    pub keep_recent_checkouts: Option<usize>,
+
+    /// Do not delete the merged local branches whose tips are newer than the age, like `12h`, `3d` or `2w`,
+    /// so the recent merges stay around for a while in case they have to be reverted.
+    /// The units are `s`, `m`, `h`, `d` and `w`.
+    #[clap(long, value_name = "DURATION")]
+    pub merged_older_than: Option<Age>,
+
+    /// Delete only the branches that share the prefix of the HEAD branch up to the last `/` or `-`,
+    /// e.g. `user/feature-` when `user/feature-3` is checked out. It is useful to trim stacked pull requests.
+    #[clap(long)]
+    pub stack_prefix: bool,
+
+    /// Don't access the remotes. It skips `--update` and `git ls-remote` for the branches without remote tracking branches.
+    /// Those branches are kept back instead of being classified as stray, because their remote state is unknown.
+    #[clap(long)]
+    pub offline: bool,
+
+    /// Leave `main`, `master` and the bases out of the classification entirely,
+    /// instead of classifying them and keeping them back.
+    #[clap(long)]
+    pub skip_defaults: bool,
+
+    /// Leave the local branches that have neither an upstream nor a push remote out of the classification entirely.
+    /// They are never deleted nor reported, like scratch branches that are never pushed.
+    #[clap(long)]
+    pub limit_to_tracked: bool,
+
+    /// Never delete stray and diverged branches, even when `--delete` includes `stray` or `diverged`.
+    /// They are kept back, since they might have unpushed or unmerged work.
+    #[clap(long)]
+    pub assume_unmerged_safe: bool,
+
+    /// Cross-check the gone upstreams with `git branch -vv`, logging where it disagrees with git-trim.
+    /// Branches that stray by their gone upstreams are kept back unless git also marks them `[gone]`.
+    #[clap(long)]
+    pub cross_check_gone: bool,
+
+    /// Delete remote branches only from the push remote, the one you own, in triangular workflows.
+    /// Branches on the remotes that are only fetched from are kept back.
+    #[clap(long)]
+    pub remote_default_only: bool,
+
+    /// Error instead of warning when a local base is behind its upstream.
+    #[clap(long)]
+    pub strict: bool,
+
+    /// Fail without deleting anything when there are stray local branches, even if they are kept back.
+    /// Useful as a CI check together with `--dry-run`.
+    #[clap(long)]
+    pub fail_on_stray: bool,
+
+    /// Abort the network operations, `git remote update`, `git fetch`, `git ls-remote` and `git push`,
+    /// after the given seconds. The state of the remotes that timed out is treated as unknown.
+    #[clap(long, value_name = "SECONDS")]
+    pub timeout: Option<u64>,
+
+    /// Do not delete branches, list local branches that have neither an upstream nor a push remote.
+    /// Those branches are never cleaned up by merge detection.
+    #[clap(long)]
+    pub report_orphans: bool,
+
+    /// Do not delete branches, list groups of local branches that point at the same commit,
+    /// whether they are merged or not, so that they can be consolidated.
+    #[clap(long)]
+    pub report_duplicates: bool,
+
+    /// Format of the log records written to stderr. Options are "text" and "json".
+    /// "json" writes a JSON object per line with `level`, `target`, `message` and `timestamp`.
+    /// [default: text]
+    #[clap(long)]
+    pub log_format: Option<LogFormat>,
+
+    /// Do not delete branches, print how the branches to delete change when `<compare-base>` is used as the base
+    /// instead of the current bases.
+    #[clap(long)]
+    pub compare_base: Option<String>,
+
+    /// Prune stale remote tracking branches of all remotes before classification,
+    /// like `git remote prune <remote>` does. Remote branches are never deleted.
+    #[clap(long)]
+    pub prune: bool,
+
+    /// Fetch the remotes of the bases before classification, even when updates are skipped.
+    #[clap(long)]
+    pub fetch: bool,
+
+    /// Do not detect squash merges. Squash detection is the slowest part on huge repositories.
+    /// [config: trim.squashDetect]
+    #[clap(long)]
+    pub no_squash_detect: bool,
+    #[clap(long, hidden(true))]
+    pub squash_detect: bool,
+
+    /// Detect a squash merge only when a commit of the base has the same tree as the branch.
+    /// Otherwise the changes of the branch are compared, and branches with merge commits are
+    /// never detected as squash merged.
+    #[clap(long)]
+    pub strict_squash: bool,
+
+    /// Cache the results of the merge checks in `.git/git-trim/cache.json` to speed up the next runs.
+    #[clap(long)]
+    pub cache: bool,
+
+    /// Reuse the classifications of the previous run in `.git/git-trim/classifications.json`,
+    /// and classify only the branches that are new or whose commits, upstreams or bases have moved.
+    #[clap(long)]
+    pub incremental: bool,
+
+    /// Keep merged branches back when their merges are reverted on the bases with `git revert`.
+    /// Their commits are still in the bases, but their changes are not.
+    #[clap(long)]
+    pub detect_reverted: bool,
+
+    /// Keep stray branches back when their upstreams are renamed, like force-moved pull request branches.
+    /// A remote branch with the same tip or the same patch at the tip is taken as the renamed upstream.
+    #[clap(long)]
+    pub detect_renamed_upstream: bool,
+
+    /// Keep merged remote branches back when they have more than <N> commits that are in neither the bases
+    /// nor their local branches, like the ones pushed after a squash merge. Their local branches are still deleted.
+    #[clap(long, value_name = "N")]
+    pub remote_ahead_guard: Option<usize>,
+
+    /// Read newline separated branch names from stdin, like the head branches of merged pull requests,
+    /// and trim those local branches and their upstreams as merged without detecting the merges.
+    /// Bases, protected branches and the other keep-backs still apply. Use it with `--no-confirm`.
+    #[clap(long)]
+    pub merged_from_stdin: bool,
+
+    /// Compare each branch with the target branch of its pull request instead of the bases,
+    /// like `release/2.0`. Pull requests are listed with the GitHub CLI `gh`.
+    /// Branches without pull requests are compared with the bases, and `branch.<name>.git-trim-base` takes precedence.
+    #[clap(long)]
+    pub base_from_pr: bool,
+
+    /// Print only errors to stderr. The plan is still printed to stdout.
+    #[clap(short, long, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Print more logs to stderr. Repeat it for more: `-v` for info, `-vv` for debug, `-vvv` for trace.
+    /// Warnings are printed by default, or `RUST_LOG` is respected when neither `-q` nor `-v` is given.
+    #[clap(short, long, parse(from_occurrences))]
+    pub verbose: u64,
+
+    /// Report the number of commits that are reachable only from the deleted local branches,
+    /// as an estimate of what `git gc` can reclaim. It never runs `git gc`.
+    #[clap(long)]
+    pub report_savings: bool,
+
+    /// Report the number of the remote URLs to contact and the remote refs to delete, then exit.
+    /// Nothing is fetched, listed nor pushed. `--prune` and `--fetch` are skipped and not counted.
+    /// The remote branches of the direct fetch branches aren't listed, so they are reported as unknown.
+    #[clap(long)]
+    pub estimate: bool,
+
+    /// Write an audit report in JSON to `<report>` after the deletions.
+    /// It has the bases, the delete filter, the deleted branches with their commits and whether their deletions failed,
+    /// and the kept branches with the reasons.
+    #[clap(long)]
+    pub report: Option<PathBuf>,
+
+    /// Record the deleted branches with their commits in a note of the base commit under `refs/notes/git-trim`.
+    /// The note is appended to, so it keeps the deletions of the previous runs.
+    #[clap(long)]
+    pub note_deletions: bool,
+
+    /// Retry deleting remote branches with exponential backoff when it fails with transient network errors.
+    /// Authentication errors and missing remote refs are never retried.
+    /// [default: 2]
+    #[clap(long)]
+    pub retries: Option<u32>,
+
+    /// Keep the tips of the branches as refs under `--archive-namespace` before deleting them,
+    /// so that they can be recovered even after `git gc`.
+    /// Remote branches are archived only when their remote tracking branches exist.
+    #[clap(long)]
+    pub archive: bool,
+
+    /// The ref namespace of `--archive`.
+    /// [default: refs/trim-archive]
+    #[clap(long)]
+    pub archive_namespace: Option<String>,
+
+    /// Rename the local branches to `<PREFIX>/<branch>` instead of deleting them, keeping their reflogs.
+    /// Branches whose new names are taken are reported and left as they are.
+    #[clap(long, value_name = "PREFIX")]
+    pub rename_archive: Option<String>,
+
+    /// Do not delete branches, list the archives under `--archive-namespace`
+    /// with the archived branches, their commits and when they were archived.
+    #[clap(long)]
+    pub list_archives: bool,
+
+    /// Do not delete branches, recreate the local branch from its archive under `--archive-namespace`.
+    /// It fails when the branch exists, unless `--force` is given.
+    #[clap(long, value_name = "BRANCH")]
+    pub restore: Option<String>,
+
+    /// Overwrite the existing branch with `--restore`.
+    #[clap(long, requires = "restore")]
+    pub force: bool,
+
+    /// Delete the archive after `--restore` recreates the branch from it.
+    #[clap(long, requires = "restore")]
+    pub drop_archive: bool,
+
+    /// Exit with 2 when any branches are deleted, like `git diff --exit-code`.
+    /// Branches that would be deleted count with `--dry-run`.
+    /// Otherwise it exits with 0 on success, and 1 on errors regardless of this flag.
+    #[clap(long)]
+    pub exit_code: bool,
+
+    /// Print why each branch is deleted next to it, from the messages of its classifications.
+    /// Kept back branches always show their reasons.
+    #[clap(long)]
+    pub print_reasons: bool,
+
+    /// Group the branches to delete in the plan by where they are deleted instead of by classification.
+    /// The only option is "remote": local branches are listed under "local", and remote branches under their remotes.
+    #[clap(long, value_name = "KEY")]
+    pub group_by: Option<GroupBy>,
+
+    /// Order the branches in the summary and in the `local`, `remote` and `json-lines` porcelain outputs.
+    /// "name" sorts by the branch name, "age" by the committer time of the tip with the oldest first,
+    /// and "ahead" by the number of commits that aren't in HEAD with the fewest first. Ties break by the name.
+    #[clap(long, value_name = "KEY")]
+    pub sort: Option<SortKey>,
+
+    /// Trim each initialized submodule in its own repository after the superproject, with the same options.
+    /// Each submodule is reported separately, and a failure in a submodule doesn't stop the others.
+    #[clap(long)]
+    pub recurse_submodules: bool,
+
+    /// Trim every repository under the directory instead of the current one, with the same options.
+    /// Each repository is reported separately by its path, and a failure in a repository doesn't stop the others.
+    /// Directories that aren't git repositories are skipped, and repositories aren't searched further.
+    #[clap(long, value_name = "DIR")]
+    pub workspace: Option<PathBuf>,
+
+    /// How many levels of directories `--workspace` searches for repositories.
+    /// [default: 3]
+    #[clap(long, value_name = "N")]
+    pub workspace_depth: Option<usize>,
+
+    /// Print the configuration resolved from the CLI options, `GIT_TRIM_*` environment variables and `git config` as JSON,
+    /// then exit without classifying branches.
+    #[clap(long)]
+    pub config_dump: bool,
+
+    /// Do not delete branches, explain how the branch is classified: its tip, each base with the merge base,
+    /// the results of the rev-list and the squash merge detections, the upstreams, and the final classification.
+    #[clap(long, value_name = "NAME")]
+    pub explain_branch: Option<String>,
+
+    /// Do not delete branches, print every classification of the branches against the bases as JSON.
+    /// Alive branches are listed as well with their messages, which helps to debug why a branch is not deleted.
+    #[clap(long)]
+    pub dump_classifications: bool,
+
+    /// Write the inputs of the classification, the refs, the repository config and the remote heads, to the file,
+    /// then exit without classifying branches. `--replay` classifies them later.
+    #[clap(long, value_name = "PATH")]
+    pub snapshot: Option<PathBuf>,
+
+    /// Do not touch the repository nor the remotes, classify the branches of the snapshot of `--snapshot` instead.
+    /// It implies `--dry-run` and `--no-update`. The commits are read from the repository where the snapshot was taken,
+    /// so the repository must still be there.
+    #[clap(long, value_name = "PATH")]
+    pub replay: Option<PathBuf>,
+
+    /// Trim the repository at `<PATH>` instead of the one at the current working directory, like `git -C`.
+    /// The repository is discovered from the path, so it can be a subdirectory of the worktree.
+    /// Other paths like `--report` are still relative to the current working directory.
+    #[clap(short = 'C', value_name = "PATH")]
+    pub repository: Option<PathBuf>,
+
+    #[clap(subcommand)]
+    pub subcommand: Option<SubCommand>,
+}
+
+#[derive(Clap)]
+pub enum SubCommand {
+    /// Prints whether a branch is merged into a base, without trimming anything.
+    /// Exits with 0 when it is merged, 2 when it is not, and 1 on errors.
+    IsMerged {
+        /// A local branch or a remote tracking branch
+        branch: String,
+        /// A branch or a ref that the branch is compared with
+        base: String,
+    },
 }
 
 impl Args {
@@ -98,6 +504,28 @@ impl Args {
     pub fn detach(&self) -> Option<bool> {
         exclusive_bool(("detach", self.detach), ("no-detach", self.no_detach))
     }
+
+    pub fn squash_detect(&self) -> Option<bool> {
+        exclusive_bool(
+            ("squash-detect", self.squash_detect),
+            ("no-squash-detect", self.no_squash_detect),
+        )
+    }
+
+    /// Whether it only prints something, like `--porcelain` except `ndjson-events`.
+    /// Those modes never change any refs, so they are always dry runs including `git remote update --prune`.
+    pub fn print_only(&self) -> bool {
+        let porcelain = match self.porcelain {
+            Some(PorcelainFormat::NDJSONEvents) | None => false,
+            Some(_) => true,
+        };
+        porcelain || self.compare_base.is_some() || self.dump_classifications
+    }
+
+    /// `--dry-run`, or implied by the print only modes.
+    pub fn dry_run(&self) -> bool {
+        self.dry_run || self.print_only()
+    }
 }
 
 impl paw::ParseArgs for Args {
@@ -140,6 +568,14 @@ pub enum PorcelainFormat {
     RemoteBranches,
     /// Full structured JSON output
     JSON,
+    /// A JSON object per line for each action on a branch
+    JSONLines,
+    /// XML of the branches to delete and the kept back branches
+    XML,
+    /// A Markdown table of the branches to delete and a collapsible list of the kept back branches
+    Markdown,
+    /// A JSON object per line for each event of an actual run
+    NDJSONEvents,
 }
 
 impl FromStr for PorcelainFormat {
@@ -151,6 +587,10 @@ impl FromStr for PorcelainFormat {
                 message: "Porcelain format is empty".to_owned(),
             }),
             "json" => Ok(PorcelainFormat::JSON),
+            "jsonl" => Ok(PorcelainFormat::JSONLines),
+            "xml" => Ok(PorcelainFormat::XML),
+            "markdown" | "md" => Ok(PorcelainFormat::Markdown),
+            "ndjson-events" => Ok(PorcelainFormat::NDJSONEvents),
             "local" | "l" => Ok(PorcelainFormat::LocalBranches),
             "remote" | "r" => Ok(PorcelainFormat::RemoteBranches),
             unknown => Err(PorcelainFormatParseError {
@@ -160,12 +600,191 @@ impl FromStr for PorcelainFormat {
     }
 }
 
+impl std::fmt::Display for PorcelainFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PorcelainFormat::LocalBranches => write!(f, "local"),
+            PorcelainFormat::RemoteBranches => write!(f, "remote"),
+            PorcelainFormat::JSON => write!(f, "json"),
+            PorcelainFormat::JSONLines => write!(f, "jsonl"),
+            PorcelainFormat::XML => write!(f, "xml"),
+            PorcelainFormat::Markdown => write!(f, "markdown"),
+            PorcelainFormat::NDJSONEvents => write!(f, "ndjson-events"),
+        }
+    }
+}
+
 #[derive(Error, Debug)]
 #[error("{message}")]
 pub struct PorcelainFormatParseError {
     message: String,
 }
 
+/// Configuration of --group-by.
+#[derive(Debug)]
+pub enum GroupBy {
+    /// Local branches and each remote
+    Remote,
+}
+
+impl FromStr for GroupBy {
+    type Err = GroupByParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "" => Err(GroupByParseError {
+                message: "Group key is empty".to_owned(),
+            }),
+            "remote" => Ok(GroupBy::Remote),
+            unknown => Err(GroupByParseError {
+                message: format!("Unknown group key: {}", unknown),
+            }),
+        }
+    }
+}
+
+impl std::fmt::Display for GroupBy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GroupBy::Remote => write!(f, "remote"),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+#[error("{message}")]
+pub struct GroupByParseError {
+    message: String,
+}
+
+/// Configuration of --sort.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SortKey {
+    /// The branch name
+    Name,
+    /// The committer time of the tip
+    Age,
+    /// The number of commits that aren't in HEAD
+    Ahead,
+}
+
+impl Default for SortKey {
+    fn default() -> Self {
+        SortKey::Name
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = SortKeyParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "" => Err(SortKeyParseError {
+                message: "Sort key is empty".to_owned(),
+            }),
+            "name" => Ok(SortKey::Name),
+            "age" => Ok(SortKey::Age),
+            "ahead" => Ok(SortKey::Ahead),
+            unknown => Err(SortKeyParseError {
+                message: format!("Unknown sort key: {}", unknown),
+            }),
+        }
+    }
+}
+
+impl std::fmt::Display for SortKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SortKey::Name => write!(f, "name"),
+            SortKey::Age => write!(f, "age"),
+            SortKey::Ahead => write!(f, "ahead"),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+#[error("{message}")]
+pub struct SortKeyParseError {
+    message: String,
+}
+
+/// An age like `3d`: a number followed by a unit of `s`, `m`, `h`, `d` or `w`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Age(pub Duration);
+
+impl FromStr for Age {
+    type Err = AgeParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(AgeParseError {
+                message: "Age is empty".to_owned(),
+            });
+        }
+        let unit_at = s
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or_else(|| s.len());
+        let (number, unit) = s.split_at(unit_at);
+        let seconds = match unit {
+            "s" => 1,
+            "m" => 60,
+            "h" => 60 * 60,
+            "d" => 24 * 60 * 60,
+            "w" => 7 * 24 * 60 * 60,
+            _ => {
+                return Err(AgeParseError {
+                    message: format!("Unknown unit of age: {}", s),
+                })
+            }
+        };
+        let invalid = || AgeParseError {
+            message: format!("Invalid age: {}", s),
+        };
+        let number: u64 = number.parse().map_err(|_| invalid())?;
+        let seconds = number.checked_mul(seconds).ok_or_else(invalid)?;
+        Ok(Age(Duration::from_secs(seconds)))
+    }
+}
+
+#[derive(Error, Debug)]
+#[error("{message}")]
+pub struct AgeParseError {
+    message: String,
+}
+
+/// Configuration of --log-format.
+#[derive(Debug)]
+pub enum LogFormat {
+    /// Human readable text from `env_logger`
+    Text,
+    /// A JSON object per line
+    JSON,
+}
+
+impl FromStr for LogFormat {
+    type Err = LogFormatParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "" => Err(LogFormatParseError {
+                message: "Log format is empty".to_owned(),
+            }),
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::JSON),
+            unknown => Err(LogFormatParseError {
+                message: format!("Unknown log format: {}", unknown),
+            }),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+#[error("{message}")]
+pub struct LogFormatParseError {
+    message: String,
+}
+
 #[derive(Hash, Eq, PartialEq, Clone, Debug)]
 pub enum Scope {
     All,
@@ -186,6 +805,12 @@ impl FromStr for Scope {
     }
 }
 
+impl Scope {
+    fn origin() -> Self {
+        Scope::Scoped("origin".to_owned())
+    }
+}
+
 #[derive(Error, Debug)]
 #[error("{message}")]
 pub struct ScopeParseError {
@@ -201,6 +826,10 @@ pub enum DeleteRange {
     Diverged(Scope),
     Local,
     Remote(Scope),
+    Safe,
+    Standard(Scope),
+    Aggressive(Scope),
+    StaleTracking(Scope),
 }
 
 #[derive(Hash, Eq, PartialEq, Clone, Debug)]
@@ -211,6 +840,7 @@ pub enum DeleteUnit {
     Diverged(Scope),
     MergedNonTrackingLocal,
     MergedNonUpstreamRemoteTracking(Scope),
+    StaleTracking(Scope),
 }
 
 impl FromStr for DeleteRange {
@@ -226,6 +856,13 @@ impl FromStr for DeleteRange {
             ["merged-remote", remote] => Ok(DeleteRange::MergedRemote(remote.parse()?)),
             ["local"] => Ok(DeleteRange::Local),
             ["remote", remote] => Ok(DeleteRange::Remote(remote.parse()?)),
+            ["safe"] => Ok(DeleteRange::Safe),
+            ["standard"] => Ok(DeleteRange::Standard(Scope::origin())),
+            ["standard", remote] => Ok(DeleteRange::Standard(remote.parse()?)),
+            ["aggressive"] => Ok(DeleteRange::Aggressive(Scope::origin())),
+            ["aggressive", remote] => Ok(DeleteRange::Aggressive(remote.parse()?)),
+            ["stale-tracking"] => Ok(DeleteRange::StaleTracking(Scope::All)),
+            ["stale-tracking", remote] => Ok(DeleteRange::StaleTracking(remote.parse()?)),
             _ => Err(DeleteParseError::InvalidDeleteRangeFormat(arg.to_owned())),
         }
     }
@@ -246,15 +883,24 @@ impl DeleteRange {
             DeleteRange::Remote(scope) => {
                 vec![DeleteUnit::MergedNonUpstreamRemoteTracking(scope.clone())]
             }
+            DeleteRange::Safe => vec![DeleteUnit::MergedLocal],
+            DeleteRange::Standard(scope) => vec![
+                DeleteUnit::MergedLocal,
+                DeleteUnit::MergedRemote(scope.clone()),
+            ],
+            DeleteRange::Aggressive(scope) => vec![
+                DeleteUnit::MergedLocal,
+                DeleteUnit::MergedRemote(scope.clone()),
+                DeleteUnit::Stray,
+                DeleteUnit::Diverged(scope.clone()),
+            ],
+            DeleteRange::StaleTracking(scope) => vec![DeleteUnit::StaleTracking(scope.clone())],
         }
     }
 
     pub fn merged_origin() -> Vec<Self> {
         use DeleteRange::*;
-        vec![
-            MergedLocal,
-            MergedRemote(Scope::Scoped("origin".to_string())),
-        ]
+        vec![MergedLocal, MergedRemote(Scope::origin())]
     }
 }
 
@@ -269,6 +915,38 @@ pub enum DeleteParseError {
 #[derive(Debug, Clone, Eq, PartialEq, Default)]
 pub struct DeleteFilter(HashSet<DeleteUnit>);
 
+impl std::fmt::Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Scope::All => write!(f, "*"),
+            Scope::Scoped(remote) => write!(f, "{}", remote),
+        }
+    }
+}
+
+impl std::fmt::Display for DeleteUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeleteUnit::MergedLocal => write!(f, "merged-local"),
+            DeleteUnit::MergedRemote(scope) => write!(f, "merged-remote:{}", scope),
+            DeleteUnit::Stray => write!(f, "stray"),
+            DeleteUnit::Diverged(scope) => write!(f, "diverged:{}", scope),
+            DeleteUnit::MergedNonTrackingLocal => write!(f, "local"),
+            DeleteUnit::MergedNonUpstreamRemoteTracking(scope) => write!(f, "remote:{}", scope),
+            DeleteUnit::StaleTracking(scope) => write!(f, "stale-tracking:{}", scope),
+        }
+    }
+}
+
+/// Comma separated delete ranges, sorted for a reproducible output.
+impl std::fmt::Display for DeleteFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut units: Vec<_> = self.0.iter().map(DeleteUnit::to_string).collect();
+        units.sort();
+        write!(f, "{}", units.join(","))
+    }
+}
+
 impl DeleteFilter {
     pub fn scan_tracking(&self) -> bool {
         self.0.iter().any(|unit| {
@@ -299,6 +977,46 @@ impl DeleteFilter {
         false
     }
 
+    pub fn scan_stale_tracking(&self) -> bool {
+        self.0
+            .iter()
+            .any(|unit| matches!(unit, DeleteUnit::StaleTracking(_)))
+    }
+
+    /// The filter of `--only-merged`.
+    pub fn only_merged(&self) -> DeleteFilter {
+        use DeleteUnit::*;
+
+        let mut units = vec![MergedLocal];
+        let mut scopes = Vec::new();
+        for unit in &self.0 {
+            match unit {
+                MergedLocal | MergedNonTrackingLocal | MergedNonUpstreamRemoteTracking(_) => {
+                    units.push(unit.clone())
+                }
+                MergedRemote(scope) | Diverged(scope) => scopes.push(scope.clone()),
+                Stray | StaleTracking(_) => {}
+            }
+        }
+        if scopes.is_empty() {
+            scopes.push(Scope::origin());
+        }
+        units.extend(scopes.into_iter().map(MergedRemote));
+        DeleteFilter::from_iter(units)
+    }
+
+    /// The filter of `--all-remotes`, an alias of adding `remote:*`.
+    pub fn with_all_remotes(&self) -> DeleteFilter {
+        let mut units = self.0.clone();
+        units.insert(DeleteUnit::MergedNonUpstreamRemoteTracking(Scope::All));
+        DeleteFilter(units)
+    }
+
+    /// The filter of `--only-stray`.
+    pub fn only_stray(&self) -> DeleteFilter {
+        DeleteFilter::from_iter(vec![DeleteUnit::Stray])
+    }
+
     pub fn delete_merged_local(&self) -> bool {
         self.0.contains(&DeleteUnit::MergedLocal)
     }
@@ -349,6 +1067,19 @@ impl DeleteFilter {
         }
         false
     }
+
+    pub fn delete_stale_tracking(&self, remote: &str) -> bool {
+        for unit in self.0.iter() {
+            match unit {
+                DeleteUnit::StaleTracking(Scope::All) => return true,
+                DeleteUnit::StaleTracking(Scope::Scoped(specific)) if specific == remote => {
+                    return true
+                }
+                _ => {}
+            }
+        }
+        false
+    }
 }
 
 impl FromIterator<DeleteUnit> for DeleteFilter {
@@ -365,7 +1096,10 @@ impl FromIterator<DeleteUnit> for DeleteFilter {
                 MergedLocal | Stray | MergedNonTrackingLocal => {
                     result.insert(unit.clone());
                 }
-                MergedRemote(All) | Diverged(All) | MergedNonUpstreamRemoteTracking(All) => {
+                MergedRemote(All)
+                | Diverged(All)
+                | MergedNonUpstreamRemoteTracking(All)
+                | StaleTracking(All) => {
                     result.retain(|x| discriminant(x) != discriminant(&unit));
                     result.insert(unit.clone());
                 }
@@ -384,6 +1118,11 @@ impl FromIterator<DeleteUnit> for DeleteFilter {
                         result.insert(unit.clone());
                     }
                 }
+                StaleTracking(_) => {
+                    if !result.contains(&StaleTracking(All)) {
+                        result.insert(unit.clone());
+                    }
+                }
             }
         }
 
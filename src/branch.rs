@@ -1,19 +1,22 @@
+use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
 
 use anyhow::{Context, Result};
-use git2::{Branch, Config, Direction, Reference, Repository};
+use git2::{Branch, Config, Direction, Oid, Reference, Repository};
 use log::*;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use crate::config;
-use crate::simple_glob::{expand_refspec, ExpansionSide};
+use crate::simple_glob::{expand_fetch_refspecs, expand_refspec, fetch_refspecs, ExpansionSide};
 
 pub trait Refname {
     fn refname(&self) -> &str;
 }
 
-#[derive(Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Clone, Serialize)]
+#[derive(Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Clone, Serialize, Deserialize)]
 pub struct LocalBranch {
     pub refname: String,
 }
@@ -26,6 +29,16 @@ impl LocalBranch {
         }
     }
 
+    /// `None` for a ref outside of `refs/heads/`, like `refs/local/` of other tools, which isn't a branch.
+    pub fn from_refname(refname: &str) -> Option<Self> {
+        if refname.starts_with("refs/heads/") {
+            Some(Self::new(refname))
+        } else {
+            debug!("ignore a ref outside of refs/heads/: {}", refname);
+            None
+        }
+    }
+
     pub fn short_name(&self) -> &str {
         &self.refname["refs/heads/".len()..]
     }
@@ -34,6 +47,16 @@ impl LocalBranch {
         &self,
         repo: &Repository,
         config: &Config,
+    ) -> Result<RemoteTrackingBranchStatus> {
+        self.fetch_upstream_with(repo, config, &RemoteRefspecs::default())
+    }
+
+    /// Same as `fetch_upstream`, but the remotes are looked up through `refspecs`.
+    pub fn fetch_upstream_with(
+        &self,
+        repo: &Repository,
+        config: &Config,
+        refspecs: &RemoteRefspecs,
     ) -> Result<RemoteTrackingBranchStatus> {
         let remote_name = if let Some(remote_name) = config::get_remote_name(config, self)? {
             remote_name
@@ -46,14 +69,64 @@ impl LocalBranch {
             return Ok(RemoteTrackingBranchStatus::None);
         };
 
-        RemoteTrackingBranch::from_remote_branch(
+        RemoteTrackingBranch::from_remote_branch_with(
             repo,
             &RemoteBranch {
                 remote: remote_name,
                 refname: merge,
             },
+            refspecs,
+        )
+    }
+
+    /// The remote tracking branch of the same name on the push remote.
+    pub fn push_upstream(
+        &self,
+        repo: &Repository,
+        config: &Config,
+    ) -> Result<RemoteTrackingBranchStatus> {
+        let remote_name = config::get_push_remote(config, self)?;
+        RemoteTrackingBranch::from_remote_branch(
+            repo,
+            &RemoteBranch {
+                remote: remote_name,
+                refname: self.refname.clone(),
+            },
         )
     }
+
+    /// Remote tracking branches of the same name on every push destination, see `config::get_push_remote_names_all`.
+    pub fn push_upstreams(
+        &self,
+        repo: &Repository,
+        config: &Config,
+    ) -> Result<Vec<RemoteTrackingBranch>> {
+        self.push_upstreams_with(repo, config, &RemoteRefspecs::default())
+    }
+
+    /// Same as `push_upstreams`, but the remotes are looked up through `refspecs`.
+    pub fn push_upstreams_with(
+        &self,
+        repo: &Repository,
+        config: &Config,
+        refspecs: &RemoteRefspecs,
+    ) -> Result<Vec<RemoteTrackingBranch>> {
+        let mut result = Vec::new();
+        for remote_name in config::get_push_remote_names_all(config, self)? {
+            let status = RemoteTrackingBranch::from_remote_branch_with(
+                repo,
+                &RemoteBranch {
+                    remote: remote_name,
+                    refname: self.refname.clone(),
+                },
+                refspecs,
+            )?;
+            if let RemoteTrackingBranchStatus::Exists(upstream) = status {
+                result.push(upstream);
+            }
+        }
+        Ok(result)
+    }
 }
 
 impl Refname for LocalBranch {
@@ -76,7 +149,10 @@ impl<'repo> TryFrom<&git2::Reference<'repo>> for LocalBranch {
 
     fn try_from(reference: &Reference<'repo>) -> Result<Self> {
         if !reference.is_branch() {
-            anyhow::anyhow!("Reference {:?} is not a branch", reference.name());
+            return Err(anyhow::anyhow!(
+                "Reference {:?} is not a branch",
+                reference.name()
+            ));
         }
 
         let refname = reference.name().context("non-utf8 reference name")?;
@@ -84,7 +160,50 @@ impl<'repo> TryFrom<&git2::Reference<'repo>> for LocalBranch {
     }
 }
 
-#[derive(Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Clone, Serialize)]
+/// The fetch refspecs of the remotes, looked up once per remote and reused for the rest of a run.
+/// Many branches usually share a few remotes, and looking up a remote reads its config every time.
+#[derive(Default)]
+pub struct RemoteRefspecs {
+    refspecs: Mutex<HashMap<String, Option<Vec<(String, String)>>>>,
+    lookups: AtomicUsize,
+}
+
+impl RemoteRefspecs {
+    /// The number of the remotes looked up so far. A cached remote isn't counted again.
+    pub fn lookups(&self) -> usize {
+        self.lookups.load(Ordering::Relaxed)
+    }
+
+    /// `None` when the remote doesn't exist.
+    fn get(&self, repo: &Repository, remote_name: &str) -> Result<Option<Vec<(String, String)>>> {
+        if let Some(cached) = self.refspecs.lock().unwrap().get(remote_name) {
+            return Ok(cached.clone());
+        }
+
+        self.lookups.fetch_add(1, Ordering::Relaxed);
+        let refspecs = match config::get_remote(repo, remote_name)? {
+            Some(remote) => Some(fetch_refspecs(&remote)?),
+            None => None,
+        };
+        self.refspecs
+            .lock()
+            .unwrap()
+            .insert(remote_name.to_owned(), refspecs.clone());
+        Ok(refspecs)
+    }
+}
+
+/// The commit that the other branches are compared with.
+/// It is usually the tip of a remote tracking branch, but it might have no ref of its own,
+/// like the merge base of `merge-base:<base>` or the commit of a tag.
+#[derive(Eq, PartialEq, Debug, Hash, Clone)]
+pub struct BaseCommit {
+    pub oid: Oid,
+    /// The refname of the base, or how it is given when the commit has no ref, like `merge-base:<refname>`.
+    pub name: String,
+}
+
+#[derive(Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Clone, Serialize, Deserialize)]
 pub struct RemoteTrackingBranch {
     pub refname: String,
 }
@@ -101,14 +220,21 @@ impl RemoteTrackingBranch {
         repo: &Repository,
         remote_branch: &RemoteBranch,
     ) -> Result<RemoteTrackingBranchStatus> {
-        let remote = config::get_remote(repo, &remote_branch.remote)?;
-        if let Some(remote) = remote {
-            let refname = if let Some(expanded) = expand_refspec(
-                &remote,
+        Self::from_remote_branch_with(repo, remote_branch, &RemoteRefspecs::default())
+    }
+
+    /// Same as `from_remote_branch`, but the remote is looked up through `refspecs`.
+    pub fn from_remote_branch_with(
+        repo: &Repository,
+        remote_branch: &RemoteBranch,
+        refspecs: &RemoteRefspecs,
+    ) -> Result<RemoteTrackingBranchStatus> {
+        if let Some(fetch_refspecs) = refspecs.get(repo, &remote_branch.remote)? {
+            let refname = if let Some(expanded) = expand_fetch_refspecs(
+                &fetch_refspecs,
                 &remote_branch.refname,
-                Direction::Fetch,
                 ExpansionSide::Right,
-            )? {
+            ) {
                 expanded
             } else {
                 return Ok(RemoteTrackingBranchStatus::None);
@@ -125,6 +251,11 @@ impl RemoteTrackingBranch {
         Ok(RemoteTrackingBranchStatus::None)
     }
 
+    /// Branch name without `refs/remotes/` prefix, like `origin/master`.
+    pub fn short_name(&self) -> &str {
+        &self.refname["refs/remotes/".len()..]
+    }
+
     /// Convert to the associated RemoteBranch.
     pub fn to_remote_branch<'a>(
         &self,
@@ -143,7 +274,7 @@ impl RemoteTrackingBranch {
                 });
             }
         }
-        error!("Remote not found for branch {}", self.refname);
+        debug!("Remote not found for branch {}", self.refname);
         Err(RemoteBranchError::RemoteNotFound)
     }
 }
@@ -176,18 +307,67 @@ impl<'repo> TryFrom<&git2::Reference<'repo>> for RemoteTrackingBranch {
     }
 }
 
+/// GitHub style pull request head, `refs/pull/<number>/head`, fetched into the local repository.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct PullRequestHead {
+    pub refname: String,
+}
+
+impl Refname for PullRequestHead {
+    fn refname(&self) -> &str {
+        &self.refname
+    }
+}
+
 pub enum RemoteTrackingBranchStatus {
     Exists(RemoteTrackingBranch),
     Gone(String),
     None,
 }
 
-#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Hash, Debug, Serialize)]
+#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Hash, Debug, Serialize, Deserialize)]
 pub struct RemoteBranch {
     pub remote: String,
     pub refname: String,
 }
 
+impl RemoteBranch {
+    /// Parses a human string like `origin/feature/x` into the branch `refs/heads/feature/x` of `origin`.
+    /// The longest known remote that prefixes it wins, since both names may contain slashes.
+    pub fn parse(s: &str, known_remotes: &[&str]) -> Result<RemoteBranch> {
+        let mut matched: Option<(&str, &str)> = None;
+        for remote in known_remotes {
+            let branch = match s
+                .strip_prefix(remote)
+                .and_then(|rest| rest.strip_prefix('/'))
+            {
+                Some(branch) if !branch.is_empty() => branch,
+                _ => continue,
+            };
+            if matched.map_or(true, |(longest, _)| remote.len() > longest.len()) {
+                matched = Some((*remote, branch));
+            }
+        }
+        match matched {
+            Some((remote, branch)) => Ok(RemoteBranch {
+                remote: remote.to_owned(),
+                refname: format!("refs/heads/{}", branch),
+            }),
+            None => Err(anyhow::anyhow!("`{}` doesn't start with a known remote", s)),
+        }
+    }
+
+    /// Branch name without `refs/heads/` prefix.
+    /// Other refnames like `refs/pulls/123` are returned as is.
+    pub fn short_name(&self) -> &str {
+        if self.refname.starts_with("refs/heads/") {
+            &self.refname["refs/heads/".len()..]
+        } else {
+            &self.refname
+        }
+    }
+}
+
 impl std::fmt::Display for RemoteBranch {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}, {}", self.remote, self.refname)
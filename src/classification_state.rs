@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use git2::Repository;
+use log::*;
+use serde::{Deserialize, Serialize};
+
+use crate::core::{ClassifiedBranch, CLASSIFICATION_MESSAGES};
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Entry {
+    fingerprint: String,
+    message: String,
+    result: Vec<ClassifiedBranch>,
+}
+
+/// Classifications of the previous run for `--incremental`, persisted in `.git/git-trim/classifications.json`.
+/// An entry is keyed by the branch and the base, and reused only when the commits of every ref
+/// that the classification read are the same, so the results equal the ones of a full run.
+#[derive(Default)]
+pub struct ClassificationState {
+    loaded: HashMap<String, Entry>,
+    // Only the entries of this run are saved, so the deleted branches don't stay forever.
+    used: HashMap<String, Entry>,
+}
+
+fn key(branch: &str, base: &str) -> String {
+    format!("{}..{}", base, branch)
+}
+
+impl ClassificationState {
+    fn path(repo: &Repository) -> PathBuf {
+        repo.path().join("git-trim").join("classifications.json")
+    }
+
+    pub fn load(repo: &Repository) -> Result<Self> {
+        let path = Self::path(repo);
+        let loaded = match std::fs::read(&path) {
+            Ok(content) => serde_json::from_slice(&content).unwrap_or_else(|err| {
+                warn!("Ignore the corrupted state {}: {}", path.display(), err);
+                HashMap::new()
+            }),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Self {
+            loaded,
+            ..Self::default()
+        })
+    }
+
+    pub fn save(&self, repo: &Repository) -> Result<()> {
+        let path = Self::path(repo);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, serde_json::to_vec(&self.used)?)?;
+        Ok(())
+    }
+
+    /// The previous classification when none of the refs in the `fingerprint` have moved.
+    /// A message that this version doesn't know, like the one of another version, is a miss.
+    pub fn get(
+        &mut self,
+        branch: &str,
+        base: &str,
+        fingerprint: &str,
+    ) -> Option<(&'static str, Vec<ClassifiedBranch>)> {
+        let key = key(branch, base);
+        let entry = self.loaded.get(&key)?;
+        if entry.fingerprint != fingerprint {
+            return None;
+        }
+        let message = *CLASSIFICATION_MESSAGES
+            .iter()
+            .find(|message| **message == entry.message)?;
+        trace!("reuse the classification: {}", key);
+        let entry = entry.clone();
+        let result = entry.result.clone();
+        self.used.insert(key, entry);
+        Some((message, result))
+    }
+
+    pub fn insert(
+        &mut self,
+        branch: &str,
+        base: &str,
+        fingerprint: String,
+        message: &str,
+        result: &[ClassifiedBranch],
+    ) {
+        self.used.insert(
+            key(branch, base),
+            Entry {
+                fingerprint,
+                message: message.to_owned(),
+                result: result.to_vec(),
+            },
+        );
+    }
+}
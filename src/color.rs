@@ -0,0 +1,20 @@
+use console::{Style, StyledObject};
+
+/// Colors are used only when stdout is a terminal and `NO_COLOR` is not set.
+/// See https://no-color.org
+pub fn enabled() -> bool {
+    let no_color = matches!(std::env::var_os("NO_COLOR"), Some(value) if !value.is_empty());
+    !no_color && console::Term::stdout().is_term()
+}
+
+pub fn merged<D>(enabled: bool, value: D) -> StyledObject<D> {
+    Style::new().green().force_styling(enabled).apply_to(value)
+}
+
+pub fn stray<D>(enabled: bool, value: D) -> StyledObject<D> {
+    Style::new().yellow().force_styling(enabled).apply_to(value)
+}
+
+pub fn kept<D>(enabled: bool, value: D) -> StyledObject<D> {
+    Style::new().dim().force_styling(enabled).apply_to(value)
+}
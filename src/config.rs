@@ -22,6 +22,7 @@ pub struct Config {
     pub update_interval: ConfigValue<u64>,
     pub confirm: ConfigValue<bool>,
     pub detach: ConfigValue<bool>,
+    pub squash_detect: ConfigValue<bool>,
     pub delete: ConfigValue<DeleteFilter>,
 }
 
@@ -36,11 +37,14 @@ impl Config {
         }
 
         let bases = get_comma_separated_multi(config, "trim.bases")
+            .with_alias("trim.base")
             .with_explicit(non_empty(args.bases.clone()))
-            .with_default(get_branches_tracks_remote_heads(repo, config)?)
+            .with_env("GIT_TRIM_BASE")
+            .with_default(get_default_bases(repo, config)?)
             .parses_and_collect::<HashSet<String>>()?;
         let protected = get_comma_separated_multi(config, "trim.protected")
             .with_explicit(non_empty(args.protected.clone()))
+            .with_env("GIT_TRIM_PROTECT")
             .parses_and_collect::<Vec<String>>()?;
         let update = get(config, "trim.update")
             .with_explicit(args.update())
@@ -62,10 +66,28 @@ impl Config {
             .with_default(true)
             .read()?
             .expect("has default");
+        let squash_detect = get(config, "trim.squashDetect")
+            .with_explicit(args.squash_detect())
+            .with_default(true)
+            .read()?
+            .expect("has default");
         let delete = get_comma_separated_multi(config, "trim.delete")
             .with_explicit(non_empty(args.delete.clone()))
+            .with_env("GIT_TRIM_DELETE")
             .with_default(DeleteRange::merged_origin())
             .parses_and_collect::<DeleteFilter>()?;
+        let delete = if args.only_merged {
+            ConfigValue::Explicit(delete.only_merged())
+        } else if args.only_stray {
+            ConfigValue::Explicit(delete.only_stray())
+        } else {
+            delete
+        };
+        let delete = if args.all_remotes {
+            delete.map(|delete| delete.with_all_remotes())
+        } else {
+            delete
+        };
 
         Ok(Config {
             bases,
@@ -74,9 +96,65 @@ impl Config {
             update_interval,
             confirm,
             detach,
+            squash_detect,
             delete,
         })
     }
+
+    /// The resolved values with their sources. Bases are sorted for a reproducible output.
+    pub fn to_json(&self) -> serde_json::Value {
+        fn entry<T: serde::Serialize>(value: &ConfigValue<T>) -> serde_json::Value {
+            serde_json::json!({
+                "value": **value,
+                "source": value.source(),
+            })
+        }
+
+        let mut bases: Vec<_> = self.bases.iter().collect();
+        bases.sort();
+        serde_json::json!({
+            "bases": {
+                "value": bases,
+                "source": self.bases.source(),
+            },
+            "protected": entry(&self.protected),
+            "update": entry(&self.update),
+            "update_interval": entry(&self.update_interval),
+            "confirm": entry(&self.confirm),
+            "detach": entry(&self.detach),
+            "squash_detect": entry(&self.squash_detect),
+            "delete": {
+                "value": self.delete.to_string(),
+                "source": self.delete.source(),
+            },
+        })
+    }
+}
+
+/// Bases are detected in this order, and the first one found is used:
+/// 1. Local branches that track `git symbolic-ref refs/remotes/*/HEAD`, or `refs/remotes/*/HEAD` themselves
+/// 2. `git config init.defaultBranch`
+/// 3. `main`
+/// 4. `master`
+fn get_default_bases(repo: &Repository, config: &GitConfig) -> Result<Vec<String>> {
+    let bases = get_branches_tracks_remote_heads(repo, config)?;
+    if !bases.is_empty() {
+        return Ok(bases);
+    }
+
+    let mut candidates = Vec::new();
+    if let Some(default_branch) = get::<String>(config, "init.defaultBranch").read()? {
+        candidates.push(default_branch.unwrap());
+    }
+    candidates.push("main".to_owned());
+    candidates.push("master".to_owned());
+    for candidate in candidates {
+        if !crate::resolve_bases(repo, config, &[candidate.as_str()])?.is_empty() {
+            debug!("fallback base: {}", candidate);
+            return Ok(vec![candidate]);
+        }
+    }
+    Ok(Vec::new())
 }
 
 fn get_branches_tracks_remote_heads(repo: &Repository, config: &GitConfig) -> Result<Vec<String>> {
@@ -123,6 +201,9 @@ fn get_branches_tracks_remote_heads(repo: &Repository, config: &GitConfig) -> Re
 #[derive(Debug, Eq, PartialEq)]
 pub enum ConfigValue<T> {
     Explicit(T),
+    /// Comma separated values of an environment variable like `GIT_TRIM_BASE`.
+    /// They are overridden by the CLI options, and override `git config`.
+    Env(T),
     GitConfig(T),
     Implicit(T),
 }
@@ -130,17 +211,44 @@ pub enum ConfigValue<T> {
 impl<T> ConfigValue<T> {
     pub fn unwrap(self) -> T {
         match self {
-            ConfigValue::Explicit(x) | ConfigValue::GitConfig(x) | ConfigValue::Implicit(x) => x,
+            ConfigValue::Explicit(x)
+            | ConfigValue::Env(x)
+            | ConfigValue::GitConfig(x)
+            | ConfigValue::Implicit(x) => x,
+        }
+    }
+
+    /// Keeps where the value came from, like `--all-remotes` on top of `trim.delete`.
+    pub fn map<U, F>(self, f: F) -> ConfigValue<U>
+    where
+        F: FnOnce(T) -> U,
+    {
+        match self {
+            ConfigValue::Explicit(x) => ConfigValue::Explicit(f(x)),
+            ConfigValue::Env(x) => ConfigValue::Env(f(x)),
+            ConfigValue::GitConfig(x) => ConfigValue::GitConfig(f(x)),
+            ConfigValue::Implicit(x) => ConfigValue::Implicit(f(x)),
         }
     }
 
     pub fn is_implicit(&self) -> bool {
         match self {
             ConfigValue::Explicit(_) => false,
+            ConfigValue::Env(_) => false,
             ConfigValue::GitConfig(_) => false,
             ConfigValue::Implicit(_) => true,
         }
     }
+
+    /// Where the value came from, for `--config-dump`.
+    pub fn source(&self) -> &'static str {
+        match self {
+            ConfigValue::Explicit(_) => "cli",
+            ConfigValue::Env(_) => "env",
+            ConfigValue::GitConfig(_) => "git-config",
+            ConfigValue::Implicit(_) => "default",
+        }
+    }
 }
 
 impl<T> Deref for ConfigValue<T> {
@@ -148,7 +256,10 @@ impl<T> Deref for ConfigValue<T> {
 
     fn deref(&self) -> &Self::Target {
         match self {
-            ConfigValue::Explicit(x) | ConfigValue::GitConfig(x) | ConfigValue::Implicit(x) => x,
+            ConfigValue::Explicit(x)
+            | ConfigValue::Env(x)
+            | ConfigValue::GitConfig(x)
+            | ConfigValue::Implicit(x) => x,
         }
     }
 }
@@ -157,8 +268,10 @@ pub struct ConfigBuilder<'a, T> {
     config: &'a GitConfig,
     key: &'a str,
     explicit: Option<T>,
+    env: Option<&'a str>,
     default: Option<T>,
     comma_separated: bool,
+    alias: Option<&'a str>,
 }
 
 pub fn get<'a, T>(config: &'a GitConfig, key: &'a str) -> ConfigBuilder<'a, T> {
@@ -166,8 +279,10 @@ pub fn get<'a, T>(config: &'a GitConfig, key: &'a str) -> ConfigBuilder<'a, T> {
         config,
         key,
         explicit: None,
+        env: None,
         default: None,
         comma_separated: false,
+        alias: None,
     }
 }
 
@@ -179,8 +294,10 @@ pub fn get_comma_separated_multi<'a, T>(
         config,
         key,
         explicit: None,
+        env: None,
         default: None,
         comma_separated: true,
+        alias: None,
     }
 }
 
@@ -196,12 +313,27 @@ impl<'a, T> ConfigBuilder<'a, T> {
         }
     }
 
+    pub fn with_env(self, name: &'a str) -> ConfigBuilder<'a, T> {
+        ConfigBuilder {
+            env: Some(name),
+            ..self
+        }
+    }
+
     pub fn with_default(self, value: T) -> ConfigBuilder<'a, T> {
         ConfigBuilder {
             default: Some(value),
             ..self
         }
     }
+
+    /// Another key whose values accumulate with the values of the key.
+    pub fn with_alias(self, key: &'a str) -> ConfigBuilder<'a, T> {
+        ConfigBuilder {
+            alias: Some(key),
+            ..self
+        }
+    }
 }
 
 impl<'a, T> ConfigBuilder<'a, T>
@@ -238,7 +370,36 @@ impl<'a, T> ConfigBuilder<'a, T> {
             return Ok(ConfigValue::Explicit(value.into_iter().collect()));
         }
 
-        let result = match Vec::<String>::get_config_value(self.config, self.key) {
+        if let Some(name) = self.env {
+            if let Some(value) = std::env::var_os(name) {
+                let value = value
+                    .into_string()
+                    .map_err(|_| anyhow::anyhow!("non utf-8 environment variable `{}`", name))?;
+                let mut result = Vec::new();
+                for item in value.split(',').map(str::trim) {
+                    if !item.is_empty() {
+                        let value = <T::Item>::from_str(item).with_context(|| {
+                            format!("Failed to parse the environment variable `{}`", name)
+                        })?;
+                        result.push(value);
+                    }
+                }
+                if !result.is_empty() {
+                    return Ok(ConfigValue::Env(result.into_iter().collect()));
+                }
+            }
+        }
+
+        let entries = match self.alias {
+            Some(alias) => {
+                Vec::<String>::get_config_value(self.config, self.key).and_then(|mut entries| {
+                    entries.extend(Vec::<String>::get_config_value(self.config, alias)?);
+                    Ok(entries)
+                })
+            }
+            None => Vec::<String>::get_config_value(self.config, self.key),
+        };
+        let result = match entries {
             Ok(entries) if !entries.is_empty() => {
                 let mut result = Vec::new();
                 if self.comma_separated {
@@ -287,7 +448,13 @@ impl ConfigValues for String {
 impl ConfigValues for Vec<String> {
     fn get_config_value(config: &GitConfig, key: &str) -> Result<Self, git2::Error> {
         let mut result = Vec::new();
-        for entry in &config.entries(Some(key))? {
+        // Not `entries`, which takes a regex that `trim.base` would match `trim.bases` with.
+        let entries = match config.multivar(key, None) {
+            Ok(entries) => entries,
+            Err(err) if config_not_exist(&err) => return Ok(result),
+            Err(err) => return Err(err),
+        };
+        for entry in &entries {
             let entry = entry?;
             if let Some(value) = entry.value() {
                 result.push(value.to_owned());
@@ -322,6 +489,16 @@ fn config_not_exist(err: &git2::Error) -> bool {
     err.code() == ErrorCode::NotFound && err.class() == ErrorClass::Config
 }
 
+/// `core.ignorecase` is set by git on case insensitive filesystems, where refnames differing only in case
+/// are the same reference.
+pub fn get_ignore_case(config: &GitConfig) -> Result<bool> {
+    Ok(get(config, "core.ignorecase")
+        .with_default(false)
+        .read()?
+        .expect("has default")
+        .unwrap())
+}
+
 pub fn get_push_remote(config: &GitConfig, branch: &LocalBranch) -> Result<String> {
     let push_remote_key = format!("branch.{}.pushRemote", branch.short_name());
     if let Some(push_remote) = get::<String>(config, &push_remote_key).read()? {
@@ -344,6 +521,90 @@ pub fn get_remote_name(config: &GitConfig, branch: &LocalBranch) -> Result<Optio
     }
 }
 
+/// `branch.<name>.git-trim-base` overrides the bases for the branch.
+pub fn get_base_override(config: &GitConfig, branch: &LocalBranch) -> Result<Option<String>> {
+    let key = format!("branch.{}.git-trim-base", branch.short_name());
+    match config.get_string(&key) {
+        Ok(base) => Ok(Some(base)),
+        Err(err) if config_not_exist(&err) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+pub fn get_push_remote_name(config: &GitConfig, branch: &LocalBranch) -> Result<Option<String>> {
+    let key = format!("branch.{}.pushRemote", branch.short_name());
+    match config.get_string(&key) {
+        Ok(remote) => Ok(Some(remote)),
+        Err(err) if config_not_exist(&err) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// All push destinations of the branch.
+/// `branch.<name>.pushRemote` can be set multiple times with `git config --add` to push a branch to several remotes.
+/// A push remote with several `remote.<name>.pushurl` pushes to all of them at once,
+/// so the remotes fetching from those URLs are push destinations as well.
+pub fn get_push_remote_names_all(config: &GitConfig, branch: &LocalBranch) -> Result<Vec<String>> {
+    let key = format!("branch.{}.pushRemote", branch.short_name());
+    let mut result = Vec::new();
+    for value in get_multivar(config, &key)? {
+        if !result.contains(&value) {
+            result.push(value);
+        }
+    }
+
+    let push_remotes = if result.is_empty() {
+        vec![get_push_remote(config, branch)?]
+    } else {
+        result.clone()
+    };
+    for push_remote in push_remotes {
+        for remote in get_remotes_fetching_push_urls(config, &push_remote)? {
+            if !result.contains(&remote) {
+                result.push(remote);
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Other remotes whose `remote.<name>.url` is one of the `remote.<push_remote>.pushurl`.
+fn get_remotes_fetching_push_urls(config: &GitConfig, push_remote: &str) -> Result<Vec<String>> {
+    let push_urls = get_multivar(config, &format!("remote.{}.pushurl", push_remote))?;
+    let mut result = Vec::new();
+    if push_urls.is_empty() {
+        return Ok(result);
+    }
+    let entries = config.entries(Some(r"remote\..*\.url"))?;
+    for entry in &entries {
+        let entry = entry?;
+        let (name, url) = match (entry.name(), entry.value()) {
+            (Some(name), Some(url)) => (name, url),
+            _ => continue,
+        };
+        let remote = &name["remote.".len()..name.len() - ".url".len()];
+        if remote != push_remote && push_urls.iter().any(|push_url| push_url == url) {
+            result.push(remote.to_owned());
+        }
+    }
+    Ok(result)
+}
+
+fn get_multivar(config: &GitConfig, key: &str) -> Result<Vec<String>> {
+    let mut result = Vec::new();
+    let entries = match config.multivar(key, None) {
+        Ok(entries) => entries,
+        Err(err) if config_not_exist(&err) => return Ok(result),
+        Err(err) => return Err(err.into()),
+    };
+    for entry in &entries {
+        let entry = entry?;
+        let value = entry.value().context("non utf-8 config value")?;
+        result.push(value.to_owned());
+    }
+    Ok(result)
+}
+
 pub fn get_remote<'a>(repo: &'a Repository, remote_name: &str) -> Result<Option<Remote<'a>>> {
     fn error_is_missing_remote(err: &Error) -> bool {
         err.class() == ErrorClass::Config && err.code() == ErrorCode::InvalidSpec
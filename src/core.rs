@@ -1,19 +1,27 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::convert::TryFrom;
 use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use anyhow::{Context, Result};
-use git2::{BranchType, Config, Repository};
+use git2::{BranchType, Config, ErrorCode, Oid, Repository};
 use log::*;
 use rayon::prelude::*;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-use crate::args::DeleteFilter;
+use crate::args::{DeleteFilter, SortKey};
 use crate::branch::{
-    LocalBranch, Refname, RemoteBranch, RemoteTrackingBranch, RemoteTrackingBranchStatus,
+    BaseCommit, LocalBranch, PullRequestHead, Refname, RemoteBranch, RemoteBranchError,
+    RemoteRefspecs, RemoteTrackingBranch, RemoteTrackingBranchStatus,
 };
-use crate::merge_tracker::MergeTracker;
-use crate::subprocess::{self, get_worktrees, RemoteHead};
+use crate::classification_state::ClassificationState;
+use crate::merge_tracker::{MergeState, MergeTracker};
+use crate::revwalk::{
+    has_only_base_merges, is_merge_reverted, is_merged_by_cherry_pick, patch_id, reverted_commits,
+};
+use crate::simple_glob::glob_match;
+use crate::subprocess::{self, get_worktrees, RemoteHead, TimeoutError};
 use crate::util::get_remotes;
 use crate::util::ForceSendSync;
 use crate::{config, BaseSpec, Git};
@@ -21,11 +29,145 @@ use crate::{config, BaseSpec, Git};
 use indicatif::ParallelProgressIterator;
 use rayon::iter::ParallelIterator;
 
+/// Sorts `items` by `key` of the refs that `refname` gives them.
+/// Ties are ordered by the items themselves, which is by the name for branches,
+/// and the items without a ref come last.
+pub fn sort_by_ref<T: Ord>(
+    repo: &Repository,
+    items: &mut Vec<T>,
+    key: SortKey,
+    refname: impl Fn(&T) -> Option<String>,
+) -> Result<()> {
+    if key == SortKey::Name {
+        items.sort();
+        return Ok(());
+    }
+    let head = repo
+        .head()
+        .ok()
+        .and_then(|head| head.peel_to_commit().ok())
+        .map(|commit| commit.id());
+    let mut keys = Vec::new();
+    for item in items.iter() {
+        let value = match refname(item) {
+            Some(refname) => {
+                let commit = repo.find_reference(&refname)?.peel_to_commit()?;
+                match key {
+                    SortKey::Name => None,
+                    SortKey::Age => Some(commit.committer().when().seconds()),
+                    SortKey::Ahead => match head {
+                        Some(head) => Some(repo.graph_ahead_behind(commit.id(), head)?.0 as i64),
+                        None => None,
+                    },
+                }
+            }
+            None => None,
+        };
+        keys.push(value);
+    }
+    let mut keyed: Vec<_> = keys.into_iter().zip(items.drain(..)).collect();
+    keyed.sort_by(|(a_key, a), (b_key, b)| {
+        (a_key.is_none(), a_key, a).cmp(&(b_key.is_none(), b_key, b))
+    });
+    items.extend(keyed.into_iter().map(|(_, item)| item));
+    Ok(())
+}
+
+/// Whether the index of the worktree differs from its HEAD.
+/// A worktree that can't be opened, like a locked one on a removable disk, is considered clean.
+fn has_staged_changes(path: &str) -> Result<bool> {
+    let repo = match Repository::open(path) {
+        Ok(repo) => repo,
+        Err(err) => {
+            debug!("Cannot open the worktree {}: {}", path, err);
+            return Ok(false);
+        }
+    };
+    let head = match repo.head() {
+        Ok(head) => Some(head.peel_to_tree()?),
+        Err(err) if err.code() == ErrorCode::UnbornBranch => None,
+        Err(err) => return Err(err.into()),
+    };
+    let diff = repo.diff_tree_to_index(head.as_ref(), None, None)?;
+    Ok(diff.deltas().len() > 0)
+}
+
 #[derive(Serialize)]
 pub struct TrimPlan {
     pub skipped: HashMap<String, SkipSuggestion>,
     pub to_delete: HashSet<ClassifiedBranch>,
     pub preserved: Vec<Preserved>,
+    /// Messages of the classifications that led to each branch.
+    #[serde(skip)]
+    pub messages: HashMap<ClassifiedBranch, Vec<&'static str>>,
+    /// Every classification, including the ones of the alive branches that aren't classified to anything.
+    #[serde(skip)]
+    pub classifications: Vec<Classification>,
+    /// Whether the upstream of each merged tracking local branch still exists, by the refname of the local.
+    pub remote_states: HashMap<String, RemoteState>,
+    /// Warnings met while planning, which the callers log as well.
+    /// Absent in the JSON output when there is none, so the schema stays as it was without warnings.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub diagnostics: Vec<Diagnostic>,
+    /// Merged remote tracking branches that are deleted only locally while their remote branches are kept,
+    /// like the change refs of `--gerrit`. They stay in their merged categories.
+    #[serde(skip_serializing_if = "HashSet::is_empty")]
+    pub local_only: HashSet<RemoteTrackingBranch>,
+}
+
+/// A warning of the planning in a form that tools can read, unlike the log lines.
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub message: String,
+    /// The ref or the remote that the diagnostic is about.
+    pub subject: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum DiagnosticKind {
+    /// A ref under `refs/heads/` that isn't classified, like a symbolic ref or a non-utf8 ref.
+    SkippedRef,
+    /// A remote whose heads couldn't be listed in time.
+    RemoteTimedOut,
+    /// libgit2 and `git branch -vv` disagree on whether an upstream is gone.
+    GoneMismatch,
+}
+
+impl Diagnostic {
+    pub fn new(kind: DiagnosticKind, message: String, subject: Option<String>) -> Self {
+        Self {
+            kind,
+            message,
+            subject,
+        }
+    }
+}
+
+/// Whether the remote branch of a merged local branch is already deleted, or is still there to delete.
+#[derive(Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum RemoteState {
+    Deleted,
+    Present,
+}
+
+/// Difference of the branches to delete between two plans.
+pub struct TrimPlanDiff {
+    /// Branches that are deleted only by the other plan.
+    pub added: HashSet<ClassifiedBranch>,
+    /// Branches that are deleted only by this plan.
+    pub removed: HashSet<ClassifiedBranch>,
+}
+
+/// Commits that are reachable only from the local branches to delete.
+/// `git gc` can reclaim them once the reflogs expire.
+pub struct UniqueCommits {
+    /// Sorted by refname.
+    pub per_branch: Vec<(LocalBranch, usize)>,
+    /// Commits shared by multiple deleted branches are counted once.
+    pub total: usize,
 }
 
 #[derive(Serialize)]
@@ -33,9 +175,74 @@ pub struct Preserved {
     pub branch: ClassifiedBranch,
     pub reason: String,
     pub base: bool,
+    /// Only the remote side of `branch` is kept back, and its local is deleted.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub remote_only: bool,
+}
+
+impl Preserved {
+    /// The local that is kept back, if any.
+    pub fn local(&self) -> Option<&LocalBranch> {
+        if self.remote_only {
+            None
+        } else {
+            self.branch.local()
+        }
+    }
 }
 
 impl TrimPlan {
+    /// Why the branch is classified so. Falls back to its category when it isn't classified by a request.
+    pub fn reason(&self, branch: &ClassifiedBranch) -> String {
+        match self.messages.get(branch) {
+            Some(messages) if !messages.is_empty() => messages.join("; "),
+            _ => branch.category().to_owned(),
+        }
+    }
+
+    /// Whether nothing is left to delete after the keep-back and the filtering.
+    pub fn is_empty(&self) -> bool {
+        self.to_delete.is_empty()
+    }
+
+    /// The number of the classified branches to delete.
+    /// A branch can delete both of a local branch and a remote branch.
+    pub fn len(&self) -> usize {
+        self.to_delete.len()
+    }
+
+    /// The number of the local branches to delete.
+    pub fn local_len(&self) -> usize {
+        self.to_delete
+            .iter()
+            .filter(|branch| branch.local().is_some())
+            .count()
+    }
+
+    /// The number of the remote branches and the remote tracking branches to delete.
+    pub fn remote_len(&self) -> usize {
+        self.to_delete
+            .iter()
+            .filter(|branch| {
+                branch.upstream().is_some()
+                    || matches!(
+                        branch,
+                        ClassifiedBranch::MergedDirectFetch { .. }
+                            | ClassifiedBranch::DivergedDirectFetch { .. }
+                    )
+            })
+            .count()
+    }
+
+    /// The number of the branches to delete per `ClassifiedBranch::category`.
+    pub fn len_by_category(&self) -> BTreeMap<&'static str, usize> {
+        let mut result = BTreeMap::new();
+        for branch in &self.to_delete {
+            *result.entry(branch.category()).or_insert(0) += 1;
+        }
+        result
+    }
+
     pub fn locals_to_delete(&self) -> Vec<&LocalBranch> {
         let mut result = Vec::new();
         for branch in &self.to_delete {
@@ -46,16 +253,189 @@ impl TrimPlan {
         result
     }
 
+    /// Stale remote tracking branches. They are deleted only from the local repository.
+    pub fn stale_remote_trackings_to_delete(&self) -> Vec<&RemoteTrackingBranch> {
+        let mut result = Vec::new();
+        for branch in &self.to_delete {
+            if let ClassifiedBranch::StaleRemoteTracking(upstream) = branch {
+                result.push(upstream)
+            }
+        }
+        result.sort();
+        result
+    }
+
+    /// The remote tracking branch of `branch` when it is deleted only from the local repository,
+    /// either a stale one or one in `local_only`.
+    pub fn deleted_locally<'b>(
+        &self,
+        branch: &'b ClassifiedBranch,
+    ) -> Option<&'b RemoteTrackingBranch> {
+        match branch {
+            ClassifiedBranch::StaleRemoteTracking(upstream) => Some(upstream),
+            _ => branch
+                .upstream()
+                .filter(|upstream| self.local_only.contains(*upstream)),
+        }
+    }
+
+    /// Remote tracking branches that are deleted only from the local repository, sorted by refname.
+    pub fn remote_trackings_to_delete_locally(&self) -> Vec<&RemoteTrackingBranch> {
+        let mut result: Vec<_> = self
+            .to_delete
+            .iter()
+            .filter_map(|branch| self.deleted_locally(branch))
+            .collect();
+        result.sort();
+        result
+    }
+
     pub fn remotes_to_delete(&self, repo: &Repository) -> Result<Vec<RemoteBranch>> {
         let mut result = Vec::new();
         let remotes = get_remotes(&repo)?;
         for branch in &self.to_delete {
+            if self.deleted_locally(branch).is_some() {
+                continue;
+            }
             if let Some(remote) = branch.remote(&remotes)? {
                 result.push(remote);
             }
         }
         Ok(result)
     }
+
+    pub fn diff(&self, other: &TrimPlan) -> TrimPlanDiff {
+        TrimPlanDiff {
+            added: other
+                .to_delete
+                .difference(&self.to_delete)
+                .cloned()
+                .collect(),
+            removed: self
+                .to_delete
+                .difference(&other.to_delete)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Count the commits of the local branches to delete, which are not reachable from any remaining ref.
+    /// It must be called before the deletion.
+    pub fn unique_commits(&self, repo: &Repository) -> Result<UniqueCommits> {
+        let locals = self.sorted_locals_to_delete();
+        let mut removed = HashSet::new();
+        for local in &locals {
+            removed.insert(local.refname.as_str());
+        }
+        for branch in &self.to_delete {
+            if let Some(upstream) = branch.upstream() {
+                removed.insert(upstream.refname.as_str());
+            }
+        }
+
+        let mut remaining = Vec::new();
+        for reference in repo.references()? {
+            let reference = reference?;
+            let refname = reference.name().context("non utf-8 refname")?;
+            if removed.contains(refname) {
+                continue;
+            }
+            // Refs to trees or blobs don't retain any commits.
+            if let Ok(commit) = reference.peel_to_commit() {
+                remaining.push(commit.id());
+            }
+        }
+
+        let count = |locals: &[&LocalBranch]| -> Result<usize> {
+            let mut revwalk = repo.revwalk()?;
+            for local in locals {
+                revwalk.push_ref(&local.refname)?;
+            }
+            for oid in &remaining {
+                revwalk.hide(*oid)?;
+            }
+            Ok(revwalk.count())
+        };
+
+        let mut per_branch = Vec::new();
+        for local in &locals {
+            per_branch.push(((*local).clone(), count(&[local])?));
+        }
+        Ok(UniqueCommits {
+            per_branch,
+            total: count(&locals)?,
+        })
+    }
+
+    /// `None` unless the local branch is a merged tracking branch.
+    pub fn remote_state(&self, local: &LocalBranch) -> Option<RemoteState> {
+        self.remote_states.get(&local.refname).copied()
+    }
+
+    /// Same as `locals_to_delete`, but sorted by refname for a reproducible output.
+    pub fn sorted_locals_to_delete(&self) -> Vec<&LocalBranch> {
+        let mut result = self.locals_to_delete();
+        result.sort();
+        result
+    }
+
+    /// Same as `remotes_to_delete`, but sorted by remote name and refname for a reproducible output.
+    pub fn sorted_remotes_to_delete(&self, repo: &Repository) -> Result<Vec<RemoteBranch>> {
+        let mut result = self.remotes_to_delete(repo)?;
+        result.sort();
+        Ok(result)
+    }
+
+    /// Same as `sorted_locals_to_delete`, but sorted by `key` for `--sort`.
+    pub fn sorted_locals_to_delete_by(
+        &self,
+        repo: &Repository,
+        key: SortKey,
+    ) -> Result<Vec<&LocalBranch>> {
+        let mut result = self.locals_to_delete();
+        sort_by_ref(repo, &mut result, key, |local| Some(local.refname.clone()))?;
+        Ok(result)
+    }
+
+    /// Same as `sorted_remotes_to_delete`, but sorted by `key` for `--sort`.
+    /// The remote tracking branches give the keys, so direct fetched branches come last.
+    pub fn sorted_remotes_to_delete_by(
+        &self,
+        repo: &Repository,
+        key: SortKey,
+    ) -> Result<Vec<RemoteBranch>> {
+        let mut result = self.remotes_to_delete(repo)?;
+        let mut refnames = HashMap::new();
+        for remote in &result {
+            if let RemoteTrackingBranchStatus::Exists(upstream) =
+                RemoteTrackingBranch::from_remote_branch(repo, remote)?
+            {
+                refnames.insert(remote.clone(), upstream.refname);
+            }
+        }
+        sort_by_ref(repo, &mut result, key, |remote| {
+            refnames.get(remote).cloned()
+        })?;
+        Ok(result)
+    }
+
+    /// Stray local branches sorted by refname, whether they are deleted or kept back.
+    /// Bases are not counted.
+    pub fn stray_locals(&self) -> Vec<&LocalBranch> {
+        let mut result = Vec::new();
+        let preserved = self
+            .preserved
+            .iter()
+            .filter(|preserved| !preserved.base)
+            .map(|preserved| &preserved.branch);
+        for branch in self.to_delete.iter().chain(preserved) {
+            if let ClassifiedBranch::Stray(local) = branch {
+                result.push(local);
+            }
+        }
+        result.sort();
+        result
+    }
 }
 
 impl TrimPlan {
@@ -69,9 +449,13 @@ impl TrimPlan {
             repo: &Repository,
             config: &Config,
             base_specs: &[BaseSpec],
+            ignore_case: bool,
             local: &LocalBranch,
         ) -> Result<Option<String>> {
-            if base_specs.iter().any(|spec| spec.is_local(local)) {
+            if base_specs
+                .iter()
+                .any(|spec| spec.is_local(local, ignore_case))
+            {
                 return Ok(Some("base".to_owned()));
             }
 
@@ -79,7 +463,7 @@ impl TrimPlan {
                 RemoteTrackingBranchStatus::Exists(upstream) => {
                     if let Some(pattern) = base_specs
                         .iter()
-                        .find_map(|spec| spec.remote_pattern(upstream.refname()))
+                        .find_map(|spec| spec.remote_pattern(upstream.refname(), ignore_case))
                     {
                         return Ok(Some(format!("tracks base `{}`", pattern)));
                     }
@@ -87,7 +471,7 @@ impl TrimPlan {
                 RemoteTrackingBranchStatus::Gone(upstream) => {
                     if let Some(pattern) = base_specs
                         .iter()
-                        .find_map(|spec| spec.remote_pattern(&upstream))
+                        .find_map(|spec| spec.remote_pattern(&upstream, ignore_case))
                     {
                         return Ok(Some(format!("tracked base `{}`", pattern)));
                     }
@@ -97,6 +481,7 @@ impl TrimPlan {
             Ok(None)
         }
 
+        let ignore_case = config::get_ignore_case(config)?;
         let mut preserve = Vec::new();
         for branch in &self.to_delete {
             match &branch {
@@ -105,51 +490,59 @@ impl TrimPlan {
                 | ClassifiedBranch::MergedDirectFetch { local, .. }
                 | ClassifiedBranch::DivergedDirectFetch { local, .. }
                 | ClassifiedBranch::MergedNonTrackingLocal(local) => {
-                    if let Some(reason) = local_is_or_tracks_base(repo, config, base_specs, local)?
+                    if let Some(reason) =
+                        local_is_or_tracks_base(repo, config, base_specs, ignore_case, local)?
                     {
                         preserve.push(Preserved {
                             branch: branch.clone(),
                             reason,
                             base: true,
+                            remote_only: false,
                         });
                         continue;
                     }
                 }
                 ClassifiedBranch::MergedRemoteTracking(upstream)
-                | ClassifiedBranch::MergedNonUpstreamRemoteTracking(upstream) => {
+                | ClassifiedBranch::MergedNonUpstreamRemoteTracking(upstream)
+                | ClassifiedBranch::StaleRemoteTracking(upstream) => {
                     if base_specs
                         .iter()
-                        .any(|spec| spec.covers_remote(upstream.refname()))
+                        .any(|spec| spec.covers_remote(upstream.refname(), ignore_case))
                     {
                         preserve.push(Preserved {
                             branch: branch.clone(),
                             reason: "base".to_owned(),
                             base: true,
+                            remote_only: false,
                         });
                         continue;
                     }
                 }
                 ClassifiedBranch::DivergedRemoteTracking { local, upstream } => {
-                    if let Some(reason) = local_is_or_tracks_base(repo, config, base_specs, local)?
+                    if let Some(reason) =
+                        local_is_or_tracks_base(repo, config, base_specs, ignore_case, local)?
                     {
                         preserve.push(Preserved {
                             branch: branch.clone(),
                             reason,
                             base: true,
+                            remote_only: false,
                         });
                         continue;
                     } else if base_specs
                         .iter()
-                        .any(|spec| spec.covers_remote(upstream.refname()))
+                        .any(|spec| spec.covers_remote(upstream.refname(), ignore_case))
                     {
                         preserve.push(Preserved {
                             branch: branch.clone(),
                             reason: "base".to_owned(),
                             base: true,
+                            remote_only: false,
                         });
                         continue;
                     }
                 }
+                ClassifiedBranch::BaseLocal(_) | ClassifiedBranch::BaseRemoteTracking(_) => {}
             };
         }
 
@@ -164,34 +557,19 @@ impl TrimPlan {
     pub fn preserve_protected(
         &mut self,
         repo: &Repository,
+        config: &Config,
         preserved_patterns: &[&str],
     ) -> Result<()> {
+        let ignore_case = config::get_ignore_case(config)?;
         let mut preserve = Vec::new();
         for branch in &self.to_delete {
-            let pattern =
-                match &branch {
-                    ClassifiedBranch::MergedLocal(local)
-                    | ClassifiedBranch::Stray(local)
-                    | ClassifiedBranch::MergedDirectFetch { local, .. }
-                    | ClassifiedBranch::DivergedDirectFetch { local, .. }
-                    | ClassifiedBranch::MergedNonTrackingLocal(local) => {
-                        get_protect_pattern(&repo, preserved_patterns, local)?
-                    }
-                    ClassifiedBranch::MergedRemoteTracking(upstream)
-                    | ClassifiedBranch::MergedNonUpstreamRemoteTracking(upstream) => {
-                        get_protect_pattern(&repo, preserved_patterns, upstream)?
-                    }
-                    ClassifiedBranch::DivergedRemoteTracking { local, upstream } => {
-                        get_protect_pattern(&repo, preserved_patterns, local)?
-                            .or(get_protect_pattern(&repo, preserved_patterns, upstream)?)
-                    }
-                };
-
+            let pattern = get_matching_pattern(repo, preserved_patterns, ignore_case, branch)?;
             if let Some(pattern) = pattern {
                 preserve.push(Preserved {
                     branch: branch.clone(),
                     reason: format!("protected by a pattern `{}`", pattern),
                     base: false,
+                    remote_only: false,
                 });
             }
         }
@@ -204,26 +582,27 @@ impl TrimPlan {
         Ok(())
     }
 
-    /// `hub-cli` can checkout pull request branch. However they are stored in `refs/pulls/`.
-    /// This prevents to remove them.
-    pub fn preserve_non_heads_remotes(&mut self, repo: &Repository) -> Result<()> {
+    /// Keep branches whose tips are one of the `commits`, whatever their names are.
+    pub fn preserve_protected_commits(&mut self, repo: &Repository, commits: &[Oid]) -> Result<()> {
         let mut preserve = Vec::new();
-
-        let remotes = get_remotes(&repo)?;
         for branch in &self.to_delete {
-            let remote = if let Some(remote) = branch.remote(&remotes)? {
-                remote
-            } else {
-                continue;
-            };
-
-            if !remote.refname.starts_with("refs/heads/") {
-                trace!("filter-out: remote ref {}", remote);
-                preserve.push(Preserved {
-                    branch: branch.clone(),
-                    reason: "a non-heads remote".to_owned(),
-                    base: false,
-                });
+            let refnames = branch
+                .local()
+                .map(|local| &local.refname)
+                .into_iter()
+                .chain(branch.upstream().map(|upstream| &upstream.refname));
+            for refname in refnames {
+                let tip = repo.find_reference(refname)?.peel_to_commit()?.id();
+                if commits.contains(&tip) {
+                    trace!("filter-out: {} is at a protected commit {}", refname, tip);
+                    preserve.push(Preserved {
+                        branch: branch.clone(),
+                        reason: "tip matches protected commit".to_owned(),
+                        base: false,
+                        remote_only: false,
+                    });
+                    break;
+                }
             }
         }
 
@@ -235,20 +614,42 @@ impl TrimPlan {
         Ok(())
     }
 
-    pub fn preserve_worktree(&mut self, repo: &Repository) -> Result<()> {
-        let worktrees = get_worktrees(repo)?;
+    /// Keep branches that are merged into `target` as well, like a staging branch.
+    /// `name` is how the user gave the target. The merge checks are the same as `merge_tracker`'s.
+    pub fn preserve_merged_into(
+        &mut self,
+        repo: &Repository,
+        config: &Config,
+        merge_tracker: &MergeTracker,
+        name: &str,
+        target: &str,
+    ) -> Result<()> {
+        let target = BaseCommit {
+            oid: repo.revparse_single(target)?.peel_to_commit()?.id(),
+            name: target.to_owned(),
+        };
+        let merge_tracker =
+            merge_tracker.for_other_bases(repo, config, std::slice::from_ref(&target))?;
         let mut preserve = Vec::new();
         for branch in &self.to_delete {
-            let local = if let Some(local) = branch.local() {
-                local
-            } else {
-                continue;
-            };
-            if let Some(path) = worktrees.get(local) {
+            let mut merged = false;
+            if let Some(local) = branch.local() {
+                merged |= merge_tracker
+                    .check_and_track(repo, target.oid, local)?
+                    .merged;
+            }
+            if let Some(upstream) = branch.upstream() {
+                merged |= merge_tracker
+                    .check_and_track(repo, target.oid, upstream)?
+                    .merged;
+            }
+            if merged {
+                trace!("filter-out: {:?} is merged into {}", branch, name);
                 preserve.push(Preserved {
                     branch: branch.clone(),
-                    reason: format!("worktree at {}", path),
+                    reason: format!("still merged into {}", name),
                     base: false,
+                    remote_only: false,
                 });
             }
         }
@@ -261,87 +662,32 @@ impl TrimPlan {
         Ok(())
     }
 
-    pub fn apply_delete_range_filter(
+    /// Keep branches that match `--exclude` patterns regardless of the delete filter.
+    pub fn preserve_excluded(
         &mut self,
         repo: &Repository,
-        filter: &DeleteFilter,
+        config: &Config,
+        exclude_patterns: &[&str],
     ) -> Result<()> {
+        let ignore_case = config::get_ignore_case(config)?;
+        let remotes = get_remotes(repo)?;
         let mut preserve = Vec::new();
-
-        let remotes = get_remotes(&repo)?;
-
         for branch in &self.to_delete {
-            let range = match branch {
-                ClassifiedBranch::MergedLocal(_) => {
-                    if !filter.delete_merged_local() {
-                        Some("merged-local".to_owned())
-                    } else {
-                        None
-                    }
-                }
-                ClassifiedBranch::Stray(_) => {
-                    if !filter.delete_stray() {
-                        Some("stray".to_owned())
-                    } else {
-                        None
-                    }
-                }
-                ClassifiedBranch::MergedRemoteTracking(upstream) => {
-                    let remote = upstream.to_remote_branch(&remotes)?;
-                    if !filter.delete_merged_remote(&remote.remote) {
-                        Some(format!("merged-remote:{}", &remote.remote))
-                    } else {
-                        None
-                    }
-                }
-                ClassifiedBranch::DivergedRemoteTracking { upstream, .. } => {
-                    let remote = upstream.to_remote_branch(&remotes)?;
-                    if !filter.delete_diverged(&remote.remote) {
-                        Some(format!("diverged:{}", &remote.remote))
-                    } else {
-                        None
-                    }
-                }
-
-                ClassifiedBranch::MergedDirectFetch { remote, .. } => {
-                    if !filter.delete_merged_remote(&remote.remote) {
-                        Some(format!("merged-remote:{}", &remote.remote))
-                    } else {
-                        None
-                    }
-                }
-                ClassifiedBranch::DivergedDirectFetch { remote, .. } => {
-                    if !filter.delete_diverged(&remote.remote) {
-                        Some(format!("diverged:{}", &remote.remote))
-                    } else {
-                        None
-                    }
-                }
-
-                ClassifiedBranch::MergedNonTrackingLocal(_) => {
-                    if !filter.delete_merged_non_tracking_local() {
-                        Some("local".to_owned())
-                    } else {
-                        None
-                    }
-                }
-                ClassifiedBranch::MergedNonUpstreamRemoteTracking(upstream) => {
-                    let remote = upstream.to_remote_branch(&remotes)?;
-                    if !filter.delete_merged_non_upstream_remote_tracking(&remote.remote) {
-                        Some(format!("remote:{}", &remote.remote))
-                    } else {
-                        None
-                    }
-                }
+            // `keep/*` excludes `origin/keep/*` too.
+            let remote_matches = match branch.remote(&remotes)? {
+                Some(remote) => exclude_patterns
+                    .iter()
+                    .any(|pattern| glob_match(pattern, remote.short_name(), ignore_case)),
+                None => false,
             };
-
-            trace!("Delete range result: {:?} => {:?}", branch, range);
-
-            if let Some(range) = range {
+            if remote_matches
+                || get_matching_pattern(repo, exclude_patterns, ignore_case, branch)?.is_some()
+            {
                 preserve.push(Preserved {
                     branch: branch.clone(),
-                    reason: format!("delete range `{}` was not given", range),
+                    reason: "matched exclude pattern".to_owned(),
                     base: false,
+                    remote_only: false,
                 });
             }
         }
@@ -354,22 +700,29 @@ impl TrimPlan {
         Ok(())
     }
 
-    pub fn adjust_not_to_detach(&mut self, repo: &Repository) -> Result<()> {
-        if repo.head_detached()? {
-            return Ok(());
-        }
-        let head = repo.head()?;
-        let head_name = head.name().context("non-utf8 head ref name")?;
-        let head_branch = LocalBranch::new(head_name);
-
+    /// Keep branches that don't share the prefix of the HEAD branch up to the last `/` or `-`,
+    /// e.g. `user/feature-` of `user/feature-3`, for stacked pull requests.
+    pub fn preserve_outside_stack(&mut self, repo: &Repository) -> Result<()> {
+        let prefix = get_stack_prefix(repo)?;
+        let remotes = get_remotes(repo)?;
         let mut preserve = Vec::new();
-
         for branch in &self.to_delete {
-            if branch.local() == Some(&head_branch) {
+            let name = if let Some(local) = branch.local() {
+                local.short_name().to_owned()
+            } else if let Some(remote) = branch.remote(&remotes)? {
+                remote.short_name().to_owned()
+            } else if let Some(upstream) = branch.upstream() {
+                upstream.short_name().to_owned()
+            } else {
+                continue;
+            };
+            if !name.starts_with(&prefix) {
+                trace!("filter-out: {} is outside the stack {}", name, prefix);
                 preserve.push(Preserved {
                     branch: branch.clone(),
-                    reason: "HEAD".to_owned(),
+                    reason: format!("outside the stack `{}`", prefix),
                     base: false,
+                    remote_only: false,
                 });
             }
         }
@@ -378,21 +731,992 @@ impl TrimPlan {
             self.to_delete.remove(&preserved.branch);
         }
         self.preserved.extend(preserve);
-        Ok(())
-    }
 
-    pub fn get_preserved_local(&self, target: &LocalBranch) -> Option<&Preserved> {
-        for preserved in &self.preserved {
-            if preserved.branch.local() == Some(target) {
-                return Some(preserved);
-            }
-        }
-        None
+        Ok(())
     }
 
-    pub fn get_preserved_upstream(&self, target: &RemoteTrackingBranch) -> Option<&Preserved> {
-        for preserved in &self.preserved {
-            if preserved.branch.upstream() == Some(target) {
+    /// Keep local branches whose fetch or push upstream is protected.
+    pub fn preserve_protected_upstream(
+        &mut self,
+        repo: &Repository,
+        config: &Config,
+        preserved_patterns: &[&str],
+    ) -> Result<()> {
+        let ignore_case = config::get_ignore_case(config)?;
+        let mut preserve = Vec::new();
+        for branch in &self.to_delete {
+            let local = if let Some(local) = branch.local() {
+                local
+            } else {
+                continue;
+            };
+
+            for upstream in &[
+                local.fetch_upstream(repo, config)?,
+                local.push_upstream(repo, config)?,
+            ] {
+                let upstream = if let RemoteTrackingBranchStatus::Exists(upstream) = upstream {
+                    upstream
+                } else {
+                    continue;
+                };
+                if get_protect_pattern(repo, preserved_patterns, ignore_case, upstream)?.is_some() {
+                    trace!("filter-out: upstream {} is protected", upstream.refname);
+                    preserve.push(Preserved {
+                        branch: branch.clone(),
+                        reason: "upstream is protected".to_owned(),
+                        base: false,
+                        remote_only: false,
+                    });
+                    break;
+                }
+            }
+        }
+
+        for preserved in &preserve {
+            self.to_delete.remove(&preserved.branch);
+        }
+        self.preserved.extend(preserve);
+
+        Ok(())
+    }
+
+    /// Remote tracking branches that are not covered by any fetch refspec might be stale.
+    /// We don't know their real state, so they are not deleted.
+    pub fn preserve_outside_fetch_refspec(&mut self, repo: &Repository) -> Result<()> {
+        let mut preserve = Vec::new();
+
+        let remotes = get_remotes(repo)?;
+        for branch in &self.to_delete {
+            let upstream = if let Some(upstream) = branch.upstream() {
+                upstream
+            } else {
+                continue;
+            };
+
+            match upstream.to_remote_branch(&remotes) {
+                Ok(_) => {}
+                Err(RemoteBranchError::RemoteNotFound) => {
+                    trace!("filter-out: outside fetch refspec {}", upstream.refname);
+                    preserve.push(Preserved {
+                        branch: branch.clone(),
+                        reason: "outside fetch refspec".to_owned(),
+                        base: false,
+                        remote_only: false,
+                    });
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        for preserved in &preserve {
+            self.to_delete.remove(&preserved.branch);
+        }
+        self.preserved.extend(preserve);
+
+        Ok(())
+    }
+
+    /// Someone might have pushed to the upstream of a merged local branch directly.
+    /// Keep such an upstream when it has commits that are in neither the local nor the base,
+    /// while the local branch is still deleted.
+    pub fn preserve_remote_ahead(&mut self, repo: &Repository) -> Result<()> {
+        let mut preserve = Vec::new();
+        for branch in &self.to_delete {
+            if let ClassifiedBranch::DivergedRemoteTracking { local, upstream } = branch {
+                // The upstream is not merged into the base already.
+                if !is_merged_by_cherry_pick(repo, &local.refname, &upstream.refname)? {
+                    trace!(
+                        "filter-out: {} is ahead of {}",
+                        upstream.refname,
+                        local.refname
+                    );
+                    preserve.push(Preserved {
+                        branch: branch.clone(),
+                        reason: "remote has commits not in local or base".to_owned(),
+                        base: false,
+                        remote_only: false,
+                    });
+                }
+            }
+        }
+
+        self.preserve_remote_sides(preserve);
+
+        Ok(())
+    }
+
+    /// Keeps back only the remote sides of the branches, and deletes their locals as merged ones.
+    /// The branches without locals are kept back as a whole.
+    fn preserve_remote_sides(&mut self, preserve: Vec<Preserved>) {
+        for preserved in preserve {
+            self.to_delete.remove(&preserved.branch);
+            if let Some(local) = preserved.branch.local() {
+                self.to_delete
+                    .insert(ClassifiedBranch::MergedLocal(local.clone()));
+            }
+            self.preserved.push(Preserved {
+                remote_only: preserved.branch.local().is_some(),
+                ..preserved
+            });
+        }
+    }
+
+    /// A merged branch that an unmerged local branch is built on is kept with it.
+    /// Unmerged ones are the local branches that aren't deleted, whose tips aren't in any of the bases.
+    pub fn preserve_ancestors_of_active(
+        &mut self,
+        repo: &Repository,
+        base_tips: &[Oid],
+    ) -> Result<()> {
+        let deleted: HashSet<_> = self
+            .to_delete
+            .iter()
+            .filter_map(|branch| branch.local())
+            .collect();
+        let mut active_tips = Vec::new();
+        for branch in repo.branches(Some(BranchType::Local))? {
+            let (branch, _) = branch?;
+            if branch.get().symbolic_target_bytes().is_some() {
+                continue;
+            }
+            let local = match branch.get().name().and_then(LocalBranch::from_refname) {
+                Some(local) => local,
+                None => continue,
+            };
+            if deleted.contains(&local) {
+                continue;
+            }
+            let tip = branch.get().peel_to_commit()?.id();
+            let mut merged = false;
+            for base in base_tips {
+                if *base == tip || repo.graph_descendant_of(*base, tip)? {
+                    merged = true;
+                    break;
+                }
+            }
+            if !merged {
+                active_tips.push(tip);
+            }
+        }
+
+        let mut preserve = Vec::new();
+        for branch in &self.to_delete {
+            let local = match branch.local() {
+                Some(local) => local,
+                None => continue,
+            };
+            let tip = repo.find_reference(&local.refname)?.peel_to_commit()?.id();
+            for active in &active_tips {
+                if *active == tip || repo.graph_descendant_of(*active, tip)? {
+                    preserve.push(Preserved {
+                        branch: branch.clone(),
+                        reason: "ancestor of active branch".to_owned(),
+                        base: false,
+                        remote_only: false,
+                    });
+                    break;
+                }
+            }
+        }
+
+        for preserved in &preserve {
+            self.to_delete.remove(&preserved.branch);
+        }
+        self.preserved.extend(preserve);
+        Ok(())
+    }
+
+    /// A merged branch might have been merged and reverted on a base later.
+    /// Its commits are still in the base, but the changes are not, so keep it.
+    pub fn preserve_reverted(&mut self, repo: &Repository, base_tips: &[Oid]) -> Result<()> {
+        let mut reverted = HashSet::new();
+        for base in base_tips {
+            reverted.extend(reverted_commits(repo, *base)?);
+        }
+        if reverted.is_empty() {
+            return Ok(());
+        }
+
+        let mut preserve = Vec::new();
+        for branch in &self.to_delete {
+            let refname = match branch {
+                ClassifiedBranch::Stray(_) | ClassifiedBranch::StaleRemoteTracking(_) => continue,
+                _ => match (branch.local(), branch.upstream()) {
+                    (Some(local), _) => &local.refname,
+                    (None, Some(upstream)) => &upstream.refname,
+                    (None, None) => continue,
+                },
+            };
+            let tip = repo.find_reference(refname)?.peel_to_commit()?.id();
+            if is_merge_reverted(repo, &reverted, tip)? {
+                trace!("filter-out: the merge of {} was reverted", refname);
+                preserve.push(Preserved {
+                    branch: branch.clone(),
+                    reason: "merge was reverted".to_owned(),
+                    base: false,
+                    remote_only: false,
+                });
+            }
+        }
+
+        for preserved in &preserve {
+            self.to_delete.remove(&preserved.branch);
+        }
+        self.preserved.extend(preserve);
+
+        Ok(())
+    }
+
+    /// A merged remote branch might have got more commits after its merge, e.g. in a fork.
+    /// Keep the remote branches that have more than `limit` commits which aren't in any of the bases.
+    /// The commits in the locals that track them are merged already, like the squash merged ones.
+    /// Their local branches are still deleted.
+    pub fn preserve_remote_ahead_of_bases(
+        &mut self,
+        repo: &Repository,
+        config: &Config,
+        bases: &[BaseCommit],
+        limit: usize,
+    ) -> Result<()> {
+        let base_oids: Vec<_> = bases.iter().map(|base| base.oid).collect();
+
+        // The tips of the locals by the refnames of their upstreams.
+        let mut local_tips = HashMap::new();
+        for local in repo.branches(Some(BranchType::Local))? {
+            let (local, _) = local?;
+            let tip = match local.get().target() {
+                Some(tip) => tip,
+                None => continue,
+            };
+            let local = match local.get().name() {
+                Some(refname) => LocalBranch::new(refname),
+                None => continue,
+            };
+            let mut upstreams = local.push_upstreams(repo, config)?;
+            if let RemoteTrackingBranchStatus::Exists(upstream) =
+                local.fetch_upstream(repo, config)?
+            {
+                upstreams.push(upstream);
+            }
+            for upstream in upstreams {
+                local_tips
+                    .entry(upstream.refname)
+                    .or_insert_with(Vec::new)
+                    .push(tip);
+            }
+        }
+
+        let mut preserve = Vec::new();
+        for branch in &self.to_delete {
+            let upstream = match branch {
+                ClassifiedBranch::MergedRemoteTracking(upstream)
+                | ClassifiedBranch::MergedNonUpstreamRemoteTracking(upstream)
+                | ClassifiedBranch::DivergedRemoteTracking { upstream, .. } => upstream,
+                _ => continue,
+            };
+            let tip = repo
+                .find_reference(&upstream.refname)?
+                .peel_to_commit()?
+                .id();
+            // It is merged into one of the bases, so compare with the closest one.
+            let mut ahead = Vec::new();
+            for base in &base_oids {
+                let mut revwalk = repo.revwalk()?;
+                revwalk.push(tip)?;
+                revwalk.hide(*base)?;
+                for local_tip in local_tips.get(&upstream.refname).into_iter().flatten() {
+                    revwalk.hide(*local_tip)?;
+                }
+                let mut count = 0;
+                for oid in revwalk {
+                    oid?;
+                    count += 1;
+                }
+                ahead.push(count);
+            }
+            if let Some(ahead) = ahead.into_iter().min().filter(|ahead| *ahead > limit) {
+                trace!(
+                    "filter-out: {} is {} commit(s) ahead of the bases",
+                    upstream.refname,
+                    ahead
+                );
+                preserve.push(Preserved {
+                    branch: branch.clone(),
+                    reason: format!("remote is {} commit(s) ahead of the base", ahead),
+                    base: false,
+                    remote_only: false,
+                });
+            }
+        }
+
+        self.preserve_remote_sides(preserve);
+
+        Ok(())
+    }
+
+    /// Keeps back the remote branches that aren't merged, which would be deleted along with their merged locals.
+    /// The locals are still deleted. It is applied after the delete filter,
+    /// so that the locals are filtered by the categories of their branches.
+    pub fn preserve_stray_remotes(&mut self) {
+        let mut preserve = Vec::new();
+        for branch in &self.to_delete {
+            if matches!(
+                branch,
+                ClassifiedBranch::DivergedRemoteTracking { .. }
+                    | ClassifiedBranch::DivergedDirectFetch { .. }
+            ) {
+                trace!("filter-out: {:?} is a stray remote", branch);
+                preserve.push(Preserved {
+                    branch: branch.clone(),
+                    reason: "stray remote deletion disabled".to_owned(),
+                    base: false,
+                    remote_only: false,
+                });
+            }
+        }
+
+        self.preserve_remote_sides(preserve);
+    }
+
+    /// Keep back the upstreams that aren't merged while the other upstream of the same local is,
+    /// see `classify_disagreeing_upstreams`.
+    pub fn preserve_disagreeing_upstreams(&mut self) {
+        let mut preserve = Vec::new();
+        for branch in &self.to_delete {
+            if !matches!(branch, ClassifiedBranch::DivergedRemoteTracking { .. }) {
+                continue;
+            }
+            let messages = match self.messages.get(branch) {
+                Some(messages) => messages,
+                None => continue,
+            };
+            let reason = if messages.contains(&FETCH_UPSTREAM_MERGED_ONLY) {
+                "not merged, while the fetch upstream is"
+            } else if messages.contains(&PUSH_UPSTREAM_MERGED_ONLY) {
+                "not merged, while a push upstream is"
+            } else {
+                continue;
+            };
+            preserve.push(Preserved {
+                branch: branch.clone(),
+                reason: reason.to_owned(),
+                base: false,
+                remote_only: false,
+            });
+        }
+
+        for preserved in &preserve {
+            self.to_delete.remove(&preserved.branch);
+        }
+        self.preserved.extend(preserve);
+    }
+
+    /// Keeps back the local and the upstream when the upstream is force-updated before it is merged.
+    /// The local might have commits that are lost by the force-update.
+    pub fn preserve_force_updated_upstreams(&mut self) {
+        let mut preserve = Vec::new();
+        for branch in &self.to_delete {
+            match self.messages.get(branch) {
+                Some(messages) if messages.contains(&UPSTREAM_FORCE_UPDATED) => {}
+                _ => continue,
+            }
+            preserve.push(Preserved {
+                branch: branch.clone(),
+                reason: UPSTREAM_FORCE_UPDATED.to_owned(),
+                base: false,
+                remote_only: false,
+            });
+        }
+
+        for preserved in &preserve {
+            self.to_delete.remove(&preserved.branch);
+        }
+        self.preserved.extend(preserve);
+    }
+
+    /// Keeps back the push upstreams of the branches whose fetch upstreams are gone.
+    /// The locals are left to delete.
+    pub fn preserve_push_only_upstreams(&mut self) {
+        let mut preserve = Vec::new();
+        for branch in &self.to_delete {
+            if !matches!(
+                branch,
+                ClassifiedBranch::MergedRemoteTracking(_)
+                    | ClassifiedBranch::DivergedRemoteTracking { .. }
+            ) {
+                continue;
+            }
+            match self.messages.get(branch) {
+                Some(messages) if messages.contains(&PUSH_UPSTREAM_ONLY) => {}
+                _ => continue,
+            }
+            preserve.push(Preserved {
+                branch: branch.clone(),
+                reason: "merged status is inferred from the push upstream only".to_owned(),
+                base: false,
+                remote_only: false,
+            });
+        }
+
+        self.preserve_remote_sides(preserve);
+    }
+
+    /// `hub-cli` can checkout pull request branch. However they are stored in `refs/pulls/`.
+    /// This prevents to remove them.
+    /// With `gerrit`, the merged remote tracking refs of Gerrit changes are deleted only locally instead.
+    /// Abandoned changes are out of scope, since only the Gerrit server knows about them.
+    pub fn preserve_non_heads_remotes(&mut self, repo: &Repository, gerrit: bool) -> Result<()> {
+        let mut preserve = Vec::new();
+        let mut local_only = Vec::new();
+
+        let remotes = get_remotes(&repo)?;
+        for branch in &self.to_delete {
+            let remote = if let Some(remote) = branch.remote(&remotes)? {
+                remote
+            } else {
+                continue;
+            };
+
+            if gerrit && remote.refname.starts_with(GERRIT_CHANGES_PREFIX) {
+                if let ClassifiedBranch::MergedRemoteTracking(upstream)
+                | ClassifiedBranch::MergedNonUpstreamRemoteTracking(upstream) = branch
+                {
+                    trace!("local only: Gerrit change ref {}", remote);
+                    local_only.push((branch.clone(), upstream.clone()));
+                    continue;
+                }
+            }
+            if !remote.refname.starts_with("refs/heads/") {
+                trace!("filter-out: remote ref {}", remote);
+                preserve.push(Preserved {
+                    branch: branch.clone(),
+                    reason: "a non-heads remote".to_owned(),
+                    base: false,
+                    remote_only: false,
+                });
+            }
+        }
+
+        for (branch, upstream) in local_only {
+            self.messages
+                .entry(branch)
+                .or_default()
+                .push("merged Gerrit change, deleted locally");
+            self.local_only.insert(upstream);
+        }
+        for preserved in &preserve {
+            self.to_delete.remove(&preserved.branch);
+        }
+        self.preserved.extend(preserve);
+
+        Ok(())
+    }
+
+    /// Only the push remote of each branch, the one you own, is trimmed.
+    /// Remote tracking branches without a local branch use `remote.pushDefault` or `origin`.
+    pub fn preserve_fetch_only_remotes(
+        &mut self,
+        repo: &Repository,
+        config: &Config,
+    ) -> Result<()> {
+        let push_default = match config::get::<String>(config, "remote.pushDefault").read()? {
+            Some(push_default) => push_default.unwrap(),
+            None => "origin".to_owned(),
+        };
+
+        let mut preserve = Vec::new();
+        let remotes = get_remotes(&repo)?;
+        for branch in &self.to_delete {
+            let remote = if let Some(remote) = branch.remote(&remotes)? {
+                remote
+            } else {
+                continue;
+            };
+            let push_remote = match branch.local() {
+                Some(local) => config::get_push_remote(config, local)?,
+                None => push_default.clone(),
+            };
+
+            if remote.remote != push_remote {
+                trace!("filter-out: {} is not on the push remote", remote);
+                preserve.push(Preserved {
+                    branch: branch.clone(),
+                    reason: "fetch-only remote".to_owned(),
+                    base: false,
+                    remote_only: false,
+                });
+            }
+        }
+
+        for preserved in &preserve {
+            self.to_delete.remove(&preserved.branch);
+        }
+        self.preserved.extend(preserve);
+
+        Ok(())
+    }
+
+    pub fn preserve_worktree(&mut self, repo: &Repository) -> Result<()> {
+        let worktrees = get_worktrees(repo)?;
+        let mut preserve = Vec::new();
+        for branch in &self.to_delete {
+            let local = if let Some(local) = branch.local() {
+                local
+            } else {
+                continue;
+            };
+            if let Some(path) = worktrees.get(local) {
+                // It is kept back anyway. The staged changes only tell why, so that they aren't
+                // forgotten when the worktree is removed by hand to trim the branch.
+                let reason = match has_staged_changes(path) {
+                    Ok(true) => "staged changes present".to_owned(),
+                    Ok(false) => format!("worktree at {}", path),
+                    Err(err) => {
+                        warn!(
+                            "Failed to check the staged changes of the worktree {}: {}",
+                            path, err
+                        );
+                        format!("worktree at {}", path)
+                    }
+                };
+                preserve.push(Preserved {
+                    branch: branch.clone(),
+                    reason,
+                    base: false,
+                    remote_only: false,
+                });
+            }
+        }
+
+        for preserved in &preserve {
+            self.to_delete.remove(&preserved.branch);
+        }
+        self.preserved.extend(preserve);
+
+        Ok(())
+    }
+
+    /// Without the remote heads, a direct fetch branch is stray only because its remote branch is not found.
+    pub fn preserve_unknown_remote_state(
+        &mut self,
+        direct_fetch_branches: &[(LocalBranch, RemoteBranch)],
+        reason: &str,
+    ) {
+        let mut preserve = Vec::new();
+        for branch in &self.to_delete {
+            if let ClassifiedBranch::Stray(local) = branch {
+                if direct_fetch_branches.iter().any(|(l, _)| l == local) {
+                    trace!("filter-out: {} has unknown remote state", local.refname);
+                    preserve.push(Preserved {
+                        branch: branch.clone(),
+                        reason: reason.to_owned(),
+                        base: false,
+                        remote_only: false,
+                    });
+                }
+            }
+        }
+
+        for preserved in &preserve {
+            self.to_delete.remove(&preserved.branch);
+        }
+        self.preserved.extend(preserve);
+    }
+
+    /// Keep local branches that stashes are created on. `git stash` records the branch only in its message.
+    pub fn preserve_stashed(&mut self, repo: &Repository) -> Result<()> {
+        let stashed = get_stashed_branches(repo)?;
+        let mut preserve = Vec::new();
+        for branch in &self.to_delete {
+            let local = if let Some(local) = branch.local() {
+                local
+            } else {
+                continue;
+            };
+            if stashed.contains(local.short_name()) {
+                trace!("filter-out: {} has associated stash", local.refname);
+                preserve.push(Preserved {
+                    branch: branch.clone(),
+                    reason: "has associated stash".to_owned(),
+                    base: false,
+                    remote_only: false,
+                });
+            }
+        }
+
+        for preserved in &preserve {
+            self.to_delete.remove(&preserved.branch);
+        }
+        self.preserved.extend(preserve);
+
+        Ok(())
+    }
+
+    /// Keep the `count` local branches with the newest committer times with their upstreams.
+    /// Ties break by the refname. The HEAD branch doesn't count.
+    pub fn preserve_recent(&mut self, repo: &Repository, count: usize) -> Result<()> {
+        let head = repo
+            .head()
+            .ok()
+            .and_then(|head| head.name().map(str::to_owned));
+        let mut candidates = Vec::new();
+        let locals: HashSet<_> = self.locals_to_delete().into_iter().collect();
+        for local in locals {
+            if Some(&local.refname) == head.as_ref() {
+                continue;
+            }
+            let commit = repo.find_reference(&local.refname)?.peel_to_commit()?;
+            candidates.push((commit.committer().when().seconds(), local.clone()));
+        }
+        candidates.sort_by(|(a_time, a), (b_time, b)| b_time.cmp(a_time).then(a.cmp(b)));
+        let recent: HashSet<_> = candidates
+            .into_iter()
+            .take(count)
+            .map(|(_, local)| local)
+            .collect();
+        self.preserve_locals_with_upstreams(
+            &recent,
+            &format!("among {} most recent branches", count),
+        );
+        Ok(())
+    }
+
+    /// Keep the local branches of the last `count` distinct checkouts in the HEAD reflog with their upstreams.
+    pub fn preserve_recent_checkouts(&mut self, repo: &Repository, count: usize) -> Result<()> {
+        let recent: HashSet<_> = get_recent_checkouts(repo, count)?
+            .iter()
+            .map(|name| LocalBranch::new(&format!("refs/heads/{}", name)))
+            .collect();
+        self.preserve_locals_with_upstreams(&recent, "recently checked out");
+        Ok(())
+    }
+
+    /// Keep the merged local branches whose tips are committed within `age` with their upstreams,
+    /// in case the merges have to be reverted soon.
+    pub fn preserve_merged_recently(&mut self, repo: &Repository, age: Duration) -> Result<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+        let threshold = now - age.as_secs() as i64;
+        let mut recent = HashSet::new();
+        for branch in &self.to_delete {
+            let local = match branch {
+                ClassifiedBranch::MergedLocal(local) => local,
+                _ => continue,
+            };
+            let commit = repo.find_reference(&local.refname)?.peel_to_commit()?;
+            if commit.committer().when().seconds() > threshold {
+                recent.insert(local.clone());
+            }
+        }
+        self.preserve_locals_with_upstreams(&recent, "merged recently");
+        Ok(())
+    }
+
+    fn preserve_locals_with_upstreams(&mut self, locals: &HashSet<LocalBranch>, reason: &str) {
+        // The upstreams classified along with the branches are kept as well.
+        let refnames: HashSet<_> = locals.iter().map(|local| local.refname.as_str()).collect();
+        let mut classified = HashSet::new();
+        for classification in &self.classifications {
+            if refnames.contains(classification.branch.as_str()) {
+                classified.extend(classification.result.iter());
+            }
+        }
+
+        let mut preserve = Vec::new();
+        for branch in &self.to_delete {
+            let keep = match branch.local() {
+                Some(local) => locals.contains(local),
+                None => classified.contains(branch),
+            };
+            if keep {
+                trace!("filter-out: {:?} is {}", branch, reason);
+                preserve.push(Preserved {
+                    branch: branch.clone(),
+                    reason: reason.to_owned(),
+                    base: false,
+                    remote_only: false,
+                });
+            }
+        }
+
+        for preserved in &preserve {
+            self.to_delete.remove(&preserved.branch);
+        }
+        self.preserved.extend(preserve);
+    }
+
+    /// Strays by gone upstreams are kept back when `git branch -vv` doesn't mark their upstreams `[gone]`.
+    pub fn preserve_uncorroborated_strays(
+        &mut self,
+        gone_by_libgit2: &HashSet<LocalBranch>,
+        gone_by_git: &HashSet<LocalBranch>,
+    ) {
+        let mut preserve = Vec::new();
+        for branch in &self.to_delete {
+            if let ClassifiedBranch::Stray(local) = branch {
+                if gone_by_libgit2.contains(local) && !gone_by_git.contains(local) {
+                    preserve.push(Preserved {
+                        branch: branch.clone(),
+                        reason: "upstream isn't gone for git".to_owned(),
+                        base: false,
+                        remote_only: false,
+                    });
+                }
+            }
+        }
+
+        for preserved in &preserve {
+            self.to_delete.remove(&preserved.branch);
+        }
+        self.preserved.extend(preserve);
+    }
+
+    /// A stray branch whose upstream is renamed, like a force-moved pull request branch, isn't stray.
+    /// Its work lives on under a remote branch with the same tip, or with the same patch at the tip.
+    pub fn preserve_renamed_upstreams(&mut self, repo: &Repository) -> Result<()> {
+        let mut remote_tips = Vec::new();
+        for branch in repo.branches(Some(BranchType::Remote))? {
+            let (branch, _) = branch?;
+            let reference = branch.get();
+            if reference.symbolic_target_bytes().is_some() {
+                continue;
+            }
+            // Peeled, since a remote tracking branch might point to an annotated tag.
+            if let (Some(refname), Ok(tip)) = (reference.name(), reference.peel_to_commit()) {
+                remote_tips.push((RemoteTrackingBranch::new(refname), tip.id()));
+            }
+        }
+        remote_tips.sort();
+
+        let mut preserve = Vec::new();
+        for branch in &self.to_delete {
+            let local = match branch {
+                ClassifiedBranch::Stray(local) => local,
+                _ => continue,
+            };
+            let tip = repo.find_reference(&local.refname)?.peel_to_commit()?.id();
+            let mut renamed = remote_tips
+                .iter()
+                .find(|(_, remote_tip)| *remote_tip == tip)
+                .map(|(upstream, _)| upstream);
+            if renamed.is_none() {
+                let tip_patch = patch_id(repo, tip)?;
+                for (upstream, remote_tip) in &remote_tips {
+                    if patch_id(repo, *remote_tip)? == tip_patch {
+                        renamed = Some(upstream);
+                        break;
+                    }
+                }
+            }
+            if let Some(upstream) = renamed {
+                preserve.push(Preserved {
+                    branch: branch.clone(),
+                    reason: format!("upstream renamed to {}", upstream.short_name()),
+                    base: false,
+                    remote_only: false,
+                });
+            }
+        }
+
+        for preserved in &preserve {
+            self.to_delete.remove(&preserved.branch);
+        }
+        self.preserved.extend(preserve);
+        Ok(())
+    }
+
+    /// Stray and diverged branches might have unpushed or unmerged work,
+    /// so they are never deleted regardless of the delete filter.
+    pub fn preserve_strays(&mut self) {
+        let mut preserve = Vec::new();
+        for branch in &self.to_delete {
+            let reason = match branch {
+                ClassifiedBranch::Stray(_) => "stray deletion disabled",
+                ClassifiedBranch::DivergedRemoteTracking { .. }
+                | ClassifiedBranch::DivergedDirectFetch { .. } => "diverged deletion disabled",
+                _ => continue,
+            };
+            trace!("filter-out: {:?} is stray or diverged", branch);
+            preserve.push(Preserved {
+                branch: branch.clone(),
+                reason: reason.to_owned(),
+                base: false,
+                remote_only: false,
+            });
+        }
+
+        for preserved in &preserve {
+            self.to_delete.remove(&preserved.branch);
+        }
+        self.preserved.extend(preserve);
+    }
+
+    pub fn apply_delete_range_filter(
+        &mut self,
+        repo: &Repository,
+        filter: &DeleteFilter,
+    ) -> Result<()> {
+        let mut preserve = Vec::new();
+
+        let remotes = get_remotes(&repo)?;
+
+        for branch in &self.to_delete {
+            let range = match branch {
+                ClassifiedBranch::MergedLocal(_) => {
+                    if !filter.delete_merged_local() {
+                        Some("merged-local".to_owned())
+                    } else {
+                        None
+                    }
+                }
+                ClassifiedBranch::Stray(_) => {
+                    if !filter.delete_stray() {
+                        Some("stray".to_owned())
+                    } else {
+                        None
+                    }
+                }
+                ClassifiedBranch::MergedRemoteTracking(upstream) => {
+                    let remote = upstream.to_remote_branch(&remotes)?;
+                    if !filter.delete_merged_remote(&remote.remote) {
+                        Some(format!("merged-remote:{}", &remote.remote))
+                    } else {
+                        None
+                    }
+                }
+                ClassifiedBranch::DivergedRemoteTracking { upstream, .. } => {
+                    let remote = upstream.to_remote_branch(&remotes)?;
+                    if !filter.delete_diverged(&remote.remote) {
+                        Some(format!("diverged:{}", &remote.remote))
+                    } else {
+                        None
+                    }
+                }
+
+                ClassifiedBranch::MergedDirectFetch { remote, .. } => {
+                    if !filter.delete_merged_remote(&remote.remote) {
+                        Some(format!("merged-remote:{}", &remote.remote))
+                    } else {
+                        None
+                    }
+                }
+                ClassifiedBranch::DivergedDirectFetch { remote, .. } => {
+                    if !filter.delete_diverged(&remote.remote) {
+                        Some(format!("diverged:{}", &remote.remote))
+                    } else {
+                        None
+                    }
+                }
+
+                ClassifiedBranch::MergedNonTrackingLocal(_) => {
+                    if !filter.delete_merged_non_tracking_local() {
+                        Some("local".to_owned())
+                    } else {
+                        None
+                    }
+                }
+                ClassifiedBranch::MergedNonUpstreamRemoteTracking(upstream) => {
+                    let remote = upstream.to_remote_branch(&remotes)?;
+                    if !filter.delete_merged_non_upstream_remote_tracking(&remote.remote) {
+                        Some(format!("remote:{}", &remote.remote))
+                    } else {
+                        None
+                    }
+                }
+                ClassifiedBranch::StaleRemoteTracking(upstream) => {
+                    let remote = upstream.to_remote_branch(&remotes)?;
+                    if !filter.delete_stale_tracking(&remote.remote) {
+                        Some(format!("stale-tracking:{}", &remote.remote))
+                    } else {
+                        None
+                    }
+                }
+                // Bases are kept back before classification.
+                ClassifiedBranch::BaseLocal(_) | ClassifiedBranch::BaseRemoteTracking(_) => {
+                    continue
+                }
+            };
+
+            trace!("Delete range result: {:?} => {:?}", branch, range);
+
+            if let Some(range) = range {
+                preserve.push(Preserved {
+                    branch: branch.clone(),
+                    reason: format!("delete range `{}` was not given", range),
+                    base: false,
+                    remote_only: false,
+                });
+            }
+        }
+
+        for preserved in &preserve {
+            self.to_delete.remove(&preserved.branch);
+        }
+        self.preserved.extend(preserve);
+
+        Ok(())
+    }
+
+    pub fn adjust_not_to_detach(&mut self, repo: &Repository) -> Result<()> {
+        if repo.head_detached()? {
+            return Ok(());
+        }
+        let head = repo.head()?;
+        let head_name = head.name().context("non-utf8 head ref name")?;
+        let head_branch = match LocalBranch::from_refname(head_name) {
+            Some(head_branch) => head_branch,
+            None => return Ok(()),
+        };
+        self.preserve_head(&head_branch);
+        Ok(())
+    }
+
+    /// `head` is the branch given by `--head`, which is kept back as HEAD along with the checked out one,
+    /// even when HEAD is detached.
+    pub fn preserve_head_override(&mut self, repo: &Repository, head: &str) -> Result<()> {
+        let refname = if head.starts_with("refs/heads/") {
+            head.to_owned()
+        } else {
+            format!("refs/heads/{}", head)
+        };
+        repo.find_reference(&refname)
+            .with_context(|| format!("`--head {}` is not a local branch", head))?;
+        self.preserve_head(&LocalBranch::new(&refname));
+        Ok(())
+    }
+
+    fn preserve_head(&mut self, head_branch: &LocalBranch) {
+        let mut preserve = Vec::new();
+
+        for branch in &self.to_delete {
+            if branch.local() == Some(head_branch) {
+                preserve.push(Preserved {
+                    branch: branch.clone(),
+                    reason: "HEAD".to_owned(),
+                    base: false,
+                    remote_only: false,
+                });
+            }
+        }
+
+        for preserved in &preserve {
+            self.to_delete.remove(&preserved.branch);
+        }
+        self.preserved.extend(preserve);
+    }
+
+    pub fn get_preserved_local(&self, target: &LocalBranch) -> Option<&Preserved> {
+        for preserved in &self.preserved {
+            if preserved.local() == Some(target) {
+                return Some(preserved);
+            }
+        }
+        None
+    }
+
+    pub fn get_preserved_upstream(&self, target: &RemoteTrackingBranch) -> Option<&Preserved> {
+        for preserved in &self.preserved {
+            if preserved.branch.upstream() == Some(target) {
                 return Some(preserved);
             }
         }
@@ -408,31 +1732,151 @@ pub enum SkipSuggestion {
     NonUpstream(String),
 }
 
-impl SkipSuggestion {
-    pub const KIND_TRACKING: i32 = 1;
-    pub const KIND_NON_TRACKING: i32 = 2;
-    pub const KIND_NON_UPSTREAM: i32 = 3;
-
-    pub fn kind(&self) -> i32 {
-        match self {
-            SkipSuggestion::Tracking => Self::KIND_TRACKING,
-            SkipSuggestion::TrackingRemote(_) => Self::KIND_TRACKING,
-            SkipSuggestion::NonTracking => Self::KIND_NON_TRACKING,
-            SkipSuggestion::NonUpstream(_) => Self::KIND_NON_UPSTREAM,
+impl SkipSuggestion {
+    pub const KIND_TRACKING: i32 = 1;
+    pub const KIND_NON_TRACKING: i32 = 2;
+    pub const KIND_NON_UPSTREAM: i32 = 3;
+
+    pub fn kind(&self) -> i32 {
+        match self {
+            SkipSuggestion::Tracking => Self::KIND_TRACKING,
+            SkipSuggestion::TrackingRemote(_) => Self::KIND_TRACKING,
+            SkipSuggestion::NonTracking => Self::KIND_NON_TRACKING,
+            SkipSuggestion::NonUpstream(_) => Self::KIND_NON_UPSTREAM,
+        }
+    }
+}
+
+fn get_stack_prefix(repo: &Repository) -> Result<String> {
+    let error = || anyhow::anyhow!("The stack prefix requires HEAD to be a branch with `/` or `-`");
+    if repo.head_detached()? {
+        return Err(error());
+    }
+    let head = repo.head()?;
+    let head = LocalBranch::from_refname(head.name().context("non-utf8 head ref name")?)
+        .ok_or_else(error)?;
+    let name = head.short_name();
+    match name.rfind(|c: char| c == '/' || c == '-') {
+        Some(index) if index > 0 => Ok(name[..=index].to_owned()),
+        _ => Err(error()),
+    }
+}
+
+/// Branch names in the messages of the stash reflog,
+/// which are either `WIP on <branch>: <commit> <subject>` or `On <branch>: <message>`.
+fn get_stashed_branches(repo: &Repository) -> Result<HashSet<String>> {
+    let mut result = HashSet::new();
+    for entry in repo.reflog("refs/stash")?.iter() {
+        let message = entry.message().context("non utf-8 stash message")?;
+        let rest = if let Some(rest) = message.strip_prefix("WIP on ") {
+            rest
+        } else if let Some(rest) = message.strip_prefix("On ") {
+            rest
+        } else {
+            continue;
+        };
+        // `:` is not allowed in branch names.
+        if let Some(end) = rest.find(':') {
+            result.insert(rest[..end].to_owned());
+        }
+    }
+    Ok(result)
+}
+
+/// The last `count` distinct local branches that are checked out, by `checkout: moving from <a> to <b>` of the HEAD reflog.
+/// Commits and deleted branches don't count.
+fn get_recent_checkouts(repo: &Repository, count: usize) -> Result<Vec<String>> {
+    let mut result = Vec::new();
+    for entry in repo.reflog("HEAD")?.iter() {
+        if result.len() >= count {
+            break;
+        }
+        let message = match entry.message() {
+            Some(message) => message,
+            None => continue,
+        };
+        let rest = match message.strip_prefix("checkout: moving from ") {
+            Some(rest) => rest,
+            None => continue,
+        };
+        // Spaces are not allowed in branch names.
+        let name = match rest.rfind(" to ") {
+            Some(start) => &rest[start + " to ".len()..],
+            None => continue,
+        };
+        if result.iter().any(|recent| recent == name)
+            || repo.find_branch(name, BranchType::Local).is_err()
+        {
+            continue;
+        }
+        result.push(name.to_owned());
+    }
+    Ok(result)
+}
+
+fn get_matching_pattern<'a>(
+    repo: &Repository,
+    patterns: &[&'a str],
+    ignore_case: bool,
+    branch: &ClassifiedBranch,
+) -> Result<Option<&'a str>> {
+    let pattern = match branch {
+        ClassifiedBranch::MergedLocal(local)
+        | ClassifiedBranch::Stray(local)
+        | ClassifiedBranch::MergedDirectFetch { local, .. }
+        | ClassifiedBranch::DivergedDirectFetch { local, .. }
+        | ClassifiedBranch::MergedNonTrackingLocal(local)
+        | ClassifiedBranch::BaseLocal(local) => {
+            get_protect_pattern(repo, patterns, ignore_case, local)?
+        }
+        ClassifiedBranch::MergedRemoteTracking(upstream)
+        | ClassifiedBranch::MergedNonUpstreamRemoteTracking(upstream)
+        | ClassifiedBranch::StaleRemoteTracking(upstream)
+        | ClassifiedBranch::BaseRemoteTracking(upstream) => {
+            get_protect_pattern(repo, patterns, ignore_case, upstream)?
         }
+        ClassifiedBranch::DivergedRemoteTracking { local, upstream } => get_protect_pattern(
+            repo,
+            patterns,
+            ignore_case,
+            local,
+        )?
+        .or(get_protect_pattern(repo, patterns, ignore_case, upstream)?),
+    };
+    Ok(pattern)
+}
+
+/// Full refname patterns of a protected pattern.
+/// A short name like `main` is a local branch `refs/heads/main` or a remote tracking branch like `origin/main`,
+/// while a full refname like `refs/heads/main` is used as is.
+fn protected_refname_patterns(pattern: &str) -> Vec<String> {
+    if pattern.starts_with("refs/") {
+        vec![pattern.to_owned()]
+    } else {
+        vec![
+            format!("refs/heads/{}", pattern),
+            format!("refs/remotes/{}", pattern),
+        ]
     }
 }
 
+/// On case insensitive filesystems, `ignore_case` makes `main` protect `Main` too.
 fn get_protect_pattern<'a, B: Refname>(
     repo: &Repository,
     protected_patterns: &[&'a str],
+    ignore_case: bool,
     branch: &B,
 ) -> Result<Option<&'a str>> {
-    let prefixes = &["", "refs/remotes/", "refs/heads/"];
     let target_refname = branch.refname();
     for protected_pattern in protected_patterns {
-        for prefix in prefixes {
-            for reference in repo.references_glob(&format!("{}{}", prefix, protected_pattern))? {
+        for pattern in protected_refname_patterns(protected_pattern) {
+            if ignore_case {
+                if glob_match(&pattern, target_refname, true) {
+                    return Ok(Some(protected_pattern));
+                }
+                continue;
+            }
+            for reference in repo.references_glob(&pattern)? {
                 let reference = reference?;
                 let refname = reference.name().context("non utf-8 refname")?;
                 if target_refname == refname {
@@ -444,7 +1888,7 @@ fn get_protect_pattern<'a, B: Refname>(
     Ok(None)
 }
 
-#[derive(Hash, Eq, PartialEq, Debug, Clone, Serialize)]
+#[derive(Hash, Eq, PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum ClassifiedBranch {
     MergedLocal(LocalBranch),
     Stray(LocalBranch),
@@ -465,6 +1909,12 @@ pub enum ClassifiedBranch {
 
     MergedNonTrackingLocal(LocalBranch),
     MergedNonUpstreamRemoteTracking(RemoteTrackingBranch),
+    /// The remote branch is gone, but the remote tracking branch is not pruned.
+    StaleRemoteTracking(RemoteTrackingBranch),
+
+    /// Bases are kept back before classification, so they are neither merged nor stray.
+    BaseLocal(LocalBranch),
+    BaseRemoteTracking(RemoteTrackingBranch),
 }
 
 impl ClassifiedBranch {
@@ -475,7 +1925,8 @@ impl ClassifiedBranch {
             | ClassifiedBranch::DivergedRemoteTracking { local, .. }
             | ClassifiedBranch::MergedDirectFetch { local, .. }
             | ClassifiedBranch::DivergedDirectFetch { local, .. }
-            | ClassifiedBranch::MergedNonTrackingLocal(local) => Some(local),
+            | ClassifiedBranch::MergedNonTrackingLocal(local)
+            | ClassifiedBranch::BaseLocal(local) => Some(local),
             _ => None,
         }
     }
@@ -484,7 +1935,9 @@ impl ClassifiedBranch {
         match self {
             ClassifiedBranch::MergedRemoteTracking(upstream)
             | ClassifiedBranch::DivergedRemoteTracking { upstream, .. }
-            | ClassifiedBranch::MergedNonUpstreamRemoteTracking(upstream) => Some(upstream),
+            | ClassifiedBranch::MergedNonUpstreamRemoteTracking(upstream)
+            | ClassifiedBranch::StaleRemoteTracking(upstream)
+            | ClassifiedBranch::BaseRemoteTracking(upstream) => Some(upstream),
             _ => None,
         }
     }
@@ -503,6 +1956,24 @@ impl ClassifiedBranch {
         }
     }
 
+    pub fn category(&self) -> &'static str {
+        match self {
+            ClassifiedBranch::MergedLocal(_) => "merged local",
+            ClassifiedBranch::Stray(_) => "stray",
+            ClassifiedBranch::MergedRemoteTracking(_) => "merged remote tracking",
+            ClassifiedBranch::DivergedRemoteTracking { .. } => "diverged remote tracking",
+            ClassifiedBranch::MergedDirectFetch { .. } => "merged direct fetch",
+            ClassifiedBranch::DivergedDirectFetch { .. } => "diverged direct fetch",
+            ClassifiedBranch::MergedNonTrackingLocal(_) => "merged non-tracking local",
+            ClassifiedBranch::MergedNonUpstreamRemoteTracking(_) => {
+                "merged non-upstream remote tracking"
+            }
+            ClassifiedBranch::StaleRemoteTracking(_) => "stale remote tracking",
+            ClassifiedBranch::BaseLocal(_) => "base local",
+            ClassifiedBranch::BaseRemoteTracking(_) => "base remote tracking",
+        }
+    }
+
     pub fn message_local(&self) -> String {
         match self {
             ClassifiedBranch::MergedLocal(_) | ClassifiedBranch::MergedDirectFetch { .. } => {
@@ -510,6 +1981,7 @@ impl ClassifiedBranch {
             }
             ClassifiedBranch::MergedNonTrackingLocal(_) => "merged non-tracking".to_owned(),
             ClassifiedBranch::Stray(_) => "stray".to_owned(),
+            ClassifiedBranch::BaseLocal(_) => "base".to_owned(),
             ClassifiedBranch::DivergedRemoteTracking {
                 upstream: remote, ..
             } => format!("diverged with {}", remote.refname),
@@ -527,6 +1999,8 @@ impl ClassifiedBranch {
             ClassifiedBranch::MergedNonUpstreamRemoteTracking(_) => {
                 "merged non-upstream".to_owned()
             }
+            ClassifiedBranch::StaleRemoteTracking(_) => "stale".to_owned(),
+            ClassifiedBranch::BaseRemoteTracking(_) => "base".to_owned(),
             ClassifiedBranch::DivergedRemoteTracking { local, .. } => {
                 format!("diverged with {}", local.refname)
             }
@@ -542,6 +2016,9 @@ pub struct Classifier<'a> {
     git: &'a Git,
     merge_tracker: &'a MergeTracker,
     tasks: Vec<Box<dyn FnOnce() -> Result<ClassificationResponseWithId> + Send + Sync + 'a>>,
+    state: Option<Arc<Mutex<ClassificationState>>>,
+    // The options of the merge checks and the config they read, which are a part of every fingerprint.
+    options: String,
 }
 
 impl<'a> Classifier<'a> {
@@ -550,7 +2027,39 @@ impl<'a> Classifier<'a> {
             git,
             merge_tracker,
             tasks: Vec::new(),
+            state: None,
+            options: String::new(),
+        }
+    }
+
+    /// Reuse the classifications of the previous run for the branches that haven't moved.
+    /// The state is saved again after `classify`.
+    pub fn with_state(mut self, state: ClassificationState) -> Result<Self> {
+        // Every option that changes what the merge tracker takes as merged.
+        self.options = format!(
+            "squash-detect={} strict-squash={} squash-message={:?} pull-refs={:?}",
+            self.merge_tracker.squash_detect(),
+            self.merge_tracker.strict_squash(),
+            self.merge_tracker.squash_message(),
+            self.merge_tracker.merged_pull_requests(),
+        );
+        self.state = Some(Arc::new(Mutex::new(state)));
+        Ok(self)
+    }
+
+    /// The commits of the refs that a classification reads, and the options of the merge checks.
+    fn fingerprint(&self, base: &BaseCommit, refnames: &[&str]) -> String {
+        let mut result = format!("{} {}={}", self.options, base.name, base.oid);
+        for refname in refnames {
+            let oid = self
+                .git
+                .repo
+                .revparse_single(refname)
+                .and_then(|object| object.peel_to_commit())
+                .map_or_else(|_| "-".to_owned(), |commit| commit.id().to_string());
+            result.push_str(&format!(" {}={}", refname, oid));
         }
+        result
     }
 
     pub fn queue_request<R: ClassificationRequest + Send + Sync + Debug + 'a>(&mut self, req: R) {
@@ -558,10 +2067,44 @@ impl<'a> Classifier<'a> {
         trace!("Enqueue #{}: {:#?}", id, req);
         let git = ForceSendSync::new(self.git);
         let merge_tracker = self.merge_tracker;
+        let fingerprint = self
+            .state
+            .as_ref()
+            .map(|_| self.fingerprint(req.base(), &req.refnames()));
+        if let (Some(state), Some(fingerprint)) = (&self.state, &fingerprint) {
+            let previous = state
+                .lock()
+                .unwrap()
+                .get(req.branch(), &req.base().name, fingerprint);
+            if let Some((message, result)) = previous {
+                let branch = req.branch().to_owned();
+                let base = req.base().name.clone();
+                self.tasks.push(Box::new(move || {
+                    // Other branches at the same commits are merged as well, as if it is classified again.
+                    track_merged(&git.repo, merge_tracker, &result)?;
+                    Ok(ClassificationResponseWithId {
+                        id,
+                        branch,
+                        base,
+                        fingerprint: None,
+                        reused: true,
+                        response: ClassificationResponse { message, result },
+                    })
+                }));
+                return;
+            }
+        }
         self.tasks.push(Box::new(move || {
             req.classify(git, merge_tracker)
                 .with_context(|| format!("Failed to classify #{}: {:#?}", id, req))
-                .map(|response| ClassificationResponseWithId { id, response })
+                .map(|response| ClassificationResponseWithId {
+                    id,
+                    branch: req.branch().to_owned(),
+                    base: req.base().name.clone(),
+                    fingerprint,
+                    reused: false,
+                    response,
+                })
         }));
     }
 
@@ -580,38 +2123,114 @@ impl<'a> Classifier<'a> {
         self.tasks.push(Box::new(move || {
             req.classify_with_context(git, merge_tracker, context)
                 .with_context(|| format!("Failed to classify #{}: {:#?}", id, req))
-                .map(|response| ClassificationResponseWithId { id, response })
+                .map(|response| ClassificationResponseWithId {
+                    id,
+                    branch: req.branch().to_owned(),
+                    base: req.base().name.clone(),
+                    fingerprint: None,
+                    reused: false,
+                    response,
+                })
         }));
     }
 
-    pub fn classify(self) -> Result<Vec<ClassificationResponse>> {
+    pub fn classify(self) -> Result<Vec<Classification>> {
         info!("Classify {} requests", self.tasks.len());
 
-        let len = self.tasks.len() as u64;
-        let results: Result<Vec<_>> = self
-            .tasks
+        let Classifier {
+            git, tasks, state, ..
+        } = self;
+        let len = tasks.len() as u64;
+        let results: Result<Vec<_>> = tasks
             .into_par_iter()
             .progress_count(len)
             .map(|task| task())
             .map(|res| {
-                let ClassificationResponseWithId { id, response } = res?;
+                let ClassificationResponseWithId {
+                    id,
+                    branch,
+                    base,
+                    fingerprint,
+                    reused,
+                    response,
+                } = res?;
                 debug!("Result #{}: {:#?}", id, response);
-                Ok(response)
+                if let (Some(state), Some(fingerprint)) = (&state, fingerprint) {
+                    state.lock().unwrap().insert(
+                        &branch,
+                        &base,
+                        fingerprint,
+                        response.message,
+                        &response.result,
+                    );
+                }
+                Ok(Classification {
+                    branch,
+                    base,
+                    message: response.message,
+                    result: response.result,
+                    reused,
+                })
             })
             .collect();
 
-        return results;
+        let results = results?;
+        if let Some(state) = &state {
+            state.lock().unwrap().save(&git.repo)?;
+        }
+        Ok(results)
+    }
+}
+
+/// Track the merged branches of a reused classification.
+fn track_merged(
+    repo: &Repository,
+    merge_tracker: &MergeTracker,
+    result: &[ClassifiedBranch],
+) -> Result<()> {
+    for branch in result {
+        match branch {
+            ClassifiedBranch::MergedLocal(local)
+            | ClassifiedBranch::MergedNonTrackingLocal(local)
+            | ClassifiedBranch::DivergedRemoteTracking { local, .. } => {
+                merge_tracker.track(repo, local)?
+            }
+            ClassifiedBranch::MergedRemoteTracking(upstream)
+            | ClassifiedBranch::MergedNonUpstreamRemoteTracking(upstream) => {
+                merge_tracker.track(repo, upstream)?
+            }
+            _ => {}
+        }
     }
+    Ok(())
 }
 
 struct ClassificationResponseWithId {
     id: usize,
+    branch: String,
+    base: String,
+    /// To save the classification for `--incremental`.
+    fingerprint: Option<String>,
+    reused: bool,
     response: ClassificationResponse,
 }
 
+/// A classification of a branch against a base.
+/// An alive branch has one too, with its message and without any result.
+#[derive(Serialize, Debug)]
+pub struct Classification {
+    /// The refname of the local branch, or the remote tracking branch for non-upstream ones.
+    pub branch: String,
+    pub base: String,
+    pub message: &'static str,
+    pub result: Vec<ClassifiedBranch>,
+    /// Whether it is the result of the previous run, reused by `--incremental`.
+    pub reused: bool,
+}
+
 #[derive(Debug)]
 pub struct ClassificationResponse {
-    message: &'static str,
+    pub message: &'static str,
     pub result: Vec<ClassifiedBranch>,
 }
 
@@ -621,6 +2240,18 @@ pub trait ClassificationRequest {
         git: ForceSendSync<&Git>,
         merge_tracker: &MergeTracker,
     ) -> Result<ClassificationResponse>;
+
+    /// The refname of the branch to classify.
+    fn branch(&self) -> &str;
+
+    /// The base that the branch is compared with.
+    fn base(&self) -> &BaseCommit;
+
+    /// The refs that the classification reads besides the base.
+    /// `--incremental` reuses the previous result while none of them nor the base has moved.
+    fn refnames(&self) -> Vec<&str> {
+        vec![self.branch()]
+    }
 }
 
 pub trait ClassificationRequestWithContext<C> {
@@ -630,13 +2261,20 @@ pub trait ClassificationRequestWithContext<C> {
         merge_tracker: &MergeTracker,
         context: C,
     ) -> Result<ClassificationResponse>;
+
+    /// The refname of the branch to classify.
+    fn branch(&self) -> &str;
+
+    /// The base that the branch is compared with.
+    fn base(&self) -> &BaseCommit;
 }
 
 #[derive(Debug)]
 pub struct TrackingBranchClassificationRequest<'a> {
-    pub base: &'a RemoteTrackingBranch,
+    pub base: &'a BaseCommit,
     pub local: &'a LocalBranch,
     pub upstream: Option<&'a RemoteTrackingBranch>,
+    pub pushes: &'a [RemoteTrackingBranch],
 }
 
 impl<'a> ClassificationRequest for TrackingBranchClassificationRequest<'a> {
@@ -645,54 +2283,283 @@ impl<'a> ClassificationRequest for TrackingBranchClassificationRequest<'a> {
         git: ForceSendSync<&Git>,
         merge_tracker: &MergeTracker,
     ) -> Result<ClassificationResponse> {
-        let local = merge_tracker.check_and_track(&git.repo, &self.base.refname, self.local)?;
-        let upstream = if let Some(upstream) = self.upstream {
-            merge_tracker.check_and_track(&git.repo, &self.base.refname, upstream)?
-        } else {
-            let result = if local.merged {
-                ClassificationResponse {
-                    message: "local is merged but remote is gone",
-                    result: vec![ClassifiedBranch::MergedLocal(local.branch)],
+        let mut local = merge_tracker.check_and_track(&git.repo, self.base.oid, self.local)?;
+        // The branch was kept up to date with the base, but has nothing else.
+        let only_base_merges = has_only_base_merges(&git.repo, self.base.oid, &self.local.refname)?;
+        if only_base_merges {
+            local.merged = true;
+        }
+        let upstream = match self.upstream {
+            Some(upstream) => Some(check_and_track_base_merges(
+                &git.repo,
+                merge_tracker,
+                self.base.oid,
+                upstream,
+            )?),
+            None => None,
+        };
+        let mut pushes = Vec::new();
+        for push in self.pushes {
+            pushes.push(check_and_track_base_merges(
+                &git.repo,
+                merge_tracker,
+                self.base.oid,
+                push,
+            )?);
+        }
+        let upstream = match upstream {
+            Some(upstream) if pushes.iter().any(|push| push.merged != upstream.merged) => {
+                return Ok(classify_disagreeing_upstreams(local, upstream, pushes));
+            }
+            upstream => upstream,
+        };
+
+        let mut result = if let Some(upstream) = upstream {
+            match (local.merged, upstream.merged) {
+                (true, true) => ClassificationResponse {
+                    message: "local & upstream are merged",
+                    result: vec![
+                        ClassifiedBranch::MergedLocal(local.branch.clone()),
+                        ClassifiedBranch::MergedRemoteTracking(upstream.branch),
+                    ],
+                },
+                (true, false) => ClassificationResponse {
+                    message: "local is merged but diverged with upstream",
+                    result: vec![ClassifiedBranch::DivergedRemoteTracking {
+                        local: local.branch.clone(),
+                        upstream: upstream.branch,
+                    }],
+                },
+                (false, true) if was_in_upstream(&git.repo, &local.branch, &upstream.branch)? => {
+                    ClassificationResponse {
+                        message: "upstream is merged, and the local was in it before rewritten",
+                        result: vec![
+                            ClassifiedBranch::MergedLocal(local.branch.clone()),
+                            ClassifiedBranch::MergedRemoteTracking(upstream.branch),
+                        ],
+                    }
                 }
-            } else {
-                ClassificationResponse {
-                    message: "local is stray but remote is gone",
-                    result: vec![ClassifiedBranch::Stray(local.branch)],
+                (false, true) if is_force_updated(&git.repo, &local.branch, &upstream.branch)? => {
+                    ClassificationResponse {
+                        message: UPSTREAM_FORCE_UPDATED,
+                        result: vec![
+                            ClassifiedBranch::Stray(local.branch.clone()),
+                            ClassifiedBranch::MergedRemoteTracking(upstream.branch),
+                        ],
+                    }
                 }
-            };
-            return Ok(result);
+                (false, true) => ClassificationResponse {
+                    message: "upstream is merged, but the local strays",
+                    result: vec![
+                        ClassifiedBranch::Stray(local.branch.clone()),
+                        ClassifiedBranch::MergedRemoteTracking(upstream.branch),
+                    ],
+                },
+                (false, false) => ClassificationResponse {
+                    message: "local & upstream are not merged yet",
+                    result: vec![],
+                },
+            }
+        } else if !pushes.is_empty() {
+            ClassificationResponse {
+                message: PUSH_UPSTREAM_ONLY,
+                result: if local.merged {
+                    vec![ClassifiedBranch::MergedLocal(local.branch.clone())]
+                } else {
+                    vec![ClassifiedBranch::Stray(local.branch.clone())]
+                },
+            }
+        } else if local.merged {
+            ClassificationResponse {
+                message: "local is merged but remote is gone",
+                result: vec![ClassifiedBranch::MergedLocal(local.branch.clone())],
+            }
+        } else {
+            ClassificationResponse {
+                message: "local is stray but remote is gone",
+                result: vec![ClassifiedBranch::Stray(local.branch.clone())],
+            }
         };
 
-        let result = match (local.merged, upstream.merged) {
-            (true, true) => ClassificationResponse {
-                message: "local & upstream are merged",
-                result: vec![
-                    ClassifiedBranch::MergedLocal(local.branch),
-                    ClassifiedBranch::MergedRemoteTracking(upstream.branch),
-                ],
-            },
-            (true, false) => ClassificationResponse {
-                message: "local is merged but diverged with upstream",
-                result: vec![ClassifiedBranch::DivergedRemoteTracking {
-                    local: local.branch,
-                    upstream: upstream.branch,
-                }],
-            },
-            (false, true) => ClassificationResponse {
-                message: "upstream is merged, but the local strays",
-                result: vec![
-                    ClassifiedBranch::Stray(local.branch),
-                    ClassifiedBranch::MergedRemoteTracking(upstream.branch),
-                ],
-            },
-            (false, false) => ClassificationResponse {
-                message: "local & upstream are not merged yet",
-                result: vec![],
-            },
-        };
+        if only_base_merges
+            && result
+                .result
+                .contains(&ClassifiedBranch::MergedLocal(local.branch.clone()))
+        {
+            result.message = ONLY_BASE_MERGES;
+        }
+
+        // Every push destination is classified like the upstream.
+        for push in pushes {
+            if push.merged {
+                result
+                    .result
+                    .push(ClassifiedBranch::MergedRemoteTracking(push.branch));
+            } else if local.merged {
+                result
+                    .result
+                    .push(ClassifiedBranch::DivergedRemoteTracking {
+                        local: local.branch.clone(),
+                        upstream: push.branch,
+                    });
+            }
+        }
 
         Ok(result)
     }
+
+    fn branch(&self) -> &str {
+        self.local.refname()
+    }
+
+    fn base(&self) -> &BaseCommit {
+        self.base
+    }
+
+    fn refnames(&self) -> Vec<&str> {
+        let mut result = vec![self.local.refname()];
+        result.extend(self.upstream.map(|upstream| upstream.refname()));
+        result.extend(self.pushes.iter().map(|push| push.refname()));
+        result
+    }
+}
+
+/// A branch that was kept up to date with the base, but has nothing else, is merged
+/// even when the merge tracker doesn't find it merged, like without the squash detection.
+fn check_and_track_base_merges<T>(
+    repo: &Repository,
+    merge_tracker: &MergeTracker,
+    base: Oid,
+    branch: &T,
+) -> Result<MergeState<T>>
+where
+    T: Refname + Clone,
+{
+    let mut state = merge_tracker.check_and_track(repo, base, branch)?;
+    if !state.merged && has_only_base_merges(repo, base, branch.refname())? {
+        state.merged = true;
+    }
+    Ok(state)
+}
+
+/// The message when the unique commits of the local are only merges of the base without content of their own.
+pub const ONLY_BASE_MERGES: &str = "no unique content, only base merges";
+/// The message when the upstream is merged after it is force-updated, and the local diverged with it.
+/// `TrimPlan::preserve_force_updated_upstreams` keeps them back for a manual review.
+pub const UPSTREAM_FORCE_UPDATED: &str = "upstream force-updated; manual review";
+/// The message when the fetch upstream is gone, and only the push upstreams are left to compare.
+/// `TrimPlan::preserve_push_only_upstreams` keeps back the push upstreams with `--cautious-push-only`.
+pub const PUSH_UPSTREAM_ONLY: &str =
+    "upstream is gone; merged status is inferred from the push upstream only";
+/// The message when the fetch upstream is merged but a push upstream isn't.
+pub const FETCH_UPSTREAM_MERGED_ONLY: &str = "fetch upstream is merged, but push upstream isn't";
+/// The message when a push upstream is merged but the fetch upstream isn't.
+pub const PUSH_UPSTREAM_MERGED_ONLY: &str = "push upstream is merged, but fetch upstream isn't";
+/// Every message of the classifications. `ClassificationState` maps the loaded messages back to them,
+/// and an unknown one is a cache miss.
+pub(crate) const CLASSIFICATION_MESSAGES: &[&str] = &[
+    ONLY_BASE_MERGES,
+    UPSTREAM_FORCE_UPDATED,
+    PUSH_UPSTREAM_ONLY,
+    FETCH_UPSTREAM_MERGED_ONLY,
+    PUSH_UPSTREAM_MERGED_ONLY,
+    "local & upstream are merged",
+    "local is merged but diverged with upstream",
+    "upstream is merged, and the local was in it before rewritten",
+    "upstream is merged, but the local strays",
+    "local & upstream are not merged yet",
+    "local is merged but remote is gone",
+    "local is stray but remote is gone",
+    "local & remote are merged",
+    "local is merged, but diverged with upstream",
+    "local is merged and its upstream is gone",
+    "local is not merged but the remote is gone somehow",
+    "local is not merged yet",
+    "local is merged, and its upstream is a local branch",
+    "local is not merged but its local upstream is gone",
+    "non-tracking local is merged",
+    "non-tracking local is not merged",
+    "non-upstream local is merged",
+    "non-upstream local is not merged",
+];
+
+/// The fetch upstream and the push upstreams disagree on whether they are merged,
+/// for example when the fetch upstream is stale after the branch is pushed and merged from a fork.
+/// The merged ones are classified as usual, and the other ones are classified as diverged
+/// only to be kept back by `TrimPlan::preserve_disagreeing_upstreams`, with the local.
+/// The local is deleted only when it is merged by itself.
+fn classify_disagreeing_upstreams(
+    local: MergeState<LocalBranch>,
+    upstream: MergeState<RemoteTrackingBranch>,
+    pushes: Vec<MergeState<RemoteTrackingBranch>>,
+) -> ClassificationResponse {
+    let message = if upstream.merged {
+        FETCH_UPSTREAM_MERGED_ONLY
+    } else {
+        PUSH_UPSTREAM_MERGED_ONLY
+    };
+    let mut result = Vec::new();
+    if local.merged {
+        result.push(ClassifiedBranch::MergedLocal(local.branch.clone()));
+    }
+    for remote in std::iter::once(upstream).chain(pushes) {
+        if remote.merged {
+            result.push(ClassifiedBranch::MergedRemoteTracking(remote.branch));
+        } else {
+            result.push(ClassifiedBranch::DivergedRemoteTracking {
+                local: local.branch.clone(),
+                upstream: remote.branch,
+            });
+        }
+    }
+    ClassificationResponse { message, result }
+}
+
+/// The server might rewrite the upstream on a rebase merge, so the local tip isn't an ancestor of it anymore.
+/// The local is still in the upstream when any of the last-known tips of the upstream in its reflog contains it.
+/// The reflog is gone when the upstream is pruned.
+fn was_in_upstream(
+    repo: &Repository,
+    local: &LocalBranch,
+    upstream: &RemoteTrackingBranch,
+) -> Result<bool> {
+    let local_oid = repo.find_reference(&local.refname)?.peel_to_commit()?.id();
+    let reflog = match repo.reflog(&upstream.refname) {
+        Ok(reflog) => reflog,
+        Err(err) if err.code() == ErrorCode::NotFound => return Ok(false),
+        Err(err) => return Err(err.into()),
+    };
+    for entry in reflog.iter() {
+        let tip = entry.id_new();
+        if tip == local_oid {
+            return Ok(true);
+        }
+        match repo.graph_descendant_of(tip, local_oid) {
+            Ok(true) => return Ok(true),
+            Ok(false) => {}
+            // The old tip might be garbage collected.
+            Err(err) if err.code() == ErrorCode::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(false)
+}
+
+/// The upstream is force-updated when neither the local nor the upstream contains the other.
+/// The local has commits that never reached the upstream, and the upstream lost commits of the local.
+fn is_force_updated(
+    repo: &Repository,
+    local: &LocalBranch,
+    upstream: &RemoteTrackingBranch,
+) -> Result<bool> {
+    let local_oid = repo.find_reference(&local.refname)?.peel_to_commit()?.id();
+    let upstream_oid = repo
+        .find_reference(&upstream.refname)?
+        .peel_to_commit()?
+        .id();
+    Ok(local_oid != upstream_oid
+        && !repo.graph_descendant_of(local_oid, upstream_oid)?
+        && !repo.graph_descendant_of(upstream_oid, local_oid)?)
 }
 
 /// `hub-cli` style branch classification request.
@@ -700,7 +2567,7 @@ impl<'a> ClassificationRequest for TrackingBranchClassificationRequest<'a> {
 /// However we can try manual classification without `remote.{remote}` entry.
 #[derive(Debug)]
 pub struct DirectFetchClassificationRequest<'a> {
-    pub base: &'a RemoteTrackingBranch,
+    pub base: &'a BaseCommit,
     pub local: &'a LocalBranch,
     pub remote: &'a RemoteBranch,
 }
@@ -714,7 +2581,7 @@ impl<'a> ClassificationRequestWithContext<&'a [RemoteHead]>
         merge_tracker: &MergeTracker,
         remote_heads: &[RemoteHead],
     ) -> Result<ClassificationResponse> {
-        let local = merge_tracker.check_and_track(&git.repo, &self.base.refname, self.local)?;
+        let local = merge_tracker.check_and_track(&git.repo, self.base.oid, self.local)?;
         let remote_head = remote_heads
             .iter()
             .find(|h| h.remote == self.remote.remote && h.refname == self.remote.refname)
@@ -751,11 +2618,66 @@ impl<'a> ClassificationRequestWithContext<&'a [RemoteHead]>
 
         Ok(result)
     }
+
+    fn branch(&self) -> &str {
+        self.local.refname()
+    }
+
+    fn base(&self) -> &BaseCommit {
+        self.base
+    }
+}
+
+#[derive(Debug)]
+pub struct LocalTrackingBranchClassificationRequest<'a> {
+    pub base: &'a BaseCommit,
+    pub local: &'a LocalBranch,
+    /// `None` when the local upstream is deleted.
+    pub upstream: Option<&'a LocalBranch>,
+}
+
+impl<'a> ClassificationRequest for LocalTrackingBranchClassificationRequest<'a> {
+    fn classify(
+        &self,
+        git: ForceSendSync<&Git>,
+        merge_tracker: &MergeTracker,
+    ) -> Result<ClassificationResponse> {
+        let local = merge_tracker.check_and_track(&git.repo, self.base.oid, self.local)?;
+        let result = match (local.merged, self.upstream) {
+            (true, _) => ClassificationResponse {
+                message: "local is merged, and its upstream is a local branch",
+                result: vec![ClassifiedBranch::MergedLocal(local.branch)],
+            },
+            (false, None) => ClassificationResponse {
+                message: "local is not merged but its local upstream is gone",
+                result: vec![ClassifiedBranch::Stray(local.branch)],
+            },
+            (false, Some(_)) => ClassificationResponse {
+                message: "local is not merged yet",
+                result: vec![],
+            },
+        };
+        Ok(result)
+    }
+
+    fn branch(&self) -> &str {
+        self.local.refname()
+    }
+
+    fn base(&self) -> &BaseCommit {
+        self.base
+    }
+
+    fn refnames(&self) -> Vec<&str> {
+        let mut result = vec![self.local.refname()];
+        result.extend(self.upstream.map(|upstream| upstream.refname()));
+        result
+    }
 }
 
 #[derive(Debug)]
 pub struct NonTrackingBranchClassificationRequest<'a> {
-    pub base: &'a RemoteTrackingBranch,
+    pub base: &'a BaseCommit,
     pub local: &'a LocalBranch,
 }
 
@@ -765,7 +2687,7 @@ impl<'a> ClassificationRequest for NonTrackingBranchClassificationRequest<'a> {
         git: ForceSendSync<&Git>,
         merge_tracker: &MergeTracker,
     ) -> Result<ClassificationResponse> {
-        let local = merge_tracker.check_and_track(&git.repo, &self.base.refname, self.local)?;
+        let local = merge_tracker.check_and_track(&git.repo, self.base.oid, self.local)?;
         let result = if local.merged {
             ClassificationResponse {
                 message: "non-tracking local is merged",
@@ -779,11 +2701,19 @@ impl<'a> ClassificationRequest for NonTrackingBranchClassificationRequest<'a> {
         };
         Ok(result)
     }
+
+    fn branch(&self) -> &str {
+        self.local.refname()
+    }
+
+    fn base(&self) -> &BaseCommit {
+        self.base
+    }
 }
 
 #[derive(Debug)]
 pub struct NonUpstreamBranchClassificationRequest<'a> {
-    pub base: &'a RemoteTrackingBranch,
+    pub base: &'a BaseCommit,
     pub remote: &'a RemoteTrackingBranch,
 }
 
@@ -793,7 +2723,7 @@ impl<'a> ClassificationRequest for NonUpstreamBranchClassificationRequest<'a> {
         git: ForceSendSync<&Git>,
         merge_tracker: &MergeTracker,
     ) -> Result<ClassificationResponse> {
-        let remote = merge_tracker.check_and_track(&git.repo, &self.base.refname, self.remote)?;
+        let remote = merge_tracker.check_and_track(&git.repo, self.base.oid, self.remote)?;
         let result = if remote.merged {
             ClassificationResponse {
                 message: "non-upstream local is merged",
@@ -809,24 +2739,107 @@ impl<'a> ClassificationRequest for NonUpstreamBranchClassificationRequest<'a> {
         };
         Ok(result)
     }
+
+    fn branch(&self) -> &str {
+        self.remote.refname()
+    }
+
+    fn base(&self) -> &BaseCommit {
+        self.base
+    }
 }
 
-pub fn get_tracking_branches(
-    git: &Git,
-) -> Result<Vec<(LocalBranch, Option<RemoteTrackingBranch>)>> {
+#[derive(Debug)]
+pub struct TrackingBranch {
+    pub local: LocalBranch,
+    /// `None` when the upstream is gone.
+    pub upstream: Option<RemoteTrackingBranch>,
+    /// Remote tracking branches on the push remotes other than the upstream.
+    pub pushes: Vec<RemoteTrackingBranch>,
+}
+
+/// The refs under `refs/heads/` that `get_local_branches` skips.
+pub(crate) fn get_skipped_local_refs(git: &Git) -> Result<Vec<Diagnostic>> {
     let mut result = Vec::new();
     for branch in git.repo.branches(Some(BranchType::Local))? {
-        let local = LocalBranch::try_from(&branch?.0)?;
+        let (branch, _) = branch?;
+        let reference = branch.get();
+        let refname = String::from_utf8_lossy(reference.name_bytes()).into_owned();
+        let message = if reference.name().is_none() {
+            format!("Skip a non-utf8 ref: {}", refname)
+        } else if let Some(target) = reference.symbolic_target_bytes() {
+            format!(
+                "Skip a symbolic ref: {} -> {}",
+                refname,
+                String::from_utf8_lossy(target)
+            )
+        } else if reference.peel_to_commit().is_err() {
+            format!("Skip a ref to a non-commit object: {}", refname)
+        } else {
+            continue;
+        };
+        result.push(Diagnostic::new(
+            DiagnosticKind::SkippedRef,
+            message,
+            Some(refname),
+        ));
+    }
+    Ok(result)
+}
 
-        match local.fetch_upstream(&git.repo, &git.config)? {
-            RemoteTrackingBranchStatus::Exists(upstream) => {
-                result.push((local, Some(upstream)));
+/// Symbolic refs under `refs/heads/` are aliases of other branches, not independent branches.
+fn get_local_branches(git: &Git) -> Result<Vec<LocalBranch>> {
+    let mut result = Vec::new();
+    for branch in git.repo.branches(Some(BranchType::Local))? {
+        let (branch, _) = branch?;
+        if branch.get().symbolic_target_bytes().is_some() {
+            debug!(
+                "skip a symbolic ref: {}",
+                branch.get().name().unwrap_or_default()
+            );
+            continue;
+        }
+        let refname = match branch.get().name() {
+            Some(refname) => refname,
+            None => {
+                debug!(
+                    "skip a non-utf8 ref: {}",
+                    String::from_utf8_lossy(branch.get().name_bytes())
+                );
+                continue;
             }
-            RemoteTrackingBranchStatus::Gone(_) => result.push((local, None)),
+        };
+        // An annotated tag is peeled to its commit, but a ref to a tree or a blob can't be classified.
+        if branch.get().peel_to_commit().is_err() {
+            debug!("skip a ref to a non-commit object: {}", refname);
+            continue;
+        }
+        // Only `refs/heads/` are classified, never the refs of other tools under other namespaces.
+        if let Some(local) = LocalBranch::from_refname(refname) {
+            result.push(local);
+        }
+    }
+    Ok(result)
+}
+
+pub fn get_tracking_branches(git: &Git) -> Result<Vec<TrackingBranch>> {
+    let mut result = Vec::new();
+    let refspecs = RemoteRefspecs::default();
+    for local in get_local_branches(git)? {
+        let upstream = match local.fetch_upstream_with(&git.repo, &git.config, &refspecs)? {
+            RemoteTrackingBranchStatus::Exists(upstream) => Some(upstream),
+            RemoteTrackingBranchStatus::Gone(_) => None,
             _ => {
                 continue;
             }
         };
+        let mut pushes = local.push_upstreams_with(&git.repo, &git.config, &refspecs)?;
+        pushes.retain(|push| Some(push) != upstream.as_ref());
+        result.push(TrackingBranch {
+            local,
+            upstream,
+            pushes,
+        });
     }
 
     Ok(result)
@@ -835,16 +2848,14 @@ pub fn get_tracking_branches(
 /// Get `hub-cli` style direct fetched branches
 pub fn get_direct_fetch_branches(git: &Git) -> Result<Vec<(LocalBranch, RemoteBranch)>> {
     let mut result = Vec::new();
-    for branch in git.repo.branches(Some(BranchType::Local))? {
-        let local = LocalBranch::try_from(&branch?.0)?;
-
+    for local in get_local_branches(git)? {
         let remote = if let Some(remote) = config::get_remote_name(&git.config, &local)? {
             remote
         } else {
             continue;
         };
 
-        if config::get_remote(&git.repo, &remote)?.is_some() {
+        if remote == LOCAL_REMOTE || config::get_remote(&git.repo, &remote)?.is_some() {
             continue;
         }
 
@@ -864,12 +2875,39 @@ pub fn get_direct_fetch_branches(git: &Git) -> Result<Vec<(LocalBranch, RemoteBr
     Ok(result)
 }
 
+/// `branch.<name>.remote = .` makes the upstream a branch of the local repository.
+const LOCAL_REMOTE: &str = ".";
+
+/// Gerrit keeps the patch sets of the changes under it, like `refs/changes/34/1234/2`.
+const GERRIT_CHANGES_PREFIX: &str = "refs/changes/";
+
+/// Get local branches whose upstreams are local branches, with `branch.<name>.remote = .`.
+/// The upstream is `None` when it is deleted.
+pub fn get_local_tracking_branches(git: &Git) -> Result<Vec<(LocalBranch, Option<LocalBranch>)>> {
+    let mut result = Vec::new();
+    for local in get_local_branches(git)? {
+        if config::get_remote_name(&git.config, &local)?.as_deref() != Some(LOCAL_REMOTE) {
+            continue;
+        }
+        let merge = match config::get_merge(&git.config, &local)? {
+            Some(merge) if merge.starts_with("refs/heads/") => merge,
+            _ => continue,
+        };
+        let upstream = match git.repo.find_reference(&merge) {
+            Ok(_) => Some(LocalBranch::new(&merge)),
+            Err(err) if err.code() == ErrorCode::NotFound => None,
+            Err(err) => return Err(err.into()),
+        };
+        result.push((local, upstream));
+    }
+
+    Ok(result)
+}
+
 /// Get local branches that doesn't track any branch.
 pub fn get_non_tracking_local_branches(git: &Git) -> Result<Vec<LocalBranch>> {
     let mut result = Vec::new();
-    for branch in git.repo.branches(Some(BranchType::Local))? {
-        let branch = LocalBranch::try_from(&branch?.0)?;
-
+    for branch in get_local_branches(git)? {
         if config::get_remote_name(&git.config, &branch)?.is_some() {
             continue;
         }
@@ -880,15 +2918,56 @@ pub fn get_non_tracking_local_branches(git: &Git) -> Result<Vec<LocalBranch>> {
     Ok(result)
 }
 
+/// Get local branches that have neither a fetch upstream nor a push remote.
+/// Merge detection never cleans them up.
+pub fn get_orphan_local_branches(git: &Git) -> Result<Vec<LocalBranch>> {
+    let mut result = Vec::new();
+    for branch in get_non_tracking_local_branches(git)? {
+        if config::get_push_remote_name(&git.config, &branch)?.is_some() {
+            continue;
+        }
+
+        result.push(branch);
+    }
+
+    result.sort();
+    Ok(result)
+}
+
+/// Get groups of local branches that point at the same commit, regardless of their merge states.
+/// Each group is sorted by refname, and the groups by their first branches.
+pub fn get_duplicate_local_branches(git: &Git) -> Result<Vec<(Oid, Vec<LocalBranch>)>> {
+    let mut by_tip: HashMap<Oid, Vec<LocalBranch>> = HashMap::new();
+    for branch in get_local_branches(git)? {
+        let tip = git
+            .repo
+            .find_reference(branch.refname())?
+            .peel_to_commit()?
+            .id();
+        by_tip.entry(tip).or_default().push(branch);
+    }
+
+    let mut result = Vec::new();
+    for (tip, mut branches) in by_tip {
+        if branches.len() > 1 {
+            branches.sort();
+            result.push((tip, branches));
+        }
+    }
+    result.sort_by(|(_, lhs), (_, rhs)| lhs.cmp(rhs));
+    Ok(result)
+}
+
 /// Get remote tracking branches that doesn't tracked by any branch.
 pub fn get_non_upstream_remote_tracking_branches(git: &Git) -> Result<Vec<RemoteTrackingBranch>> {
     let mut upstreams = HashSet::new();
 
     let tracking_branches = get_tracking_branches(git)?;
-    for (_local, upstream) in tracking_branches {
-        if let Some(upstream) = upstream {
+    for tracking in tracking_branches {
+        if let Some(upstream) = tracking.upstream {
             upstreams.insert(upstream);
         }
+        upstreams.extend(tracking.pushes);
     }
 
     let mut result = Vec::new();
@@ -897,6 +2976,13 @@ pub fn get_non_upstream_remote_tracking_branches(git: &Git) -> Result<Vec<Remote
         if branch.get().symbolic_target_bytes().is_some() {
             continue;
         }
+        if branch.get().peel_to_commit().is_err() {
+            warn!(
+                "Skip a ref to a non-commit object: {}",
+                String::from_utf8_lossy(branch.get().name_bytes())
+            );
+            continue;
+        }
 
         let branch = RemoteTrackingBranch::try_from(&branch)?;
 
@@ -910,24 +2996,109 @@ pub fn get_non_upstream_remote_tracking_branches(git: &Git) -> Result<Vec<Remote
     Ok(result)
 }
 
-pub fn get_remote_heads(git: &Git, branches: &[RemoteBranch]) -> Result<Vec<RemoteHead>> {
+/// Get remote tracking branches whose remote branches are gone, with `git ls-remote --heads`.
+/// Remote tracking branches of the remotes that timed out are never stale, since their state is unknown.
+pub fn get_stale_remote_tracking_branches(
+    git: &Git,
+    filter: &DeleteFilter,
+    timeout: Option<Duration>,
+) -> Result<Vec<RemoteTrackingBranch>> {
+    let remotes = get_remotes(&git.repo)?;
+    let mut remote_heads_per_remote = HashMap::new();
+    let mut result = Vec::new();
+    for branch in git.repo.branches(Some(BranchType::Remote))? {
+        let (branch, _) = branch?;
+        if branch.get().symbolic_target_bytes().is_some() {
+            continue;
+        }
+
+        let remote_tracking = RemoteTrackingBranch::try_from(&branch)?;
+        let remote_branch = match remote_tracking.to_remote_branch(&remotes) {
+            Ok(remote_branch) => remote_branch,
+            Err(RemoteBranchError::RemoteNotFound) => continue,
+            Err(err) => return Err(err.into()),
+        };
+        if !remote_branch.refname.starts_with("refs/heads/")
+            || !filter.delete_stale_tracking(&remote_branch.remote)
+        {
+            continue;
+        }
+
+        if !remote_heads_per_remote.contains_key(&remote_branch.remote) {
+            let remote_heads =
+                match subprocess::ls_remote_heads(&git.repo, &remote_branch.remote, timeout) {
+                    Ok(heads) => Some(
+                        heads
+                            .into_iter()
+                            .map(|head| head.refname)
+                            .collect::<HashSet<_>>(),
+                    ),
+                    Err(err) if err.downcast_ref::<TimeoutError>().is_some() => {
+                        warn!("{}", err);
+                        None
+                    }
+                    Err(err) => return Err(err),
+                };
+            remote_heads_per_remote.insert(remote_branch.remote.clone(), remote_heads);
+        }
+        if let Some(remote_heads) = &remote_heads_per_remote[&remote_branch.remote] {
+            if !remote_heads.contains(&remote_branch.refname) {
+                result.push(remote_tracking);
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+/// Get GitHub style `refs/pull/*/head` refs that are fetched into the local repository.
+pub fn get_pull_request_heads(git: &Git) -> Result<Vec<PullRequestHead>> {
+    let mut result = Vec::new();
+    for reference in git.repo.references_glob("refs/pull/*/head")? {
+        let reference = reference?;
+        let refname = reference.name().context("non utf-8 pull request refname")?;
+        result.push(PullRequestHead {
+            refname: refname.to_owned(),
+        });
+    }
+
+    Ok(result)
+}
+
+/// Remotes that timed out are returned separately, since their state is unknown.
+pub fn get_remote_heads(
+    git: &Git,
+    branches: &[RemoteBranch],
+    timeout: Option<Duration>,
+) -> Result<(Vec<RemoteHead>, HashSet<String>)> {
     let mut remote_urls = Vec::new();
 
     for branch in branches {
         remote_urls.push(&branch.remote);
     }
 
-    Ok(remote_urls
+    let results = remote_urls
         .into_par_iter()
         .map({
             let git = ForceSendSync::new(git);
             move |remote_url| {
-                subprocess::ls_remote_heads(&git.repo, &remote_url)
-                    .with_context(|| format!("remote_url={}", remote_url))
+                let result = subprocess::ls_remote_heads(&git.repo, &remote_url, timeout);
+                (remote_url.clone(), result)
             }
         })
-        .collect::<Result<Vec<Vec<RemoteHead>>, _>>()?
-        .into_iter()
-        .flatten()
-        .collect::<Vec<RemoteHead>>())
+        .collect::<Vec<_>>();
+
+    let mut remote_heads = Vec::new();
+    let mut timed_out = HashSet::new();
+    for (remote_url, result) in results {
+        match result {
+            Ok(heads) => remote_heads.extend(heads),
+            Err(err) if err.downcast_ref::<TimeoutError>().is_some() => {
+                warn!("{}", err);
+                timed_out.insert(remote_url);
+            }
+            Err(err) => return Err(err.context(format!("remote_url={}", remote_url))),
+        }
+    }
+    Ok((remote_heads, timed_out))
 }
@@ -1,34 +1,64 @@
 pub mod args;
 mod branch;
+mod classification_state;
+pub mod color;
 pub mod config;
 mod core;
+mod lock;
+mod merge_cache;
 mod merge_tracker;
 pub mod porcelain_outputs;
+pub mod pull_request;
+pub mod report;
+mod revwalk;
 mod simple_glob;
+pub mod snapshot;
 mod subprocess;
 pub mod util; // TODO(siedentop): Some help appreciated here. I couldn't get it to work without the 'pub'.
 
 use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
+use std::time::Duration;
 
 use anyhow::{Context, Result};
-use git2::{Config as GitConfig, Error as GitError, ErrorCode, Repository};
+use git2::{
+    Branch, BranchType, Config as GitConfig, Error as GitError, ErrorClass, ErrorCode, Oid,
+    Reference, Repository, Signature, WorktreeLockStatus, WorktreePruneOptions,
+};
 use log::*;
+use serde::Serialize;
 
 use crate::args::DeleteFilter;
 use crate::branch::RemoteTrackingBranchStatus;
 pub use crate::branch::{
-    LocalBranch, Refname, RemoteBranch, RemoteBranchError, RemoteTrackingBranch,
+    BaseCommit, LocalBranch, Refname, RemoteBranch, RemoteBranchError, RemoteRefspecs,
+    RemoteTrackingBranch,
 };
+use crate::classification_state::ClassificationState;
 use crate::core::{
-    get_direct_fetch_branches, get_non_tracking_local_branches,
-    get_non_upstream_remote_tracking_branches, get_remote_heads, get_tracking_branches, Classifier,
-    DirectFetchClassificationRequest, NonTrackingBranchClassificationRequest,
-    NonUpstreamBranchClassificationRequest, TrackingBranchClassificationRequest,
+    get_direct_fetch_branches, get_local_tracking_branches, get_non_tracking_local_branches,
+    get_non_upstream_remote_tracking_branches, get_pull_request_heads, get_remote_heads,
+    get_skipped_local_refs, get_stale_remote_tracking_branches, get_tracking_branches, Classifier,
+    DirectFetchClassificationRequest, LocalTrackingBranchClassificationRequest,
+    NonTrackingBranchClassificationRequest, NonUpstreamBranchClassificationRequest,
+    TrackingBranchClassificationRequest,
+};
+pub use crate::core::{
+    get_duplicate_local_branches, get_orphan_local_branches, sort_by_ref, Classification,
+    ClassifiedBranch, Diagnostic, DiagnosticKind, Preserved, RemoteState, SkipSuggestion, TrimPlan,
+    TrimPlanDiff, UniqueCommits,
 };
-pub use crate::core::{ClassifiedBranch, SkipSuggestion, TrimPlan};
+pub use crate::lock::{LockHeld, RepoLock};
+use crate::merge_cache::MergeCache;
 use crate::merge_tracker::MergeTracker;
-pub use crate::subprocess::{ls_remote_head, remote_update, RemoteHead};
+pub use crate::merge_tracker::SQUASH_TEST_MESSAGE;
+use crate::revwalk::is_merged_by_cherry_pick;
+pub use crate::revwalk::is_merged_by_revwalk;
+pub use crate::subprocess::{
+    get_gone_upstream_locals, is_merged_by_rev_list, ls_remote_head, ls_remote_head_with_timeout,
+    remote_prune, remote_update, PushDeleteError, RemoteHead, TimeoutError,
+};
+use crate::util::get_common_dir;
 pub use crate::util::{get_remotes, ForceSendSync};
 
 pub struct Git {
@@ -47,65 +77,279 @@ impl TryFrom<Repository> for Git {
 
 pub struct PlanParam<'a> {
     pub bases: Vec<&'a str>,
+    /// Glob patterns of tags like `v2.*`. Each matching tag is a base as well.
+    pub merged_into_tags: Vec<&'a str>,
     pub protected_patterns: Vec<&'a str>,
+    /// Commits, possibly abbreviated, whose branches are kept regardless of their names.
+    pub protected_commits: Vec<&'a str>,
+    /// Refs like a staging branch. Branches merged into them are kept back.
+    pub protect_merged_into: Vec<&'a str>,
+    pub exclude_patterns: Vec<&'a str>,
     pub delete: DeleteFilter,
     pub detach: bool,
+    /// The local branch to keep back as HEAD along with the checked out one, even when HEAD is detached.
+    pub head: Option<&'a str>,
+    pub pull_refs: bool,
+    /// Delete the remote tracking refs of the merged Gerrit changes locally, instead of keeping them back.
+    pub gerrit: bool,
+    pub keep_if_protected_upstream: bool,
+    /// Keep the push upstreams of the branches whose fetch upstreams are gone.
+    pub cautious_push_only: bool,
+    /// Keep the remote branches that aren't merged, while their merged locals are still deleted.
+    pub no_stray_remote_delete: bool,
+    /// Keep the upstreams that have commits in neither their merged locals nor the bases.
+    pub keep_remote_ahead: bool,
+    pub keep_stashed: bool,
+    /// Keep the local branches that are ancestors of the unmerged local branches.
+    pub keep_ancestors: bool,
+    /// Keep the N local branches to delete with the newest committer times.
+    pub keep_recent: Option<usize>,
+    /// Keep the local branches of the last N distinct checkouts in the HEAD reflog.
+    pub keep_recent_checkouts: Option<usize>,
+    /// Keep the merged local branches whose tips are newer than it.
+    pub merged_older_than: Option<Duration>,
+    /// Trim only the branches that share the prefix of the HEAD branch.
+    pub stack_prefix: bool,
+    /// Don't list the remote heads. Stray branches that need them are kept back.
+    pub offline: bool,
+    /// Leave `main`, `master` and the bases out of the classification, so they are never reported.
+    pub skip_defaults: bool,
+    /// Leave the local branches that have neither an upstream nor a push remote out of the classification.
+    pub limit_to_tracked: bool,
+    /// Never delete stray and diverged branches, whatever the delete filter is.
+    pub assume_unmerged_safe: bool,
+    /// Cross-check the gone upstreams with `git branch -vv`, and keep the strays back when it disagrees.
+    pub cross_check_gone: bool,
+    /// Delete remote branches only from the push remote of each branch.
+    pub remote_default_only: bool,
+    /// Network operations time out after it. The remotes that timed out are treated as unknown.
+    pub timeout: Option<Duration>,
+    pub squash_detect: bool,
+    /// Detect squash merges only by the commits of the bases with the same trees as the branches.
+    pub strict_squash: bool,
+    pub cache: bool,
+    /// Reuse the classifications of the previous run for the branches that haven't moved.
+    pub incremental: bool,
+    /// Keep merged branches whose merges are reverted on the bases.
+    pub detect_reverted: bool,
+    /// Keep stray branches whose tips, or the patches of them, are on other remote branches.
+    pub detect_renamed_upstream: bool,
+    /// Keep merged remote branches that have more commits than it which aren't in the bases.
+    pub remote_ahead_guard: Option<usize>,
+    /// Maps the refname of a local branch to its base, like `refs/heads/hotfix/a` to `release`.
+    /// `branch.<name>.git-trim-base` takes precedence over it, and `None` falls back to the bases.
+    pub base_resolver: Option<&'a dyn Fn(&str) -> Option<String>>,
+    /// Local branches, by their short names or refnames, to trim with their upstreams as merged
+    /// without detecting the merges.
+    pub merged_branches: Vec<&'a str>,
 }
 
 pub fn get_trim_plan(git: &Git, param: &PlanParam) -> Result<TrimPlan> {
-    let bases = resolve_bases(&git.repo, &git.config, &param.bases)?;
-    let base_upstreams: Vec<_> = bases
+    plan_branches(git, param, None)
+}
+
+/// With `only`, just the branch of the refname and its upstreams are classified,
+/// and neither the merge cache nor the incremental state is saved.
+fn plan_branches(git: &Git, param: &PlanParam, only: Option<&str>) -> Result<TrimPlan> {
+    let mut bases = resolve_bases(&git.repo, &git.config, &param.bases)?;
+    bases.extend(resolve_tag_bases(&git.repo, &param.merged_into_tags)?);
+    let base_commits = bases
         .iter()
-        .map(|b| match b {
-            BaseSpec::Local { upstream, .. } => upstream.clone(),
-            BaseSpec::Remote { remote, .. } => remote.clone(),
-        })
-        .collect();
+        .map(|base| base.compared(&git.repo))
+        .collect::<Result<Vec<_>>>()?;
     trace!("bases: {:#?}", bases);
 
-    let tracking_branches = get_tracking_branches(git)?;
+    let mut tracking_branches = get_tracking_branches(git)?;
     debug!("tracking_branches: {:#?}", tracking_branches);
 
-    let direct_fetch_branches = get_direct_fetch_branches(git)?;
+    let gone_by_libgit2: HashSet<_> = tracking_branches
+        .iter()
+        .filter(|tracking| tracking.upstream.is_none())
+        .map(|tracking| tracking.local.clone())
+        .collect();
+
+    let mut direct_fetch_branches = get_direct_fetch_branches(git)?;
     debug!("direct_fetch_branches: {:#?}", direct_fetch_branches);
 
-    let non_tracking_branches = get_non_tracking_local_branches(git)?;
+    let mut local_tracking_branches = get_local_tracking_branches(git)?;
+    debug!("local_tracking_branches: {:#?}", local_tracking_branches);
+
+    let mut non_tracking_branches = get_non_tracking_local_branches(git)?;
     debug!("non_tracking_branches: {:#?}", non_tracking_branches);
 
-    let non_upstream_branches = get_non_upstream_remote_tracking_branches(git)?;
+    let mut non_upstream_branches = get_non_upstream_remote_tracking_branches(git)?;
     debug!("non_upstream_branches: {:#?}", non_upstream_branches);
 
-    let remote_heads = if param.delete.scan_tracking() {
+    if let Some(refname) = only {
+        tracking_branches.retain(|tracking| {
+            tracking.local.refname == refname
+                || tracking
+                    .upstream
+                    .as_ref()
+                    .map_or(false, |upstream| upstream.refname == refname)
+                || tracking.pushes.iter().any(|push| push.refname == refname)
+        });
+        direct_fetch_branches.retain(|(local, _)| local.refname == refname);
+        local_tracking_branches.retain(|(local, _)| local.refname == refname);
+        non_tracking_branches.retain(|local| local.refname == refname);
+        non_upstream_branches.retain(|remote| remote.refname == refname);
+    }
+
+    if param.skip_defaults {
+        let is_default = |refname: &str| is_default_branch(&bases, refname);
+        tracking_branches.retain(|tracking| {
+            !is_default(tracking.local.refname())
+                && !tracking
+                    .upstream
+                    .as_ref()
+                    .map_or(false, |upstream| is_default(upstream.refname()))
+        });
+        direct_fetch_branches.retain(|(local, _)| !is_default(local.refname()));
+        local_tracking_branches.retain(|(local, _)| !is_default(local.refname()));
+        non_tracking_branches.retain(|local| !is_default(local.refname()));
+        non_upstream_branches.retain(|remote| !is_default(remote.refname()));
+    }
+
+    if param.limit_to_tracked {
+        let untracked: HashSet<_> = get_orphan_local_branches(git)?.into_iter().collect();
+        debug!("untracked: {:#?}", untracked);
+        non_tracking_branches.retain(|local| !untracked.contains(local));
+    }
+
+    // A base is never compared against itself, so it's kept back before classification.
+    let ignore_case = config::get_ignore_case(&git.config)?;
+    let is_base_local =
+        |local: &LocalBranch| bases.iter().any(|spec| spec.is_local(local, ignore_case));
+    let is_base_remote = |remote: &RemoteTrackingBranch| {
+        bases
+            .iter()
+            .any(|spec| spec.covers_remote(remote.refname(), ignore_case))
+    };
+    let mut base_branches = Vec::new();
+    tracking_branches.retain(|tracking| {
+        if !is_base_local(&tracking.local) {
+            return true;
+        }
+        base_branches.push(ClassifiedBranch::BaseLocal(tracking.local.clone()));
+        if let Some(upstream) = tracking.upstream.as_ref().filter(|u| is_base_remote(*u)) {
+            base_branches.push(ClassifiedBranch::BaseRemoteTracking(upstream.clone()));
+        }
+        false
+    });
+    let mut keep_non_base_local = |local: &LocalBranch| {
+        if is_base_local(local) {
+            base_branches.push(ClassifiedBranch::BaseLocal(local.clone()));
+            false
+        } else {
+            true
+        }
+    };
+    direct_fetch_branches.retain(|(local, _)| keep_non_base_local(local));
+    local_tracking_branches.retain(|(local, _)| keep_non_base_local(local));
+    non_tracking_branches.retain(|local| keep_non_base_local(local));
+    non_upstream_branches.retain(|remote| {
+        if is_base_remote(remote) {
+            base_branches.push(ClassifiedBranch::BaseRemoteTracking(remote.clone()));
+            false
+        } else {
+            true
+        }
+    });
+    debug!("base_branches: {:#?}", base_branches);
+
+    // The listed branches are known to be merged, so they are not classified.
+    let is_listed = |local: &LocalBranch| {
+        param
+            .merged_branches
+            .iter()
+            .any(|name| *name == local.short_name() || *name == local.refname())
+    };
+    let mut listed_branches = Vec::new();
+    tracking_branches.retain(|tracking| {
+        if !is_listed(&tracking.local) {
+            return true;
+        }
+        listed_branches.push(ClassifiedBranch::MergedLocal(tracking.local.clone()));
+        if let Some(upstream) = &tracking.upstream {
+            listed_branches.push(ClassifiedBranch::MergedRemoteTracking(upstream.clone()));
+        }
+        false
+    });
+    let mut keep_unlisted_local = |local: &LocalBranch| {
+        if is_listed(local) {
+            listed_branches.push(ClassifiedBranch::MergedLocal(local.clone()));
+            false
+        } else {
+            true
+        }
+    };
+    direct_fetch_branches.retain(|(local, _)| keep_unlisted_local(local));
+    local_tracking_branches.retain(|(local, _)| keep_unlisted_local(local));
+    non_tracking_branches.retain(|local| keep_unlisted_local(local));
+    debug!("listed_branches: {:#?}", listed_branches);
+
+    let (remote_heads, timed_out_remotes) = if param.delete.scan_tracking() && !param.offline {
         let remotes: Vec<_> = direct_fetch_branches
             .iter()
             .map(|(_, r)| r.clone())
             .collect();
-        get_remote_heads(git, &remotes)?
+        get_remote_heads(git, &remotes, param.timeout)?
     } else {
-        Vec::new()
+        (Vec::new(), HashSet::new())
     };
     debug!("remote_heads: {:#?}", remote_heads);
 
-    let merge_tracker = MergeTracker::with_base_upstreams(&git.repo, &git.config, &base_upstreams)?;
+    let mut merge_tracker = MergeTracker::with_bases(&git.repo, &git.config, &base_commits)?
+        .with_squash_detect(param.squash_detect)
+        .with_strict_squash(param.strict_squash);
+    if param.cache {
+        merge_tracker = merge_tracker.with_cache(MergeCache::load(&git.repo)?);
+    }
+    if param.pull_refs {
+        // A local branch is merged when a merged pull request head contains it.
+        merge_tracker = merge_tracker.with_pull_request_heads(
+            &git.repo,
+            &base_commits,
+            &get_pull_request_heads(git)?,
+        )?;
+    }
+    let mut base_overrides = HashMap::new();
+    let locals = tracking_branches
+        .iter()
+        .map(|tracking| &tracking.local)
+        .chain(direct_fetch_branches.iter().map(|(local, _)| local))
+        .chain(local_tracking_branches.iter().map(|(local, _)| local))
+        .chain(non_tracking_branches.iter());
+    for local in locals {
+        if let Some(base) = resolve_base_override(git, local, param.base_resolver)? {
+            base_overrides.insert(local.clone(), vec![base]);
+        }
+    }
+    debug!("base_overrides: {:#?}", base_overrides);
+
     let mut classifier = Classifier::new(git, &merge_tracker);
+    if param.incremental && only.is_none() {
+        classifier = classifier.with_state(ClassificationState::load(&git.repo)?)?;
+    }
     let mut skipped = HashMap::new();
 
     let remotes = get_remotes(&git.repo)?;
 
     info!("Enqueue classification requests");
     if param.delete.scan_tracking() {
-        for (local, upstream) in &tracking_branches {
-            for base in &base_upstreams {
+        for tracking in &tracking_branches {
+            for base in bases_of(&base_overrides, &base_commits, &tracking.local) {
                 classifier.queue_request(TrackingBranchClassificationRequest {
                     base,
-                    local,
-                    upstream: upstream.as_ref(),
+                    local: &tracking.local,
+                    upstream: tracking.upstream.as_ref(),
+                    pushes: &tracking.pushes,
                 });
             }
         }
 
         for (local, remote) in &direct_fetch_branches {
-            for base in &base_upstreams {
+            for base in bases_of(&base_overrides, &base_commits, local) {
                 classifier.queue_request_with_context(
                     DirectFetchClassificationRequest {
                         base,
@@ -116,9 +360,20 @@ pub fn get_trim_plan(git: &Git, param: &PlanParam) -> Result<TrimPlan> {
                 );
             }
         }
+
+        for (local, upstream) in &local_tracking_branches {
+            for base in bases_of(&base_overrides, &base_commits, local) {
+                classifier.queue_request(LocalTrackingBranchClassificationRequest {
+                    base,
+                    local,
+                    upstream: upstream.as_ref(),
+                });
+            }
+        }
     } else {
-        for (local, upstream) in &tracking_branches {
-            if let Some(upstream) = upstream {
+        for tracking in &tracking_branches {
+            let local = &tracking.local;
+            if let Some(upstream) = &tracking.upstream {
                 let remote = upstream.to_remote_branch(&remotes)?.remote;
                 let suggestion = SkipSuggestion::TrackingRemote(remote);
                 skipped.insert(local.refname.clone(), suggestion.clone());
@@ -126,16 +381,24 @@ pub fn get_trim_plan(git: &Git, param: &PlanParam) -> Result<TrimPlan> {
             } else {
                 skipped.insert(local.refname.clone(), SkipSuggestion::Tracking);
             }
+            for push in &tracking.pushes {
+                let remote = push.to_remote_branch(&remotes)?.remote;
+                skipped.insert(push.refname.clone(), SkipSuggestion::TrackingRemote(remote));
+            }
         }
 
         for (local, _) in &direct_fetch_branches {
             skipped.insert(local.refname.clone(), SkipSuggestion::Tracking);
         }
+
+        for (local, _) in &local_tracking_branches {
+            skipped.insert(local.refname.clone(), SkipSuggestion::Tracking);
+        }
     }
 
     if param.delete.scan_non_tracking_local() {
-        for base in &base_upstreams {
-            for local in &non_tracking_branches {
+        for local in &non_tracking_branches {
+            for base in bases_of(&base_overrides, &base_commits, local) {
                 classifier.queue_request(NonTrackingBranchClassificationRequest { base, local });
             }
         }
@@ -145,9 +408,21 @@ pub fn get_trim_plan(git: &Git, param: &PlanParam) -> Result<TrimPlan> {
         }
     }
 
-    for base in &base_upstreams {
+    for base in &base_commits {
         for remote_tracking in &non_upstream_branches {
-            let remote = remote_tracking.to_remote_branch(&remotes)?;
+            let remote = match remote_tracking.to_remote_branch(&remotes) {
+                Ok(remote) => remote,
+                Err(RemoteBranchError::RemoteNotFound) => {
+                    // We don't know the real state of the remote branch.
+                    // It is classified only to be kept back by `preserve_outside_fetch_refspec`.
+                    classifier.queue_request(NonUpstreamBranchClassificationRequest {
+                        base,
+                        remote: remote_tracking,
+                    });
+                    continue;
+                }
+                Err(err) => return Err(err.into()),
+            };
             if param.delete.scan_non_upstream_remote(&remote.remote) {
                 classifier.queue_request(NonUpstreamBranchClassificationRequest {
                     base,
@@ -163,75 +438,896 @@ pub fn get_trim_plan(git: &Git, param: &PlanParam) -> Result<TrimPlan> {
         }
     }
 
-    let classifications = classifier.classify()?;
+    let mut classifications = classifier.classify()?;
+    if only.is_none() {
+        merge_tracker.save_cache(&git.repo)?;
+    }
+    classifications.sort_by(|a, b| (&a.branch, &a.base).cmp(&(&b.branch, &b.base)));
 
     let mut result = TrimPlan {
         skipped,
         to_delete: HashSet::new(),
-        preserved: Vec::new(),
+        preserved: base_branches
+            .into_iter()
+            .map(|branch| Preserved {
+                branch,
+                reason: "base".to_owned(),
+                base: true,
+                remote_only: false,
+            })
+            .collect(),
+        messages: HashMap::new(),
+        classifications: Vec::new(),
+        remote_states: HashMap::new(),
+        diagnostics: get_skipped_local_refs(git)?,
+        local_only: HashSet::new(),
     };
-    for classification in classifications {
-        result.to_delete.extend(classification.result);
+    for classification in &classifications {
+        for branch in &classification.result {
+            let messages = result.messages.entry(branch.clone()).or_default();
+            if !messages.contains(&classification.message) {
+                messages.push(classification.message);
+            }
+        }
+        result
+            .to_delete
+            .extend(classification.result.iter().cloned());
+    }
+    for tracking in &tracking_branches {
+        let merged = ClassifiedBranch::MergedLocal(tracking.local.clone());
+        if !result.to_delete.contains(&merged) {
+            continue;
+        }
+        let state = if tracking.upstream.is_some() {
+            RemoteState::Present
+        } else {
+            RemoteState::Deleted
+        };
+        result
+            .remote_states
+            .insert(tracking.local.refname.clone(), state);
+    }
+    for branch in listed_branches {
+        result
+            .messages
+            .insert(branch.clone(), vec!["listed as merged"]);
+        result.to_delete.insert(branch);
+    }
+    result.classifications = classifications;
+    if param.delete.scan_stale_tracking() {
+        for stale in get_stale_remote_tracking_branches(git, &param.delete, param.timeout)? {
+            if param.skip_defaults && is_default_branch(&bases, stale.refname()) {
+                continue;
+            }
+            result
+                .to_delete
+                .insert(ClassifiedBranch::StaleRemoteTracking(stale));
+        }
     }
 
+    if param.offline {
+        result.preserve_unknown_remote_state(
+            &direct_fetch_branches,
+            "remote state unknown (offline)",
+        );
+    } else if !timed_out_remotes.is_empty() {
+        let mut remotes: Vec<_> = timed_out_remotes.iter().collect();
+        remotes.sort();
+        for remote in remotes {
+            result.diagnostics.push(Diagnostic::new(
+                DiagnosticKind::RemoteTimedOut,
+                format!("Listing the heads of `{}` timed out", remote),
+                Some(remote.clone()),
+            ));
+        }
+        let timed_out: Vec<_> = direct_fetch_branches
+            .iter()
+            .filter(|(_, remote)| timed_out_remotes.contains(&remote.remote))
+            .cloned()
+            .collect();
+        result.preserve_unknown_remote_state(&timed_out, "remote state unknown (timed out)");
+    }
+    result.preserve_outside_fetch_refspec(&git.repo)?;
     result.preserve_bases(&git.repo, &git.config, &bases)?;
-    result.preserve_protected(&git.repo, &param.protected_patterns)?;
-    result.preserve_non_heads_remotes(&git.repo)?;
+    result.preserve_protected(&git.repo, &git.config, &param.protected_patterns)?;
+    if !param.protected_commits.is_empty() {
+        let mut commits = Vec::new();
+        for commit in &param.protected_commits {
+            let object = git
+                .repo
+                .revparse_single(commit)
+                .with_context(|| format!("The protected commit `{}` can't be resolved", commit))?;
+            commits.push(object.peel_to_commit()?.id());
+        }
+        result.preserve_protected_commits(&git.repo, &commits)?;
+    }
+    for target in &param.protect_merged_into {
+        let reference = git
+            .repo
+            .resolve_reference_from_short_name(target)
+            .with_context(|| format!("The ref `{}` can't be resolved", target))?;
+        let refname = reference.name().context("non-utf8 refname")?;
+        result.preserve_merged_into(&git.repo, &git.config, &merge_tracker, target, refname)?;
+    }
+    if !param.protect_merged_into.is_empty() && only.is_none() {
+        merge_tracker.save_cache(&git.repo)?;
+    }
+    if param.keep_if_protected_upstream {
+        result.preserve_protected_upstream(&git.repo, &git.config, &param.protected_patterns)?;
+    }
+    if param.keep_stashed {
+        result.preserve_stashed(&git.repo)?;
+    }
+    if let Some(count) = param.keep_recent {
+        result.preserve_recent(&git.repo, count)?;
+    }
+    if let Some(count) = param.keep_recent_checkouts {
+        result.preserve_recent_checkouts(&git.repo, count)?;
+    }
+    if let Some(age) = param.merged_older_than {
+        result.preserve_merged_recently(&git.repo, age)?;
+    }
+    // The disagreeing upstreams keep their locals back, while `preserve_remote_ahead` doesn't.
+    result.preserve_disagreeing_upstreams();
+    result.preserve_force_updated_upstreams();
+    if param.cautious_push_only {
+        result.preserve_push_only_upstreams();
+    }
+    if param.keep_remote_ahead {
+        result.preserve_remote_ahead(&git.repo)?;
+    }
+    if let Some(limit) = param.remote_ahead_guard {
+        result.preserve_remote_ahead_of_bases(&git.repo, &git.config, &base_commits, limit)?;
+    }
+    if param.detect_reverted {
+        let mut base_tips = Vec::new();
+        for spec in &bases {
+            base_tips.push(spec.tip(&git.repo)?);
+        }
+        result.preserve_reverted(&git.repo, &base_tips)?;
+    }
+    if param.detect_renamed_upstream {
+        result.preserve_renamed_upstreams(&git.repo)?;
+    }
+    result.preserve_non_heads_remotes(&git.repo, param.gerrit)?;
     result.preserve_worktree(&git.repo)?;
+    // Excludes take precedence over the delete filter.
+    result.preserve_excluded(&git.repo, &git.config, &param.exclude_patterns)?;
+    if param.stack_prefix {
+        result.preserve_outside_stack(&git.repo)?;
+    }
+    if param.cross_check_gone {
+        if subprocess::has_git_binary() {
+            let gone_by_git = get_gone_upstream_locals(&git.repo)?;
+            for local in gone_by_libgit2.difference(&gone_by_git) {
+                let message = format!(
+                    "The upstream of `{}` is gone, but `git branch -vv` doesn't mark it [gone]",
+                    local.short_name()
+                );
+                warn!("{}", message);
+                result.diagnostics.push(Diagnostic::new(
+                    DiagnosticKind::GoneMismatch,
+                    message,
+                    Some(local.refname.clone()),
+                ));
+            }
+            for local in gone_by_git.difference(&gone_by_libgit2) {
+                let message = format!(
+                    "`git branch -vv` marks the upstream of `{}` [gone], but it isn't gone",
+                    local.short_name()
+                );
+                warn!("{}", message);
+                result.diagnostics.push(Diagnostic::new(
+                    DiagnosticKind::GoneMismatch,
+                    message,
+                    Some(local.refname.clone()),
+                ));
+            }
+            result.preserve_uncorroborated_strays(&gone_by_libgit2, &gone_by_git);
+        } else {
+            warn!("`git` is not found. Skip cross-checking the gone upstreams");
+        }
+    }
+    if param.assume_unmerged_safe {
+        result.preserve_strays();
+    }
+    if param.remote_default_only {
+        result.preserve_fetch_only_remotes(&git.repo, &git.config)?;
+    }
     result.apply_delete_range_filter(&git.repo, &param.delete)?;
+    if param.no_stray_remote_delete {
+        result.preserve_stray_remotes();
+    }
+    // After every other keep-back, since the kept back branches are active as well.
+    if param.keep_ancestors {
+        let mut base_tips = Vec::new();
+        for spec in &bases {
+            base_tips.push(spec.tip(&git.repo)?);
+        }
+        result.preserve_ancestors_of_active(&git.repo, &base_tips)?;
+    }
 
     if !param.detach {
         result.adjust_not_to_detach(&git.repo)?;
     }
+    if let Some(head) = param.head {
+        result.preserve_head_override(&git.repo, head)?;
+    }
+
+    Ok(result)
+}
+
+/// The network operations of a trim, counted without performing them.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct NetworkEstimate {
+    /// Distinct URLs of the remotes that are fetched, listed or pushed to.
+    pub remote_urls: usize,
+    /// `git remote update` fetches each remote.
+    pub fetches: usize,
+    /// `git ls-remote` lists the remote of each branch that is fetched directly from a URL.
+    pub listings: usize,
+    /// A push deletes the remote branches of each remote.
+    pub pushes: usize,
+    pub remote_refs_to_delete: usize,
+    /// The direct fetch branches to delete or kept back as stray, whose remote branches might be
+    /// deleted as well. Their remote states are unknown, since the remote heads aren't listed for the estimate.
+    pub unknown_remote_refs: usize,
+}
+
+/// Counts the network operations of trimming with the `plan`.
+/// `update` is whether the remotes are updated first, and `list_remote_heads` is whether
+/// the remote heads are listed for the direct fetch branches, which the plan doesn't tell.
+/// The `plan` is expected to be made offline, not to list the remote heads for the estimate.
+pub fn estimate_network(
+    git: &Git,
+    plan: &TrimPlan,
+    update: bool,
+    list_remote_heads: bool,
+) -> Result<NetworkEstimate> {
+    let mut urls = HashSet::new();
+    let mut estimate = NetworkEstimate::default();
+    if update {
+        for remote in get_remotes(&git.repo)? {
+            urls.insert(remote.url().context("non-utf8 remote url")?.to_owned());
+            estimate.fetches += 1;
+        }
+    }
+    if list_remote_heads {
+        for (local, remote) in get_direct_fetch_branches(git)? {
+            urls.insert(remote.remote);
+            estimate.listings += 1;
+            let stray = ClassifiedBranch::Stray(local.clone());
+            if plan
+                .to_delete
+                .iter()
+                .any(|branch| branch.local() == Some(&local))
+                || plan
+                    .preserved
+                    .iter()
+                    .any(|preserved| preserved.branch == stray)
+            {
+                estimate.unknown_remote_refs += 1;
+            }
+        }
+    }
+    let mut pushed = HashSet::new();
+    for remote_branch in plan.remotes_to_delete(&git.repo)? {
+        estimate.remote_refs_to_delete += 1;
+        if !pushed.insert(remote_branch.remote.clone()) {
+            continue;
+        }
+        estimate.pushes += 1;
+        let remote = git.repo.find_remote(&remote_branch.remote)?;
+        let url = remote.pushurl().or_else(|| remote.url());
+        urls.insert(url.context("non-utf8 remote url")?.to_owned());
+    }
+    estimate.remote_urls = urls.len();
+    Ok(estimate)
+}
+
+/// A local base that is behind its upstream.
+#[derive(Debug)]
+pub struct BehindBase {
+    pub local: LocalBranch,
+    pub upstream: RemoteTrackingBranch,
+    /// The number of the commits that are only in the upstream.
+    pub behind: usize,
+}
 
+/// Local bases that are behind their upstreams.
+/// The branches are compared with the upstreams, but a base left behind hints that they might be stale as well.
+pub fn get_behind_bases(git: &Git, bases: &[&str]) -> Result<Vec<BehindBase>> {
+    let mut result = Vec::new();
+    for spec in resolve_bases(&git.repo, &git.config, bases)? {
+        if let BaseSpec::Local {
+            local, upstream, ..
+        } = spec
+        {
+            let local_oid = git
+                .repo
+                .find_reference(&local.refname)?
+                .peel_to_commit()?
+                .id();
+            let upstream_oid = git
+                .repo
+                .find_reference(&upstream.refname)?
+                .peel_to_commit()?
+                .id();
+            let (_, behind) = git.repo.graph_ahead_behind(local_oid, upstream_oid)?;
+            if behind > 0 {
+                result.push(BehindBase {
+                    local,
+                    upstream,
+                    behind,
+                });
+            }
+        }
+    }
     Ok(result)
 }
 
+/// `branch.<name>.git-trim-base` or the `resolver` of the library consumers overrides the bases for the branch.
+/// The config that the user set wins over the inferred one.
+/// It falls back to the global bases with a warning when it can't be resolved.
+fn resolve_base_override(
+    git: &Git,
+    local: &LocalBranch,
+    resolver: Option<&dyn Fn(&str) -> Option<String>>,
+) -> Result<Option<BaseCommit>> {
+    let base = match config::get_base_override(&git.config, local)? {
+        Some(base) => base,
+        None => match resolver.and_then(|resolve| resolve(&local.refname)) {
+            Some(base) => base,
+            None => return Ok(None),
+        },
+    };
+    match resolve_bases(&git.repo, &git.config, &[base.as_str()])?.first() {
+        Some(spec) => Ok(Some(spec.compared(&git.repo)?)),
+        None => {
+            warn!(
+                "The base `{}` of `{}` can't be resolved. Fall back to the global bases",
+                base,
+                local.short_name()
+            );
+            Ok(None)
+        }
+    }
+}
+
+fn bases_of<'b>(
+    overrides: &'b HashMap<LocalBranch, Vec<BaseCommit>>,
+    bases: &'b [BaseCommit],
+    local: &LocalBranch,
+) -> &'b [BaseCommit] {
+    overrides.get(local).map_or(bases, |overridden| overridden)
+}
+
+/// Check whether `branch` is merged into `base` with the same detection `get_trim_plan` uses.
+/// Both are resolved like `git rev-parse` does, so short names are accepted.
+/// `merge-base:<base>` compares with the merge base of HEAD and the base.
+pub fn is_merged(git: &Git, branch: &str, base: &str) -> Result<bool> {
+    let (merge_base, base) = split_merge_base(base);
+    let base = git.repo.resolve_reference_from_short_name(base)?;
+    let base = base.name().context("non-utf8 base refname")?;
+    let base = if merge_base {
+        match get_merge_base_with_head(&git.repo, base)? {
+            Some(merge_base) => merge_base,
+            // Nothing can be merged into the base of unrelated histories.
+            None => return Ok(false),
+        }
+    } else {
+        git.repo.revparse_single(base)?.peel_to_commit()?.id()
+    };
+    let merge_tracker = MergeTracker::with_bases(&git.repo, &git.config, &[])?;
+
+    let reference = git.repo.resolve_reference_from_short_name(branch)?;
+    let merged = if reference.is_branch() {
+        let local = LocalBranch::try_from(&reference)?;
+        merge_tracker
+            .check_and_track(&git.repo, base, &local)?
+            .merged
+    } else if reference.is_remote() {
+        let remote = RemoteTrackingBranch::try_from(&reference)?;
+        merge_tracker
+            .check_and_track(&git.repo, base, &remote)?
+            .merged
+    } else {
+        return Err(anyhow::anyhow!(
+            "`{}` is neither a local branch nor a remote tracking branch",
+            branch
+        ));
+    };
+    Ok(merged)
+}
+
+/// The inputs and the outcome of the classification of a single branch, for `--explain-branch`.
+#[derive(Debug)]
+pub struct BranchExplanation {
+    pub refname: String,
+    pub tip: Oid,
+    /// The fetch upstream of a local branch. `None` when it is gone or not set.
+    pub upstream: Option<RemoteTrackingBranch>,
+    pub pushes: Vec<RemoteTrackingBranch>,
+    pub bases: Vec<BaseExplanation>,
+    /// The messages and the results of the classifications of the branch against each base.
+    pub classifications: Vec<(String, &'static str, Vec<ClassifiedBranch>)>,
+    pub to_delete: Vec<ClassifiedBranch>,
+    /// Kept back branches with their reasons.
+    pub preserved: Vec<(ClassifiedBranch, String)>,
+}
+
+/// How the branch compares to a base.
+#[derive(Debug)]
+pub struct BaseExplanation {
+    pub base: String,
+    pub tip: Oid,
+    /// `None` when they don't share any history.
+    pub merge_base: Option<Oid>,
+    /// Whether every commit of the branch is in the base by `git rev-list --cherry-pick`.
+    pub rev_list_merged: bool,
+    /// `None` when squash detection is disabled or skipped.
+    pub squash_merged: Option<bool>,
+    /// The verdict of the merge tracker, which combines them.
+    pub merged: bool,
+    /// The fetch upstream and the push upstreams with whether they are merged.
+    pub upstreams_merged: Vec<(RemoteTrackingBranch, bool)>,
+}
+
+/// Explains how the branch is classified with `param`.
+/// Only the branch and its upstreams are classified, so the filters that compare the branches
+/// with each other, like `keep_recent`, see them alone. Neither the cache nor the incremental state is saved.
+pub fn explain_branch(git: &Git, param: &PlanParam, branch: &str) -> Result<BranchExplanation> {
+    let repo = &git.repo;
+    let reference = repo.resolve_reference_from_short_name(branch)?;
+    let refname = reference
+        .name()
+        .context("non-utf8 branch refname")?
+        .to_owned();
+    if !reference.is_branch() && !reference.is_remote() {
+        return Err(anyhow::anyhow!(
+            "`{}` is neither a local branch nor a remote tracking branch",
+            branch
+        ));
+    }
+    let tip = reference.peel_to_commit()?.id();
+
+    let (upstream, pushes) = if reference.is_branch() {
+        let local = LocalBranch::new(&refname);
+        let upstream = match local.fetch_upstream(repo, &git.config)? {
+            RemoteTrackingBranchStatus::Exists(upstream) => Some(upstream),
+            _ => None,
+        };
+        let mut pushes = local.push_upstreams(repo, &git.config)?;
+        pushes.retain(|push| Some(push) != upstream.as_ref());
+        (upstream, pushes)
+    } else {
+        (None, Vec::new())
+    };
+
+    let plan = plan_branches(git, param, Some(&refname))?;
+    let involves = |classified: &ClassifiedBranch| {
+        classified.local().map(|local| local.refname.as_str()) == Some(refname.as_str())
+            || classified
+                .upstream()
+                .map(|upstream| upstream.refname.as_str())
+                == Some(refname.as_str())
+    };
+    let classifications: Vec<_> = plan
+        .classifications
+        .iter()
+        .filter(|classification| {
+            classification.branch == refname || classification.result.iter().any(involves)
+        })
+        .map(|classification| {
+            (
+                classification.base.clone(),
+                classification.message,
+                classification.result.clone(),
+            )
+        })
+        .collect();
+
+    let mut candidates = Vec::new();
+    if reference.is_branch() {
+        let local = LocalBranch::new(&refname);
+        candidates.extend(resolve_base_override(git, &local, param.base_resolver)?);
+    }
+    let mut specs = resolve_bases(repo, &git.config, &param.bases)?;
+    specs.extend(resolve_tag_bases(repo, &param.merged_into_tags)?);
+    for spec in specs {
+        candidates.push(spec.compared(repo)?);
+    }
+    let mut base_commits = Vec::new();
+    for (base, _, _) in &classifications {
+        if let Some(candidate) = candidates.iter().find(|candidate| &candidate.name == base) {
+            if !base_commits.contains(candidate) {
+                base_commits.push(candidate.clone());
+            }
+        }
+    }
+    if base_commits.is_empty() {
+        base_commits = candidates;
+    }
+
+    let merge_tracker = MergeTracker::with_bases(repo, &git.config, &[])?
+        .with_squash_detect(param.squash_detect)
+        .with_strict_squash(param.strict_squash);
+    let mut bases = Vec::new();
+    for base in base_commits {
+        let merged = if reference.is_branch() {
+            let local = LocalBranch::new(&refname);
+            merge_tracker
+                .check_and_track(repo, base.oid, &local)?
+                .merged
+        } else {
+            let remote = RemoteTrackingBranch::new(&refname);
+            merge_tracker
+                .check_and_track(repo, base.oid, &remote)?
+                .merged
+        };
+        let mut upstreams_merged = Vec::new();
+        for upstream in upstream.iter().chain(&pushes) {
+            let merged = merge_tracker
+                .check_and_track(repo, base.oid, upstream)?
+                .merged;
+            upstreams_merged.push((upstream.clone(), merged));
+        }
+        bases.push(BaseExplanation {
+            merge_base: repo.merge_base(base.oid, tip).ok(),
+            rev_list_merged: is_merged_by_cherry_pick(repo, &base.oid.to_string(), &refname)?,
+            squash_merged: if param.squash_detect {
+                merge_tracker.check_squash(repo, base.oid, tip, &refname)?
+            } else {
+                None
+            },
+            merged,
+            upstreams_merged,
+            tip: base.oid,
+            base: base.name,
+        });
+    }
+
+    let mut to_delete: Vec<_> = plan
+        .to_delete
+        .iter()
+        .filter(|b| involves(b))
+        .cloned()
+        .collect();
+    to_delete.sort_by_key(|branch| branch.category());
+    let preserved = plan
+        .preserved
+        .iter()
+        .filter(|preserved| involves(&preserved.branch))
+        .map(|preserved| (preserved.branch.clone(), preserved.reason.clone()))
+        .collect();
+
+    Ok(BranchExplanation {
+        refname,
+        tip,
+        upstream,
+        pushes,
+        bases,
+        classifications,
+        to_delete,
+        preserved,
+    })
+}
+
+/// Fetch the remotes of the bases, so that branches are compared with the latest base tips.
+pub fn fetch_bases(
+    git: &Git,
+    bases: &[&str],
+    dry_run: bool,
+    timeout: Option<Duration>,
+) -> Result<()> {
+    let remotes = get_remotes(&git.repo)?;
+    let mut fetched = HashSet::new();
+    for base in resolve_bases(&git.repo, &git.config, bases)? {
+        let remote = base.upstream().to_remote_branch(&remotes)?.remote;
+        if fetched.insert(remote.clone()) {
+            subprocess::fetch(&git.repo, &remote, dry_run, timeout)?;
+        }
+    }
+    Ok(())
+}
+
 #[derive(Debug)]
 pub(crate) enum BaseSpec<'a> {
     Local {
         pattern: &'a str,
         local: LocalBranch,
         upstream: RemoteTrackingBranch,
+        /// The merge base of HEAD and the upstream for `merge-base:<base>`.
+        merge_base: Option<Oid>,
     },
     Remote {
         pattern: &'a str,
         remote: RemoteTrackingBranch,
+        /// The merge base of HEAD and the remote for `merge-base:<base>`.
+        merge_base: Option<Oid>,
     },
+    /// Either a lightweight or an annotated tag.
+    /// The branches of the same name are kept back, since `git` resolves the name to the tag.
+    Tag {
+        pattern: &'a str,
+        tag: String,
+        /// The commit that the tag is peeled to, or its merge base with HEAD for `merge-base:<base>`.
+        commit: Oid,
+    },
+}
+
+/// The branch name of a local or a remote tracking branch, `main` for `refs/remotes/origin/main`.
+fn branch_name(refname: &str) -> Option<&str> {
+    if let Some(name) = refname.strip_prefix("refs/heads/") {
+        Some(name)
+    } else if let Some(name) = refname.strip_prefix("refs/remotes/") {
+        name.splitn(2, '/').nth(1)
+    } else {
+        None
+    }
+}
+
+fn refname_eq(lhs: &str, rhs: &str, ignore_case: bool) -> bool {
+    if ignore_case {
+        lhs.to_lowercase() == rhs.to_lowercase()
+    } else {
+        lhs == rhs
+    }
 }
 
 impl<'a> BaseSpec<'a> {
-    fn is_local(&self, branch: &LocalBranch) -> bool {
-        matches!(self, BaseSpec::Local { local, .. } if local == branch)
+    /// The commit of the base itself, regardless of `merge-base:<base>`.
+    fn tip(&self, repo: &Repository) -> Result<Oid> {
+        let refname = match self {
+            BaseSpec::Local { upstream, .. } => &upstream.refname,
+            BaseSpec::Remote { remote, .. } => &remote.refname,
+            BaseSpec::Tag { tag, .. } => tag,
+        };
+        Ok(repo.find_reference(refname)?.peel_to_commit()?.id())
     }
 
-    fn covers_remote(&self, refname: &str) -> bool {
-        match self {
-            BaseSpec::Local { upstream, .. } if upstream.refname() == refname => true,
-            BaseSpec::Remote { remote, .. } if remote.refname() == refname => true,
+    /// What the other branches are compared with.
+    /// The merge base is named `merge-base:<refname>` after the base, and a tag after its refname.
+    fn compared(&self, repo: &Repository) -> Result<BaseCommit> {
+        let result = match self {
+            BaseSpec::Local {
+                upstream: base,
+                merge_base: Some(oid),
+                ..
+            }
+            | BaseSpec::Remote {
+                remote: base,
+                merge_base: Some(oid),
+                ..
+            } => BaseCommit {
+                oid: *oid,
+                name: format!("merge-base:{}", base.refname),
+            },
+            BaseSpec::Tag { tag, commit, .. } => BaseCommit {
+                oid: *commit,
+                name: tag.clone(),
+            },
+            BaseSpec::Local { upstream: base, .. } | BaseSpec::Remote { remote: base, .. } => {
+                BaseCommit {
+                    oid: self.tip(repo)?,
+                    name: base.refname.clone(),
+                }
+            }
+        };
+        Ok(result)
+    }
+
+    fn is_tag_name(&self, refname: &str, ignore_case: bool) -> bool {
+        match (self, branch_name(refname)) {
+            (BaseSpec::Tag { tag, .. }, Some(name)) => {
+                refname_eq(tag.trim_start_matches("refs/tags/"), name, ignore_case)
+            }
             _ => false,
         }
     }
 
-    fn remote_pattern(&self, refname: &str) -> Option<&str> {
+    fn is_local(&self, branch: &LocalBranch, ignore_case: bool) -> bool {
+        match self {
+            BaseSpec::Local { local, .. } => {
+                refname_eq(local.refname(), branch.refname(), ignore_case)
+            }
+            BaseSpec::Remote { .. } => false,
+            BaseSpec::Tag { .. } => self.is_tag_name(branch.refname(), ignore_case),
+        }
+    }
+
+    fn covers_remote(&self, refname: &str, ignore_case: bool) -> bool {
+        match self {
+            BaseSpec::Local { upstream, .. } => {
+                refname_eq(upstream.refname(), refname, ignore_case)
+            }
+            BaseSpec::Remote { remote, .. } => refname_eq(remote.refname(), refname, ignore_case),
+            BaseSpec::Tag { .. } => self.is_tag_name(refname, ignore_case),
+        }
+    }
+
+    fn remote_pattern(&self, refname: &str, ignore_case: bool) -> Option<&str> {
         match self {
-            BaseSpec::Remote { pattern, remote } if remote.refname() == refname => Some(pattern),
+            BaseSpec::Remote {
+                pattern, remote, ..
+            } if refname_eq(remote.refname(), refname, ignore_case) => Some(pattern),
+            BaseSpec::Tag { pattern, .. } if self.is_tag_name(refname, ignore_case) => {
+                Some(pattern)
+            }
             _ => None,
         }
     }
 }
 
+const DEFAULT_BRANCH_NAMES: &[&str] = &["main", "master"];
+
+/// `main`, `master` and the bases, either local or remote tracking branches.
+fn is_default_branch(bases: &[BaseSpec], refname: &str) -> bool {
+    if let Some(name) = branch_name(refname) {
+        if DEFAULT_BRANCH_NAMES.contains(&name) {
+            return true;
+        }
+    }
+    bases.iter().any(|base| match base {
+        BaseSpec::Local {
+            local, upstream, ..
+        } => local.refname() == refname || upstream.refname() == refname,
+        BaseSpec::Remote { remote, .. } => remote.refname() == refname,
+        BaseSpec::Tag { .. } => base.is_tag_name(refname, false),
+    })
+}
+
+/// `merge-base:<base>` is compared with the merge base of HEAD and `<base>` instead of its tip.
+fn split_merge_base(base: &str) -> (bool, &str) {
+    match base.strip_prefix("merge-base:") {
+        Some(base) => (true, base),
+        None => (false, base),
+    }
+}
+
+/// `None` when HEAD and the base have unrelated histories.
+fn get_merge_base_with_head(repo: &Repository, refname: &str) -> Result<Option<Oid>> {
+    let head = repo.head()?.peel_to_commit()?.id();
+    let base = repo.find_reference(refname)?.peel_to_commit()?.id();
+    match repo.merge_base(head, base) {
+        Ok(merge_base) => Ok(Some(merge_base)),
+        Err(err) if err.class() == ErrorClass::Merge && err.code() == ErrorCode::NotFound => {
+            Ok(None)
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Accepts `main`, `origin/main`, `refs/heads/main` and `refs/remotes/origin/main`.
+/// `main` falls back to `<remote>/main` when there is no local `main` and only one remote has it.
+fn resolve_base_reference<'r>(repo: &'r Repository, base: &str) -> Result<Option<Reference<'r>>> {
+    match repo.resolve_reference_from_short_name(base) {
+        Ok(reference) => return Ok(Some(reference)),
+        Err(err) if err.code() == ErrorCode::NotFound => {}
+        Err(err) => return Err(err.into()),
+    }
+
+    let mut candidates = Vec::new();
+    for remote_name in repo.remotes()?.iter() {
+        let remote_name = remote_name.context("non-utf8 remote name")?;
+        let refname = format!("refs/remotes/{}/{}", remote_name, base);
+        match repo.find_reference(&refname) {
+            Ok(reference) => candidates.push(reference),
+            Err(err)
+                if err.code() == ErrorCode::NotFound || err.code() == ErrorCode::InvalidSpec => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+    if candidates.len() == 1 {
+        Ok(candidates.pop())
+    } else {
+        Ok(None)
+    }
+}
+
+/// The tags that match the glob `patterns`, as tag bases.
+fn resolve_tag_bases<'a>(repo: &Repository, patterns: &[&'a str]) -> Result<Vec<BaseSpec<'a>>> {
+    let mut result = Vec::new();
+    for pattern in patterns {
+        for reference in repo.references_glob(&format!("refs/tags/{}", pattern))? {
+            let reference = reference?;
+            let tag = reference.name().context("non-utf8 tag refname")?.to_owned();
+            let commit = match reference.peel_to_commit() {
+                Ok(commit) => commit.id(),
+                Err(_) => {
+                    warn!("Skip the tag `{}` of a non-commit object", tag);
+                    continue;
+                }
+            };
+            result.push(BaseSpec::Tag {
+                pattern,
+                tag,
+                commit,
+            });
+        }
+    }
+    Ok(result)
+}
+
+/// Resolves a base to the commit that the other branches are compared with.
+/// A local branch is resolved to its upstream, and a tag to the commit that it is peeled to.
+pub fn resolve_base(git: &Git, base: &str) -> Result<BaseCommit> {
+    let (_, base) = split_merge_base(base);
+    let reference = resolve_base_reference(&git.repo, base)?.with_context(|| {
+        format!(
+            "The base `{}` can't be resolved to an existing branch. \
+             Use a local branch like `main` or a remote tracking branch like `origin/main`",
+            base
+        )
+    })?;
+    if reference.is_branch() {
+        let local = LocalBranch::try_from(&reference)?;
+        match local.fetch_upstream(&git.repo, &git.config)? {
+            RemoteTrackingBranchStatus::Exists(upstream) => Ok(BaseCommit {
+                oid: git
+                    .repo
+                    .find_reference(&upstream.refname)?
+                    .peel_to_commit()?
+                    .id(),
+                name: upstream.refname,
+            }),
+            _ => Err(anyhow::anyhow!(
+                "The base `{}` is a local branch without an existing upstream",
+                base
+            )),
+        }
+    } else if reference.is_remote() || reference.is_tag() {
+        Ok(BaseCommit {
+            oid: reference.peel_to_commit()?.id(),
+            name: reference
+                .name()
+                .context("non-utf8 base refname")?
+                .to_owned(),
+        })
+    } else {
+        Err(anyhow::anyhow!(
+            "The base `{}` is neither a branch nor a tag",
+            base
+        ))
+    }
+}
+
+/// The commit of the first base that can be resolved, where `--note-deletions` records the deletions.
+pub fn resolve_base_commit(repo: &Repository, bases: &[&str]) -> Result<Option<Oid>> {
+    for base in bases {
+        let (_, base) = split_merge_base(base);
+        if let Some(reference) = resolve_base_reference(repo, base)? {
+            return Ok(Some(reference.peel_to_commit()?.id()));
+        }
+    }
+    Ok(None)
+}
+
 pub(crate) fn resolve_bases<'a>(
     repo: &Repository,
     config: &GitConfig,
     bases: &[&'a str],
 ) -> Result<Vec<BaseSpec<'a>>> {
     let mut result = Vec::new();
-    for base in bases {
-        let reference = match repo.resolve_reference_from_short_name(base) {
-            Ok(reference) => reference,
-            Err(err) if err.code() == ErrorCode::NotFound => continue,
-            Err(err) => return Err(err.into()),
+    for pattern in bases {
+        let (merge_base, base) = split_merge_base(pattern);
+        let reference = if let Some(reference) = resolve_base_reference(repo, base)? {
+            reference
+        } else {
+            continue;
+        };
+        // A local base is compared with its upstream, so the merge base is taken from the upstream as well.
+        // `None` skips the base, which has no merge base with HEAD.
+        let get_merge_base = |upstream: &RemoteTrackingBranch| -> Result<Option<Option<Oid>>> {
+            if !merge_base {
+                return Ok(Some(None));
+            }
+            let merge_base = get_merge_base_with_head(repo, upstream.refname())?;
+            if merge_base.is_none() {
+                warn!("Skip the base `{}` unrelated to HEAD", pattern);
+                return Ok(None);
+            }
+            Ok(Some(merge_base))
         };
 
         if reference.is_branch() {
@@ -239,16 +1335,44 @@ pub(crate) fn resolve_bases<'a>(
             if let RemoteTrackingBranchStatus::Exists(upstream) =
                 local.fetch_upstream(repo, config)?
             {
+                let merge_base = match get_merge_base(&upstream)? {
+                    Some(merge_base) => merge_base,
+                    None => continue,
+                };
                 result.push(BaseSpec::Local {
-                    pattern: base,
+                    pattern,
                     local,
+                    merge_base,
                     upstream,
                 })
             }
-        } else {
+        } else if reference.is_tag() {
+            let tag = reference.name().context("non-utf8 tag refname")?.to_owned();
+            let commit = if merge_base {
+                match get_merge_base_with_head(repo, &tag)? {
+                    Some(commit) => commit,
+                    None => {
+                        warn!("Skip the base `{}` unrelated to HEAD", pattern);
+                        continue;
+                    }
+                }
+            } else {
+                reference.peel_to_commit()?.id()
+            };
+            result.push(BaseSpec::Tag {
+                pattern,
+                tag,
+                commit,
+            })
+        } else if reference.is_remote() {
             let remote = RemoteTrackingBranch::try_from(&reference)?;
+            let merge_base = match get_merge_base(&remote)? {
+                Some(merge_base) => merge_base,
+                None => continue,
+            };
             result.push(BaseSpec::Remote {
-                pattern: base,
+                pattern,
+                merge_base,
                 remote,
             })
         }
@@ -261,6 +1385,16 @@ pub fn delete_local_branches(
     repo: &Repository,
     branches: &[&LocalBranch],
     dry_run: bool,
+) -> Result<()> {
+    delete_local_branches_with(repo, branches, dry_run, false)
+}
+
+/// `quiet` doesn't print the deleted branches, like when stdout is an event stream.
+fn delete_local_branches_with(
+    repo: &Repository,
+    branches: &[&LocalBranch],
+    dry_run: bool,
+    quiet: bool,
 ) -> Result<()> {
     if branches.is_empty() {
         return Ok(());
@@ -281,15 +1415,228 @@ pub fn delete_local_branches(
     if let Some(head) = detach_to {
         subprocess::checkout(repo, head, dry_run)?;
     }
-    subprocess::branch_delete(repo, branches, dry_run)?;
+    let refnames: Vec<_> = branches
+        .iter()
+        .map(|branch| branch.refname.as_str())
+        .collect();
+    branch_delete(repo, &refnames, BranchType::Local, dry_run, quiet)?;
+
+    Ok(())
+}
+
+/// Renames the branches to `<prefix>/<branch>` like `git branch --move`, which keeps their reflogs.
+/// A branch is skipped with a message when its new name is already taken.
+pub fn rename_local_branches(
+    repo: &Repository,
+    branches: &[&LocalBranch],
+    prefix: &str,
+    dry_run: bool,
+) -> Result<()> {
+    rename_local_branches_with(repo, branches, prefix, dry_run, false)
+}
+
+fn rename_local_branches_with(
+    repo: &Repository,
+    branches: &[&LocalBranch],
+    prefix: &str,
+    dry_run: bool,
+    quiet: bool,
+) -> Result<()> {
+    let prefix = prefix.trim_end_matches('/');
+    let (branches, _) = split_taken_rename_targets(repo, branches, prefix);
+    for branch in branches {
+        let new_name = format!("{}/{}", prefix, branch.short_name());
+        if dry_run {
+            info!(
+                "rename branch: {} to refs/heads/{} (dry-run)",
+                branch.refname, new_name
+            );
+            if !quiet {
+                println!(
+                    "Rename branch {} to {} (dry run).",
+                    branch.short_name(),
+                    new_name
+                );
+            }
+            continue;
+        }
+        info!(
+            "rename branch: {} to refs/heads/{}",
+            branch.refname, new_name
+        );
+        // Renamed as a branch, so that `branch.<name>.*` moves along like `git branch --move`.
+        repo.find_branch(branch.short_name(), BranchType::Local)?
+            .rename(&new_name, false)?;
+        if !quiet {
+            println!("Renamed branch {} to {}.", branch.short_name(), new_name);
+        }
+    }
+    Ok(())
+}
 
+/// Splits the branches into the ones to rename and the ones whose new names are already taken.
+/// The taken ones are reported.
+fn split_taken_rename_targets<'a>(
+    repo: &Repository,
+    branches: &[&'a LocalBranch],
+    prefix: &str,
+) -> (Vec<&'a LocalBranch>, Vec<&'a LocalBranch>) {
+    let prefix = prefix.trim_end_matches('/');
+    let mut available = Vec::new();
+    let mut taken = Vec::new();
+    for branch in branches {
+        let new_name = format!("{}/{}", prefix, branch.short_name());
+        if repo
+            .find_reference(&format!("refs/heads/{}", new_name))
+            .is_ok()
+        {
+            eprintln!(
+                "Skip renaming branch {} since {} already exists.",
+                branch.short_name(),
+                new_name
+            );
+            taken.push(*branch);
+        } else {
+            available.push(*branch);
+        }
+    }
+    (available, taken)
+}
+
+/// Prunes the worktrees that the `branches` are checked out in, when their directories are removed
+/// and they aren't locked. Git refuses to delete a branch that is checked out in a worktree,
+/// even when its directory is removed. The other worktrees are left as they are,
+/// unlike `git worktree prune`, which prunes every removed worktree of the repository.
+fn prune_removed_worktrees_of(
+    repo: &Repository,
+    branches: &[&LocalBranch],
+    dry_run: bool,
+) -> Result<()> {
+    if branches.is_empty() {
+        return Ok(());
+    }
+    let common_dir = get_common_dir(repo)?;
+    for name in repo.worktrees()?.iter().flatten() {
+        let worktree = repo.find_worktree(name)?;
+        if worktree.path().exists() {
+            continue;
+        }
+        if let WorktreeLockStatus::Locked(_) = worktree.is_locked()? {
+            continue;
+        }
+        let head_path = common_dir.join("worktrees").join(name).join("HEAD");
+        let head = match std::fs::read_to_string(&head_path) {
+            Ok(head) => head,
+            Err(err) => {
+                debug!("Skip the worktree {}: {}", name, err);
+                continue;
+            }
+        };
+        let checked_out = head.trim_end().trim_start_matches("ref: ");
+        if !branches.iter().any(|branch| branch.refname == checked_out) {
+            continue;
+        }
+        if dry_run {
+            info!("prune worktree: {} (dry-run)", name);
+            continue;
+        }
+        info!("prune worktree: {}", name);
+        // Its directory is already removed, so only the administrative files are removed.
+        worktree.prune(Some(WorktreePruneOptions::new().valid(true)))?;
+    }
+    Ok(())
+}
+
+/// Deletes the branches with libgit2 like `git branch --delete --force`.
+/// It removes a branch from both of the loose refs and `packed-refs` under their locks,
+/// so an interrupted deletion doesn't leave `packed-refs` half written or a loose ref behind.
+fn branch_delete(
+    repo: &Repository,
+    refnames: &[&str],
+    branch_type: BranchType,
+    dry_run: bool,
+    quiet: bool,
+) -> Result<()> {
+    let kind = match branch_type {
+        BranchType::Local => "branch",
+        BranchType::Remote => "remote-tracking branch",
+    };
+    // Checked up front, so that a batch with a stray ref deletes nothing.
+    for refname in refnames {
+        let is_kind = match branch_type {
+            BranchType::Local => refname.starts_with("refs/heads/"),
+            BranchType::Remote => refname.starts_with("refs/remotes/"),
+        };
+        if !is_kind {
+            return Err(anyhow::anyhow!("{} is not a {}", refname, kind));
+        }
+    }
+    for refname in refnames {
+        let reference = repo.find_reference(refname)?;
+        let branch_name = reference
+            .shorthand()
+            .context("non utf-8 branch name")?
+            .to_owned();
+        if dry_run {
+            info!("delete {}: {} (dry-run)", kind, refname);
+            if !quiet {
+                println!("Delete {} {} (dry run).", kind, branch_name);
+            }
+            continue;
+        }
+        let oid = reference.peel_to_commit()?.id().to_string();
+        info!("delete {}: {}", kind, refname);
+        Branch::wrap(reference).delete()?;
+        if !quiet {
+            println!("Deleted {} {} (was {}).", kind, branch_name, &oid[..7]);
+        }
+    }
     Ok(())
 }
 
+pub fn delete_remote_tracking_branches(
+    repo: &Repository,
+    branches: &[&RemoteTrackingBranch],
+    dry_run: bool,
+) -> Result<()> {
+    delete_remote_tracking_branches_with(repo, branches, dry_run, false)
+}
+
+fn delete_remote_tracking_branches_with(
+    repo: &Repository,
+    branches: &[&RemoteTrackingBranch],
+    dry_run: bool,
+    quiet: bool,
+) -> Result<()> {
+    let refnames: Vec<_> = branches
+        .iter()
+        .map(|branch| branch.refname.as_str())
+        .collect();
+    branch_delete(repo, &refnames, BranchType::Remote, dry_run, quiet)
+}
+
+fn is_transient(err: &anyhow::Error) -> bool {
+    matches!(err.downcast_ref::<PushDeleteError>(), Some(err) if err.transient)
+}
+
+/// Pushes to a remote are retried up to `retries` times with exponential backoff
+/// when they fail with transient network errors.
 pub fn delete_remote_branches(
     repo: &Repository,
     remote_branches: &[RemoteBranch],
+    retries: u32,
+    dry_run: bool,
+) -> Result<()> {
+    delete_remote_branches_with(repo, remote_branches, retries, dry_run, None)
+}
+
+/// Same as `delete_remote_branches`, but each push is aborted after `timeout`. It isn't retried.
+fn delete_remote_branches_with(
+    repo: &Repository,
+    remote_branches: &[RemoteBranch],
+    retries: u32,
     dry_run: bool,
+    timeout: Option<Duration>,
 ) -> Result<()> {
     if remote_branches.is_empty() {
         return Ok(());
@@ -302,7 +1649,513 @@ pub fn delete_remote_branches(
         entry.push(remote_branch);
     }
     for (remote_name, remote_refnames) in per_remote.iter() {
-        subprocess::push_delete(repo, remote_name, remote_refnames, dry_run)?;
+        let mut retried = 0;
+        loop {
+            match subprocess::push_delete(repo, remote_name, remote_refnames, dry_run, timeout) {
+                Ok(()) => break,
+                Err(err) if retried < retries && is_transient(&err) => {
+                    let backoff = Duration::from_millis(500 * 2u64.pow(retried));
+                    warn!(
+                        "Failed to delete branches from {}: {}. Retry in {:?}",
+                        remote_name, err, backoff
+                    );
+                    std::thread::sleep(backoff);
+                    retried += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        if retried > 0 {
+            let names = remote_refnames
+                .iter()
+                .map(|remote_branch| {
+                    format!("{}/{}", remote_branch.remote, remote_branch.short_name())
+                })
+                .collect::<Vec<_>>();
+            eprintln!(
+                "Deleted after {} {}: {}",
+                retried,
+                if retried == 1 { "retry" } else { "retries" },
+                names.join(", ")
+            );
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DeletionOrder {
+    /// Delete remote branches first, so that local branches are left to retry when pushes fail.
+    RemoteFirst,
+    LocalFirst,
+}
+
+pub struct DeleteOptions {
+    pub dry_run: bool,
+    /// The maximum number of branches to delete with a single git command. `None` deletes all at once.
+    pub batch_size: Option<usize>,
+    pub order: DeletionOrder,
+    /// Retries of the pushes to delete remote branches on transient network errors.
+    pub retries: u32,
+    /// The ref namespace to archive the branches under before the deletion. `None` doesn't archive.
+    pub archive: Option<String>,
+    /// The prefix to rename the local branches under instead of deleting them. `None` deletes them.
+    pub rename_archive: Option<String>,
+    /// Don't print each deletion, like when stdout is an event stream.
+    pub quiet: bool,
+    /// Abort each push to delete remote branches after it. `None` waits as long as it takes.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for DeleteOptions {
+    fn default() -> Self {
+        Self {
+            dry_run: false,
+            batch_size: None,
+            order: DeletionOrder::RemoteFirst,
+            retries: 2,
+            archive: None,
+            rename_archive: None,
+            quiet: false,
+            timeout: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum DeletionTarget {
+    Local(LocalBranch),
+    Remote(RemoteBranch),
+    /// Remote tracking branches that are deleted only from the local repository.
+    RemoteTracking(RemoteTrackingBranch),
+}
+
+#[derive(Serialize, Debug, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum DeletionStatus {
+    Deleted,
+    DryRun,
+    Failed,
+    /// A previous deletion failed, so it is not tried.
+    NotAttempted,
+    /// It is left as it is, like a branch whose `--rename-archive` name is taken.
+    Skipped,
+}
+
+#[derive(Debug)]
+pub struct DeletionOutcome {
+    pub target: DeletionTarget,
+    pub status: DeletionStatus,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct DeletionReport {
+    pub outcomes: Vec<DeletionOutcome>,
+}
+
+impl DeletionReport {
+    pub fn failures(&self) -> Vec<&DeletionOutcome> {
+        self.outcomes
+            .iter()
+            .filter(|outcome| outcome.status == DeletionStatus::Failed)
+            .collect()
+    }
+
+    pub fn outcome(&self, target: &DeletionTarget) -> Option<&DeletionOutcome> {
+        self.outcomes
+            .iter()
+            .find(|outcome| &outcome.target == target)
+    }
+
+    /// Records `items` as skipped with `reason`, without trying them.
+    fn skip<T>(
+        &mut self,
+        items: &[T],
+        to_target: impl Fn(&T) -> DeletionTarget,
+        reason: impl Fn(&T) -> String,
+        on_outcome: &mut dyn FnMut(&DeletionOutcome) -> Result<()>,
+    ) -> Result<()> {
+        for item in items {
+            let outcome = DeletionOutcome {
+                target: to_target(item),
+                status: DeletionStatus::Skipped,
+                error: Some(reason(item)),
+            };
+            on_outcome(&outcome)?;
+            self.outcomes.push(outcome);
+        }
+        Ok(())
+    }
+
+    /// Deletes `items` batch by batch. Nothing is tried after a failure.
+    /// `on_outcome` is called as soon as each outcome is known.
+    fn run_batches<T>(
+        &mut self,
+        items: &[T],
+        options: &DeleteOptions,
+        to_target: impl Fn(&T) -> DeletionTarget,
+        mut delete: impl FnMut(&[T]) -> Result<()>,
+        on_outcome: &mut dyn FnMut(&DeletionOutcome) -> Result<()>,
+    ) -> Result<()> {
+        if items.is_empty() {
+            return Ok(());
+        }
+        let batch_size = options.batch_size.unwrap_or(items.len()).max(1);
+        for batch in items.chunks(batch_size) {
+            let (status, error) = if !self.failures().is_empty() {
+                (DeletionStatus::NotAttempted, None)
+            } else {
+                match delete(batch) {
+                    Ok(()) if options.dry_run => (DeletionStatus::DryRun, None),
+                    Ok(()) => (DeletionStatus::Deleted, None),
+                    Err(err) => (DeletionStatus::Failed, Some(format!("{:#}", err))),
+                }
+            };
+            for item in batch {
+                let outcome = DeletionOutcome {
+                    target: to_target(item),
+                    status,
+                    error: error.clone(),
+                };
+                on_outcome(&outcome)?;
+                self.outcomes.push(outcome);
+            }
+        }
+        Ok(())
+    }
+}
+
+pub const DEFAULT_ARCHIVE_NAMESPACE: &str = "refs/trim-archive";
+
+/// The ref that a branch is archived to under `namespace`.
+/// Local branches are archived to `<namespace>/<branch>`,
+/// and remote tracking branches to `<namespace>/remotes/<remote>/<branch>`.
+pub fn archive_refname(namespace: &str, refname: &str) -> String {
+    let namespace = namespace.trim_end_matches('/');
+    if refname.starts_with("refs/heads/") {
+        format!("{}/{}", namespace, &refname["refs/heads/".len()..])
+    } else {
+        format!("{}/{}", namespace, &refname["refs/".len()..])
+    }
+}
+
+/// Keep the tips of the branches to delete as refs under `namespace`, so that they can be recovered
+/// even after `git gc`. Remote branches are archived only when their remote tracking branches exist.
+pub fn archive_branches(
+    repo: &Repository,
+    plan: &TrimPlan,
+    namespace: &str,
+    dry_run: bool,
+) -> Result<()> {
+    archive_branches_with(repo, plan, namespace, dry_run, false)
+}
+
+fn archive_branches_with(
+    repo: &Repository,
+    plan: &TrimPlan,
+    namespace: &str,
+    dry_run: bool,
+    quiet: bool,
+) -> Result<()> {
+    let mut refnames = Vec::new();
+    for local in plan.sorted_locals_to_delete() {
+        refnames.push(local.refname.clone());
+    }
+    for remote in plan.sorted_remotes_to_delete(repo)? {
+        if let RemoteTrackingBranchStatus::Exists(upstream) =
+            RemoteTrackingBranch::from_remote_branch(repo, &remote)?
+        {
+            refnames.push(upstream.refname);
+        }
+    }
+    for upstream in plan.remote_trackings_to_delete_locally() {
+        refnames.push(upstream.refname.clone());
+    }
+
+    for refname in refnames {
+        let oid = repo.refname_to_id(&refname)?;
+        let archive = archive_refname(namespace, &refname);
+        if dry_run {
+            if !quiet {
+                println!("Archive {} to {} (dry run).", refname, archive);
+            }
+            continue;
+        }
+        info!("Archive {} to {}", refname, archive);
+        let message = format!("git-trim: archive {}", refname);
+        repo.reference(&archive, oid, true, &message)?;
+        // Refs outside of `refs/heads/` and `refs/remotes/` aren't logged by default,
+        // but `--list-archives` prints when they are archived.
+        let mut reflog = repo.reflog(&archive)?;
+        let logged = reflog.get(0).map_or(false, |entry| {
+            entry.id_new() == oid && entry.message() == Some(message.as_str())
+        });
+        if !logged {
+            let signature = repo
+                .signature()
+                .or_else(|_| Signature::now("git-trim", "git-trim"))?;
+            reflog.append(oid, &signature, Some(&message))?;
+            reflog.write()?;
+        }
     }
     Ok(())
 }
+
+/// A ref under an archive namespace and the branch it keeps.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Archive {
+    pub refname: String,
+    /// The refname of the archived branch, like `refs/heads/feature`.
+    pub branch: String,
+    pub oid: Oid,
+    /// Seconds since the unix epoch when it was archived. `None` when the reflog is missing.
+    pub timestamp: Option<i64>,
+}
+
+/// The inverse of `archive_refname`.
+fn archived_refname(namespace: &str, archive: &str) -> Option<String> {
+    let rest = archive.strip_prefix(namespace)?.strip_prefix('/')?;
+    if rest.starts_with("remotes/") {
+        Some(format!("refs/{}", rest))
+    } else {
+        Some(format!("refs/heads/{}", rest))
+    }
+}
+
+/// Lists the archives under `namespace` sorted by refname.
+pub fn list_archives(repo: &Repository, namespace: &str) -> Result<Vec<Archive>> {
+    let namespace = namespace.trim_end_matches('/');
+    let mut result = Vec::new();
+    for reference in repo.references()? {
+        let reference = reference?;
+        let refname = reference.name().context("non-utf8 archive refname")?;
+        let branch = match archived_refname(namespace, refname) {
+            Some(branch) => branch,
+            None => continue,
+        };
+        let timestamp = match repo.reflog(refname) {
+            Ok(reflog) => reflog
+                .get(0)
+                .map(|entry| entry.committer().when().seconds()),
+            Err(err) if err.code() == ErrorCode::NotFound => None,
+            Err(err) => return Err(err.into()),
+        };
+        result.push(Archive {
+            refname: refname.to_owned(),
+            branch,
+            oid: reference.peel_to_commit()?.id(),
+            timestamp,
+        });
+    }
+    result.sort_by(|a, b| a.refname.cmp(&b.refname));
+    Ok(result)
+}
+
+/// Recreates the local branch from its archive under `namespace`, and returns the restored commit.
+/// It fails when the branch exists, unless `force` overwrites it.
+pub fn restore_archive(
+    repo: &Repository,
+    namespace: &str,
+    branch: &str,
+    force: bool,
+    drop_archive: bool,
+) -> Result<Oid> {
+    let archive = archive_refname(namespace, &format!("refs/heads/{}", branch));
+    let mut reference = repo
+        .find_reference(&archive)
+        .with_context(|| format!("The branch `{}` isn't archived under {}", branch, namespace))?;
+    let commit = reference.peel_to_commit()?;
+    if !force && repo.find_branch(branch, BranchType::Local).is_ok() {
+        return Err(anyhow::anyhow!(
+            "The branch `{}` already exists. Use `--force` to overwrite it",
+            branch
+        ));
+    }
+    repo.branch(branch, &commit, force)?;
+    if drop_archive {
+        reference.delete()?;
+    }
+    Ok(commit.id())
+}
+
+/// The deleted targets of the `report` that still exist, like when a concurrent process recreated them,
+/// or a push succeeded without deleting them. The remote branches are checked with `git ls-remote`,
+/// unless `offline` is set. A remote that doesn't answer within `timeout` isn't checked.
+pub fn verify_deletions(
+    git: &Git,
+    report: &DeletionReport,
+    offline: bool,
+    timeout: Option<Duration>,
+) -> Result<Vec<DeletionTarget>> {
+    let exists = |refname: &str| match git.repo.find_reference(refname) {
+        Ok(_) => Ok(true),
+        Err(err) if err.code() == ErrorCode::NotFound => Ok(false),
+        Err(err) => Err(err),
+    };
+    let mut survived = Vec::new();
+    let mut per_remote = HashMap::new();
+    for outcome in &report.outcomes {
+        if outcome.status != DeletionStatus::Deleted {
+            continue;
+        }
+        match &outcome.target {
+            DeletionTarget::Local(local) if exists(local.refname())? => {
+                survived.push(outcome.target.clone())
+            }
+            DeletionTarget::RemoteTracking(upstream) if exists(upstream.refname())? => {
+                survived.push(outcome.target.clone())
+            }
+            DeletionTarget::Remote(remote) if !offline => per_remote
+                .entry(remote.remote.as_str())
+                .or_insert_with(Vec::new)
+                .push(remote),
+            _ => {}
+        }
+    }
+    for (remote_name, remote_branches) in per_remote {
+        let refnames: Vec<_> = remote_branches
+            .iter()
+            .map(|remote_branch| remote_branch.refname.as_str())
+            .collect();
+        let remaining = match subprocess::ls_remote_refs(&git.repo, remote_name, &refnames, timeout)
+        {
+            Ok(remaining) => remaining,
+            Err(err) if err.downcast_ref::<TimeoutError>().is_some() => {
+                warn!(
+                    "{}. The deletions on {} are not verified.",
+                    err, remote_name
+                );
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        for remote_branch in remote_branches {
+            if remaining
+                .iter()
+                .any(|head| head.refname == remote_branch.refname)
+            {
+                survived.push(DeletionTarget::Remote(remote_branch.clone()));
+            }
+        }
+    }
+    Ok(survived)
+}
+
+/// Deletes the branches of the `plan` the same way the CLI does.
+/// Remote tracking branches whose remote branches are gone are deleted last.
+pub fn apply_trim_plan(
+    git: &Git,
+    plan: &TrimPlan,
+    options: &DeleteOptions,
+) -> Result<DeletionReport> {
+    apply_trim_plan_with(git, plan, options, &mut |_| Ok(()))
+}
+
+/// Same as `apply_trim_plan`, but `on_outcome` receives each outcome as soon as it is known,
+/// so that a long run can report its progress.
+pub fn apply_trim_plan_with(
+    git: &Git,
+    plan: &TrimPlan,
+    options: &DeleteOptions,
+    on_outcome: &mut dyn FnMut(&DeletionOutcome) -> Result<()>,
+) -> Result<DeletionReport> {
+    let repo = &git.repo;
+    let locals = plan.sorted_locals_to_delete();
+    let remotes = plan.sorted_remotes_to_delete(repo)?;
+    let stale_remote_trackings = plan.remote_trackings_to_delete_locally();
+
+    // Only the deletion needs the lock. The classification is read-only.
+    let _lock = if options.dry_run {
+        None
+    } else {
+        Some(RepoLock::acquire(repo)?)
+    };
+
+    // Archive before anything is deleted. Pushes delete the remote tracking branches too.
+    if let Some(namespace) = &options.archive {
+        archive_branches_with(repo, plan, namespace, options.dry_run, options.quiet)?;
+    }
+    // The branches of the removed worktrees aren't kept back, so prune their worktrees before deleting them.
+    prune_removed_worktrees_of(repo, &locals, options.dry_run)?;
+
+    let mut report = DeletionReport::default();
+    let delete_remotes =
+        |report: &mut DeletionReport,
+         on_outcome: &mut dyn FnMut(&DeletionOutcome) -> Result<()>| {
+            report.run_batches(
+                &remotes,
+                options,
+                |remote| DeletionTarget::Remote(remote.clone()),
+                |batch| {
+                    delete_remote_branches_with(
+                        repo,
+                        batch,
+                        options.retries,
+                        options.dry_run,
+                        options.timeout,
+                    )
+                },
+                on_outcome,
+            )
+        };
+    let delete_locals =
+        |report: &mut DeletionReport,
+         on_outcome: &mut dyn FnMut(&DeletionOutcome) -> Result<()>| {
+            let prefix = match &options.rename_archive {
+                Some(prefix) => prefix,
+                None => {
+                    return report.run_batches(
+                        &locals,
+                        options,
+                        |local| DeletionTarget::Local((*local).clone()),
+                        |batch| {
+                            delete_local_branches_with(repo, batch, options.dry_run, options.quiet)
+                        },
+                        on_outcome,
+                    )
+                }
+            };
+            // A branch whose new name is taken is left as it is, rather than counted as renamed.
+            let (renamed, taken) = split_taken_rename_targets(repo, &locals, prefix);
+            report.skip(
+                &taken,
+                |local| DeletionTarget::Local((*local).clone()),
+                |local| {
+                    format!(
+                        "{}/{} already exists",
+                        prefix.trim_end_matches('/'),
+                        local.short_name()
+                    )
+                },
+                &mut *on_outcome,
+            )?;
+            report.run_batches(
+                &renamed,
+                options,
+                |local| DeletionTarget::Local((*local).clone()),
+                |batch| {
+                    rename_local_branches_with(repo, batch, prefix, options.dry_run, options.quiet)
+                },
+                on_outcome,
+            )
+        };
+    match options.order {
+        DeletionOrder::RemoteFirst => {
+            delete_remotes(&mut report, &mut *on_outcome)?;
+            delete_locals(&mut report, &mut *on_outcome)?;
+        }
+        DeletionOrder::LocalFirst => {
+            delete_locals(&mut report, &mut *on_outcome)?;
+            delete_remotes(&mut report, &mut *on_outcome)?;
+        }
+    }
+    report.run_batches(
+        &stale_remote_trackings,
+        options,
+        |upstream| DeletionTarget::RemoteTracking((*upstream).clone()),
+        |batch| delete_remote_tracking_branches_with(repo, batch, options.dry_run, options.quiet),
+        on_outcome,
+    )?;
+    Ok(report)
+}
@@ -0,0 +1,54 @@
+use std::fs::OpenOptions;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use git2::Repository;
+use log::*;
+use thiserror::Error;
+
+use crate::util::get_common_dir;
+
+#[derive(Error, Debug)]
+#[error("Another git-trim is deleting branches of this repository. Remove `{}` if none is running", .0.display())]
+pub struct LockHeld(pub PathBuf);
+
+/// An advisory lock on `.git/git-trim/lock` that is held while branches are deleted.
+/// It is released when dropped. It fails fast instead of waiting for the other process.
+/// Linked worktrees share the refs, so they share the lock in the common git dir too.
+pub struct RepoLock {
+    path: PathBuf,
+}
+
+impl RepoLock {
+    pub fn path(repo: &Repository) -> Result<PathBuf> {
+        Ok(get_common_dir(repo)?.join("git-trim").join("lock"))
+    }
+
+    pub fn acquire(repo: &Repository) -> Result<Self> {
+        let path = Self::path(repo)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(_) => {
+                debug!("lock: {}", path.display());
+                Ok(Self { path })
+            }
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => Err(LockHeld(path).into()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+impl Drop for RepoLock {
+    fn drop(&mut self) {
+        if let Err(err) = std::fs::remove_file(&self.path) {
+            warn!(
+                "Failed to release the lock {}: {}",
+                self.path.display(),
+                err
+            );
+        }
+    }
+}
@@ -1,88 +1,419 @@
 mod remote_head_change_checker;
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::convert::TryFrom;
+use std::io::{Read, Write};
 use std::iter::FromIterator;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use anyhow::{Context, Result};
 use dialoguer::Confirm;
 use git2::{BranchType, Repository};
 use log::*;
 
+use git_trim::color;
 use git_trim::config::{self, get, Config, ConfigValue};
-use git_trim::porcelain_outputs::{print_json, print_local, print_remote};
+use git_trim::porcelain_outputs::{
+    emit_sorted_by, print_json, print_xml, EventWriter, JsonLinesWriter, LocalWriter,
+    MarkdownWriter, RemoteWriter,
+};
+use git_trim::pull_request::{GhCli, PullRequestBases};
+use git_trim::report::AuditReport;
+use git_trim::snapshot::Snapshot;
 use git_trim::{
-    args::{Args, PorcelainFormat},
-    get_remotes,
+    apply_trim_plan_with, estimate_network, explain_branch, fetch_bases, get_behind_bases,
+    get_duplicate_local_branches, get_orphan_local_branches, get_trim_plan, is_merged,
+    list_archives, ls_remote_head_with_timeout, remote_prune, remote_update, resolve_base,
+    resolve_base_commit, restore_archive, sort_by_ref, verify_deletions, BranchExplanation,
+    ClassifiedBranch, DeleteOptions, DeletionTarget, ForceSendSync, Git, LocalBranch,
+    NetworkEstimate, PlanParam, RemoteBranchError, RemoteHead, RemoteTrackingBranch,
+    SkipSuggestion, TimeoutError, TrimPlan, TrimPlanDiff, UniqueCommits, DEFAULT_ARCHIVE_NAMESPACE,
 };
 use git_trim::{
-    delete_local_branches, delete_remote_branches, get_trim_plan, ls_remote_head, remote_update,
-    ClassifiedBranch, ForceSendSync, Git, LocalBranch, PlanParam, RemoteHead, RemoteTrackingBranch,
-    SkipSuggestion, TrimPlan,
+    args::{Args, GroupBy, LogFormat, PorcelainFormat, SortKey, SubCommand},
+    get_remotes,
 };
 
+/// The exit code of `--exit-code` when any branches are deleted.
+/// Errors exit with 1.
+const EXIT_CODE_TRIMMED: i32 = 2;
+
+/// The exit code of `is-merged` when the branch isn't merged.
+/// Errors exit with 1.
+const EXIT_CODE_NOT_MERGED: i32 = 2;
+
+/// How deep `--workspace` searches for repositories by default.
+const DEFAULT_WORKSPACE_DEPTH: usize = 3;
+
 #[paw::main]
 fn main(args: Args) -> Result<()> {
-    env_logger::init();
+    init_logger(&args);
     info!("SEMVER: {}", env!("VERGEN_SEMVER"));
     info!("SHA: {}", env!("VERGEN_SHA"));
     info!("COMMIT_DATE: {}", env!("VERGEN_COMMIT_DATE"));
     info!("TARGET_TRIPLE: {}", env!("VERGEN_TARGET_TRIPLE"));
 
-    let git = Git::try_from(Repository::open_from_env()?)?;
+    if let Some(path) = args.replay.clone() {
+        return replay(&path, args);
+    }
+    if let Some(dir) = &args.workspace {
+        let trimmed = trim_workspace(dir, &args)?;
+        if args.exit_code && trimmed {
+            std::process::exit(EXIT_CODE_TRIMMED);
+        }
+        return Ok(());
+    }
+
+    let repo = match &args.repository {
+        Some(path) => Repository::discover(path)?,
+        None => Repository::open_from_env()?,
+    };
+    let git = Git::try_from(repo)?;
+
+    if let Some(SubCommand::IsMerged { branch, base }) = &args.subcommand {
+        if is_merged(&git, branch, base)? {
+            println!("merged");
+            return Ok(());
+        } else {
+            println!("not merged");
+            std::process::exit(EXIT_CODE_NOT_MERGED);
+        }
+    }
+
+    let archive_namespace = args
+        .archive_namespace
+        .as_deref()
+        .unwrap_or(DEFAULT_ARCHIVE_NAMESPACE);
+    if args.list_archives {
+        for archive in list_archives(&git.repo, archive_namespace)? {
+            let timestamp = archive
+                .timestamp
+                .map_or_else(|| "-".to_owned(), |timestamp| timestamp.to_string());
+            let name = archive
+                .branch
+                .strip_prefix("refs/heads/")
+                .or_else(|| archive.branch.strip_prefix("refs/remotes/"))
+                .unwrap_or(&archive.branch);
+            println!("{} {} {}", name, archive.oid, timestamp);
+        }
+        return Ok(());
+    }
+    if let Some(branch) = &args.restore {
+        let oid = restore_archive(
+            &git.repo,
+            archive_namespace,
+            branch,
+            args.force,
+            args.drop_archive,
+        )?;
+        println!("Restored branch {} at {}.", branch, &oid.to_string()[..7]);
+        return Ok(());
+    }
+
+    let mut trimmed = trim(&git, &args)?;
+    if args.recurse_submodules {
+        trimmed |= trim_submodules(&git.repo, &args, "")?;
+    }
+    if args.exit_code && trimmed {
+        std::process::exit(EXIT_CODE_TRIMMED);
+    }
+    Ok(())
+}
+
+/// Trims the scratch repository rebuilt from the snapshot, without deleting anything.
+fn replay(path: &Path, args: Args) -> Result<()> {
+    let snapshot = Snapshot::load(path)?;
+    let scratch = std::env::temp_dir().join(format!("git-trim-replay-{}", std::process::id()));
+    let result = snapshot.replay(&scratch).and_then(|git| {
+        trim(
+            &git,
+            &Args {
+                dry_run: true,
+                no_update: true,
+                replay: None,
+                ..args
+            },
+        )
+    });
+    if scratch.exists() {
+        std::fs::remove_dir_all(&scratch)?;
+    }
+    result.map(|_| ())
+}
 
+/// Returns whether any branches are deleted, or would be deleted with `--dry-run`.
+fn trim(git: &Git, args: &Args) -> Result<bool> {
     if git.repo.remotes()?.is_empty() {
         return Err(anyhow::anyhow!("git-trim requires at least one remote"));
     }
 
-    let config = Config::read(&git.repo, &git.config, &args)?;
+    if args.report_orphans {
+        for branch in get_orphan_local_branches(git)? {
+            println!("{}", branch.short_name());
+        }
+        return Ok(false);
+    }
+
+    if args.report_duplicates {
+        for (tip, branches) in get_duplicate_local_branches(git)? {
+            let names: Vec<_> = branches.iter().map(|branch| branch.short_name()).collect();
+            println!("{} {}", &tip.to_string()[..7], names.join(" "));
+        }
+        return Ok(false);
+    }
+
+    if let Some(path) = &args.snapshot {
+        Snapshot::capture(git)?.save(path)?;
+        return Ok(false);
+    }
+
+    let config = Config::read(&git.repo, &git.config, args)?;
     info!("config: {:?}", config);
+    if args.config_dump {
+        print_config_dump(&config, args)?;
+        return Ok(false);
+    }
     if config.bases.is_empty() {
-        return error_no_bases(&git.repo, &config.bases);
+        return error_no_bases(&git.repo, &config.bases).map(|_| false);
+    }
+    if let ConfigValue::Explicit(bases) | ConfigValue::Env(bases) = &config.bases {
+        for base in bases {
+            resolve_base(git, base)?;
+        }
     }
 
+    let timeout = args.timeout.map(Duration::from_secs);
     let mut checker = None;
-    if *config.update {
-        if should_update(&git, *config.update_interval, config.update)? {
-            checker = Some(remote_head_change_checker::RemoteHeadChangeChecker::spawn()?);
-            remote_update(&git.repo, args.dry_run)?;
-            println!();
+    let mut update = false;
+    if *config.update && !args.offline {
+        if should_update(git, *config.update_interval, config.update)? {
+            update = true;
+            if !args.estimate {
+                checker = Some(remote_head_change_checker::RemoteHeadChangeChecker::spawn(
+                    timeout,
+                )?);
+                skip_timed_out(remote_update(&git.repo, args.dry_run(), timeout))?;
+                println!();
+            }
         } else {
             println!("Repository is updated recently. Skip to update it")
         }
     }
 
-    let plan = get_trim_plan(
-        &git,
-        &PlanParam {
-            bases: config.bases.iter().map(String::as_str).collect(),
-            protected_patterns: config.protected.iter().map(String::as_str).collect(),
-            delete: config.delete.clone(),
-            detach: *config.detach,
+    if args.prune && !args.estimate {
+        for remote in get_remotes(&git.repo)? {
+            let remote_name = remote.name().context("non-utf8 remote name")?;
+            remote_prune(&git.repo, remote_name, args.dry_run())?;
+        }
+    }
+
+    let bases: Vec<_> = config.bases.iter().map(String::as_str).collect();
+    if args.fetch && !args.estimate {
+        skip_timed_out(fetch_bases(git, &bases, args.dry_run(), timeout))?;
+    }
+
+    for behind in get_behind_bases(git, &bases)? {
+        let message = format!(
+            "The base `{}` is {} commit(s) behind `{}`. Update it with `git pull`, or try `--fetch`",
+            behind.local.short_name(),
+            behind.behind,
+            behind.upstream.short_name(),
+        );
+        if args.strict {
+            return Err(anyhow::anyhow!(message));
+        }
+        eprintln!("{} {}", color::stray(color::enabled(), "warning:"), message);
+    }
+
+    let mut merged_branches = Vec::new();
+    if args.merged_from_stdin {
+        let mut input = String::new();
+        std::io::stdin()
+            .read_to_string(&mut input)
+            .context("Failed to read the merged branches from stdin")?;
+        merged_branches.extend(
+            input
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_owned),
+        );
+    }
+
+    let pull_request_bases = if args.base_from_pr {
+        let client = GhCli {
+            working_directory: git
+                .repo
+                .workdir()
+                .unwrap_or_else(|| git.repo.path())
+                .to_owned(),
+        };
+        Some(PullRequestBases::fetch(&client)?)
+    } else {
+        None
+    };
+    let resolve_from_pull_request =
+        |refname: &str| pull_request_bases.as_ref()?.resolve(&git.config, refname);
+
+    let param = PlanParam {
+        bases,
+        merged_into_tags: args.merged_into_tags.iter().map(String::as_str).collect(),
+        protected_patterns: config.protected.iter().map(String::as_str).collect(),
+        protected_commits: args.protect_commit.iter().map(String::as_str).collect(),
+        protect_merged_into: args
+            .protect_merged_into
+            .iter()
+            .map(String::as_str)
+            .collect(),
+        exclude_patterns: args.exclude.iter().map(String::as_str).collect(),
+        delete: config.delete.clone(),
+        detach: *config.detach,
+        head: args.head.as_deref(),
+        pull_refs: args.pull_refs,
+        gerrit: args.gerrit,
+        keep_if_protected_upstream: args.keep_if_protected_upstream,
+        cautious_push_only: args.cautious_push_only,
+        no_stray_remote_delete: args.no_stray_remote_delete,
+        keep_remote_ahead: args.keep_remote_ahead,
+        keep_stashed: args.keep_stashed,
+        keep_ancestors: args.keep_ancestors,
+        keep_recent: args.keep_recent,
+        keep_recent_checkouts: args.keep_recent_checkouts,
+        merged_older_than: args.merged_older_than.map(|age| age.0),
+        stack_prefix: args.stack_prefix,
+        // The estimate counts the listings instead.
+        offline: args.offline || args.estimate,
+        skip_defaults: args.skip_defaults,
+        limit_to_tracked: args.limit_to_tracked,
+        assume_unmerged_safe: args.assume_unmerged_safe,
+        cross_check_gone: args.cross_check_gone,
+        remote_default_only: args.remote_default_only,
+        timeout,
+        squash_detect: *config.squash_detect,
+        strict_squash: args.strict_squash,
+        cache: args.cache,
+        incremental: args.incremental,
+        detect_reverted: args.detect_reverted,
+        detect_renamed_upstream: args.detect_renamed_upstream,
+        remote_ahead_guard: args.remote_ahead_guard,
+        base_resolver: if args.base_from_pr {
+            Some(&resolve_from_pull_request)
+        } else {
+            None
         },
-    )?;
+        merged_branches: merged_branches.iter().map(String::as_str).collect(),
+    };
+    if let Some(branch) = &args.explain_branch {
+        print_explanation(&explain_branch(git, &param, branch)?);
+        return Ok(false);
+    }
+
+    let plan = get_trim_plan(git, &param)?;
+
+    if args.estimate {
+        let list_remote_heads = param.delete.scan_tracking() && !args.offline;
+        print_estimate(&estimate_network(git, &plan, update, list_remote_heads)?);
+        return Ok(false);
+    }
+
+    if args.fail_on_stray {
+        let strays = plan.stray_locals();
+        if !strays.is_empty() {
+            let names: Vec<_> = strays.iter().map(|local| local.short_name()).collect();
+            return Err(anyhow::anyhow!(
+                "Found stray local branches: {}",
+                names.join(", ")
+            ));
+        }
+    }
+
+    if let Some(compare_base) = &args.compare_base {
+        let other = get_trim_plan(
+            git,
+            &PlanParam {
+                bases: vec![compare_base.as_str()],
+                ..param
+            },
+        )?;
+        print_plan_diff(compare_base, &plan.diff(&other));
+        return Ok(false);
+    }
 
+    if args.dump_classifications {
+        serde_json::to_writer_pretty(std::io::stdout(), &plan.classifications)?;
+        println!();
+        return Ok(false);
+    }
+
+    let mut events = None;
     match args.porcelain {
         None => {
-            print_summary(&plan, &git.repo)?;
+            print_summary(
+                &plan,
+                &git.repo,
+                args.print_reasons,
+                args.group_by.as_ref(),
+                args.sort.unwrap_or_default(),
+            )?;
         }
         Some(PorcelainFormat::LocalBranches) => {
-            print_local(&plan, &git.repo, &mut std::io::stdout())?;
-            return Ok(());
+            emit_sorted_by(
+                &plan,
+                &git.repo,
+                &mut LocalWriter::new(std::io::stdout()),
+                args.sort.unwrap_or_default(),
+            )?;
+            return Ok(false);
         }
         Some(PorcelainFormat::RemoteBranches) => {
-            print_remote(&plan, &git.repo, &mut std::io::stdout())?;
-            return Ok(());
+            emit_sorted_by(
+                &plan,
+                &git.repo,
+                &mut RemoteWriter::new(std::io::stdout()),
+                args.sort.unwrap_or_default(),
+            )?;
+            return Ok(false);
         }
         Some(PorcelainFormat::JSON) => {
             print_json(&plan, &git.repo, &mut std::io::stdout())?;
-            return Ok(());
+            return Ok(false);
+        }
+        Some(PorcelainFormat::JSONLines) => {
+            emit_sorted_by(
+                &plan,
+                &git.repo,
+                &mut JsonLinesWriter::new(std::io::stdout())
+                    .with_remote_states(plan.remote_states.clone()),
+                args.sort.unwrap_or_default(),
+            )?;
+            return Ok(false);
+        }
+        Some(PorcelainFormat::XML) => {
+            print_xml(&plan, &git.repo, &mut std::io::stdout())?;
+            return Ok(false);
+        }
+        Some(PorcelainFormat::Markdown) => {
+            emit_sorted_by(
+                &plan,
+                &git.repo,
+                &mut MarkdownWriter::new(std::io::stdout()),
+                args.sort.unwrap_or_default(),
+            )?;
+            return Ok(false);
+        }
+        Some(PorcelainFormat::NDJSONEvents) => {
+            let mut writer = EventWriter::new(std::io::stdout(), &git.repo);
+            emit_sorted_by(&plan, &git.repo, &mut writer, args.sort.unwrap_or_default())?;
+            events = Some(writer);
         }
     }
 
-    let locals = plan.locals_to_delete();
-    let remotes = plan.remotes_to_delete(&git.repo)?;
-    let any_branches_to_remove = !(locals.is_empty() && remotes.is_empty());
+    let locals = plan.sorted_locals_to_delete();
+    let remotes = plan.sorted_remotes_to_delete(&git.repo)?;
+    let stale_remote_trackings = plan.remote_trackings_to_delete_locally();
+    let any_branches_to_remove =
+        !(locals.is_empty() && remotes.is_empty() && stale_remote_trackings.is_empty());
 
     if !args.dry_run
         && *config.confirm
@@ -93,20 +424,289 @@ fn main(args: Args) -> Result<()> {
             .interact()?
     {
         println!("Cancelled");
-        return Ok(());
+        return Ok(false);
     }
 
-    delete_remote_branches(&git.repo, remotes.as_slice(), args.dry_run)?;
-    delete_local_branches(&git.repo, &locals, args.dry_run)?;
+    // Count before the deletion while the branches still exist.
+    let unique_commits = if args.report_savings {
+        Some(plan.unique_commits(&git.repo)?)
+    } else {
+        None
+    };
 
-    prompt_survey_on_push_upstream(&git)?;
+    let mut report = if args.report.is_some() || args.note_deletions {
+        Some(AuditReport::new(
+            &git.repo,
+            &plan,
+            &param.bases,
+            &param.delete,
+            args.dry_run,
+        )?)
+    } else {
+        None
+    };
+    let deletion = apply_trim_plan_with(
+        git,
+        &plan,
+        &DeleteOptions {
+            dry_run: args.dry_run,
+            retries: args.retries.unwrap_or(2),
+            archive: if args.archive {
+                Some(
+                    args.archive_namespace
+                        .clone()
+                        .unwrap_or_else(|| DEFAULT_ARCHIVE_NAMESPACE.to_owned()),
+                )
+            } else {
+                None
+            },
+            rename_archive: args.rename_archive.clone(),
+            quiet: events.is_some(),
+            timeout: args.timeout.map(Duration::from_secs),
+            ..DeleteOptions::default()
+        },
+        &mut |outcome| match &mut events {
+            Some(events) => events.on_outcome(outcome),
+            None => Ok(()),
+        },
+    )?;
+    if let Some(events) = &mut events {
+        events.finish()?;
+    }
+    if let Some(report) = &mut report {
+        report.record(&deletion);
+        if let Some(path) = &args.report {
+            report.write(path)?;
+        }
+        if args.note_deletions {
+            match resolve_base_commit(&git.repo, &param.bases)? {
+                Some(base) => report.append_note(&git.repo, base)?,
+                None => warn!("No base to record the deletions in a note"),
+            }
+        }
+    }
+    if let Some(failure) = deletion.failures().first() {
+        return Err(anyhow::anyhow!(
+            "{}",
+            failure
+                .error
+                .as_deref()
+                .unwrap_or("Failed to delete branches")
+        ));
+    }
+    if args.dry_run {
+        eprintln!("dry run: nothing deleted");
+    } else {
+        let timeout = args.timeout.map(Duration::from_secs);
+        let survived = verify_deletions(git, &deletion, args.offline, timeout)?;
+        if !survived.is_empty() {
+            for target in &survived {
+                let name = match target {
+                    DeletionTarget::Local(local) => local.short_name().to_owned(),
+                    DeletionTarget::Remote(remote) => {
+                        format!("{}/{}", remote.remote, remote.short_name())
+                    }
+                    DeletionTarget::RemoteTracking(upstream) => upstream.refname.clone(),
+                };
+                eprintln!("Still exists after the deletion: {}", name);
+            }
+            return Err(anyhow::anyhow!(
+                "{} branches still exist after the deletion",
+                survived.len()
+            ));
+        }
+    }
+    if let Some(unique_commits) = unique_commits {
+        print_unique_commits(&unique_commits);
+    }
+
+    if events.is_none() {
+        prompt_survey_on_push_upstream(git)?;
+    }
 
     if let Some(checker) = checker.take() {
         checker.check_and_notify(&git.repo)?;
     }
+    Ok(any_branches_to_remove)
+}
+
+/// Trims each initialized submodule in its own repository.
+/// A failure in a submodule is reported, and the others are still trimmed.
+fn trim_submodules(repo: &Repository, args: &Args, prefix: &str) -> Result<bool> {
+    let mut trimmed = false;
+    let mut failed = Vec::new();
+    for submodule in repo.submodules()? {
+        let path = format!("{}{}", prefix, submodule.path().display());
+        if submodule.workdir_id().is_none() {
+            debug!("skip an uninitialized submodule: {}", path);
+            continue;
+        }
+        println!();
+        println!("Submodule `{}`:", path);
+        let result = submodule
+            .open()
+            .map_err(anyhow::Error::from)
+            .and_then(|sub_repo| {
+                let git = Git::try_from(sub_repo)?;
+                let trimmed = trim(&git, args)?;
+                let nested = trim_submodules(&git.repo, args, &format!("{}/", path))?;
+                Ok(trimmed || nested)
+            });
+        match result {
+            Ok(sub_trimmed) => trimmed |= sub_trimmed,
+            Err(err) => {
+                eprintln!("Failed to trim submodule `{}`: {:#}", path, err);
+                failed.push(path);
+            }
+        }
+    }
+    if !failed.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Failed to trim submodules: {}",
+            failed.join(", ")
+        ));
+    }
+    Ok(trimmed)
+}
+
+/// Trims each repository under the `dir`, like `trim_submodules`.
+fn trim_workspace(dir: &Path, args: &Args) -> Result<bool> {
+    let depth = args.workspace_depth.unwrap_or(DEFAULT_WORKSPACE_DEPTH);
+    let mut trimmed = false;
+    let mut failed = Vec::new();
+    for repo_dir in find_repositories(dir, depth)? {
+        let path = match repo_dir.strip_prefix(dir) {
+            Ok(path) if path.as_os_str().is_empty() => ".".to_owned(),
+            Ok(path) => path.display().to_string(),
+            Err(_) => repo_dir.display().to_string(),
+        };
+        println!();
+        println!("Repository `{}`:", path);
+        let result = Repository::open(&repo_dir)
+            .map_err(anyhow::Error::from)
+            .and_then(|repo| {
+                let git = Git::try_from(repo)?;
+                let trimmed = trim(&git, args)?;
+                if args.recurse_submodules {
+                    return Ok(trim_submodules(&git.repo, args, "")? || trimmed);
+                }
+                Ok(trimmed)
+            });
+        match result {
+            Ok(repo_trimmed) => trimmed |= repo_trimmed,
+            Err(err) => {
+                eprintln!("Failed to trim repository `{}`: {:#}", path, err);
+                failed.push(path);
+            }
+        }
+    }
+    if !failed.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Failed to trim repositories: {}",
+            failed.join(", ")
+        ));
+    }
+    Ok(trimmed)
+}
+
+/// The worktrees under the `dir` up to `depth` levels down, in the order of their paths.
+/// A worktree isn't searched further, so its submodules and nested repositories aren't listed.
+fn find_repositories(dir: &Path, depth: usize) -> Result<Vec<PathBuf>> {
+    if dir.join(".git").exists() {
+        return Ok(vec![dir.to_owned()]);
+    }
+    let mut result = Vec::new();
+    if depth == 0 {
+        return Ok(result);
+    }
+    let mut children = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        // Symlinks aren't followed, so the search never loops.
+        if entry.file_type()?.is_dir() {
+            children.push(entry.path());
+        }
+    }
+    children.sort();
+    for child in children {
+        result.extend(find_repositories(&child, depth - 1)?);
+    }
+    Ok(result)
+}
+
+/// A timed out network operation leaves the remote state as it is, so keep going with it.
+fn skip_timed_out(result: Result<()>) -> Result<()> {
+    match result {
+        Err(err) if err.downcast_ref::<TimeoutError>().is_some() => {
+            eprintln!("{}. Continue with the remote state unknown.", err);
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+fn print_config_dump(config: &Config, args: &Args) -> Result<()> {
+    let mut dump = config.to_json();
+    dump["exclude"] = serde_json::json!(args.exclude);
+    dump["porcelain"] = serde_json::json!(args.porcelain.as_ref().map(ToString::to_string));
+    dump["dry_run"] = serde_json::json!(args.dry_run);
+    dump["offline"] = serde_json::json!(args.offline);
+    serde_json::to_writer_pretty(std::io::stdout(), &dump)?;
+    println!();
     Ok(())
 }
 
+fn print_unique_commits(unique_commits: &UniqueCommits) {
+    println!("Commits that `git gc` can reclaim after the reflogs expire:");
+    for (local, count) in &unique_commits.per_branch {
+        println!("  - {}: {}", local.short_name(), count);
+    }
+    println!("Total: {} unique commits", unique_commits.total);
+}
+
+fn print_estimate(estimate: &NetworkEstimate) {
+    println!("Remote URLs to contact: {}", estimate.remote_urls);
+    println!("  - fetches: {}", estimate.fetches);
+    println!("  - listings: {}", estimate.listings);
+    println!("  - pushes: {}", estimate.pushes);
+    println!("Remote refs to delete: {}", estimate.remote_refs_to_delete);
+    println!(
+        "Remote refs of unknown state: {}",
+        estimate.unknown_remote_refs
+    );
+}
+
+fn init_logger(args: &Args) {
+    let mut builder = env_logger::Builder::new();
+    builder.filter_level(LevelFilter::Warn);
+    builder.parse_env(env_logger::Env::default());
+    let verbosity = match (args.quiet, args.verbose) {
+        (true, _) => Some(LevelFilter::Error),
+        (false, 0) => None,
+        (false, 1) => Some(LevelFilter::Info),
+        (false, 2) => Some(LevelFilter::Debug),
+        (false, _) => Some(LevelFilter::Trace),
+    };
+    if let Some(verbosity) = verbosity {
+        builder.filter_level(verbosity);
+    }
+
+    // Log records go to stderr so that they never interleave with the plan on stdout.
+    builder.target(env_logger::Target::Stderr);
+    if let Some(LogFormat::JSON) = args.log_format {
+        builder.format(|buf, record| {
+            let line = serde_json::json!({
+                "level": record.level().to_string(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+                "timestamp": buf.timestamp().to_string(),
+            });
+            writeln!(buf, "{}", line)
+        });
+    }
+    builder.init();
+}
+
 fn error_no_bases(repo: &Repository, bases: &ConfigValue<HashSet<String>>) -> Result<()> {
     fn eprint_bullet(s: &str) {
         let width = textwrap::termwidth().max(40) - 4;
@@ -129,6 +729,9 @@ fn error_no_bases(repo: &Repository, bases: &ConfigValue<HashSet<String>>) -> Re
                 "I found that you passed an empty value to the CLI option `--bases`. Don't do that."
             );
         }
+        ConfigValue::Env(_) => {
+            unreachable!("an empty `GIT_TRIM_BASE` falls through to `git config`")
+        }
         ConfigValue::GitConfig(_) => {
             eprintln!(
                 "I found that `git config trim.bases` is empty! Try any following commands to set valid bases:"
@@ -171,7 +774,89 @@ Then `git branch --set-upstream <remote>/<base branch> <base branch>` to set an
     Err(anyhow::anyhow!("No base branch is found!"))
 }
 
-pub fn print_summary(plan: &TrimPlan, repo: &Repository) -> Result<()> {
+fn print_plan_diff(compare_base: &str, diff: &TrimPlanDiff) {
+    fn print_by_category(branches: &HashSet<ClassifiedBranch>) {
+        let mut categories = BTreeMap::new();
+        for branch in branches {
+            let name = if let Some(local) = branch.local() {
+                local.short_name().to_owned()
+            } else if let Some(upstream) = branch.upstream() {
+                upstream.short_name().to_owned()
+            } else {
+                continue;
+            };
+            categories
+                .entry(branch.category())
+                .or_insert_with(Vec::new)
+                .push(name);
+        }
+        if categories.is_empty() {
+            println!("    (none)");
+        }
+        for (category, mut names) in categories {
+            names.sort();
+            println!("    {}: {}", category, names.join(", "));
+        }
+    }
+
+    println!("Compared with the base `{}`:", compare_base);
+    println!("  additionally trimmed:");
+    print_by_category(&diff.added);
+    println!("  newly kept:");
+    print_by_category(&diff.removed);
+}
+
+fn print_explanation(explanation: &BranchExplanation) {
+    let yes_no = |value: bool| if value { "yes" } else { "no" };
+    println!("branch: {} ({})", explanation.refname, explanation.tip);
+    match &explanation.upstream {
+        Some(upstream) => println!("fetch upstream: {}", upstream.refname),
+        None => println!("fetch upstream: none"),
+    }
+    for push in &explanation.pushes {
+        println!("push upstream: {}", push.refname);
+    }
+    for base in &explanation.bases {
+        println!("base: {} ({})", base.base, base.tip);
+        match base.merge_base {
+            Some(merge_base) => println!("  merge-base: {}", merge_base),
+            None => println!("  merge-base: none"),
+        }
+        println!("  rev-list merged: {}", yes_no(base.rev_list_merged));
+        match base.squash_merged {
+            Some(squash_merged) => println!("  squash merged: {}", yes_no(squash_merged)),
+            None => println!("  squash merged: skipped"),
+        }
+        println!("  merged: {}", yes_no(base.merged));
+        for (upstream, merged) in &base.upstreams_merged {
+            println!("  {} merged: {}", upstream.refname, yes_no(*merged));
+        }
+    }
+    for (base, message, result) in &explanation.classifications {
+        let result: Vec<_> = result.iter().map(ClassifiedBranch::category).collect();
+        println!(
+            "classification against {}: {} [{}]",
+            base,
+            message,
+            result.join(", ")
+        );
+    }
+    for branch in &explanation.to_delete {
+        println!("delete: {}", branch.category());
+    }
+    for (branch, reason) in &explanation.preserved {
+        println!("keep: {} ({})", branch.category(), reason);
+    }
+}
+
+pub fn print_summary(
+    plan: &TrimPlan,
+    repo: &Repository,
+    print_reasons: bool,
+    group_by: Option<&GroupBy>,
+    sort: SortKey,
+) -> Result<()> {
+    let colored = color::enabled();
     println!("Branches that will remain:");
     println!("  local branches:");
     let local_branches_to_delete = HashSet::<_>::from_iter(plan.locals_to_delete());
@@ -184,16 +869,21 @@ pub fn print_summary(plan: &TrimPlan, repo: &Repository) -> Result<()> {
             continue;
         }
         if let Some(preserved) = plan.get_preserved_local(&branch) {
-            if preserved.base && matches!(preserved.branch, ClassifiedBranch::MergedLocal(_)) {
-                println!("    {} [{}]", branch_name, preserved.reason);
+            let line = if preserved.base
+                && matches!(
+                    preserved.branch,
+                    ClassifiedBranch::MergedLocal(_) | ClassifiedBranch::BaseLocal(_)
+                ) {
+                format!("{} [{}]", branch_name, preserved.reason)
             } else {
-                println!(
-                    "    {} [{}, but: {}]",
+                format!(
+                    "{} [{}, but: {}]",
                     branch_name,
                     preserved.branch.message_local(),
                     preserved.reason
-                );
-            }
+                )
+            };
+            println!("    {}", color::kept(colored, line));
         } else if let Some(suggestion) = plan.skipped.get(refname) {
             println!("    {} *{}", branch_name, suggestion.kind());
         } else {
@@ -215,40 +905,60 @@ pub fn print_summary(plan: &TrimPlan, repo: &Repository) -> Result<()> {
             .shorthand()
             .context("non utf-8 remote ref name")?;
         let upstream = RemoteTrackingBranch::new(&refname);
-        let remote_branch = upstream.to_remote_branch(&remotes)?;
-        if remote_refs_to_delete.contains(&remote_branch) {
+        let remote_branch = match upstream.to_remote_branch(&remotes) {
+            Ok(remote_branch) => Some(remote_branch),
+            Err(RemoteBranchError::RemoteNotFound) => None,
+            Err(err) => return Err(err.into()),
+        };
+        if let Some(remote_branch) = &remote_branch {
+            if remote_refs_to_delete.contains(remote_branch) {
+                continue;
+            }
+        }
+        if plan
+            .to_delete
+            .contains(&ClassifiedBranch::StaleRemoteTracking(upstream.clone()))
+            || plan.local_only.contains(&upstream)
+        {
             continue;
         }
         if let Some(preserved) = plan.get_preserved_upstream(&upstream) {
-            if preserved.base
-                && matches!(preserved.branch, ClassifiedBranch::MergedRemoteTracking(_))
-            {
-                println!("    {} [{}]", shorthand, preserved.reason);
+            let line = if preserved.base
+                && matches!(
+                    preserved.branch,
+                    ClassifiedBranch::MergedRemoteTracking(_)
+                        | ClassifiedBranch::BaseRemoteTracking(_)
+                ) {
+                format!("{} [{}]", shorthand, preserved.reason)
             } else {
-                println!(
-                    "    {} [{}, but: {}]",
+                format!(
+                    "{} [{}, but: {}]",
                     shorthand,
                     preserved.branch.message_remote(),
                     preserved.reason
-                );
-            }
+                )
+            };
+            println!("    {}", color::kept(colored, line));
         } else if let Some(suggestion) = plan.skipped.get(refname) {
             println!("    {} *{}", shorthand, suggestion.kind());
         } else {
             println!("    {}", shorthand);
         }
-        printed_remotes.insert(remote_branch);
+        if let Some(remote_branch) = remote_branch {
+            printed_remotes.insert(remote_branch);
+        }
     }
     for preserved in &plan.preserved {
         match &preserved.branch {
             ClassifiedBranch::MergedDirectFetch { remote, .. }
             | ClassifiedBranch::DivergedDirectFetch { remote, .. } => {
-                println!(
-                    "    {} [{}, but: {}]",
+                let line = format!(
+                    "{} [{}, but: {}]",
                     remote.to_string(),
                     preserved.branch.message_remote(),
                     preserved.reason,
                 );
+                println!("    {}", color::kept(colored, line));
             }
             _ => {}
         }
@@ -323,57 +1033,260 @@ pub fn print_summary(plan: &TrimPlan, repo: &Repository) -> Result<()> {
 
     let remotes = get_remotes(&repo)?;
 
+    // The reason is printed after the branch name, so it doesn't change the order.
+    let reason = |branch: &ClassifiedBranch| {
+        if print_reasons {
+            format!(" [{}]", plan.reason(branch))
+        } else {
+            String::new()
+        }
+    };
+    if let Some(GroupBy::Remote) = group_by {
+        return print_grouped_by_remote(plan, repo, &remotes, reason, colored, sort);
+    }
     let mut merged_locals = Vec::new();
     let mut merged_remotes = Vec::new();
     let mut stray = Vec::new();
     let mut diverged_remotes = Vec::new();
+    let mut stale_remote_trackings = Vec::new();
+    // The last element is the ref that gives the key of `--sort`.
     for branch in &plan.to_delete {
         match branch {
-            ClassifiedBranch::MergedLocal(local) => {
-                merged_locals.push(local.short_name().to_owned())
+            ClassifiedBranch::MergedLocal(local) => merged_locals.push((
+                local.short_name().to_owned(),
+                reason(branch),
+                Some(local.refname.clone()),
+            )),
+            ClassifiedBranch::Stray(local) => stray.push((
+                local.short_name().to_owned(),
+                reason(branch),
+                Some(local.refname.clone()),
+            )),
+            ClassifiedBranch::MergedRemoteTracking(upstream)
+                if plan.local_only.contains(upstream) =>
+            {
+                stale_remote_trackings.push((
+                    upstream.short_name().to_owned(),
+                    reason(branch),
+                    Some(upstream.refname.clone()),
+                ));
             }
-            ClassifiedBranch::Stray(local) => stray.push(local.short_name().to_owned()),
             ClassifiedBranch::MergedRemoteTracking(upstream) => {
                 let remote = upstream.to_remote_branch(&remotes)?;
-                merged_remotes.push(remote.to_string())
+                merged_remotes.push((
+                    remote.to_string(),
+                    reason(branch),
+                    Some(upstream.refname.clone()),
+                ))
             }
             ClassifiedBranch::DivergedRemoteTracking { local, upstream } => {
                 let remote = upstream.to_remote_branch(&remotes)?;
-                merged_locals.push(local.short_name().to_owned());
-                diverged_remotes.push(remote.to_string())
+                merged_locals.push((
+                    local.short_name().to_owned(),
+                    reason(branch),
+                    Some(local.refname.clone()),
+                ));
+                diverged_remotes.push((
+                    remote.to_string(),
+                    reason(branch),
+                    Some(upstream.refname.clone()),
+                ))
             }
             ClassifiedBranch::MergedDirectFetch { local, remote }
             | ClassifiedBranch::DivergedDirectFetch { local, remote } => {
-                merged_locals.push(local.short_name().to_owned());
-                diverged_remotes.push(remote.to_string())
+                merged_locals.push((
+                    local.short_name().to_owned(),
+                    reason(branch),
+                    Some(local.refname.clone()),
+                ));
+                diverged_remotes.push((remote.to_string(), reason(branch), None))
             }
             ClassifiedBranch::MergedNonTrackingLocal(local) => {
-                merged_locals.push(format!("{} (non-tracking)", local.short_name()));
+                merged_locals.push((
+                    format!("{} (non-tracking)", local.short_name()),
+                    reason(branch),
+                    Some(local.refname.clone()),
+                ));
             }
             ClassifiedBranch::MergedNonUpstreamRemoteTracking(upstream) => {
                 let remote = upstream.to_remote_branch(&remotes)?;
-                merged_remotes.push(format!("{} (non-upstream)", remote.to_string()));
+                merged_remotes.push((
+                    format!("{} (non-upstream)", remote.to_string()),
+                    reason(branch),
+                    Some(upstream.refname.clone()),
+                ));
             }
+            ClassifiedBranch::StaleRemoteTracking(upstream) => {
+                stale_remote_trackings.push((
+                    upstream.short_name().to_owned(),
+                    reason(branch),
+                    Some(upstream.refname.clone()),
+                ));
+            }
+            // Bases are kept back before classification.
+            ClassifiedBranch::BaseLocal(_) | ClassifiedBranch::BaseRemoteTracking(_) => {}
         }
     }
 
-    fn print(label: &str, mut branches: Vec<String>) -> Result<()> {
+    let print = |label: &str,
+                 mut branches: Vec<(String, String, Option<String>)>,
+                 paint: &dyn Fn(String) -> String|
+     -> Result<()> {
         if branches.is_empty() {
             return Ok(());
         }
-        branches.sort();
+        sort_by_ref(repo, &mut branches, sort, |branch| branch.2.clone())?;
         println!("Delete {}:", label);
-        for branch in branches {
-            println!("  - {}", branch);
+        for (branch, reason, _) in branches {
+            println!("  - {}{}", paint(branch), reason);
         }
         Ok(())
+    };
+
+    let merged = |branch: String| color::merged(colored, branch).to_string();
+    let stray_or_diverged = |branch: String| color::stray(colored, branch).to_string();
+    print("merged local branches", merged_locals, &merged)?;
+    print("merged remote refs", merged_remotes, &merged)?;
+    print("stray local branches", stray, &stray_or_diverged)?;
+    print("diverged remote refs", diverged_remotes, &stray_or_diverged)?;
+    print(
+        "stale remote tracking branches",
+        stale_remote_trackings,
+        &|branch| branch,
+    )?;
+
+    Ok(())
+}
+
+/// Print the branches to delete under "local" and then under each remote, sorted by `sort`.
+fn print_grouped_by_remote(
+    plan: &TrimPlan,
+    repo: &Repository,
+    remotes: &[git2::Remote],
+    reason: impl Fn(&ClassifiedBranch) -> String,
+    colored: bool,
+    sort: SortKey,
+) -> Result<()> {
+    #[derive(Eq, PartialEq, Ord, PartialOrd)]
+    enum Paint {
+        Merged,
+        Stray,
+        Plain,
     }
 
-    print("merged local branches", merged_locals)?;
-    print("merged remote refs", merged_remotes)?;
-    print("stray local branches", stray)?;
-    print("diverged remote refs", diverged_remotes)?;
+    // The last element is the ref that gives the key of `--sort`.
+    let mut locals = Vec::new();
+    let mut by_remote = BTreeMap::<String, Vec<_>>::new();
+    for branch in &plan.to_delete {
+        match branch {
+            ClassifiedBranch::MergedLocal(local) => locals.push((
+                local.short_name().to_owned(),
+                reason(branch),
+                Paint::Merged,
+                Some(local.refname.clone()),
+            )),
+            ClassifiedBranch::Stray(local) => locals.push((
+                local.short_name().to_owned(),
+                reason(branch),
+                Paint::Stray,
+                Some(local.refname.clone()),
+            )),
+            ClassifiedBranch::MergedNonTrackingLocal(local) => locals.push((
+                format!("{} (non-tracking)", local.short_name()),
+                reason(branch),
+                Paint::Merged,
+                Some(local.refname.clone()),
+            )),
+            ClassifiedBranch::StaleRemoteTracking(upstream) => locals.push((
+                format!("{} (stale)", upstream.short_name()),
+                reason(branch),
+                Paint::Plain,
+                Some(upstream.refname.clone()),
+            )),
+            ClassifiedBranch::MergedRemoteTracking(upstream)
+                if plan.local_only.contains(upstream) =>
+            {
+                locals.push((
+                    format!("{} (local only)", upstream.short_name()),
+                    reason(branch),
+                    Paint::Merged,
+                    Some(upstream.refname.clone()),
+                ))
+            }
+            ClassifiedBranch::MergedRemoteTracking(upstream) => {
+                let remote = upstream.to_remote_branch(remotes)?;
+                by_remote.entry(remote.remote.clone()).or_default().push((
+                    remote.short_name().to_owned(),
+                    reason(branch),
+                    Paint::Merged,
+                    Some(upstream.refname.clone()),
+                ));
+            }
+            ClassifiedBranch::MergedNonUpstreamRemoteTracking(upstream) => {
+                let remote = upstream.to_remote_branch(remotes)?;
+                by_remote.entry(remote.remote.clone()).or_default().push((
+                    format!("{} (non-upstream)", remote.short_name()),
+                    reason(branch),
+                    Paint::Merged,
+                    Some(upstream.refname.clone()),
+                ));
+            }
+            ClassifiedBranch::DivergedRemoteTracking { local, upstream } => {
+                let remote = upstream.to_remote_branch(remotes)?;
+                locals.push((
+                    local.short_name().to_owned(),
+                    reason(branch),
+                    Paint::Merged,
+                    Some(local.refname.clone()),
+                ));
+                by_remote.entry(remote.remote.clone()).or_default().push((
+                    remote.short_name().to_owned(),
+                    reason(branch),
+                    Paint::Stray,
+                    Some(upstream.refname.clone()),
+                ));
+            }
+            ClassifiedBranch::MergedDirectFetch { local, remote }
+            | ClassifiedBranch::DivergedDirectFetch { local, remote } => {
+                locals.push((
+                    local.short_name().to_owned(),
+                    reason(branch),
+                    Paint::Merged,
+                    Some(local.refname.clone()),
+                ));
+                by_remote.entry(remote.remote.clone()).or_default().push((
+                    remote.short_name().to_owned(),
+                    reason(branch),
+                    Paint::Stray,
+                    None,
+                ));
+            }
+            // Bases are kept back before classification.
+            ClassifiedBranch::BaseLocal(_) | ClassifiedBranch::BaseRemoteTracking(_) => {}
+        }
+    }
 
+    let print =
+        |label: &str, mut branches: Vec<(String, String, Paint, Option<String>)>| -> Result<()> {
+            if branches.is_empty() {
+                return Ok(());
+            }
+            sort_by_ref(repo, &mut branches, sort, |branch| branch.3.clone())?;
+            println!("Delete from {}:", label);
+            for (branch, reason, paint, _) in branches {
+                let branch = match paint {
+                    Paint::Merged => color::merged(colored, branch).to_string(),
+                    Paint::Stray => color::stray(colored, branch).to_string(),
+                    Paint::Plain => branch,
+                };
+                println!("  - {}{}", branch, reason);
+            }
+            Ok(())
+        };
+    print("local", locals)?;
+    for (remote, branches) in by_remote {
+        print(&format!("remote `{}`", remote), branches)?;
+    }
     Ok(())
 }
 
@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use git2::{Oid, Repository};
+use log::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug)]
+pub enum MergeCheck {
+    RevList,
+    Squash,
+    StrictSquash,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Entries {
+    rev_list: HashMap<String, bool>,
+    squash: HashMap<String, bool>,
+    #[serde(default)]
+    strict_squash: HashMap<String, bool>,
+}
+
+impl Entries {
+    fn get_mut(&mut self, check: MergeCheck) -> &mut HashMap<String, bool> {
+        match check {
+            MergeCheck::RevList => &mut self.rev_list,
+            MergeCheck::Squash => &mut self.squash,
+            MergeCheck::StrictSquash => &mut self.strict_squash,
+        }
+    }
+}
+
+/// Results of the merge checks that only depend on the commits of a base and a branch.
+/// They are persisted across runs in `.git/git-trim/cache.json`.
+/// Entries are keyed by the commit ids, so they are not looked up anymore once the branches are updated.
+#[derive(Default)]
+pub struct MergeCache {
+    loaded: Entries,
+    // Only the entries used in this run are saved, so the cache doesn't grow forever.
+    used: Entries,
+}
+
+impl MergeCache {
+    fn path(repo: &Repository) -> PathBuf {
+        repo.path().join("git-trim").join("cache.json")
+    }
+
+    pub fn load(repo: &Repository) -> Result<Self> {
+        let path = Self::path(repo);
+        let loaded = match std::fs::read(&path) {
+            Ok(content) => serde_json::from_slice(&content).unwrap_or_else(|err| {
+                warn!("Ignore the corrupted cache {}: {}", path.display(), err);
+                Entries::default()
+            }),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Entries::default(),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Self {
+            loaded,
+            used: Entries::default(),
+        })
+    }
+
+    pub fn save(&self, repo: &Repository) -> Result<()> {
+        let path = Self::path(repo);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, serde_json::to_vec(&self.used)?)?;
+        Ok(())
+    }
+
+    pub fn get(&mut self, check: MergeCheck, base: Oid, branch: Oid) -> Option<bool> {
+        let key = format!("{}..{}", base, branch);
+        if let Some(merged) = self.used.get_mut(check).get(&key) {
+            return Some(*merged);
+        }
+        let merged = *self.loaded.get_mut(check).get(&key)?;
+        trace!("cache hit: {:?} {}", check, key);
+        self.used.get_mut(check).insert(key, merged);
+        Some(merged)
+    }
+
+    pub fn insert(&mut self, check: MergeCheck, base: Oid, branch: Oid, merged: bool) {
+        let key = format!("{}..{}", base, branch);
+        self.used.get_mut(check).insert(key, merged);
+    }
+}
@@ -6,12 +6,27 @@ use anyhow::Result;
 use git2::{Config, ErrorClass, ErrorCode, Oid, Repository, Signature};
 use log::*;
 
-use crate::branch::{Refname, RemoteTrackingBranch};
-use crate::subprocess::{self, is_merged_by_rev_list};
+use crate::branch::{BaseCommit, PullRequestHead, Refname};
+use crate::merge_cache::{MergeCache, MergeCheck};
+use crate::revwalk::is_merged_by_cherry_pick;
+use crate::subprocess;
+
+/// The message of the throwaway squash-test commit. It is never used for anything else.
+/// Override it with `GIT_TRIM_SQUASH_MESSAGE` or `git config trim.squashMessage`
+/// when policy scanners inspect dangling commits.
+pub const SQUASH_TEST_MESSAGE: &str = "git-trim: squash merge test";
 
 #[derive(Clone)]
 pub struct MergeTracker {
     merged_set: Arc<Mutex<HashSet<String>>>,
+    squash_detect: bool,
+    strict_squash: bool,
+    cache: Option<Arc<Mutex<MergeCache>>>,
+    /// The commits of the merged pull request heads, when `--pull-refs` tracks them.
+    merged_pull_requests: Option<Vec<String>>,
+    squash_message: String,
+    /// The name and the email of the squash-test commit.
+    squash_identity: (String, String),
 }
 
 #[derive(Debug, Clone)]
@@ -22,26 +37,33 @@ pub struct MergeState<B> {
 }
 
 impl MergeTracker {
-    pub fn with_base_upstreams(
-        repo: &Repository,
-        config: &Config,
-        base_upstreams: &[RemoteTrackingBranch],
-    ) -> Result<Self> {
+    pub fn with_bases(repo: &Repository, config: &Config, bases: &[BaseCommit]) -> Result<Self> {
         let tracker = Self {
             merged_set: Arc::new(Mutex::new(HashSet::new())),
+            squash_detect: true,
+            strict_squash: false,
+            cache: None,
+            merged_pull_requests: None,
+            squash_message: squash_test_message(config)?,
+            squash_identity: squash_test_identity(config)?,
         };
         info!("Initializing MergeTracker");
-        for base_upstream in base_upstreams {
-            debug!("base_upstream: {:?}", base_upstream);
-            tracker.track(repo, base_upstream)?;
+        for base in bases {
+            debug!("base: {:?}", base);
+            trace!("track: {}", base.oid);
+            tracker
+                .merged_set
+                .lock()
+                .unwrap()
+                .insert(base.oid.to_string());
         }
 
-        for merged_local in subprocess::get_noff_merged_locals(repo, config, base_upstreams)? {
+        for merged_local in subprocess::get_noff_merged_locals(repo, config, bases)? {
             debug!("merged_local: {:?}", merged_local);
             tracker.track(repo, &merged_local)?;
         }
 
-        for merged_remote in subprocess::get_noff_merged_remotes(&repo, base_upstreams)? {
+        for merged_remote in subprocess::get_noff_merged_remotes(&repo, bases)? {
             debug!("merged_remote: {:?}", merged_remote);
             tracker.track(repo, &merged_remote)?;
         }
@@ -49,6 +71,109 @@ impl MergeTracker {
         Ok(tracker)
     }
 
+    /// A tracker of other bases with the same merge checks and the same cache.
+    /// The merged set isn't shared, since the branches merged into the bases of this one
+    /// aren't necessarily merged into the others.
+    pub fn for_other_bases(
+        &self,
+        repo: &Repository,
+        config: &Config,
+        bases: &[BaseCommit],
+    ) -> Result<Self> {
+        let mut tracker = Self::with_bases(repo, config, bases)?;
+        tracker.squash_detect = self.squash_detect;
+        tracker.strict_squash = self.strict_squash;
+        tracker.cache = self.cache.clone();
+        Ok(tracker)
+    }
+
+    /// Disable the squash merge detection. Only merges and rebase merges are detected then.
+    pub fn with_squash_detect(mut self, squash_detect: bool) -> Self {
+        self.squash_detect = squash_detect;
+        self
+    }
+
+    pub fn squash_detect(&self) -> bool {
+        self.squash_detect
+    }
+
+    /// Detect a squash merge only by a commit of the base with the same tree as the branch.
+    pub fn with_strict_squash(mut self, strict_squash: bool) -> Self {
+        self.strict_squash = strict_squash;
+        self
+    }
+
+    pub fn strict_squash(&self) -> bool {
+        self.strict_squash
+    }
+
+    /// The message of the squash-test commit, see `SQUASH_TEST_MESSAGE`.
+    pub fn squash_message(&self) -> &str {
+        &self.squash_message
+    }
+
+    /// Track the pull request heads that are merged into any of the bases,
+    /// so that the branches they contain are merged as well.
+    pub fn with_pull_request_heads(
+        mut self,
+        repo: &Repository,
+        bases: &[BaseCommit],
+        heads: &[PullRequestHead],
+    ) -> Result<Self> {
+        let mut merged = Vec::new();
+        for head in heads {
+            for base in bases {
+                let state = self.check_and_track(repo, base.oid, head)?;
+                if state.merged {
+                    debug!("merged pull request head: {}", head.refname);
+                    merged.push(state.commit);
+                    break;
+                }
+            }
+        }
+        merged.sort();
+        merged.dedup();
+        self.merged_pull_requests = Some(merged);
+        Ok(self)
+    }
+
+    /// `None` unless `with_pull_request_heads` is used.
+    pub fn merged_pull_requests(&self) -> Option<&[String]> {
+        self.merged_pull_requests.as_deref()
+    }
+
+    /// Reuse the results of the expensive merge checks across runs.
+    pub fn with_cache(mut self, cache: MergeCache) -> Self {
+        self.cache = Some(Arc::new(Mutex::new(cache)));
+        self
+    }
+
+    pub fn save_cache(&self, repo: &Repository) -> Result<()> {
+        if let Some(cache) = &self.cache {
+            cache.lock().unwrap().save(repo)?;
+        }
+        Ok(())
+    }
+
+    fn cached(
+        &self,
+        check: MergeCheck,
+        base: Oid,
+        branch: Oid,
+        f: impl FnOnce() -> Result<bool>,
+    ) -> Result<bool> {
+        if let Some(cache) = &self.cache {
+            if let Some(merged) = cache.lock().unwrap().get(check, base, branch) {
+                return Ok(merged);
+            }
+        }
+        let merged = f()?;
+        if let Some(cache) = &self.cache {
+            cache.lock().unwrap().insert(check, base, branch, merged);
+        }
+        Ok(merged)
+    }
+
     pub fn track<T>(&self, repo: &Repository, branch: &T) -> Result<()>
     where
         T: Refname,
@@ -67,19 +192,31 @@ impl MergeTracker {
     pub fn check_and_track<T>(
         &self,
         repo: &Repository,
-        base: &str,
+        base_commit_id: Oid,
         branch: &T,
     ) -> Result<MergeState<T>>
     where
         T: Refname + Clone,
     {
-        let base_commit_id = repo.find_reference(base)?.peel_to_commit()?.id();
+        let base = base_commit_id.to_string();
         let target_commit_id = repo
             .find_reference(branch.refname())?
             .peel_to_commit()?
             .id();
         let target_commit_id_string = target_commit_id.to_string();
 
+        // A branch at the base tip is merged for sure. Don't bother with the expensive checks.
+        if target_commit_id == base_commit_id {
+            debug!("same as base: {} -> {}", branch.refname(), base);
+            let mut set = self.merged_set.lock().unwrap();
+            set.insert(target_commit_id_string.clone());
+            return Ok(MergeState {
+                merged: true,
+                commit: target_commit_id_string,
+                branch: branch.clone(),
+            });
+        }
+
         // TODO(siedentop/#20): Re-evaluate the need for the trace loggings in this function.
         let s = self.merged_set.lock().unwrap().len();
         trace!("check_and_track: {} {} {}", base, branch.refname(), s);
@@ -118,7 +255,8 @@ impl MergeTracker {
                         true
                     }
                     Ok(_) => continue,
-                    Err(err) if merge_base_not_found(&err) => false,
+                    // Unrelated histories. Another merged commit might still have merged it.
+                    Err(err) if merge_base_not_found(&err) => continue,
                     Err(err) => return Err(err.into()),
                 };
                 debug!("noff merged: ({}) -> {}", branch.refname(), &merged[0..7]);
@@ -134,7 +272,12 @@ impl MergeTracker {
             err.class() == ErrorClass::Merge && err.code() == ErrorCode::NotFound
         }
 
-        if is_merged_by_rev_list(repo, base, branch.refname())? {
+        if self.cached(
+            MergeCheck::RevList,
+            base_commit_id,
+            target_commit_id,
+            || is_merged_by_cherry_pick(repo, &base, &target_commit_id_string),
+        )? {
             let mut set = self.merged_set.lock().unwrap();
             set.insert(target_commit_id_string.clone());
             debug!("rebase merged: {} -> {}", branch.refname(), &base);
@@ -145,21 +288,20 @@ impl MergeTracker {
             });
         }
 
-        let squash_merged = match repo.merge_base(base_commit_id, target_commit_id) {
-            Ok(merge_base) => {
-                let merge_base = merge_base.to_string();
-                let squash_merged = is_squash_merged(repo, &merge_base, base, branch.refname())?;
-                if squash_merged {
-                    let mut set = self.merged_set.lock().unwrap();
-                    set.insert(target_commit_id_string.clone());
-                }
-                squash_merged
-            }
-            Err(err) if merge_base_not_found(&err) => false,
-            Err(err) => return Err(err.into()),
-        };
+        if !self.squash_detect {
+            return Ok(MergeState {
+                merged: false,
+                commit: target_commit_id_string,
+                branch: branch.clone(),
+            });
+        }
 
+        let squash_merged = self
+            .check_squash(repo, base_commit_id, target_commit_id, branch.refname())?
+            .unwrap_or(false);
         if squash_merged {
+            let mut set = self.merged_set.lock().unwrap();
+            set.insert(target_commit_id_string.clone());
             debug!("squash merged: {} -> {}", branch.refname(), &base);
         }
         let e = self.merged_set.lock().unwrap().len();
@@ -170,27 +312,142 @@ impl MergeTracker {
             branch: branch.clone(),
         })
     }
+
+    /// The squash detection of `check_and_track` alone, without the merged set.
+    /// `None` when it is skipped because the branch has merge commits, or there is no merge base.
+    pub fn check_squash(
+        &self,
+        repo: &Repository,
+        base_commit_id: Oid,
+        target_commit_id: Oid,
+        refname: &str,
+    ) -> Result<Option<bool>> {
+        let merge_base = match repo.merge_base(base_commit_id, target_commit_id) {
+            Ok(merge_base) => merge_base,
+            Err(err) if err.class() == ErrorClass::Merge && err.code() == ErrorCode::NotFound => {
+                return Ok(None)
+            }
+            Err(err) => return Err(err.into()),
+        };
+        if self.strict_squash {
+            let squash_merged = self.cached(
+                MergeCheck::StrictSquash,
+                base_commit_id,
+                target_commit_id,
+                || is_squash_merged_strictly(repo, merge_base, base_commit_id, target_commit_id),
+            )?;
+            return Ok(Some(squash_merged));
+        }
+        if has_merge_commits(repo, merge_base, target_commit_id)? {
+            debug!("skip squash detection for merge commits: {}", refname);
+            return Ok(None);
+        }
+        let squash_merged =
+            self.cached(MergeCheck::Squash, base_commit_id, target_commit_id, || {
+                let (name, email) = &self.squash_identity;
+                let signature = Signature::now(name, email)?;
+                is_squash_merged(
+                    repo,
+                    merge_base,
+                    &base_commit_id.to_string(),
+                    target_commit_id,
+                    &signature,
+                    &self.squash_message,
+                )
+            })?;
+        Ok(Some(squash_merged))
+    }
 }
 
 /// Source: https://stackoverflow.com/a/56026209
+///
+/// The squash-test commit is created with libgit2, so no hooks are ever invoked during
+/// classification, whether they are in `.git/hooks` or in `core.hooksPath`.
+///
+/// `tip` is the commit that the branch peels to, so a branch to an annotated tag is tested
+/// by the tree of the tagged commit.
 fn is_squash_merged(
     repo: &Repository,
-    merge_base: &str,
+    merge_base: Oid,
     base: &str,
-    refname: &str,
+    tip: Oid,
+    signature: &Signature,
+    message: &str,
 ) -> Result<bool> {
-    let tree = repo
-        .revparse_single(&format!("{}^{{tree}}", refname))?
-        .peel_to_tree()?;
-    let tmp_sig = Signature::now("git-trim", "git-trim@squash.merge.test.local")?;
+    let tree = repo.find_commit(tip)?.tree()?;
+    // `Repository::commit` only writes a commit object. Since no ref is updated and no
+    // `git commit` process is spawned, commit hooks never run.
     let dangling_commit = repo.commit(
         None,
-        &tmp_sig,
-        &tmp_sig,
-        "git-trim: squash merge test",
+        signature,
+        signature,
+        message,
         &tree,
-        &[&repo.find_commit(Oid::from_str(merge_base)?)?],
+        &[&repo.find_commit(merge_base)?],
     )?;
 
-    is_merged_by_rev_list(repo, base, &dangling_commit.to_string())
+    is_merged_by_cherry_pick(repo, base, &dangling_commit.to_string())
+}
+
+/// Squashing a branch with merge commits also squashes the merged changes,
+/// so the squash-test commit would match unrelated commits of the base.
+fn has_merge_commits(repo: &Repository, merge_base: Oid, tip: Oid) -> Result<bool> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(tip)?;
+    revwalk.hide(merge_base)?;
+    for oid in revwalk {
+        if repo.find_commit(oid?)?.parent_count() > 1 {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// A commit of the base since the merge base has exactly the tree of the branch.
+/// It misses squash merges after which the base has moved further in the same commit.
+fn is_squash_merged_strictly(
+    repo: &Repository,
+    merge_base: Oid,
+    base: Oid,
+    tip: Oid,
+) -> Result<bool> {
+    let tree = repo.find_commit(tip)?.tree_id();
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(base)?;
+    revwalk.hide(merge_base)?;
+    for oid in revwalk {
+        if repo.find_commit(oid?)?.tree_id() == tree {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn squash_test_message(config: &Config) -> Result<String> {
+    if let Ok(message) = std::env::var("GIT_TRIM_SQUASH_MESSAGE") {
+        return Ok(message);
+    }
+    match config.get_string("trim.squashMessage") {
+        Ok(message) => Ok(message),
+        Err(err) if err.code() == ErrorCode::NotFound => Ok(SQUASH_TEST_MESSAGE.to_owned()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// The identity of the throwaway squash-test commit.
+/// Use the identity of the repository config when it is set, so that the dangling commit doesn't
+/// look foreign to tools that inspect loose objects.
+fn squash_test_identity(config: &Config) -> Result<(String, String)> {
+    let get = |key: &str| match config.get_string(key) {
+        Ok(value) => Ok(Some(value)),
+        Err(err) if err.code() == ErrorCode::NotFound => Ok(None),
+        Err(err) => Err(err),
+    };
+    match (get("user.name")?, get("user.email")?) {
+        (Some(name), Some(email)) => Ok((name, email)),
+        _ => Ok((
+            "git-trim".to_owned(),
+            "git-trim@squash.merge.test.local".to_owned(),
+        )),
+    }
 }
@@ -1,54 +1,765 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
 use anyhow::Result;
 use git2::Repository;
+use serde::Serialize;
+use serde_json::{json, Value};
 
-use crate::{get_remotes, TrimPlan};
+use crate::args::SortKey;
+use crate::{
+    get_remotes, ClassifiedBranch, DeletionOutcome, DeletionStatus, DeletionTarget, Diagnostic,
+    LocalBranch, Preserved, RemoteBranch, RemoteBranchError, RemoteState, RemoteTrackingBranch,
+    SkipSuggestion, TrimPlan,
+};
 
-/// Prints all locally to-be-deleted branches.
-pub fn print_local(
+/// Receives each item of a plan in order, so that embedders can react to them programmatically.
+/// Every method does nothing by default.
+pub trait PlanSink {
+    /// A local branch to delete. `branch` is its classification.
+    fn on_delete_local(&mut self, _branch: &ClassifiedBranch, _local: &LocalBranch) -> Result<()> {
+        Ok(())
+    }
+
+    /// A remote branch to delete, including the direct fetched ones.
+    fn on_delete_remote(
+        &mut self,
+        _branch: &ClassifiedBranch,
+        _remote: &RemoteBranch,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// A remote tracking branch that is deleted only from the local repository,
+    /// a stale one or one in `TrimPlan::local_only`.
+    fn on_delete_stale(
+        &mut self,
+        _branch: &ClassifiedBranch,
+        _remote: &RemoteBranch,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// A side of a kept back branch. `remote` is `None` for a local branch, or when the remote isn't known.
+    fn on_kept_back(
+        &mut self,
+        _preserved: &Preserved,
+        _name: &str,
+        _remote: Option<&str>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// After every item of the plan. Sinks that write the plan as a whole, like `XmlWriter`, write it here.
+    fn on_finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Feeds the plan to the sink.
+/// Local branches, remote branches and stale remote tracking branches to delete come sorted,
+/// and then the kept back branches in the order of `TrimPlan::preserved`.
+pub fn emit(plan: &TrimPlan, repo: &Repository, sink: &mut dyn PlanSink) -> Result<()> {
+    emit_sorted_by(plan, repo, sink, SortKey::Name)
+}
+
+/// Same as `emit`, but the branches to delete are sorted by `key` for `--sort`.
+pub fn emit_sorted_by(
     plan: &TrimPlan,
-    _repo: &Repository,
-    mut writer: impl std::io::Write,
+    repo: &Repository,
+    sink: &mut dyn PlanSink,
+    key: SortKey,
 ) -> Result<()> {
-    let mut merged_locals = Vec::new();
+    let remotes = get_remotes(repo)?;
+    let mut locals = HashMap::new();
+    let mut remote_branches = HashMap::new();
+    let mut deleted_locally = HashMap::new();
     for branch in &plan.to_delete {
         if let Some(local) = branch.local() {
-            merged_locals.push(local.short_name().to_owned());
+            locals.insert(local, branch);
+        }
+        if let Some(upstream) = plan.deleted_locally(branch) {
+            deleted_locally.insert(upstream, branch);
+        } else if let Some(remote) = branch.remote(&remotes)? {
+            remote_branches.insert(remote, branch);
+        }
+    }
+
+    for local in plan.sorted_locals_to_delete_by(repo, key)? {
+        sink.on_delete_local(locals[local], local)?;
+    }
+    for remote in plan.sorted_remotes_to_delete_by(repo, key)? {
+        sink.on_delete_remote(remote_branches[&remote], &remote)?;
+    }
+    for upstream in plan.remote_trackings_to_delete_locally() {
+        sink.on_delete_stale(
+            deleted_locally[upstream],
+            &upstream.to_remote_branch(&remotes)?,
+        )?;
+    }
+    for preserved in &plan.preserved {
+        for (name, remote) in preserved_sides(preserved, &remotes)? {
+            sink.on_kept_back(preserved, &name, remote.as_deref())?;
+        }
+    }
+
+    sink.on_finish()
+}
+
+/// Writes the short name of each local branch to delete per line.
+/// They are never colored, so that scripts can use them as is.
+pub struct LocalWriter<W> {
+    writer: W,
+}
+
+impl<W: std::io::Write> LocalWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+}
+
+impl<W: std::io::Write> PlanSink for LocalWriter<W> {
+    fn on_delete_local(&mut self, _branch: &ClassifiedBranch, local: &LocalBranch) -> Result<()> {
+        writeln!(self.writer, "{}", local.short_name())?;
+        Ok(())
+    }
+}
+
+/// Writes each remote branch to delete per line in the form "<remote>/<branch_name>".
+/// They are never colored, so that scripts can use them as is.
+pub struct RemoteWriter<W> {
+    writer: W,
+}
+
+impl<W: std::io::Write> RemoteWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+}
+
+impl<W: std::io::Write> PlanSink for RemoteWriter<W> {
+    fn on_delete_remote(
+        &mut self,
+        _branch: &ClassifiedBranch,
+        remote: &RemoteBranch,
+    ) -> Result<()> {
+        writeln!(self.writer, "{}/{}", remote.remote, remote.short_name())?;
+        Ok(())
+    }
+}
+
+/// Prints all locally to-be-deleted branches.
+pub fn print_local(plan: &TrimPlan, repo: &Repository, writer: impl std::io::Write) -> Result<()> {
+    emit(plan, repo, &mut LocalWriter::new(writer))
+}
+
+/// Print all remotely to-be-deleted branches in the form "<remote>/<branch_name>".
+pub fn print_remote(plan: &TrimPlan, repo: &Repository, writer: impl std::io::Write) -> Result<()> {
+    emit(plan, repo, &mut RemoteWriter::new(writer))
+}
+
+/// The JSON form of a plan, in the order of the fields of `TrimPlan`.
+#[derive(Serialize)]
+struct JsonPlan<'p> {
+    skipped: &'p HashMap<String, SkipSuggestion>,
+    to_delete: &'p [ClassifiedBranch],
+    preserved: &'p [Value],
+    remote_states: &'p HashMap<String, RemoteState>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    diagnostics: Option<&'p [Diagnostic]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    local_only: Option<&'p HashSet<RemoteTrackingBranch>>,
+}
+
+/// Writes the plan as a JSON object when it is finished.
+/// The branches come from the items, and the rest of the fields from `plan`.
+pub struct JsonWriter<'p, W> {
+    writer: W,
+    plan: &'p TrimPlan,
+    seen: HashSet<ClassifiedBranch>,
+    to_delete: Vec<ClassifiedBranch>,
+    preserved: Vec<Value>,
+}
+
+impl<'p, W: std::io::Write> JsonWriter<'p, W> {
+    pub fn new(writer: W, plan: &'p TrimPlan) -> Self {
+        Self {
+            writer,
+            plan,
+            seen: HashSet::new(),
+            to_delete: Vec::new(),
+            preserved: Vec::new(),
+        }
+    }
+
+    /// Both sides of a branch are items of their own, but the branch is written once.
+    fn delete(&mut self, branch: &ClassifiedBranch) {
+        if self.seen.insert(branch.clone()) {
+            self.to_delete.push(branch.clone());
+        }
+    }
+}
+
+impl<'p, W: std::io::Write> PlanSink for JsonWriter<'p, W> {
+    fn on_delete_local(&mut self, branch: &ClassifiedBranch, _local: &LocalBranch) -> Result<()> {
+        self.delete(branch);
+        Ok(())
+    }
+
+    fn on_delete_remote(
+        &mut self,
+        branch: &ClassifiedBranch,
+        _remote: &RemoteBranch,
+    ) -> Result<()> {
+        self.delete(branch);
+        Ok(())
+    }
+
+    fn on_delete_stale(&mut self, branch: &ClassifiedBranch, _remote: &RemoteBranch) -> Result<()> {
+        self.delete(branch);
+        Ok(())
+    }
+
+    fn on_kept_back(
+        &mut self,
+        preserved: &Preserved,
+        _name: &str,
+        _remote: Option<&str>,
+    ) -> Result<()> {
+        // The sides of a kept back branch come one after another.
+        let preserved = json!(preserved);
+        if self.preserved.last() != Some(&preserved) {
+            self.preserved.push(preserved);
+        }
+        Ok(())
+    }
+
+    fn on_finish(&mut self) -> Result<()> {
+        let plan = JsonPlan {
+            skipped: &self.plan.skipped,
+            to_delete: &self.to_delete,
+            preserved: &self.preserved,
+            remote_states: &self.plan.remote_states,
+            diagnostics: Some(&self.plan.diagnostics[..]).filter(|d| !d.is_empty()),
+            local_only: Some(&self.plan.local_only).filter(|l| !l.is_empty()),
+        };
+        serde_json::to_writer(&mut self.writer, &plan)?;
+        Ok(())
+    }
+}
+
+pub fn print_json(plan: &TrimPlan, repo: &Repository, writer: impl std::io::Write) -> Result<()> {
+    emit(plan, repo, &mut JsonWriter::new(writer, plan))
+}
+
+/// The local and the remote side of a branch as pairs of the short name and the remote name.
+/// The remote name is `None` for a local branch, or when the remote isn't known.
+fn branch_sides(
+    branch: &ClassifiedBranch,
+    remotes: &[git2::Remote],
+) -> Result<Vec<(String, Option<String>)>> {
+    let mut sides = Vec::new();
+    if let Some(local) = branch.local() {
+        sides.push((local.short_name().to_owned(), None));
+    }
+    match branch {
+        ClassifiedBranch::MergedDirectFetch { remote, .. }
+        | ClassifiedBranch::DivergedDirectFetch { remote, .. } => {
+            sides.push((remote.short_name().to_owned(), Some(remote.remote.clone())));
+        }
+        _ => {
+            if let Some(upstream) = branch.upstream() {
+                match upstream.to_remote_branch(remotes) {
+                    Ok(remote) => sides.push((remote.short_name().to_owned(), Some(remote.remote))),
+                    // It is kept since we don't know which remote branch it is.
+                    Err(RemoteBranchError::RemoteNotFound) => {
+                        sides.push((upstream.short_name().to_owned(), None))
+                    }
+                    Err(err) => return Err(err.into()),
+                }
+            }
+        }
+    }
+    Ok(sides)
+}
+
+/// The sides of the kept back branch, without the local when only the remote side is kept back.
+fn preserved_sides(
+    preserved: &Preserved,
+    remotes: &[git2::Remote],
+) -> Result<Vec<(String, Option<String>)>> {
+    let mut sides = branch_sides(&preserved.branch, remotes)?;
+    if preserved.remote_only {
+        // The local is the first side.
+        sides.remove(0);
+    }
+    Ok(sides)
+}
+
+/// Writes a JSON object per line for each action on a branch,
+/// so that consumers can process a huge plan incrementally.
+pub struct JsonLinesWriter<W> {
+    writer: W,
+    remote_states: HashMap<String, RemoteState>,
+}
+
+impl<W: std::io::Write> JsonLinesWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            remote_states: HashMap::new(),
         }
     }
 
-    merged_locals.sort();
-    for branch in merged_locals {
-        writeln!(writer, "{}", branch)?;
+    /// Adds `remote_state` of `TrimPlan::remote_states` to the lines of the local branches.
+    pub fn with_remote_states(self, remote_states: HashMap<String, RemoteState>) -> Self {
+        Self {
+            remote_states,
+            ..self
+        }
+    }
+
+    fn write(
+        &mut self,
+        action: &str,
+        branch: &ClassifiedBranch,
+        name: &str,
+        remote: Option<&str>,
+        reason: Option<&str>,
+    ) -> Result<()> {
+        let mut line = json!({
+            "action": action,
+            "name": name,
+            "remote": remote,
+            "classification": branch.category(),
+            "reason": reason,
+        });
+        if remote.is_none() {
+            if let Some(state) = branch
+                .local()
+                .filter(|local| local.short_name() == name)
+                .and_then(|local| self.remote_states.get(&local.refname))
+            {
+                line["remote_state"] = json!(state);
+            }
+        }
+        writeln!(self.writer, "{}", line)?;
+        Ok(())
+    }
+}
+
+impl<W: std::io::Write> PlanSink for JsonLinesWriter<W> {
+    fn on_delete_local(&mut self, branch: &ClassifiedBranch, local: &LocalBranch) -> Result<()> {
+        self.write("delete", branch, local.short_name(), None, None)
+    }
+
+    fn on_delete_remote(&mut self, branch: &ClassifiedBranch, remote: &RemoteBranch) -> Result<()> {
+        self.write(
+            "delete",
+            branch,
+            remote.short_name(),
+            Some(remote.remote.as_str()),
+            None,
+        )
+    }
+
+    fn on_delete_stale(&mut self, branch: &ClassifiedBranch, remote: &RemoteBranch) -> Result<()> {
+        self.on_delete_remote(branch, remote)
     }
 
-    Ok(())
+    fn on_kept_back(
+        &mut self,
+        preserved: &Preserved,
+        name: &str,
+        remote: Option<&str>,
+    ) -> Result<()> {
+        self.write(
+            "keep",
+            &preserved.branch,
+            name,
+            remote,
+            Some(preserved.reason.as_str()),
+        )
+    }
 }
 
-/// Print all remotely to-be-deleted branches in the form "<remote>/<branch_name>"
-pub fn print_remote(
+/// Prints a JSON object per line for each action on a branch,
+/// so that consumers can process a huge plan incrementally.
+pub fn print_json_lines(
     plan: &TrimPlan,
     repo: &Repository,
-    mut writer: impl std::io::Write,
+    writer: impl std::io::Write,
 ) -> Result<()> {
-    let mut merged_remotes = Vec::new();
-    let remotes = get_remotes(&repo)?;
-    for branch in &plan.to_delete {
-        if let Some(remote) = branch.remote(&remotes)? {
-            merged_remotes.push(remote);
+    let mut sink = JsonLinesWriter::new(writer).with_remote_states(plan.remote_states.clone());
+    emit(plan, repo, &mut sink)
+}
+
+/// Writes a JSON object per line for each event of a run as it happens, for the job runners:
+/// `classified` for each branch to delete, `kept_back` for each side of a kept back branch,
+/// `deleted`, `delete_failed` or `delete_skipped` for each deletion, and `summary` with the counts of them at last.
+pub struct EventWriter<'r, W> {
+    writer: W,
+    repo: &'r Repository,
+    counts: BTreeMap<&'static str, usize>,
+}
+
+impl<'r, W: std::io::Write> EventWriter<'r, W> {
+    pub fn new(writer: W, repo: &'r Repository) -> Self {
+        Self {
+            writer,
+            repo,
+            counts: BTreeMap::new(),
         }
     }
 
-    merged_remotes.sort();
-    for branch in merged_remotes {
-        let branch_name = &branch.refname["/refs/heads".len()..];
-        writeln!(writer, "{}/{}", branch.remote, branch_name)?;
+    fn write(&mut self, event: &'static str, mut line: serde_json::Value) -> Result<()> {
+        line["event"] = json!(event);
+        writeln!(self.writer, "{}", line)?;
+        self.writer.flush()?;
+        *self.counts.entry(event).or_default() += 1;
+        Ok(())
     }
 
-    Ok(())
+    /// An outcome of `apply_trim_plan_with`.
+    pub fn on_outcome(&mut self, outcome: &DeletionOutcome) -> Result<()> {
+        let (name, remote) = match &outcome.target {
+            DeletionTarget::Local(local) => (local.short_name().to_owned(), None),
+            DeletionTarget::Remote(remote) => {
+                (remote.short_name().to_owned(), Some(remote.remote.clone()))
+            }
+            DeletionTarget::RemoteTracking(upstream) => {
+                let remote = upstream.to_remote_branch(&get_remotes(self.repo)?)?;
+                (remote.short_name().to_owned(), Some(remote.remote))
+            }
+        };
+        let event = match outcome.status {
+            DeletionStatus::Deleted | DeletionStatus::DryRun => "deleted",
+            DeletionStatus::Failed => "delete_failed",
+            DeletionStatus::NotAttempted | DeletionStatus::Skipped => "delete_skipped",
+        };
+        self.write(
+            event,
+            json!({
+                "branch": name,
+                "remote": remote,
+                "dry_run": outcome.status == DeletionStatus::DryRun,
+                "error": outcome.error,
+            }),
+        )
+    }
+
+    /// Writes the `summary` event.
+    pub fn finish(&mut self) -> Result<()> {
+        let counts = json!(self.counts);
+        self.write("summary", json!({ "counts": counts }))
+    }
+
+    fn classified(
+        &mut self,
+        branch: &ClassifiedBranch,
+        name: &str,
+        remote: Option<&str>,
+    ) -> Result<()> {
+        self.write(
+            "classified",
+            json!({
+                "branch": name,
+                "remote": remote,
+                "classification": branch.category(),
+            }),
+        )
+    }
 }
 
-pub fn print_json(plan: &TrimPlan, _repo: &Repository, writer: impl std::io::Write) -> Result<()> {
-    serde_json::to_writer(writer, &plan)?;
+impl<'r, W: std::io::Write> PlanSink for EventWriter<'r, W> {
+    fn on_delete_local(&mut self, branch: &ClassifiedBranch, local: &LocalBranch) -> Result<()> {
+        self.classified(branch, local.short_name(), None)
+    }
+
+    fn on_delete_remote(&mut self, branch: &ClassifiedBranch, remote: &RemoteBranch) -> Result<()> {
+        self.classified(branch, remote.short_name(), Some(remote.remote.as_str()))
+    }
 
-    Ok(())
+    fn on_delete_stale(&mut self, branch: &ClassifiedBranch, remote: &RemoteBranch) -> Result<()> {
+        self.on_delete_remote(branch, remote)
+    }
+
+    fn on_kept_back(
+        &mut self,
+        preserved: &Preserved,
+        name: &str,
+        remote: Option<&str>,
+    ) -> Result<()> {
+        self.write(
+            "kept_back",
+            json!({
+                "branch": name,
+                "remote": remote,
+                "reason": preserved.reason,
+            }),
+        )
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&apos;"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// Writes the plan as XML when it is finished, for the tools that only ingest it.
+/// Branches are sorted by the remote name and the short name for a reproducible output.
+pub struct XmlWriter<W> {
+    writer: W,
+    to_delete: Vec<(Option<String>, String, String)>,
+    preserved: Vec<(Option<String>, String, String)>,
+}
+
+impl<W: std::io::Write> XmlWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            to_delete: Vec::new(),
+            preserved: Vec::new(),
+        }
+    }
+}
+
+impl<W: std::io::Write> PlanSink for XmlWriter<W> {
+    fn on_delete_local(&mut self, branch: &ClassifiedBranch, local: &LocalBranch) -> Result<()> {
+        self.to_delete.push((
+            None,
+            local.short_name().to_owned(),
+            branch.category().to_owned(),
+        ));
+        Ok(())
+    }
+
+    fn on_delete_remote(&mut self, branch: &ClassifiedBranch, remote: &RemoteBranch) -> Result<()> {
+        self.to_delete.push((
+            Some(remote.remote.clone()),
+            remote.short_name().to_owned(),
+            branch.category().to_owned(),
+        ));
+        Ok(())
+    }
+
+    fn on_delete_stale(&mut self, branch: &ClassifiedBranch, remote: &RemoteBranch) -> Result<()> {
+        self.on_delete_remote(branch, remote)
+    }
+
+    fn on_kept_back(
+        &mut self,
+        preserved: &Preserved,
+        name: &str,
+        remote: Option<&str>,
+    ) -> Result<()> {
+        self.preserved.push((
+            remote.map(str::to_owned),
+            name.to_owned(),
+            preserved.reason.clone(),
+        ));
+        Ok(())
+    }
+
+    fn on_finish(&mut self) -> Result<()> {
+        self.to_delete.sort();
+        self.preserved.sort();
+
+        let writer = &mut self.writer;
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(writer, "<trim-plan>")?;
+        let sections = [
+            ("delete", "classification", &self.to_delete),
+            ("kept-back", "reason", &self.preserved),
+        ];
+        for (tag, key, branches) in &sections {
+            writeln!(writer, "  <{}>", tag)?;
+            for (remote, name, value) in branches.iter() {
+                match remote {
+                    Some(remote) => writeln!(
+                        writer,
+                        r#"    <remote remote="{}" {}="{}">{}</remote>"#,
+                        escape_xml(remote),
+                        key,
+                        escape_xml(value),
+                        escape_xml(name),
+                    )?,
+                    None => writeln!(
+                        writer,
+                        r#"    <local {}="{}">{}</local>"#,
+                        key,
+                        escape_xml(value),
+                        escape_xml(name),
+                    )?,
+                }
+            }
+            writeln!(writer, "  </{}>", tag)?;
+        }
+        writeln!(writer, "</trim-plan>")?;
+        Ok(())
+    }
+}
+
+/// Prints the plan as XML for the tools that only ingest it.
+/// Branches are sorted by the remote name and the short name for a reproducible output.
+pub fn print_xml(plan: &TrimPlan, repo: &Repository, writer: impl std::io::Write) -> Result<()> {
+    emit(plan, repo, &mut XmlWriter::new(writer))
+}
+
+/// A code span of `text` that can be put in a table cell.
+/// The fence is longer than any run of backticks in it, and `|` is escaped for the table.
+fn markdown_code(text: &str) -> String {
+    let mut longest = 0;
+    let mut run = 0;
+    for c in text.chars() {
+        if c == '`' {
+            run += 1;
+            longest = longest.max(run);
+        } else {
+            run = 0;
+        }
+    }
+    let fence = "`".repeat(longest + 1);
+    // A code span that starts or ends with a backtick needs spaces to be told apart from the fence.
+    let padding = if text.starts_with('`') || text.ends_with('`') {
+        " "
+    } else {
+        ""
+    };
+    format!(
+        "{}{}{}{}{}",
+        fence,
+        padding,
+        text.replace('|', "\\|"),
+        padding,
+        fence
+    )
+}
+
+fn markdown_type(remote: Option<&str>) -> String {
+    match remote {
+        Some(remote) => format!("remote {}", markdown_code(remote)),
+        None => "local".to_owned(),
+    }
+}
+
+/// Writes the plan as Markdown when it is finished, to paste in pull requests and chats.
+/// The branches to delete are in a table in the order of the items, and the kept back branches
+/// with the reasons are in a collapsible list, sorted by the remote name and the short name.
+pub struct MarkdownWriter<W> {
+    writer: W,
+    to_delete: Vec<(String, String, String)>,
+    preserved: Vec<(Option<String>, String, String)>,
+}
+
+impl<W: std::io::Write> MarkdownWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            to_delete: Vec::new(),
+            preserved: Vec::new(),
+        }
+    }
+}
+
+impl<W: std::io::Write> PlanSink for MarkdownWriter<W> {
+    fn on_delete_local(&mut self, branch: &ClassifiedBranch, local: &LocalBranch) -> Result<()> {
+        self.to_delete.push((
+            local.short_name().to_owned(),
+            markdown_type(None),
+            branch.category().to_owned(),
+        ));
+        Ok(())
+    }
+
+    fn on_delete_remote(&mut self, branch: &ClassifiedBranch, remote: &RemoteBranch) -> Result<()> {
+        self.to_delete.push((
+            remote.short_name().to_owned(),
+            markdown_type(Some(&remote.remote)),
+            branch.category().to_owned(),
+        ));
+        Ok(())
+    }
+
+    fn on_delete_stale(&mut self, branch: &ClassifiedBranch, remote: &RemoteBranch) -> Result<()> {
+        // Only the remote tracking branch is deleted. The remote branch is left as it is.
+        self.to_delete.push((
+            remote.short_name().to_owned(),
+            format!("{} (local only)", markdown_type(Some(&remote.remote))),
+            branch.category().to_owned(),
+        ));
+        Ok(())
+    }
+
+    fn on_kept_back(
+        &mut self,
+        preserved: &Preserved,
+        name: &str,
+        remote: Option<&str>,
+    ) -> Result<()> {
+        self.preserved.push((
+            remote.map(str::to_owned),
+            name.to_owned(),
+            preserved.reason.clone(),
+        ));
+        Ok(())
+    }
+
+    fn on_finish(&mut self) -> Result<()> {
+        self.preserved.sort();
+
+        let writer = &mut self.writer;
+        writeln!(writer, "| Branch | Type | Classification |")?;
+        writeln!(writer, "| --- | --- | --- |")?;
+        for (name, kind, category) in &self.to_delete {
+            writeln!(
+                writer,
+                "| {} | {} | {} |",
+                markdown_code(name),
+                kind,
+                category
+            )?;
+        }
+        if !self.preserved.is_empty() {
+            writeln!(writer)?;
+            writeln!(writer, "<details>")?;
+            writeln!(
+                writer,
+                "<summary>Kept back branches ({})</summary>",
+                self.preserved.len()
+            )?;
+            writeln!(writer)?;
+            for (remote, name, reason) in &self.preserved {
+                writeln!(
+                    writer,
+                    "- {} ({}): {}",
+                    markdown_code(name),
+                    markdown_type(remote.as_deref()),
+                    reason
+                )?;
+            }
+            writeln!(writer)?;
+            writeln!(writer, "</details>")?;
+        }
+        Ok(())
+    }
+}
+
+/// Prints the plan as Markdown to paste in pull requests and chats.
+/// The branches to delete are in a table, and the kept back branches with the reasons are in a collapsible list.
+pub fn print_markdown(
+    plan: &TrimPlan,
+    repo: &Repository,
+    writer: impl std::io::Write,
+) -> Result<()> {
+    emit(plan, repo, &mut MarkdownWriter::new(writer))
 }
@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result};
+use git2::Config as GitConfig;
+use log::*;
+use serde::Deserialize;
+
+use crate::config::get_merge;
+use crate::LocalBranch;
+
+/// A pull request of the forge, reduced to the branch names that `--base-from-pr` needs.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PullRequest {
+    /// The branch name that the pull request is opened from.
+    #[serde(rename = "headRefName")]
+    pub head: String,
+    /// The branch name that the pull request targets, like `release/2.0`.
+    #[serde(rename = "baseRefName")]
+    pub base: String,
+}
+
+pub trait PullRequestClient {
+    /// Pull requests of the repository in any state, newer ones first.
+    fn pull_requests(&self) -> Result<Vec<PullRequest>>;
+}
+
+/// Lists the pull requests with the GitHub CLI, which takes care of the host and the authentication.
+pub struct GhCli {
+    pub working_directory: PathBuf,
+}
+
+impl PullRequestClient for GhCli {
+    fn pull_requests(&self) -> Result<Vec<PullRequest>> {
+        let args = &[
+            "pr",
+            "list",
+            "--state",
+            "all",
+            "--limit",
+            "1000",
+            "--json",
+            "headRefName,baseRefName",
+        ];
+        info!("> gh {}", args.join(" "));
+        let output = Command::new("gh")
+            .args(args)
+            .current_dir(&self.working_directory)
+            .stdin(Stdio::null())
+            .output()
+            .context("`--base-from-pr` requires the GitHub CLI `gh`")?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "gh pr list failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(serde_json::from_slice(&output.stdout)?)
+    }
+}
+
+/// The target branches of the pull requests by their head branches.
+/// When a branch has several pull requests, the newest one wins.
+#[derive(Debug, Default)]
+pub struct PullRequestBases {
+    bases: HashMap<String, String>,
+}
+
+impl PullRequestBases {
+    pub fn fetch(client: &dyn PullRequestClient) -> Result<Self> {
+        let mut bases = HashMap::new();
+        for pull_request in client.pull_requests()? {
+            bases.entry(pull_request.head).or_insert(pull_request.base);
+        }
+        debug!("pull request bases: {:#?}", bases);
+        Ok(Self { bases })
+    }
+
+    /// A resolver for `PlanParam::base_resolver`.
+    /// A pull request is opened from the remote branch, so it is looked up by the upstream branch
+    /// of `branch.<name>.merge`, which might be named differently or be on a fork,
+    /// and then by the push branch of the same name.
+    /// Branches without pull requests get `None`, so they fall back to the global bases.
+    pub fn resolve(&self, config: &GitConfig, refname: &str) -> Option<String> {
+        let local = LocalBranch::from_refname(refname)?;
+        let merge = match get_merge(config, &local) {
+            Ok(merge) => merge,
+            Err(err) => {
+                warn!("Failed to read the upstream of {}: {}", refname, err);
+                None
+            }
+        };
+        merge
+            .as_deref()
+            .and_then(|merge| merge.strip_prefix("refs/heads/"))
+            .and_then(|name| self.bases.get(name))
+            .or_else(|| self.bases.get(local.short_name()))
+            .cloned()
+    }
+}
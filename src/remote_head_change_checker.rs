@@ -1,19 +1,20 @@
 use std::thread::JoinHandle;
+use std::time::Duration;
 
 use anyhow::{Context, Result};
 use git2::Repository;
 use log::*;
 use rayon::prelude::*;
 
-use crate::{ls_remote_head, ForceSendSync, RemoteHead, RemoteTrackingBranch};
+use crate::{ls_remote_head_with_timeout, ForceSendSync, RemoteHead, RemoteTrackingBranch};
 use git_trim::util::get_remotes;
 
 pub struct RemoteHeadChangeChecker {
-    join_handle: JoinHandle<Result<Vec<RemoteHead>>>,
+    join_handle: JoinHandle<Vec<RemoteHead>>,
 }
 
 impl RemoteHeadChangeChecker {
-    pub fn spawn() -> Result<Self> {
+    pub fn spawn(timeout: Option<Duration>) -> Result<Self> {
         let join_handle = {
             let repo = ForceSendSync::new(Repository::open_from_env()?);
             let remotes = {
@@ -27,7 +28,16 @@ impl RemoteHeadChangeChecker {
             std::thread::spawn(move || {
                 remotes
                     .par_iter()
-                    .map(|remote_name| ls_remote_head(&repo, remote_name))
+                    .filter_map(|remote_name| {
+                        match ls_remote_head_with_timeout(&repo, remote_name, timeout) {
+                            Ok(remote_head) => Some(remote_head),
+                            Err(err) => {
+                                // The HEAD of the remote might be detached.
+                                warn!("Failed to get HEAD of {}: {}", remote_name, err);
+                                None
+                            }
+                        }
+                    })
                     .collect()
             })
         };
@@ -35,7 +45,7 @@ impl RemoteHeadChangeChecker {
     }
 
     pub fn check_and_notify(self, repo: &Repository) -> Result<()> {
-        let fetched_remote_heads_raw = self.join_handle.join().unwrap()?;
+        let fetched_remote_heads_raw = self.join_handle.join().unwrap();
         let mut fetched_remote_heads: Vec<RemoteHead> = Vec::new();
         for remote_head in fetched_remote_heads_raw.into_iter() {
             fetched_remote_heads.push(remote_head);
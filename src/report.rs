@@ -0,0 +1,200 @@
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use git2::{ErrorCode, Oid, Repository, Signature};
+use serde::Serialize;
+
+use crate::args::DeleteFilter;
+use crate::{
+    get_remotes, ClassifiedBranch, DeletionReport, DeletionStatus, DeletionTarget, TrimPlan,
+};
+
+/// The notes ref where `--note-deletions` records the deleted branches.
+pub const NOTES_REF: &str = "refs/notes/git-trim";
+
+/// An audit artifact of a trim, written by `--report <PATH>` after the deletions.
+#[derive(Serialize)]
+pub struct AuditReport {
+    /// Seconds since the unix epoch.
+    pub timestamp: u64,
+    pub repository: String,
+    pub bases: Vec<String>,
+    pub filter: String,
+    pub dry_run: bool,
+    pub deleted: Vec<DeletedEntry>,
+    pub kept: Vec<KeptEntry>,
+}
+
+#[derive(Serialize)]
+pub struct DeletedEntry {
+    pub name: String,
+    /// `None` for the branches in the local repository.
+    pub remote: Option<String>,
+    pub refname: String,
+    /// The commit that the branch pointed to before the deletion.
+    pub oid: Option<String>,
+    pub classification: &'static str,
+    pub status: DeletionStatus,
+    pub error: Option<String>,
+    #[serde(skip)]
+    target: DeletionTarget,
+}
+
+#[derive(Serialize)]
+pub struct KeptEntry {
+    pub refname: String,
+    pub classification: &'static str,
+    pub reason: String,
+}
+
+impl AuditReport {
+    /// It must be created before the deletion to record the commits of the branches.
+    pub fn new(
+        repo: &Repository,
+        plan: &TrimPlan,
+        bases: &[&str],
+        filter: &DeleteFilter,
+        dry_run: bool,
+    ) -> Result<Self> {
+        let remotes = get_remotes(repo)?;
+        let oid = |refname: &str| repo.refname_to_id(refname).ok().map(|oid| oid.to_string());
+
+        let mut deleted = Vec::new();
+        for branch in &plan.to_delete {
+            if let Some(local) = branch.local() {
+                deleted.push(DeletedEntry {
+                    name: local.short_name().to_owned(),
+                    remote: None,
+                    refname: local.refname.clone(),
+                    oid: oid(&local.refname),
+                    classification: branch.category(),
+                    status: DeletionStatus::NotAttempted,
+                    error: None,
+                    target: DeletionTarget::Local(local.clone()),
+                });
+            }
+            if let Some(upstream) = plan.deleted_locally(branch) {
+                deleted.push(DeletedEntry {
+                    name: upstream.short_name().to_owned(),
+                    remote: None,
+                    refname: upstream.refname.clone(),
+                    oid: oid(&upstream.refname),
+                    classification: branch.category(),
+                    status: DeletionStatus::NotAttempted,
+                    error: None,
+                    target: DeletionTarget::RemoteTracking(upstream.clone()),
+                });
+            } else if let Some(remote) = branch.remote(&remotes)? {
+                deleted.push(DeletedEntry {
+                    name: remote.short_name().to_owned(),
+                    remote: Some(remote.remote.clone()),
+                    refname: remote.refname.clone(),
+                    oid: branch
+                        .upstream()
+                        .and_then(|upstream| oid(&upstream.refname)),
+                    classification: branch.category(),
+                    status: DeletionStatus::NotAttempted,
+                    error: None,
+                    target: DeletionTarget::Remote(remote.clone()),
+                });
+            }
+        }
+        deleted.sort_by(|a, b| (&a.remote, &a.refname).cmp(&(&b.remote, &b.refname)));
+
+        let mut kept = Vec::new();
+        for preserved in &plan.preserved {
+            let refname = match (preserved.local(), &preserved.branch) {
+                (Some(local), _) => &local.refname,
+                (None, ClassifiedBranch::DivergedDirectFetch { remote, .. }) => &remote.refname,
+                (None, branch) => match branch.upstream() {
+                    Some(upstream) => &upstream.refname,
+                    None => continue,
+                },
+            };
+            kept.push(KeptEntry {
+                refname: refname.clone(),
+                classification: preserved.branch.category(),
+                reason: preserved.reason.clone(),
+            });
+        }
+        kept.sort_by(|a, b| a.refname.cmp(&b.refname));
+
+        let workdir = repo.workdir().unwrap_or_else(|| repo.path());
+        Ok(Self {
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+            repository: workdir
+                .to_str()
+                .context("non utf-8 repository path")?
+                .to_owned(),
+            bases: bases.iter().map(|base| (*base).to_owned()).collect(),
+            filter: filter.to_string(),
+            dry_run,
+            deleted,
+            kept,
+        })
+    }
+
+    /// Branches that are not in the `report` remain not attempted.
+    pub fn record(&mut self, report: &DeletionReport) {
+        for entry in &mut self.deleted {
+            if let Some(outcome) = report.outcome(&entry.target) {
+                entry.status = outcome.status;
+                entry.error = outcome.error.clone();
+            }
+        }
+    }
+
+    /// Append a line per deleted branch to the note of the `base` commit under `NOTES_REF`.
+    /// The lines of the previous runs are kept.
+    pub fn append_note(&self, repo: &Repository, base: Oid) -> Result<()> {
+        let mut lines = Vec::new();
+        for entry in &self.deleted {
+            if entry.status != DeletionStatus::Deleted {
+                continue;
+            }
+            lines.push(format!(
+                "{} {} {} {}",
+                self.timestamp,
+                entry.remote.as_deref().unwrap_or("local"),
+                entry.refname,
+                entry.oid.as_deref().unwrap_or("-"),
+            ));
+        }
+        if lines.is_empty() {
+            return Ok(());
+        }
+
+        let mut message = match repo.find_note(Some(NOTES_REF), base) {
+            Ok(note) => note.message().context("non utf-8 note")?.to_owned(),
+            Err(err) if err.code() == ErrorCode::NotFound => String::new(),
+            Err(err) => return Err(err.into()),
+        };
+        if !message.is_empty() && !message.ends_with('\n') {
+            message.push('\n');
+        }
+        for line in lines {
+            message.push_str(&line);
+            message.push('\n');
+        }
+        let signature = repo
+            .signature()
+            .or_else(|_| Signature::now("git-trim", "git-trim"))?;
+        repo.note(
+            &signature,
+            &signature,
+            Some(NOTES_REF),
+            base,
+            &message,
+            true,
+        )?;
+        Ok(())
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let file = std::fs::File::create(path)
+            .with_context(|| format!("Failed to create the report {}", path.display()))?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+}
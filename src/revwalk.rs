@@ -0,0 +1,151 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use git2::{ErrorCode, Oid, Repository};
+
+use crate::subprocess::{self, has_git_binary};
+
+/// `subprocess::is_merged_by_rev_list` when `git` is on `PATH`, otherwise `is_merged_by_revwalk`.
+pub fn is_merged_by_cherry_pick(repo: &Repository, base: &str, commit: &str) -> Result<bool> {
+    if has_git_binary() {
+        subprocess::is_merged_by_rev_list(repo, base, commit)
+    } else {
+        is_merged_by_revwalk(repo, base, commit)
+    }
+}
+
+/// Get whether there any commits are not in the `base` from the `commit` with libgit2 only.
+/// Same as `git rev-list --cherry-pick --right-only --no-merges -n1 <base>...<commit>` is empty.
+/// A commit is in the `base` when a commit of the other side has the same patch id.
+pub fn is_merged_by_revwalk(repo: &Repository, base: &str, commit: &str) -> Result<bool> {
+    let base = repo.revparse_single(base)?.peel_to_commit()?.id();
+    let commit = repo.revparse_single(commit)?.peel_to_commit()?.id();
+
+    let right = non_merge_commits(repo, commit, base)?;
+    if right.is_empty() {
+        return Ok(true);
+    }
+
+    let mut left = HashSet::new();
+    for oid in non_merge_commits(repo, base, commit)? {
+        left.insert(patch_id(repo, oid)?);
+    }
+    for oid in right {
+        if !left.contains(&patch_id(repo, oid)?) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Whether the commits of the `commit` that aren't in the `base` are all merges without content of their own.
+/// A merge has no content of its own when its tree is the same as one of its parents',
+/// like a merge of the base that is resolved as is. False when there are no such commits at all.
+pub fn has_only_base_merges(repo: &Repository, base: Oid, commit: &str) -> Result<bool> {
+    let commit = repo.revparse_single(commit)?.peel_to_commit()?.id();
+
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(commit)?;
+    revwalk.hide(base)?;
+    let mut found = false;
+    for oid in revwalk {
+        let commit = repo.find_commit(oid?)?;
+        if commit.parent_count() < 2 {
+            return Ok(false);
+        }
+        let tree = commit.tree_id();
+        if !commit.parents().any(|parent| parent.tree_id() == tree) {
+            return Ok(false);
+        }
+        found = true;
+    }
+    Ok(found)
+}
+
+/// Commits that are reverted by `git revert` in the history of `base`.
+/// They are found by the `This reverts commit <commit>.` lines of the messages.
+/// A revert that is reverted again doesn't count.
+pub fn reverted_commits(repo: &Repository, base: Oid) -> Result<HashSet<Oid>> {
+    let mut reverts = HashMap::new();
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(base)?;
+    for oid in revwalk {
+        let oid = oid?;
+        let commit = repo.find_commit(oid)?;
+        for line in commit.message_bytes().split(|b| *b == b'\n') {
+            let line = String::from_utf8_lossy(line);
+            // A revert of a merge continues with `, reversing changes made to <commit>.`
+            let reverted = match line.trim().strip_prefix("This reverts commit ") {
+                Some(rest) => rest.split(|c| c == '.' || c == ',').next().unwrap_or(rest),
+                None => continue,
+            };
+            match repo.revparse_single(reverted) {
+                // Peeled, since the hash might name a tag, or even a non-commit object.
+                Ok(object) => {
+                    if let Ok(commit) = object.peel_to_commit() {
+                        reverts.insert(oid, commit.id());
+                    }
+                }
+                Err(err) if err.code() == ErrorCode::NotFound => {}
+                Err(err) if err.code() == ErrorCode::Ambiguous => {}
+                Err(err) if err.code() == ErrorCode::InvalidSpec => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+    Ok(reverts
+        .iter()
+        .filter(|(revert, _)| !reverts.values().any(|reverted| reverted == *revert))
+        .map(|(_, reverted)| *reverted)
+        .collect())
+}
+
+/// Whether the commit that brought the `tip` into the base is one of the `reverted` commits.
+/// It is either a merge of the `tip`, or the `tip` itself when it's fast-forwarded or cherry-picked as is.
+pub fn is_merge_reverted(repo: &Repository, reverted: &HashSet<Oid>, tip: Oid) -> Result<bool> {
+    for oid in reverted {
+        if *oid == tip {
+            return Ok(true);
+        }
+        let commit = repo.find_commit(*oid)?;
+        if commit.parent_count() < 2 {
+            continue;
+        }
+        let mainline = commit.parent_id(0)?;
+        if mainline == tip || repo.graph_descendant_of(mainline, tip)? {
+            continue;
+        }
+        for merged in commit.parent_ids().skip(1) {
+            if merged == tip || repo.graph_descendant_of(merged, tip)? {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// Commits reachable from `from` but not from `hide`, like `git rev-list --no-merges <hide>..<from>`.
+fn non_merge_commits(repo: &Repository, from: Oid, hide: Oid) -> Result<Vec<Oid>> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(from)?;
+    revwalk.hide(hide)?;
+    let mut result = Vec::new();
+    for oid in revwalk {
+        let oid = oid?;
+        if repo.find_commit(oid)?.parent_count() <= 1 {
+            result.push(oid);
+        }
+    }
+    Ok(result)
+}
+
+pub(crate) fn patch_id(repo: &Repository, oid: Oid) -> Result<Oid> {
+    let commit = repo.find_commit(oid)?;
+    let parent_tree = if commit.parent_count() == 0 {
+        None
+    } else {
+        Some(commit.parent(0)?.tree()?)
+    };
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+    Ok(diff.patchid(None)?)
+}
@@ -25,14 +25,49 @@ pub fn expand_refspec(
         ) {
             continue;
         }
-        match side {
-            ExpansionSide::Right => return Ok(expand(left, right, &reference)),
-            ExpansionSide::Left => return Ok(expand(right, left, &reference)),
+        let expanded = match side {
+            ExpansionSide::Right => expand(left, right, &reference),
+            ExpansionSide::Left => expand(right, left, &reference),
         };
+        if expanded.is_some() {
+            return Ok(expanded);
+        }
     }
     Ok(None)
 }
 
+/// The `(src, dst)` pairs of the fetch refspecs of the remote.
+pub fn fetch_refspecs(remote: &Remote) -> Result<Vec<(String, String)>> {
+    let mut result = Vec::new();
+    for refspec in remote.refspecs() {
+        if refspec.direction() == Direction::Push {
+            continue;
+        }
+        let left = refspec.src().context("non-utf8 refspec src")?;
+        let right = refspec.dst().context("non-utf8 refspec dst")?;
+        result.push((left.to_owned(), right.to_owned()));
+    }
+    Ok(result)
+}
+
+/// Same as `expand_refspec` in `Direction::Fetch`, but with the refspecs from `fetch_refspecs`.
+pub fn expand_fetch_refspecs(
+    refspecs: &[(String, String)],
+    reference: &str,
+    side: ExpansionSide,
+) -> Option<String> {
+    for (left, right) in refspecs {
+        let expanded = match side {
+            ExpansionSide::Right => expand(left, right, &reference),
+            ExpansionSide::Left => expand(right, left, &reference),
+        };
+        if expanded.is_some() {
+            return expanded;
+        }
+    }
+    None
+}
+
 fn expand(src: &str, dest: &str, reference: &str) -> Option<String> {
     let src_stars = src.chars().filter(|&c| c == '*').count();
     let dst_stars = dest.chars().filter(|&c| c == '*').count();
@@ -72,3 +107,26 @@ fn simple_match<'a>(pattern: &str, reference: &'a str) -> Option<&'a str> {
     }
     None
 }
+
+/// The glob matching of `git_reference_foreach_glob`, optionally case insensitive.
+/// `*` matches any sequence including `/`, and `?` matches a character.
+pub fn glob_match(pattern: &str, text: &str, ignore_case: bool) -> bool {
+    fn glob_match(pattern: &[char], text: &[char]) -> bool {
+        match pattern.split_first() {
+            None => text.is_empty(),
+            Some(('*', rest)) => (0..=text.len()).any(|skip| glob_match(rest, &text[skip..])),
+            Some(('?', rest)) => !text.is_empty() && glob_match(rest, &text[1..]),
+            Some((c, rest)) => text.first() == Some(c) && glob_match(rest, &text[1..]),
+        }
+    }
+
+    if ignore_case {
+        let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+        let text: Vec<char> = text.to_lowercase().chars().collect();
+        glob_match(&pattern, &text)
+    } else {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let text: Vec<char> = text.chars().collect();
+        glob_match(&pattern, &text)
+    }
+}
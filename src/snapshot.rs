@@ -0,0 +1,207 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use git2::{ConfigLevel, Oid, Repository};
+use log::*;
+use serde::{Deserialize, Serialize};
+
+use crate::subprocess::{ls_remote_head, ls_remote_heads};
+use crate::util::{get_common_dir, get_remotes};
+use crate::Git;
+
+/// The inputs of the classification: the refs, the repository config and the remote heads.
+/// A replay rebuilds a scratch repository from it, so the classification can be repeated
+/// without the real repository or the network.
+///
+/// The objects aren't copied. The replays borrow them from the repository, which is fine
+/// since the commits don't change, as long as they aren't garbage collected.
+/// So the repository must still be there when the snapshot is replayed.
+/// Worktrees other than the main one aren't captured.
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+pub struct Snapshot {
+    /// The object database of the repository.
+    pub objects: PathBuf,
+    /// The refname that `HEAD` points to, or the commit id when it is detached.
+    pub head: String,
+    /// Object ids of the refs by the refnames.
+    pub refs: BTreeMap<String, String>,
+    /// Targets of the symbolic refs by the refnames, except `HEAD`.
+    pub symbolic_refs: BTreeMap<String, String>,
+    /// Entries of the repository config in order. Each value of a multivar is an entry.
+    pub config: Vec<(String, String)>,
+    pub remotes: BTreeMap<String, RemoteSnapshot>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+pub struct RemoteSnapshot {
+    pub url: String,
+    /// The refname that the remote `HEAD` points to, if the remote tells it.
+    pub head: Option<String>,
+    /// Commit ids of the remote heads by the refnames, like `git ls-remote --heads`.
+    pub heads: BTreeMap<String, String>,
+}
+
+impl Snapshot {
+    /// Captures the repository. It lists the heads of every remote.
+    pub fn capture(git: &Git) -> Result<Self> {
+        let repo = &git.repo;
+        let head = repo.find_reference("HEAD")?;
+        let head = match head.symbolic_target() {
+            Some(target) => target.to_owned(),
+            None => head.target().context("HEAD without a target")?.to_string(),
+        };
+
+        let mut refs = BTreeMap::new();
+        let mut symbolic_refs = BTreeMap::new();
+        for reference in repo.references()? {
+            let reference = reference?;
+            let refname = reference.name().context("non-utf8 refname")?.to_owned();
+            if let Some(target) = reference.symbolic_target() {
+                symbolic_refs.insert(refname, target.to_owned());
+            } else if let Some(target) = reference.target() {
+                refs.insert(refname, target.to_string());
+            }
+        }
+
+        let mut config = Vec::new();
+        let local = repo.config()?.open_level(ConfigLevel::Local)?;
+        let mut entries = local.entries(None)?;
+        while let Some(entry) = entries.next() {
+            let entry = entry?;
+            config.push((
+                entry.name().context("non-utf8 config name")?.to_owned(),
+                entry.value().unwrap_or_default().to_owned(),
+            ));
+        }
+
+        let mut remotes = BTreeMap::new();
+        for remote in get_remotes(repo)? {
+            let name = remote.name().context("non-utf8 remote name")?;
+            let url = remote.url().context("non-utf8 remote url")?;
+            let head = match ls_remote_head(repo, name) {
+                Ok(head) => Some(head.refname),
+                Err(err) => {
+                    debug!("No HEAD of {}: {}", name, err);
+                    None
+                }
+            };
+            let heads = ls_remote_heads(repo, name, None)?
+                .into_iter()
+                .map(|head| (head.refname, head.commit))
+                .collect();
+            remotes.insert(
+                name.to_owned(),
+                RemoteSnapshot {
+                    url: url.to_owned(),
+                    head,
+                    heads,
+                },
+            );
+        }
+
+        Ok(Self {
+            // Linked worktrees have no objects of their own.
+            objects: get_common_dir(repo)?.join("objects").canonicalize()?,
+            head,
+            refs,
+            symbolic_refs,
+            config,
+            remotes,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = fs::File::open(path)
+            .with_context(|| format!("The snapshot {} can't be opened", path.display()))?;
+        Ok(serde_json::from_reader(file)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let file = fs::File::create(path)?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+
+    /// Rebuilds the repository at `path`, which must not exist.
+    /// Each remote becomes a repository under `path/.git/git-trim/remotes/` with the captured heads,
+    /// and `url.<path>.insteadOf` sends the remote operations there.
+    pub fn replay(&self, path: &Path) -> Result<Git> {
+        if path.exists() {
+            return Err(anyhow::anyhow!("{} already exists", path.display()));
+        }
+        if !self.objects.is_dir() {
+            return Err(anyhow::anyhow!(
+                "The objects of the snapshot, {}, are gone. The repository where the snapshot was taken must still be there",
+                self.objects.display()
+            ));
+        }
+        let repo = Repository::init(path)?;
+        borrow_objects(&repo, &self.objects)?;
+
+        let mut config = repo.config()?.open_level(ConfigLevel::Local)?;
+        let mut names = Vec::new();
+        for (name, value) in &self.config {
+            if names.contains(&name) {
+                // The regex that never matches appends another value of the multivar.
+                config.set_multivar(name, "$^", value)?;
+            } else {
+                config.set_str(name, value)?;
+                names.push(name);
+            }
+        }
+
+        for (refname, target) in &self.refs {
+            repo.reference(refname, Oid::from_str(target)?, true, "git-trim: replay")?;
+        }
+        for (refname, target) in &self.symbolic_refs {
+            repo.reference_symbolic(refname, target, true, "git-trim: replay")?;
+        }
+        if self.head.starts_with("refs/") {
+            repo.set_head(&self.head)?;
+        } else {
+            repo.set_head_detached(Oid::from_str(&self.head)?)?;
+        }
+        // The index of the captured repository isn't captured. It is clean in the replay.
+        if let Ok(head) = repo.head() {
+            let tree = head.peel_to_tree()?;
+            let mut index = repo.index()?;
+            index.read_tree(&tree)?;
+            index.write()?;
+        }
+
+        for (name, remote) in &self.remotes {
+            let remote_path = repo.path().join("git-trim").join("remotes").join(name);
+            let remote_repo = Repository::init_bare(&remote_path)?;
+            borrow_objects(&remote_repo, &self.objects)?;
+            // Written as is, since the commits that have never been fetched can't be referred to otherwise.
+            // Git hides those heads, so they are replayed as gone.
+            let mut packed_refs = String::from("# pack-refs with: peeled sorted \n");
+            for (refname, commit) in &remote.heads {
+                packed_refs.push_str(&format!("{} {}\n", commit, refname));
+            }
+            fs::write(remote_path.join("packed-refs"), packed_refs)?;
+            if let Some(head) = &remote.head {
+                fs::write(remote_path.join("HEAD"), format!("ref: {}\n", head))?;
+            }
+            let remote_url = remote_path
+                .to_str()
+                .context("non-utf8 path of the replayed remote")?;
+            config.set_multivar(&format!("url.{}.insteadOf", remote_url), "$^", &remote.url)?;
+        }
+
+        Ok(Git::try_from(Repository::open(path)?)?)
+    }
+}
+
+fn borrow_objects(repo: &Repository, objects: &Path) -> Result<()> {
+    let objects = objects
+        .to_str()
+        .context("non-utf8 path of the object database")?;
+    let info = repo.path().join("objects").join("info");
+    fs::create_dir_all(&info)?;
+    fs::write(info.join("alternates"), format!("{}\n", objects))?;
+    Ok(())
+}
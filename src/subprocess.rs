@@ -1,20 +1,73 @@
 use std::collections::{HashMap, HashSet};
-use std::process::{Command, Stdio};
+use std::io::Read;
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Once;
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
 use git2::{Config, Reference, Repository};
 use log::*;
+use thiserror::Error;
 
-use crate::branch::{LocalBranch, RemoteBranch, RemoteTrackingBranch, RemoteTrackingBranchStatus};
+use crate::branch::{
+    BaseCommit, LocalBranch, RemoteBranch, RemoteTrackingBranch, RemoteTrackingBranchStatus,
+};
+
+#[derive(Error, Debug)]
+#[error("`git {command}` timed out after {}s", .timeout.as_secs())]
+pub struct TimeoutError {
+    pub command: String,
+    pub timeout: Duration,
+}
+
+/// Wait for the child, and kill it after `timeout`.
+fn wait_with_timeout(
+    child: &mut Child,
+    args: &[&str],
+    timeout: Option<Duration>,
+) -> Result<ExitStatus> {
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return Ok(child.wait()?),
+    };
+    let start = Instant::now();
+    loop {
+        if let Some(exit_status) = child.try_wait()? {
+            return Ok(exit_status);
+        }
+        if start.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(TimeoutError {
+                command: args.join(" "),
+                timeout,
+            }
+            .into());
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
 
 fn git(repo: &Repository, args: &[&str], level: log::Level) -> Result<()> {
+    git_with_timeout(repo, args, level, None)
+}
+
+fn git_with_timeout(
+    repo: &Repository,
+    args: &[&str],
+    level: log::Level,
+    timeout: Option<Duration>,
+) -> Result<()> {
     let workdir = repo.workdir().context("Bare repository is not supported")?;
     let workdir = workdir.to_str().context("non utf-8 workdir")?;
     log!(level, "> git {}", args.join(" "));
 
     let mut cd_args = vec!["-C", workdir];
     cd_args.extend_from_slice(args);
-    let exit_status = Command::new("git").args(cd_args).status()?;
+    let mut child = Command::new("git").args(cd_args).spawn()?;
+    let exit_status = wait_with_timeout(&mut child, args, timeout)?;
     if !exit_status.success() {
         Err(std::io::Error::from_raw_os_error(exit_status.code().unwrap_or(-1)).into())
     } else {
@@ -23,37 +76,133 @@ fn git(repo: &Repository, args: &[&str], level: log::Level) -> Result<()> {
 }
 
 fn git_output(repo: &Repository, args: &[&str], level: log::Level) -> Result<String> {
+    git_output_with_timeout(repo, args, level, None)
+}
+
+fn git_output_with_timeout(
+    repo: &Repository,
+    args: &[&str],
+    level: log::Level,
+    timeout: Option<Duration>,
+) -> Result<String> {
     let workdir = repo.workdir().context("Bare repository is not supported")?;
     let workdir = workdir.to_str().context("non utf-8 workdir")?;
     log!(level, "> git {}", args.join(" "));
 
     let mut cd_args = vec!["-C", workdir];
     cd_args.extend_from_slice(args);
-    let output = Command::new("git")
+    let mut child = Command::new("git")
         .args(cd_args)
         .stdin(Stdio::null())
         .stdout(Stdio::piped())
-        .output()?;
-    if !output.status.success() {
-        return Err(std::io::Error::from_raw_os_error(output.status.code().unwrap_or(-1)).into());
+        .spawn()?;
+    // Read in another thread not to block the child on a full pipe.
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let reader = std::thread::spawn(move || -> std::io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        stdout.read_to_end(&mut buf)?;
+        Ok(buf)
+    });
+    // The child is killed on a timeout, which closes the pipe, so the reader is joined either way.
+    let exit_status = wait_with_timeout(&mut child, args, timeout);
+    let stdout = reader.join().expect("stdout reader panicked")?;
+    let exit_status = exit_status?;
+    if !exit_status.success() {
+        return Err(std::io::Error::from_raw_os_error(exit_status.code().unwrap_or(-1)).into());
     }
 
-    let str = std::str::from_utf8(&output.stdout)?.trim();
+    let str = std::str::from_utf8(&stdout)?.trim();
     for line in str.lines() {
         trace!("| {}", line);
     }
     Ok(str.to_string())
 }
 
-pub fn remote_update(repo: &Repository, dry_run: bool) -> Result<()> {
+/// Same as `git_with_timeout`, but stderr is captured instead of inherited, so that the caller can look into it.
+fn git_stderr_with_timeout(
+    repo: &Repository,
+    args: &[&str],
+    level: log::Level,
+    timeout: Option<Duration>,
+) -> Result<(ExitStatus, String)> {
+    let workdir = repo.workdir().context("Bare repository is not supported")?;
+    let workdir = workdir.to_str().context("non utf-8 workdir")?;
+    log!(level, "> git {}", args.join(" "));
+
+    let mut cd_args = vec!["-C", workdir];
+    cd_args.extend_from_slice(args);
+    let mut child = Command::new("git")
+        .args(cd_args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let reader = std::thread::spawn(move || -> std::io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        stderr.read_to_end(&mut buf)?;
+        Ok(buf)
+    });
+    let exit_status = wait_with_timeout(&mut child, args, timeout);
+    let stderr = reader.join().expect("stderr reader panicked")?;
+    Ok((exit_status?, String::from_utf8_lossy(&stderr).into_owned()))
+}
+
+pub fn remote_update(repo: &Repository, dry_run: bool, timeout: Option<Duration>) -> Result<()> {
     if !dry_run {
-        git(repo, &["remote", "update", "--prune"], Level::Info)
+        git_with_timeout(repo, &["remote", "update", "--prune"], Level::Info, timeout)
     } else {
         info!("> git remote update --prune (dry-run)");
         Ok(())
     }
 }
 
+pub fn fetch(
+    repo: &Repository,
+    remote: &str,
+    dry_run: bool,
+    timeout: Option<Duration>,
+) -> Result<()> {
+    if !dry_run {
+        git_with_timeout(repo, &["fetch", remote], Level::Info, timeout)
+    } else {
+        info!("> git fetch {} (dry-run)", remote);
+        Ok(())
+    }
+}
+
+/// Delete remote tracking branches whose remote branches are deleted.
+/// Remote branches are never touched.
+pub fn remote_prune(repo: &Repository, remote: &str, dry_run: bool) -> Result<()> {
+    if !dry_run {
+        // Capture the output not to mix it into `--porcelain` outputs.
+        git_output(repo, &["remote", "prune", remote], Level::Info)?;
+        Ok(())
+    } else {
+        info!("> git remote prune {} (dry-run)", remote);
+        Ok(())
+    }
+}
+
+/// Whether `git` can be spawned. It is checked once per process.
+pub fn has_git_binary() -> bool {
+    static CHECK: Once = Once::new();
+    static FOUND: AtomicBool = AtomicBool::new(false);
+    CHECK.call_once(|| {
+        let found = Command::new("git")
+            .arg("--version")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok();
+        if !found {
+            warn!("`git` is not found. Fall back to libgit2 to check rebase merges");
+        }
+        FOUND.store(found, Ordering::SeqCst);
+    });
+    FOUND.load(Ordering::SeqCst)
+}
+
 /// Get whether there any commits are not in the `base` from the `commit`
 /// `git rev-list --cherry-pick --right-only --no-merges -n1 <base>..<commit>`
 pub fn is_merged_by_rev_list(repo: &Repository, base: &str, commit: &str) -> Result<bool> {
@@ -81,7 +230,7 @@ pub fn is_merged_by_rev_list(repo: &Repository, base: &str, commit: &str) -> Res
 pub fn get_noff_merged_locals(
     repo: &Repository,
     config: &Config,
-    bases: &[RemoteTrackingBranch],
+    bases: &[BaseCommit],
 ) -> Result<HashSet<LocalBranch>> {
     let mut result = HashSet::new();
     for base in bases {
@@ -92,19 +241,19 @@ pub fn get_noff_merged_locals(
                 "--format",
                 "%(refname)",
                 "--merged",
-                &base.refname,
+                &base.oid.to_string(),
             ],
             Level::Trace,
         )?;
         for refname in refnames.lines() {
-            if !refnames.starts_with("refs/") {
+            if !refname.starts_with("refs/") {
                 // Detached HEAD is printed as '(HEAD detached at 1234abc)'
                 continue;
             }
             let branch = LocalBranch::new(refname);
             let upstream = branch.fetch_upstream(repo, config)?;
             if let RemoteTrackingBranchStatus::Exists(upstream) = upstream {
-                if base == &upstream {
+                if base.name == upstream.refname {
                     continue;
                 }
             }
@@ -118,11 +267,35 @@ pub fn get_noff_merged_locals(
     Ok(result)
 }
 
+/// Get local branches whose upstreams are gone, as `git branch -vv` marks them with `[gone]`.
+/// `git for-each-ref --format '%(refname) %(upstream:track)' refs/heads/`
+pub fn get_gone_upstream_locals(repo: &Repository) -> Result<HashSet<LocalBranch>> {
+    let output = git_output(
+        repo,
+        &[
+            "for-each-ref",
+            "--format",
+            "%(refname) %(upstream:track)",
+            "refs/heads/",
+        ],
+        Level::Trace,
+    )?;
+    let mut result = HashSet::new();
+    for line in output.lines() {
+        let mut split = line.splitn(2, ' ');
+        let refname = split.next().unwrap_or_default();
+        if split.next() == Some("[gone]") {
+            result.insert(LocalBranch::new(refname));
+        }
+    }
+    Ok(result)
+}
+
 /// Get remote tracking branches that are merged with merge commit.
 /// `git branch --format '%(refname)' --remote --merged <base>`
 pub fn get_noff_merged_remotes(
     repo: &Repository,
-    bases: &[RemoteTrackingBranch],
+    bases: &[BaseCommit],
 ) -> Result<HashSet<RemoteTrackingBranch>> {
     let mut result = HashSet::new();
     for base in bases {
@@ -134,13 +307,13 @@ pub fn get_noff_merged_remotes(
                 "%(refname)",
                 "--remote",
                 "--merged",
-                &base.refname,
+                &base.oid.to_string(),
             ],
             Level::Trace,
         )?;
         for refname in refnames.lines() {
             let branch = RemoteTrackingBranch::new(refname);
-            if base == &branch {
+            if base.name == branch.refname {
                 continue;
             }
             let reference = repo.find_reference(&refname)?;
@@ -160,9 +333,14 @@ pub struct RemoteHead {
     pub commit: String,
 }
 
-pub fn ls_remote_heads(repo: &Repository, remote_name: &str) -> Result<Vec<RemoteHead>> {
+pub fn ls_remote_heads(
+    repo: &Repository,
+    remote_name: &str,
+    timeout: Option<Duration>,
+) -> Result<Vec<RemoteHead>> {
     let mut result = Vec::new();
-    for line in git_output(repo, &["ls-remote", "--heads", remote_name], Level::Trace)?.lines() {
+    let command = &["ls-remote", "--heads", remote_name];
+    for line in git_output_with_timeout(repo, command, Level::Trace, timeout)?.lines() {
         let records = line.split_whitespace().collect::<Vec<_>>();
         let commit = records[0].to_string();
         let refname = records[1].to_string();
@@ -176,8 +354,16 @@ pub fn ls_remote_heads(repo: &Repository, remote_name: &str) -> Result<Vec<Remot
 }
 
 pub fn ls_remote_head(repo: &Repository, remote_name: &str) -> Result<RemoteHead> {
+    ls_remote_head_with_timeout(repo, remote_name, None)
+}
+
+pub fn ls_remote_head_with_timeout(
+    repo: &Repository,
+    remote_name: &str,
+    timeout: Option<Duration>,
+) -> Result<RemoteHead> {
     let command = &["ls-remote", "--symref", remote_name, "HEAD"];
-    let lines = git_output(repo, command, Level::Trace)?;
+    let lines = git_output_with_timeout(repo, command, Level::Trace, timeout)?;
     let mut refname = None;
     let mut commit = None;
     for line in lines.lines() {
@@ -202,26 +388,71 @@ pub fn ls_remote_head(repo: &Repository, remote_name: &str) -> Result<RemoteHead
     }
 }
 
+/// Get the refs among `refnames` that exist on the remote.
+/// `git ls-remote <remote> <refnames>...`
+pub fn ls_remote_refs(
+    repo: &Repository,
+    remote_name: &str,
+    refnames: &[&str],
+    timeout: Option<Duration>,
+) -> Result<Vec<RemoteHead>> {
+    let mut command = vec!["ls-remote", remote_name];
+    command.extend(refnames);
+    let mut result = Vec::new();
+    for line in git_output_with_timeout(repo, &command, Level::Trace, timeout)?.lines() {
+        let records = line.split_whitespace().collect::<Vec<_>>();
+        match records.as_slice() {
+            // The patterns match the tails of the refnames as well, like `refs/tags/refs/heads/x`.
+            [commit, refname, ..] if refnames.contains(refname) => result.push(RemoteHead {
+                remote: remote_name.to_owned(),
+                refname: refname.to_string(),
+                commit: commit.to_string(),
+            }),
+            _ => continue,
+        }
+    }
+    Ok(result)
+}
+
 /// Get worktrees and its paths without HEAD
+/// Worktrees whose directories are removed without `git worktree prune` are left out, unless they are locked.
+/// Their worktrees are pruned before the deletion, so their branches are free to delete.
 pub fn get_worktrees(repo: &Repository) -> Result<HashMap<LocalBranch, String>> {
     // TODO: `libgit2` has `git2_worktree_*` APIs. However it is not ported to `git2`. Use subprocess directly.
-    let mut result = HashMap::new();
-    let mut worktree = None;
-    let mut branch = None;
+    #[derive(Default)]
+    struct Entry {
+        worktree: Option<String>,
+        branch: Option<LocalBranch>,
+        prunable: bool,
+        locked: bool,
+    }
+
+    let mut entries = Vec::new();
+    let mut entry = Entry::default();
     for line in git_output(repo, &["worktree", "list", "--porcelain"], Level::Trace)?.lines() {
         if line.starts_with("worktree ") {
-            worktree = Some(line["worktree ".len()..].to_owned());
+            entry.worktree = Some(line["worktree ".len()..].to_owned());
         } else if line.starts_with("branch ") {
-            branch = Some(LocalBranch::new(&line["branch ".len()..]));
+            entry.branch = LocalBranch::from_refname(&line["branch ".len()..]);
+        } else if line == "prunable" || line.starts_with("prunable ") {
+            entry.prunable = true;
+        } else if line == "locked" || line.starts_with("locked ") {
+            entry.locked = true;
         } else if line.is_empty() {
-            if let (Some(worktree), Some(branch)) = (worktree.take(), branch.take()) {
-                result.insert(branch, worktree);
-            }
+            entries.push(std::mem::take(&mut entry));
         }
     }
+    entries.push(entry);
 
-    if let (Some(worktree), Some(branch)) = (worktree.take(), branch.take()) {
-        result.insert(branch, worktree);
+    let mut result = HashMap::new();
+    for entry in entries {
+        if let (Some(worktree), Some(branch)) = (entry.worktree, entry.branch) {
+            if !entry.locked && (entry.prunable || !Path::new(&worktree).exists()) {
+                debug!("Skip the prunable worktree {} of {:?}", worktree, branch);
+                continue;
+            }
+            result.insert(branch, worktree);
+        }
     }
 
     let head = repo.head()?;
@@ -252,33 +483,38 @@ pub fn checkout(repo: &Repository, head: Reference, dry_run: bool) -> Result<()>
     }
 }
 
-pub fn branch_delete(repo: &Repository, branches: &[&LocalBranch], dry_run: bool) -> Result<()> {
-    let mut args = vec!["branch", "--delete", "--force"];
-    let mut branch_names = Vec::new();
-    for branch in branches {
-        let reference = repo.find_reference(&branch.refname)?;
-        assert!(reference.is_branch());
-        let branch_name = reference.shorthand().context("non utf-8 branch name")?;
-        branch_names.push(branch_name.to_owned());
-    }
-    args.extend(branch_names.iter().map(|x| x.as_str()));
+#[derive(Error, Debug)]
+#[error("git push failed with status {status}")]
+pub struct PushDeleteError {
+    pub status: i32,
+    pub transient: bool,
+}
 
-    if !dry_run {
-        git(repo, &args, Level::Info)
-    } else {
-        info!("> git {} (dry-run)", args.join(" "));
-        for branch_name in branch_names {
-            println!("Delete branch {} (dry run).", branch_name);
-        }
-        Ok(())
-    }
+/// Messages of git and curl for network failures that might succeed when tried again.
+const TRANSIENT_ERROR_MESSAGES: &[&str] = &[
+    "Could not resolve host",
+    "Connection timed out",
+    "Connection reset",
+    "Connection refused",
+    "Operation timed out",
+    "the remote end hung up unexpectedly",
+    "early EOF",
+    "RPC failed",
+];
+
+fn is_transient_push_error(stderr: &str) -> bool {
+    TRANSIENT_ERROR_MESSAGES
+        .iter()
+        .any(|message| stderr.contains(message))
 }
 
+/// Fails with `PushDeleteError` when `git push` exits with non-zero status.
 pub fn push_delete(
     repo: &Repository,
     remote_name: &str,
     remote_branches: &[&RemoteBranch],
     dry_run: bool,
+    timeout: Option<Duration>,
 ) -> Result<()> {
     assert!(remote_branches
         .iter()
@@ -291,5 +527,16 @@ pub fn push_delete(
     for remote_branch in remote_branches {
         command.push(&remote_branch.refname);
     }
-    git(repo, &command, Level::Trace)
+
+    // Capture stderr to tell transient errors from the others, and relay it as is.
+    let (exit_status, stderr) = git_stderr_with_timeout(repo, &command, Level::Trace, timeout)?;
+    eprint!("{}", stderr);
+    if !exit_status.success() {
+        return Err(PushDeleteError {
+            status: exit_status.code().unwrap_or(-1),
+            transient: is_transient_push_error(&stderr),
+        }
+        .into());
+    }
+    Ok(())
 }
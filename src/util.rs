@@ -1,4 +1,6 @@
+use std::io::ErrorKind;
 use std::ops::Deref;
+use std::path::PathBuf;
 
 use anyhow::Context;
 
@@ -39,3 +41,15 @@ pub fn get_remotes<'a>(repo: &'a git2::Repository) -> anyhow::Result<Vec<git2::R
     }
     Ok(remotes)
 }
+
+/// Gets the git dir that is shared by all worktrees of a Repository, like `git rev-parse --git-common-dir`.
+/// It is `repo.path()` unless the Repository is opened from a linked worktree.
+pub fn get_common_dir(repo: &git2::Repository) -> anyhow::Result<PathBuf> {
+    let path = repo.path();
+    let common_dir = match std::fs::read_to_string(path.join("commondir")) {
+        Ok(common_dir) => path.join(common_dir.trim_end()),
+        Err(err) if err.kind() == ErrorKind::NotFound => path.to_owned(),
+        Err(err) => return Err(err.into()),
+    };
+    Ok(common_dir.canonicalize()?)
+}
@@ -0,0 +1,56 @@
+mod fixture;
+
+use std::process::Command;
+
+use anyhow::Result;
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+
+            git checkout -b untracked
+            touch untracked
+            git add untracked
+            git commit -m "Add untracked"
+            git checkout master
+            git merge untracked --no-ff
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+        EOF
+        "#,
+    )
+}
+
+fn remotes_to_delete(working_directory: &std::path::Path, args: &[&str]) -> Result<String> {
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm", "--porcelain", "remote"])
+        .args(args)
+        .current_dir(working_directory)
+        .output()?;
+    assert!(output.status.success());
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+#[test]
+fn test_all_remotes() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    assert_eq!(remotes_to_delete(&guard.working_directory(), &[])?, "");
+    assert_eq!(
+        remotes_to_delete(&guard.working_directory(), &["--all-remotes"])?,
+        "origin/untracked\n"
+    );
+    Ok(())
+}
@@ -0,0 +1,116 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{
+    get_trim_plan, is_merged, ClassifiedBranch, Diagnostic, DiagnosticKind, Git, LocalBranch,
+    RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            git checkout -b merged master
+            touch merged
+            git add merged
+            git commit -m "Add merged"
+            git push -u origin merged
+            git checkout master
+
+            # `git update-ref` refuses to write non-commit objects to branches.
+            git tag -a v1 -m "Version 1" merged
+            git rev-parse v1 > .git/refs/heads/tagged
+            echo blob | git hash-object -w --stdin > .git/refs/heads/blob
+        EOF
+        origin <<EOF
+            git merge merged --no-ff
+        EOF
+        local <<EOF
+            git fetch
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_branch_to_annotated_tag() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/merged")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new(
+                "refs/remotes/origin/merged"
+            )),
+        },
+    );
+    // It doesn't track anything, so it isn't scanned by default.
+    assert!(plan.skipped.contains_key("refs/heads/tagged"));
+    assert!(is_merged(&git, "tagged", "origin/master")?);
+    assert_eq!(
+        plan.diagnostics,
+        vec![Diagnostic {
+            kind: DiagnosticKind::SkippedRef,
+            message: "Skip a ref to a non-commit object: refs/heads/blob".to_owned(),
+            subject: Some("refs/heads/blob".to_owned()),
+        }],
+    );
+    Ok(())
+}
+
+#[test]
+fn test_squash_merged_annotated_tag() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        local <<EOF
+            git checkout -b squashed master
+            touch squashed-1
+            git add squashed-1
+            git commit -m "Add squashed 1"
+            touch squashed-2
+            git add squashed-2
+            git commit -m "Add squashed 2"
+            git push -u origin squashed
+            git checkout master
+
+            git tag -a v2 -m "Version 2" squashed
+            git rev-parse v2 > .git/refs/heads/squash-tagged
+        EOF
+        origin <<EOF
+            git merge --squash squashed
+            git commit -m "Squash squashed"
+        EOF
+        local <<EOF
+            git fetch
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    // The squash detection tests the tree of the tagged commit.
+    assert!(is_merged(&git, "squash-tagged", "origin/master")?);
+    Ok(())
+}
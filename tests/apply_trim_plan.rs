@@ -0,0 +1,217 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::{BranchType, Repository};
+
+use git_trim::{
+    apply_trim_plan, get_trim_plan, DeleteOptions, DeletionOrder, DeletionStatus, DeletionTarget,
+    Git, LocalBranch, LockHeld, PlanParam, RemoteBranch, RepoLock,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            for NAME in feature-1 feature-2; do
+                git checkout -b \$NAME master
+                touch \$NAME
+                git add \$NAME
+                git commit -m "Add \$NAME"
+                git push -u origin \$NAME
+            done
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge feature-1 --no-ff
+            git merge feature-2 --no-ff
+        EOF
+        "#,
+    )
+}
+
+fn param() -> PlanParam<'static> {
+    PlanParam {
+        protected_patterns: vec!["*/master"],
+        ..test_default_param()
+    }
+}
+
+fn targets() -> Vec<DeletionTarget> {
+    let mut result = Vec::new();
+    for name in &["feature-1", "feature-2"] {
+        result.push(DeletionTarget::Local(LocalBranch::new(&format!(
+            "refs/heads/{}",
+            name
+        ))));
+        result.push(DeletionTarget::Remote(RemoteBranch {
+            remote: "origin".to_owned(),
+            refname: format!("refs/heads/{}", name),
+        }));
+    }
+    result
+}
+
+#[test]
+fn test_apply() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &param())?;
+    let report = apply_trim_plan(
+        &git,
+        &plan,
+        &DeleteOptions {
+            batch_size: Some(1),
+            order: DeletionOrder::LocalFirst,
+            ..DeleteOptions::default()
+        },
+    )?;
+
+    assert_eq!(report.outcomes.len(), 4);
+    assert!(report.failures().is_empty());
+    for target in targets() {
+        let outcome = report.outcome(&target).expect("every branch is reported");
+        assert_eq!(outcome.status, DeletionStatus::Deleted);
+        assert_eq!(outcome.error, None);
+    }
+
+    let origin = Repository::open(guard.working_directory().join("../origin"))?;
+    for name in &["feature-1", "feature-2"] {
+        assert!(git.repo.find_branch(name, BranchType::Local).is_err());
+        assert!(origin.find_branch(name, BranchType::Local).is_err());
+    }
+    Ok(())
+}
+
+#[test]
+fn test_apply_dry_run() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &param())?;
+    let report = apply_trim_plan(
+        &git,
+        &plan,
+        &DeleteOptions {
+            dry_run: true,
+            ..DeleteOptions::default()
+        },
+    )?;
+
+    for target in targets() {
+        let outcome = report.outcome(&target).expect("every branch is reported");
+        assert_eq!(outcome.status, DeletionStatus::DryRun);
+    }
+    assert!(git.repo.find_branch("feature-1", BranchType::Local).is_ok());
+    Ok(())
+}
+
+#[test]
+fn test_apply_stops_at_failure() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        origin <<EOF
+            git config receive.denyDeletes true
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &param())?;
+    let report = apply_trim_plan(&git, &plan, &DeleteOptions::default())?;
+
+    for target in targets() {
+        let outcome = report.outcome(&target).expect("every branch is reported");
+        match target {
+            DeletionTarget::Remote(_) => {
+                assert_eq!(outcome.status, DeletionStatus::Failed);
+                assert!(outcome.error.is_some());
+            }
+            _ => assert_eq!(outcome.status, DeletionStatus::NotAttempted),
+        }
+    }
+    assert!(git.repo.find_branch("feature-1", BranchType::Local).is_ok());
+    Ok(())
+}
+
+#[test]
+fn test_apply_lock_held() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &param())?;
+    {
+        let _lock = RepoLock::acquire(&git.repo)?;
+        let err = apply_trim_plan(&git, &plan, &DeleteOptions::default())
+            .err()
+            .expect("the lock is held");
+        assert!(err.downcast_ref::<LockHeld>().is_some());
+        assert!(git.repo.find_branch("feature-1", BranchType::Local).is_ok());
+
+        // The dry run doesn't need the lock.
+        apply_trim_plan(
+            &git,
+            &plan,
+            &DeleteOptions {
+                dry_run: true,
+                ..DeleteOptions::default()
+            },
+        )?;
+    }
+
+    // The lock is released on drop.
+    assert!(!RepoLock::path(&git.repo)?.exists());
+    let report = apply_trim_plan(&git, &plan, &DeleteOptions::default())?;
+    assert!(report.failures().is_empty());
+    assert!(!RepoLock::path(&git.repo)?.exists());
+    Ok(())
+}
+
+#[test]
+fn test_apply_lock_held_by_another_worktree() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        local <<EOF
+            git worktree add ../linked
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let linked = Git::try_from(Repository::open(
+        guard.working_directory().join("../linked"),
+    )?)?;
+    let plan = get_trim_plan(&linked, &param())?;
+
+    // The worktrees share the refs, so they share the lock too.
+    assert_eq!(RepoLock::path(&linked.repo)?, RepoLock::path(&git.repo)?);
+    let _lock = RepoLock::acquire(&git.repo)?;
+    let err = apply_trim_plan(&linked, &plan, &DeleteOptions::default())
+        .err()
+        .expect("the lock is held");
+    assert!(err.downcast_ref::<LockHeld>().is_some());
+    assert!(linked
+        .repo
+        .find_branch("feature-1", BranchType::Local)
+        .is_ok());
+    Ok(())
+}
@@ -0,0 +1,103 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::{BranchType, Repository};
+
+use git_trim::{apply_trim_plan, get_trim_plan, DeleteOptions, Git, PlanParam};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            git checkout -b feature
+            touch awesome-patch
+            git add awesome-patch
+            git commit -m "Awesome patch"
+            git push -u origin feature
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge feature --no-ff
+        EOF
+        "#,
+    )
+}
+
+fn param() -> PlanParam<'static> {
+    PlanParam {
+        protected_patterns: vec!["*/master"],
+        ..test_default_param()
+    }
+}
+
+#[test]
+fn test_archive() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let local_oid = git.repo.refname_to_id("refs/heads/feature")?;
+    let remote_oid = git.repo.refname_to_id("refs/remotes/origin/feature")?;
+
+    let plan = get_trim_plan(&git, &param())?;
+    let report = apply_trim_plan(
+        &git,
+        &plan,
+        &DeleteOptions {
+            archive: Some("refs/trim-archive".to_owned()),
+            ..DeleteOptions::default()
+        },
+    )?;
+    assert!(report.failures().is_empty());
+
+    assert!(git.repo.find_branch("feature", BranchType::Local).is_err());
+    assert_eq!(
+        git.repo.refname_to_id("refs/trim-archive/feature")?,
+        local_oid
+    );
+    assert_eq!(
+        git.repo
+            .refname_to_id("refs/trim-archive/remotes/origin/feature")?,
+        remote_oid
+    );
+    Ok(())
+}
+
+#[test]
+fn test_archive_dry_run() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &param())?;
+    apply_trim_plan(
+        &git,
+        &plan,
+        &DeleteOptions {
+            dry_run: true,
+            archive: Some("refs/trim-archive".to_owned()),
+            ..DeleteOptions::default()
+        },
+    )?;
+
+    assert!(git
+        .repo
+        .find_reference("refs/trim-archive/feature")
+        .is_err());
+    Ok(())
+}
@@ -0,0 +1,119 @@
+mod fixture;
+
+use std::convert::TryFrom;
+use std::iter::FromIterator;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::args::{DeleteFilter, DeleteRange, Scope};
+use git_trim::{
+    get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam, RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+
+            git checkout -b stray
+            touch stray-patch
+            git add stray-patch
+            git commit -m "Stray patch"
+            git push -u origin stray
+            git checkout master
+
+            git checkout -b diverged
+            touch diverged-patch
+            git add diverged-patch
+            git commit -m "Diverged patch"
+            git push -u origin diverged
+            git checkout master
+        EOF
+        # The remote branch is deleted without merge
+        origin <<EOF
+            git branch -D stray
+        EOF
+        # Someone pushed to the remote branch after the merge
+        origin <<EOF
+            git merge diverged --no-ff
+            git checkout diverged
+            touch another-patch
+            git add another-patch
+            git commit -m "Another patch"
+            git checkout master
+        EOF
+        local <<EOF
+            git fetch --prune
+        EOF
+        "#,
+    )
+}
+
+fn param(assume_unmerged_safe: bool) -> PlanParam<'static> {
+    use DeleteRange::*;
+    PlanParam {
+        delete: DeleteFilter::from_iter(vec![
+            MergedLocal,
+            MergedRemote(Scope::All),
+            Stray,
+            Diverged(Scope::All),
+            Local,
+            Remote(Scope::All),
+        ]),
+        assume_unmerged_safe,
+        ..test_default_param()
+    }
+}
+
+#[test]
+fn test_stray_and_diverged_are_deleted() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &param(false))?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::Stray(LocalBranch::new("refs/heads/stray")),
+            ClassifiedBranch::DivergedRemoteTracking {
+                local: LocalBranch::new("refs/heads/diverged"),
+                upstream: RemoteTrackingBranch::new("refs/remotes/origin/diverged"),
+            },
+        },
+    );
+    Ok(())
+}
+
+#[test]
+fn test_stray_and_diverged_are_never_deleted() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &param(true))?;
+    assert_eq!(plan.to_delete, set! {});
+    let preserved = plan
+        .get_preserved_local(&LocalBranch::new("refs/heads/stray"))
+        .expect("preserved");
+    assert_eq!(preserved.reason, "stray deletion disabled");
+    let preserved = plan
+        .get_preserved_upstream(&RemoteTrackingBranch::new("refs/remotes/origin/diverged"))
+        .expect("preserved");
+    assert_eq!(preserved.reason, "diverged deletion disabled");
+    Ok(())
+}
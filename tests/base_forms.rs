@@ -0,0 +1,114 @@
+mod fixture;
+
+use std::convert::TryFrom;
+use std::process::Command;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{
+    get_trim_plan, resolve_base, ClassifiedBranch, Git, LocalBranch, PlanParam,
+    RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+            git branch develop
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            git checkout -b feature
+            touch awesome-patch
+            git add awesome-patch
+            git commit -m "Awesome patch"
+            git push -u origin feature
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge feature --no-ff
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_accepted_forms() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    for base in &[
+        "master",
+        "origin/master",
+        "refs/heads/master",
+        "refs/remotes/origin/master",
+    ] {
+        assert_eq!(
+            resolve_base(&git, base)?.name,
+            "refs/remotes/origin/master",
+            "{}",
+            base
+        );
+
+        let plan = get_trim_plan(
+            &git,
+            &PlanParam {
+                bases: vec![base],
+                protected_patterns: vec!["*/master"],
+                ..test_default_param()
+            },
+        )?;
+        assert_eq!(
+            plan.to_delete,
+            set! {
+                ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature")),
+                ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/feature")),
+            },
+            "{}",
+            base
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_remote_only_branch() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    assert_eq!(
+        resolve_base(&git, "develop")?.name,
+        "refs/remotes/origin/develop",
+    );
+    Ok(())
+}
+
+#[test]
+fn test_unresolvable_base() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    assert!(resolve_base(&git, "nonexistent").is_err());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm", "--bases", "nonexistent"])
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(!output.status.success());
+    let stderr = std::str::from_utf8(&output.stderr)?;
+    assert!(stderr.contains("The base `nonexistent` can't be resolved to an existing branch"));
+    Ok(())
+}
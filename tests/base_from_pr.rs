@@ -0,0 +1,125 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::pull_request::{PullRequest, PullRequestBases, PullRequestClient};
+use git_trim::{
+    get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam, RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+            git branch release/2.0
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+
+            git checkout -b backport origin/release/2.0
+            touch backport
+            git add backport
+            git commit -m "Backport"
+            git push -u origin backport
+
+            git checkout -b feature origin/master
+            touch feature
+            git add feature
+            git commit -m "Feature"
+            git push -u origin feature
+            git checkout master
+        EOF
+        origin <<EOF
+            git checkout release/2.0
+            git merge backport --no-ff
+            git checkout master
+            git merge feature --no-ff
+        EOF
+        "#,
+    )
+}
+
+struct MockClient;
+
+impl PullRequestClient for MockClient {
+    fn pull_requests(&self) -> Result<Vec<PullRequest>> {
+        Ok(vec![
+            PullRequest {
+                head: "backport".to_owned(),
+                base: "release/2.0".to_owned(),
+            },
+            PullRequest {
+                head: "backport".to_owned(),
+                base: "master".to_owned(),
+            },
+        ])
+    }
+}
+
+#[test]
+fn test_base_from_pr() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let bases = PullRequestBases::fetch(&MockClient)?;
+    assert_eq!(
+        bases.resolve(&git.config, "refs/heads/backport"),
+        Some("release/2.0".to_owned())
+    );
+    assert_eq!(bases.resolve(&git.config, "refs/heads/feature"), None);
+
+    let resolve = |refname: &str| bases.resolve(&git.config, refname);
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            base_resolver: Some(&resolve),
+            ..test_default_param()
+        },
+    )?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/backport")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/backport")),
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/feature")),
+        },
+    );
+    Ok(())
+}
+
+#[test]
+fn test_base_from_pr_of_renamed_upstream() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        local <<EOF
+            git branch -m backport my-backport
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let bases = PullRequestBases::fetch(&MockClient)?;
+    // The pull request is opened from the upstream branch, whatever the local branch is named.
+    assert_eq!(
+        bases.resolve(&git.config, "refs/heads/my-backport"),
+        Some("release/2.0".to_owned())
+    );
+    Ok(())
+}
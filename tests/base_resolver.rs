@@ -0,0 +1,133 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{
+    get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam, RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+            git branch release
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+
+            git checkout -b hotfix/crash origin/release
+            touch hotfix
+            git add hotfix
+            git commit -m "Hotfix"
+            git push -u origin hotfix/crash
+
+            git checkout -b feature origin/master
+            touch feature
+            git add feature
+            git commit -m "Feature"
+            git push -u origin feature
+            git checkout master
+        EOF
+        origin <<EOF
+            git checkout release
+            git merge hotfix/crash --no-ff
+            git checkout master
+            git merge feature --no-ff
+        EOF
+        local <<EOF
+            git fetch origin
+        EOF
+        "#,
+    )
+}
+
+fn route_hotfixes(refname: &str) -> Option<String> {
+    if refname.starts_with("refs/heads/hotfix/") {
+        Some("release".to_owned())
+    } else {
+        None
+    }
+}
+
+#[test]
+fn test_without_resolver() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/feature")),
+        },
+    );
+    Ok(())
+}
+
+#[test]
+fn test_resolver() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            base_resolver: Some(&route_hotfixes),
+            ..test_default_param()
+        },
+    )?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/hotfix/crash")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/hotfix/crash")),
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/feature")),
+        },
+    );
+    Ok(())
+}
+
+#[test]
+fn test_config_takes_precedence_over_resolver() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        local <<EOF
+            git config branch.hotfix/crash.git-trim-base master
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            base_resolver: Some(&route_hotfixes),
+            ..test_default_param()
+        },
+    )?;
+    // The base that the user set wins over the inferred one, and it isn't merged into `master`.
+    assert!(!plan
+        .to_delete
+        .contains(&ClassifiedBranch::MergedLocal(LocalBranch::new(
+            "refs/heads/hotfix/crash"
+        ))));
+    Ok(())
+}
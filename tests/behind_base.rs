@@ -0,0 +1,81 @@
+mod fixture;
+
+use std::process::Command;
+
+use anyhow::Result;
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+        EOF
+        origin <<EOF
+            touch new-patch
+            git add new-patch
+            git commit -m "New patch"
+        EOF
+        local <<EOF
+            git fetch origin
+        EOF
+        "#,
+    )
+}
+
+fn run(guard: &fixture::FixtureGuard, extra: &[&str]) -> Result<(bool, String)> {
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm", "--dry-run"])
+        .args(extra)
+        .current_dir(guard.working_directory())
+        .output()?;
+    Ok((output.status.success(), String::from_utf8(output.stderr)?))
+}
+
+#[test]
+fn test_behind_base_warns() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let (success, stderr) = run(&guard, &[])?;
+    assert!(success);
+    assert!(stderr.contains("The base `master` is 1 commit(s) behind `origin/master`"));
+    Ok(())
+}
+
+#[test]
+fn test_behind_base_errors_when_strict() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let (success, stderr) = run(&guard, &["--strict"])?;
+    assert!(!success);
+    assert!(stderr.contains("The base `master` is 1 commit(s) behind `origin/master`"));
+    Ok(())
+}
+
+#[test]
+fn test_up_to_date_base() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        local <<EOF
+            git merge --ff-only origin/master
+        EOF
+        "#,
+    )?;
+
+    let (success, stderr) = run(&guard, &["--strict"])?;
+    assert!(success);
+    assert!(!stderr.contains("behind"));
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use git_trim::RemoteBranch;
+
+fn remote_branch(refname: &str) -> RemoteBranch {
+    RemoteBranch {
+        remote: "origin".to_owned(),
+        refname: refname.to_owned(),
+    }
+}
+
+#[test]
+fn test_short_name_strips_heads() {
+    assert_eq!(remote_branch("refs/heads/feature").short_name(), "feature");
+    assert_eq!(
+        remote_branch("refs/heads/feature/nested").short_name(),
+        "feature/nested"
+    );
+}
+
+#[test]
+fn test_short_name_keeps_non_heads() {
+    assert_eq!(
+        remote_branch("refs/pulls/123").short_name(),
+        "refs/pulls/123"
+    );
+}
+
+#[test]
+fn test_short_name_already_short() {
+    assert_eq!(remote_branch("feature").short_name(), "feature");
+}
+
+#[test]
+fn test_parse() {
+    assert_eq!(
+        RemoteBranch::parse("origin/feature", &["origin", "upstream"]).unwrap(),
+        remote_branch("refs/heads/feature"),
+    );
+}
+
+#[test]
+fn test_parse_slashes() {
+    assert_eq!(
+        RemoteBranch::parse("origin/feature/x", &["origin"]).unwrap(),
+        remote_branch("refs/heads/feature/x"),
+    );
+    assert_eq!(
+        RemoteBranch::parse("team/origin/feature/x", &["team", "team/origin"]).unwrap(),
+        RemoteBranch {
+            remote: "team/origin".to_owned(),
+            refname: "refs/heads/feature/x".to_owned(),
+        },
+    );
+}
+
+#[test]
+fn test_parse_unknown_remote() {
+    assert!(RemoteBranch::parse("fork/feature", &["origin"]).is_err());
+    assert!(RemoteBranch::parse("origin", &["origin"]).is_err());
+}
@@ -0,0 +1,82 @@
+mod fixture;
+
+use std::process::Command;
+
+use anyhow::Result;
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            for NAME in squashed unmerged; do
+                git checkout -b \$NAME master
+                touch \$NAME
+                git add \$NAME
+                git commit -m "Add \$NAME"
+                git push -u origin \$NAME
+            done
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge squashed --squash && git commit --no-edit
+            git branch -D squashed
+        EOF
+        "#,
+    )
+}
+
+fn run(working_directory: &std::path::Path) -> Result<(String, usize)> {
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&[
+            "--no-update",
+            "--no-confirm",
+            "--cache",
+            "--delete",
+            "merged:origin,stray",
+            "--porcelain",
+            "local",
+        ])
+        .env("RUST_LOG", "trace")
+        .current_dir(working_directory)
+        .output()?;
+    assert!(output.status.success());
+
+    let rev_list_calls = std::str::from_utf8(&output.stderr)?
+        .lines()
+        .filter(|line| line.contains("> git rev-list"))
+        .count();
+    Ok((String::from_utf8(output.stdout)?, rev_list_calls))
+}
+
+#[test]
+fn test_second_run_reads_from_cache() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let (first_output, first_calls) = run(&guard.working_directory())?;
+    assert_eq!(first_output, "squashed\n");
+    assert!(first_calls > 0);
+    assert!(guard
+        .working_directory()
+        .join(".git/git-trim/cache.json")
+        .exists());
+
+    let (second_output, second_calls) = run(&guard.working_directory())?;
+    assert_eq!(second_output, first_output);
+    assert_eq!(second_calls, 0);
+    Ok(())
+}
@@ -0,0 +1,82 @@
+mod fixture;
+
+use std::process::Command;
+
+use anyhow::Result;
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+
+            git checkout -b feature
+            touch awesome-patch
+            git add awesome-patch
+            git commit -m "Awesome patch"
+            git push -u origin feature
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge feature --no-ff
+        EOF
+        "#,
+    )
+}
+
+fn assert_no_ansi(stdout: &[u8]) -> Result<()> {
+    let stdout = std::str::from_utf8(stdout)?;
+    assert!(!stdout.contains('\x1b'), "{:?}", stdout);
+    Ok(())
+}
+
+#[test]
+fn test_no_color_when_piped() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    for args in &[
+        vec!["--dry-run", "--no-update", "--no-confirm"],
+        vec!["--no-update", "--porcelain", "local"],
+        vec!["--no-update", "--porcelain", "remote"],
+    ] {
+        let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+            .args(args)
+            .current_dir(guard.working_directory())
+            .output()?;
+        assert!(output.status.success());
+        assert!(!output.stdout.is_empty());
+        assert_no_ansi(&output.stdout)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_no_color_env() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--dry-run", "--no-update", "--no-confirm"])
+        .env("NO_COLOR", "1")
+        .env("CLICOLOR_FORCE", "1")
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(output.status.success());
+    let stdout = std::str::from_utf8(&output.stdout)?;
+    assert!(stdout.contains("Delete merged local branches:\n  - feature\n"));
+    assert_no_ansi(&output.stdout)?;
+    Ok(())
+}
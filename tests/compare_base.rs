@@ -0,0 +1,125 @@
+mod fixture;
+
+use std::convert::TryFrom;
+use std::process::Command;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{
+    get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam, RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+            git branch develop
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            git branch develop origin/develop
+            git checkout -b feature
+            touch awesome-patch
+            git add awesome-patch
+            git commit -m "Awesome patch"
+            git push -u origin feature
+            git checkout master
+        EOF
+        origin <<EOF
+            git checkout develop
+            git merge feature --no-ff
+            git checkout master
+            git branch -D feature
+        EOF
+        "#,
+    )
+}
+
+fn param() -> PlanParam<'static> {
+    PlanParam {
+        protected_patterns: vec!["master", "develop"],
+        ..test_default_param()
+    }
+}
+
+#[test]
+fn test_diff() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &param())?;
+    let other = get_trim_plan(
+        &git,
+        &PlanParam {
+            bases: vec!["develop"],
+            ..param()
+        },
+    )?;
+
+    let diff = plan.diff(&other);
+    assert_eq!(
+        diff.added,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/master")),
+        },
+    );
+    assert_eq!(
+        diff.removed,
+        set! {
+            ClassifiedBranch::Stray(LocalBranch::new("refs/heads/feature")),
+        },
+    );
+    Ok(())
+}
+
+#[test]
+fn test_compare_base_output() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&[
+            "--no-update",
+            "--no-confirm",
+            "--bases",
+            "master",
+            "--protected",
+            "master,develop",
+            "--delete",
+            "merged:origin,stray",
+            "--compare-base",
+            "develop",
+        ])
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(output.status.success());
+
+    let stdout = std::str::from_utf8(&output.stdout)?;
+    assert_eq!(
+        stdout,
+        "Compared with the base `develop`:
+  additionally trimmed:
+    merged local: feature
+    merged remote tracking: origin/master
+  newly kept:
+    stray: feature
+"
+    );
+
+    let repo = Repository::open(guard.working_directory())?;
+    assert!(repo.find_branch("feature", git2::BranchType::Local).is_ok());
+    Ok(())
+}
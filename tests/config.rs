@@ -3,6 +3,7 @@ mod fixture;
 use std::collections::HashSet;
 use std::convert::TryFrom;
 use std::iter::FromIterator;
+use std::process::Command;
 
 use anyhow::Result;
 use git2::Repository;
@@ -182,3 +183,131 @@ fn test_delete_filter_multiple_comma_separated_values() -> Result<()> {
     );
     Ok(())
 }
+
+#[test]
+fn test_bases_init_default_branch() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        local <<EOF
+            git checkout -b trunk
+            git push -u origin trunk
+            git remote set-head origin -d
+            # Newer git recreates it on fetch otherwise
+            git config remote.origin.followRemoteHEAD never
+            git config init.defaultBranch trunk
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let config = Config::read(&git.repo, &git.config, &Args::default())?;
+
+    assert_eq!(
+        config.bases,
+        ConfigValue::Implicit(HashSet::from_iter(vec!["trunk".to_owned()])),
+    );
+    Ok(())
+}
+
+#[test]
+fn test_bases_fallback_to_master() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        local <<EOF
+            git checkout -b trunk
+            git push -u origin trunk
+            git remote set-head origin -d
+            # Newer git recreates it on fetch otherwise
+            git config remote.origin.followRemoteHEAD never
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let config = Config::read(&git.repo, &git.config, &Args::default())?;
+
+    assert_eq!(
+        config.bases,
+        ConfigValue::Implicit(HashSet::from_iter(vec!["master".to_owned()])),
+    );
+    Ok(())
+}
+
+#[test]
+fn test_only_merged() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        local <<EOF
+            git config --add trim.delete local,stray,diverged:upstream,stale-tracking
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let config = Config::read(
+        &git.repo,
+        &git.config,
+        &Args {
+            only_merged: true,
+            ..Args::default()
+        },
+    )?;
+
+    assert_eq!(
+        config.delete,
+        ConfigValue::Explicit(DeleteFilter::from_iter(vec![
+            DeleteUnit::MergedLocal,
+            DeleteUnit::MergedNonTrackingLocal,
+            DeleteUnit::MergedRemote(Scope::Scoped("upstream".to_owned())),
+        ])),
+    );
+    assert!(config.delete.delete_merged_local());
+    assert!(config.delete.delete_merged_remote("upstream"));
+    assert!(!config.delete.delete_merged_remote("origin"));
+    assert!(!config.delete.delete_stray());
+    assert!(!config.delete.delete_diverged("upstream"));
+    assert!(!config.delete.scan_stale_tracking());
+    Ok(())
+}
+
+#[test]
+fn test_only_stray() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let config = Config::read(
+        &git.repo,
+        &git.config,
+        &Args {
+            only_stray: true,
+            ..Args::default()
+        },
+    )?;
+
+    assert_eq!(
+        config.delete,
+        ConfigValue::Explicit(DeleteFilter::from_iter(vec![DeleteUnit::Stray])),
+    );
+    assert!(config.delete.delete_stray());
+    assert!(config.delete.scan_tracking());
+    assert!(!config.delete.delete_merged_local());
+    assert!(!config.delete.delete_merged_remote("origin"));
+    Ok(())
+}
+
+#[test]
+fn test_only_merged_conflicts_with_only_stray() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--dry-run", "--only-merged", "--only-stray"])
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.contains("--only-stray"));
+    Ok(())
+}
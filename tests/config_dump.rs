@@ -0,0 +1,54 @@
+mod fixture;
+
+use std::process::Command;
+
+use anyhow::Result;
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config trim.protected from-git-config
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_config_dump() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--config-dump", "--bases", "master"])
+        .env("GIT_TRIM_BASE", "develop")
+        .env("GIT_TRIM_PROTECT", "release-*")
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(output.status.success());
+    let dump: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+
+    assert_eq!(
+        dump["bases"],
+        serde_json::json!({ "value": ["master"], "source": "cli" }),
+    );
+    assert_eq!(
+        dump["protected"],
+        serde_json::json!({ "value": ["release-*"], "source": "env" }),
+    );
+    assert_eq!(
+        dump["delete"],
+        serde_json::json!({ "value": "merged-local,merged-remote:origin", "source": "default" }),
+    );
+    Ok(())
+}
@@ -0,0 +1,98 @@
+mod fixture;
+
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::iter::FromIterator;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::args::Args;
+use git_trim::config::{Config, ConfigValue};
+use git_trim::Git;
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "UpstreamTest"
+            git config user.email "upstream@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+
+        git clone origin local
+        "#,
+    )
+}
+
+// The environment variables are shared by the whole test binary.
+// Keep every case in one test so that they don't race each other.
+#[test]
+fn test_env_values() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        local <<EOF
+            git config trim.bases some-branch
+            git config trim.protected some-protected
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+
+    std::env::set_var("GIT_TRIM_BASE", "develop, main");
+    std::env::set_var("GIT_TRIM_PROTECT", "release-*,keep/*");
+    let config = Config::read(&git.repo, &git.config, &Args::default())?;
+    assert_eq!(
+        config.bases,
+        ConfigValue::Env(HashSet::from_iter(vec![
+            "develop".to_owned(),
+            "main".to_owned(),
+        ])),
+    );
+    assert_eq!(
+        config.protected,
+        ConfigValue::Env(vec!["release-*".to_owned(), "keep/*".to_owned()]),
+    );
+
+    let config = Config::read(
+        &git.repo,
+        &git.config,
+        &Args {
+            bases: vec!["another-branch".to_owned()],
+            ..Args::default()
+        },
+    )?;
+    assert_eq!(
+        config.bases,
+        ConfigValue::Explicit(HashSet::from_iter(vec!["another-branch".to_owned()])),
+    );
+
+    std::env::set_var("GIT_TRIM_DELETE", "merged:origin,no-such-range");
+    let err = Config::read(&git.repo, &git.config, &Args::default()).unwrap_err();
+    assert!(format!("{:#}", err).contains("GIT_TRIM_DELETE"));
+
+    std::env::remove_var("GIT_TRIM_DELETE");
+    // An empty value is ignored instead of leaving no bases.
+    std::env::set_var("GIT_TRIM_BASE", " , ");
+    let config = Config::read(&git.repo, &git.config, &Args::default())?;
+    assert_eq!(
+        config.bases,
+        ConfigValue::GitConfig(HashSet::from_iter(vec!["some-branch".to_owned()])),
+    );
+
+    std::env::remove_var("GIT_TRIM_BASE");
+    std::env::remove_var("GIT_TRIM_PROTECT");
+    let config = Config::read(&git.repo, &git.config, &Args::default())?;
+    assert_eq!(
+        config.bases,
+        ConfigValue::GitConfig(HashSet::from_iter(vec!["some-branch".to_owned()])),
+    );
+    Ok(())
+}
@@ -0,0 +1,103 @@
+mod fixture;
+
+use std::process::Command;
+
+use anyhow::Result;
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+            git branch develop master
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            git checkout -b develop origin/develop
+            for NAME in feature keep-a keep-b; do
+                git checkout -b \$NAME develop
+                touch \$NAME
+                git add \$NAME
+                git commit -m "Add \$NAME"
+                git push -u origin \$NAME
+            done
+            git checkout master
+        EOF
+        origin <<EOF
+            git checkout develop
+            for NAME in feature keep-a keep-b; do
+                git merge \$NAME --no-ff
+            done
+            git checkout master
+        EOF
+        "#,
+    )
+}
+
+fn trim(guard: &fixture::FixtureGuard, args: &[&str]) -> Result<String> {
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm", "--porcelain", "local"])
+        .args(&["--delete", "merged:origin"])
+        .args(args)
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(output.status.success());
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+#[test]
+fn test_without_config() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    // Nothing is merged into `master`.
+    assert_eq!(trim(&guard, &[])?, "");
+    Ok(())
+}
+
+#[test]
+fn test_base_and_protected_from_config() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        local <<EOF
+            git config trim.base develop
+            git config --add trim.protected keep-a
+            git config --add trim.protected keep-b
+        EOF
+        "#,
+    )?;
+
+    assert_eq!(trim(&guard, &[])?, "feature\n");
+    Ok(())
+}
+
+#[test]
+fn test_args_override_config() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        local <<EOF
+            git config trim.base master
+            git config --add trim.protected keep-a
+            git config --add trim.protected keep-b
+        EOF
+        "#,
+    )?;
+
+    assert_eq!(
+        trim(&guard, &["--bases", "develop", "--protected", "keep-a"])?,
+        "feature\nkeep-b\n"
+    );
+    Ok(())
+}
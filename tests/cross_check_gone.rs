@@ -0,0 +1,65 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{
+    get_gone_upstream_locals, get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            git checkout -b abandoned
+            touch abandoned
+            git add abandoned
+            git commit -m "Abandoned"
+            git push -u origin abandoned
+            git checkout master
+        EOF
+        origin <<EOF
+            git branch -D abandoned
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_both_agree_on_gone_upstream() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let abandoned = LocalBranch::new("refs/heads/abandoned");
+    assert_eq!(
+        get_gone_upstream_locals(&git.repo)?,
+        set! {abandoned.clone()}
+    );
+
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            cross_check_gone: true,
+            ..test_default_param()
+        },
+    )?;
+    assert_eq!(plan.to_delete, set! {ClassifiedBranch::Stray(abandoned)});
+    Ok(())
+}
@@ -0,0 +1,74 @@
+mod fixture;
+
+use std::convert::TryFrom;
+use std::process::Command;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{get_trim_plan, ClassifiedBranch, Git, LocalBranch, RemoteTrackingBranch};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+
+            git checkout -b feature
+            touch awesome-patch
+            git add awesome-patch
+            git commit -m "Awesome patch"
+            git push -u origin feature
+            git update-ref refs/custom/feature feature
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge feature --no-ff
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_custom_refs_are_never_classified() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/feature")),
+        },
+    );
+    assert!(plan
+        .classifications
+        .iter()
+        .all(|classification| !classification.branch.starts_with("refs/custom/")));
+
+    let status = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm"])
+        .current_dir(guard.working_directory())
+        .status()?;
+    assert!(status.success());
+
+    let repo = Repository::open(guard.working_directory())?;
+    assert!(repo.find_reference("refs/heads/feature").is_err());
+    assert!(repo.find_reference("refs/custom/feature").is_ok());
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use std::iter::FromIterator;
+
+use anyhow::Result;
+
+use git_trim::args::{DeleteFilter, DeleteRange};
+
+fn filter(delete: &str) -> Result<DeleteFilter> {
+    let mut ranges = Vec::new();
+    for range in delete.split(',') {
+        ranges.push(range.parse::<DeleteRange>()?);
+    }
+    Ok(DeleteFilter::from_iter(ranges))
+}
+
+#[test]
+fn test_safe() -> Result<()> {
+    let safe = filter("safe")?;
+    assert_eq!(safe, filter("merged-local")?);
+
+    assert!(safe.delete_merged_local());
+    assert!(!safe.delete_merged_remote("origin"));
+    assert!(!safe.delete_stray());
+    assert!(!safe.delete_diverged("origin"));
+    Ok(())
+}
+
+#[test]
+fn test_standard() -> Result<()> {
+    let standard = filter("standard")?;
+    assert_eq!(standard, filter("merged-local,merged-remote:origin")?);
+    assert_eq!(standard, filter("merged:origin")?);
+
+    assert!(standard.delete_merged_local());
+    assert!(standard.delete_merged_remote("origin"));
+    assert!(!standard.delete_merged_remote("upstream"));
+    assert!(!standard.delete_stray());
+    assert!(!standard.delete_diverged("origin"));
+    Ok(())
+}
+
+#[test]
+fn test_aggressive() -> Result<()> {
+    let aggressive = filter("aggressive:*")?;
+    assert_eq!(
+        aggressive,
+        filter("merged-local,merged-remote:*,stray,diverged:*")?
+    );
+
+    assert!(aggressive.delete_merged_local());
+    assert!(aggressive.delete_merged_remote("upstream"));
+    assert!(aggressive.delete_stray());
+    assert!(aggressive.delete_diverged("upstream"));
+    assert!(!aggressive.delete_merged_non_tracking_local());
+    Ok(())
+}
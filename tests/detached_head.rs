@@ -0,0 +1,94 @@
+mod fixture;
+
+use std::convert::TryFrom;
+use std::process::Command;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{get_trim_plan, ClassifiedBranch, Git, LocalBranch};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+
+            git checkout -b feature
+            touch awesome-patch
+            git add awesome-patch
+            git commit -m "Awesome patch"
+            git push -u origin feature
+        EOF
+        origin <<EOF
+            git merge feature --no-ff
+            git branch -D feature
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_detached_head() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        local <<EOF
+            git fetch --prune
+            git checkout --detach origin/master
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    assert!(git.repo.head_detached()?);
+    let plan = get_trim_plan(&git, &test_default_param())?;
+
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature")),
+        },
+    );
+    Ok(())
+}
+
+#[test]
+fn test_detached_remote_head() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        origin <<EOF
+            git checkout --detach master
+        EOF
+        local <<EOF
+            git checkout --detach master
+        EOF
+        "#,
+    )?;
+
+    // The remote HEAD check runs only when remotes are updated.
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--update", "--no-confirm", "--dry-run"])
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(output.status.success());
+
+    let stdout = std::str::from_utf8(&output.stdout)?;
+    assert!(stdout.contains("Delete merged local branches:\n  - feature\n"));
+    Ok(())
+}
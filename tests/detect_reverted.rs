@@ -0,0 +1,119 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{
+    get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam, RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            for NAME in kept reverted; do
+                git checkout -b \$NAME master
+                touch \$NAME
+                git add \$NAME
+                git commit -m "Add \$NAME"
+                git push -u origin \$NAME
+            done
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge kept --no-ff
+            git merge reverted --no-ff
+            git revert -m 1 --no-edit HEAD
+        EOF
+        local <<EOF
+            git fetch origin
+        EOF
+        "#,
+    )
+}
+
+fn param(detect_reverted: bool) -> PlanParam<'static> {
+    PlanParam {
+        detect_reverted,
+        ..test_default_param()
+    }
+}
+
+#[test]
+fn test_reverted_is_merged_by_default() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &param(false))?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/kept")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/kept")),
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/reverted")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/reverted")),
+        },
+    );
+    Ok(())
+}
+
+#[test]
+fn test_detect_reverted() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &param(true))?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/kept")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/kept")),
+        },
+    );
+    let preserved = plan
+        .get_preserved_local(&LocalBranch::new("refs/heads/reverted"))
+        .expect("preserved");
+    assert_eq!(preserved.reason, "merge was reverted");
+    Ok(())
+}
+
+#[test]
+fn test_revert_of_revert() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        origin <<EOF
+            git revert --no-edit HEAD
+        EOF
+        local <<EOF
+            git fetch origin
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &param(true))?;
+    assert!(plan
+        .to_delete
+        .contains(&ClassifiedBranch::MergedLocal(LocalBranch::new(
+            "refs/heads/reverted"
+        ))));
+    Ok(())
+}
@@ -0,0 +1,80 @@
+mod fixture;
+
+use std::convert::TryFrom;
+use std::process::Command;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{get_trim_plan, ClassifiedBranch, Diagnostic, DiagnosticKind, Git, LocalBranch};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            git checkout -b merged master
+            touch merged
+            git add merged
+            git commit -m "Add merged"
+            git push -u origin merged
+            git checkout master
+            git symbolic-ref refs/heads/alias refs/heads/merged
+        EOF
+        origin <<EOF
+            git merge merged --no-ff
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_skipped_ref_diagnostic() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    assert!(plan
+        .to_delete
+        .contains(&ClassifiedBranch::MergedLocal(LocalBranch::new(
+            "refs/heads/merged"
+        ))));
+    assert_eq!(
+        plan.diagnostics,
+        vec![Diagnostic {
+            kind: DiagnosticKind::SkippedRef,
+            message: "Skip a symbolic ref: refs/heads/alias -> refs/heads/merged".to_owned(),
+            subject: Some("refs/heads/alias".to_owned()),
+        }],
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm", "--porcelain", "json"])
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(
+        json["diagnostics"],
+        serde_json::json!([{
+            "kind": "skipped-ref",
+            "message": "Skip a symbolic ref: refs/heads/alias -> refs/heads/merged",
+            "subject": "refs/heads/alias",
+        }]),
+    );
+    Ok(())
+}
@@ -0,0 +1,68 @@
+mod fixture;
+
+use std::process::Command;
+
+use anyhow::Result;
+use git2::{BranchType, Repository};
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+
+            git checkout -b feature
+            touch awesome-patch
+            git add awesome-patch
+            git commit -m "Awesome patch"
+            git push -u origin feature
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge feature --no-ff
+        EOF
+        local <<EOF
+            git fetch
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_dry_run_deletes_nothing() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--dry-run", "--no-update", "--no-confirm"])
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(output.status.success());
+
+    let stdout = std::str::from_utf8(&output.stdout)?;
+    assert!(stdout.contains("Delete merged local branches:\n  - feature\n"));
+    let stderr = std::str::from_utf8(&output.stderr)?;
+    assert!(stderr.contains("dry run: nothing deleted"));
+
+    let repo = Repository::open(guard.working_directory())?;
+    assert!(repo.find_branch("feature", BranchType::Local).is_ok());
+    assert!(repo
+        .find_branch("origin/feature", BranchType::Remote)
+        .is_ok());
+    let origin = Repository::open(guard.working_directory().join("../origin"))?;
+    assert!(origin.find_branch("feature", BranchType::Local).is_ok());
+    Ok(())
+}
@@ -0,0 +1,80 @@
+mod fixture;
+
+use std::process::Command;
+
+use anyhow::Result;
+use git2::Repository;
+use serde_json::Value;
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            for NAME in alive merged; do
+                git checkout -b \$NAME master
+                touch \$NAME
+                git add \$NAME
+                git commit -m "Add \$NAME"
+                git push -u origin \$NAME
+            done
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge merged --no-ff
+        EOF
+        local <<EOF
+            git fetch origin
+        EOF
+        "#,
+    )
+}
+
+fn find<'a>(classifications: &'a Value, refname: &str) -> &'a Value {
+    classifications
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|classification| classification["branch"] == refname)
+        .unwrap_or_else(|| panic!("{} is not in {}", refname, classifications))
+}
+
+#[test]
+fn test_dump_classifications() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm", "--dump-classifications"])
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(output.status.success());
+    let classifications: Value = serde_json::from_slice(&output.stdout)?;
+
+    let alive = find(&classifications, "refs/heads/alive");
+    assert_eq!(alive["base"], "refs/remotes/origin/master");
+    assert_eq!(alive["message"], "local & upstream are not merged yet");
+    assert_eq!(alive["result"], serde_json::json!([]));
+
+    let merged = find(&classifications, "refs/heads/merged");
+    assert_eq!(merged["message"], "local & upstream are merged");
+    assert_eq!(merged["result"].as_array().map(Vec::len), Some(2));
+
+    // Nothing is deleted.
+    let repo = Repository::open(guard.working_directory())?;
+    assert!(repo.find_reference("refs/heads/merged").is_ok());
+    Ok(())
+}
@@ -0,0 +1,144 @@
+mod fixture;
+
+use std::convert::TryFrom;
+use std::process::Command;
+
+use anyhow::Result;
+use git2::{BranchType, Repository};
+
+use git_trim::{estimate_network, get_trim_plan, Git, NetworkEstimate, PlanParam};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin upstream
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git remote add upstream ../upstream
+            git fetch upstream
+
+            git checkout -b feature-1 master
+            touch feature-1
+            git add feature-1
+            git commit -m "Add feature-1"
+            git push -u origin feature-1
+
+            git checkout -b feature-2 master
+            touch feature-2
+            git add feature-2
+            git commit -m "Add feature-2"
+            git push -u upstream feature-2
+
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge feature-1 --no-ff
+            git fetch ../upstream feature-2
+            git merge FETCH_HEAD --no-ff
+        EOF
+        local <<EOF
+            git fetch origin
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_estimate_two_remotes() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    assert_eq!(
+        estimate_network(&git, &plan, true, true)?,
+        NetworkEstimate {
+            remote_urls: 2,
+            fetches: 2,
+            listings: 0,
+            pushes: 2,
+            remote_refs_to_delete: 2,
+            unknown_remote_refs: 0,
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn test_estimate_direct_fetch_is_unknown() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        origin <<EOF
+            git branch direct feature-1
+        EOF
+        local <<EOF
+            git fetch ../origin direct:direct
+            git config branch.direct.remote "../origin"
+            git config branch.direct.merge "refs/heads/direct"
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    // The estimate plans offline, like the CLI does.
+    let param = PlanParam {
+        offline: true,
+        ..test_default_param()
+    };
+    let plan = get_trim_plan(&git, &param)?;
+    assert_eq!(
+        estimate_network(&git, &plan, true, true)?,
+        NetworkEstimate {
+            remote_urls: 3,
+            fetches: 2,
+            listings: 1,
+            pushes: 2,
+            remote_refs_to_delete: 2,
+            unknown_remote_refs: 1,
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn test_estimate_cli() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm", "--bases", "master"])
+        .args(&["--delete", "merged:*"])
+        .arg("--estimate")
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        "Remote URLs to contact: 2
+  - fetches: 0
+  - listings: 0
+  - pushes: 2
+Remote refs to delete: 2
+Remote refs of unknown state: 0
+"
+    );
+
+    // Nothing is deleted.
+    let repo = Repository::open(guard.working_directory())?;
+    assert!(repo.find_branch("feature-1", BranchType::Local).is_ok());
+    assert!(repo
+        .find_branch("upstream/feature-2", BranchType::Remote)
+        .is_ok());
+    Ok(())
+}
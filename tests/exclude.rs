@@ -0,0 +1,123 @@
+mod fixture;
+
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::iter::FromIterator;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::args::{DeleteFilter, DeleteRange};
+use git_trim::{
+    get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam, RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            for NAME in feature keep/feature; do
+                git checkout -b \$NAME master
+                touch \$(basename \$NAME)
+                git add \$(basename \$NAME)
+                git commit -m "Add \$NAME"
+                git push -u origin \$NAME
+            done
+            git branch keep/non-tracking keep/feature
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge feature --no-ff
+            git merge keep/feature --no-ff
+        EOF
+        "#,
+    )
+}
+
+fn param() -> PlanParam<'static> {
+    PlanParam {
+        protected_patterns: vec!["*/master"],
+        ..test_default_param()
+    }
+}
+
+#[test]
+fn test_exclude_merged_branch() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            exclude_patterns: vec!["keep/*"],
+            ..param()
+        },
+    )?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/feature")),
+        },
+    );
+
+    let excluded: Vec<_> = plan
+        .preserved
+        .iter()
+        .filter(|preserved| preserved.reason == "matched exclude pattern")
+        .map(|preserved| preserved.branch.clone())
+        .collect();
+    assert_eq!(
+        excluded.into_iter().collect::<HashSet<_>>(),
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/keep/feature")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/keep/feature")),
+        },
+    );
+    Ok(())
+}
+
+#[test]
+fn test_exclude_takes_precedence_over_delete_filter() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            delete: DeleteFilter::from_iter(vec![
+                "merged:origin".parse::<DeleteRange>()?,
+                DeleteRange::Local,
+            ]),
+            exclude_patterns: vec!["keep/non-tracking"],
+            ..param()
+        },
+    )?;
+    let non_tracking =
+        ClassifiedBranch::MergedNonTrackingLocal(LocalBranch::new("refs/heads/keep/non-tracking"));
+    assert!(!plan.to_delete.contains(&non_tracking));
+    assert!(plan.preserved.iter().any(|preserved| {
+        preserved.branch == non_tracking && preserved.reason == "matched exclude pattern"
+    }));
+    assert!(plan
+        .to_delete
+        .contains(&ClassifiedBranch::MergedLocal(LocalBranch::new(
+            "refs/heads/keep/feature"
+        ))));
+    Ok(())
+}
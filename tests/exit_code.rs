@@ -0,0 +1,84 @@
+mod fixture;
+
+use std::process::Command;
+
+use anyhow::Result;
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+
+            git checkout -b feature
+            touch awesome-patch
+            git add awesome-patch
+            git commit -m "Awesome patch"
+            git push -u origin feature
+            git checkout master
+        EOF
+        "#,
+    )
+}
+
+fn git_trim(guard: &fixture::FixtureGuard, args: &[&str]) -> Result<Option<i32>> {
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm"])
+        .args(args)
+        .current_dir(guard.working_directory())
+        .output()?;
+    Ok(output.status.code())
+}
+
+#[test]
+fn test_trimmed() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        origin <<EOF
+            git merge feature --no-ff
+        EOF
+        "#,
+    )?;
+
+    assert_eq!(git_trim(&guard, &["--dry-run"])?, Some(0));
+    assert_eq!(git_trim(&guard, &["--dry-run", "--exit-code"])?, Some(2));
+    assert_eq!(git_trim(&guard, &["--exit-code"])?, Some(2));
+    // Nothing is left to trim
+    assert_eq!(git_trim(&guard, &["--exit-code"])?, Some(0));
+    Ok(())
+}
+
+#[test]
+fn test_nothing_to_trim() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    assert_eq!(git_trim(&guard, &[])?, Some(0));
+    assert_eq!(git_trim(&guard, &["--exit-code"])?, Some(0));
+    Ok(())
+}
+
+#[test]
+fn test_error() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    assert_eq!(
+        git_trim(&guard, &["--exit-code", "--bases", "nonexistent"])?,
+        Some(1)
+    );
+    Ok(())
+}
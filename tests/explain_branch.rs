@@ -0,0 +1,108 @@
+mod fixture;
+
+use std::convert::TryFrom;
+use std::process::Command;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{explain_branch, ClassifiedBranch, Git, LocalBranch, PlanParam};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+
+            git checkout -b feature master
+            touch feature-1 feature-2
+            git add feature-1
+            git commit -m "Add feature 1"
+            git add feature-2
+            git commit -m "Add feature 2"
+            git push -u origin feature
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge --squash feature
+            git commit -m "Squash feature"
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_explain_squash_merged_branch() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let explanation = explain_branch(&git, &test_default_param(), "feature")?;
+    assert_eq!(explanation.refname, "refs/heads/feature");
+    assert_eq!(
+        explanation
+            .upstream
+            .as_ref()
+            .map(|upstream| upstream.refname.as_str()),
+        Some("refs/remotes/origin/feature")
+    );
+    assert_eq!(explanation.bases.len(), 1);
+    let base = &explanation.bases[0];
+    assert_eq!(base.base, "refs/remotes/origin/master");
+    assert!(!base.rev_list_merged);
+    assert_eq!(base.squash_merged, Some(true));
+    assert!(base.merged);
+    assert!(explanation
+        .to_delete
+        .contains(&ClassifiedBranch::MergedLocal(LocalBranch::new(
+            "refs/heads/feature"
+        ))));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--explain-branch", "feature"])
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("  rev-list merged: no\n"));
+    assert!(stdout.contains("  squash merged: yes\n"));
+    Ok(())
+}
+
+#[test]
+fn test_explain_saves_no_state() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let explanation = explain_branch(
+        &git,
+        &PlanParam {
+            cache: true,
+            incremental: true,
+            ..test_default_param()
+        },
+        "feature",
+    )?;
+    assert!(explanation
+        .to_delete
+        .contains(&ClassifiedBranch::MergedLocal(LocalBranch::new(
+            "refs/heads/feature"
+        ))));
+    let state_dir = git.repo.path().join("git-trim");
+    assert!(!state_dir.join("cache.json").exists());
+    assert!(!state_dir.join("classifications.json").exists());
+    Ok(())
+}
@@ -0,0 +1,68 @@
+mod fixture;
+
+use std::process::Command;
+
+use anyhow::Result;
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git checkout -b feature
+            touch awesome-patch
+            git add awesome-patch
+            git commit -m "Awesome patch"
+            git push -u origin feature
+            git checkout master
+        EOF
+        "#,
+    )
+}
+
+fn run(guard: &fixture::FixtureGuard) -> Result<(bool, String)> {
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-confirm", "--dry-run", "--fail-on-stray"])
+        .current_dir(guard.working_directory())
+        .output()?;
+    Ok((output.status.success(), String::from_utf8(output.stderr)?))
+}
+
+#[test]
+fn test_no_stray() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let (success, stderr) = run(&guard)?;
+    assert!(success, "{}", stderr);
+    Ok(())
+}
+
+#[test]
+fn test_fail_on_stray() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        # The remote branch is deleted without merge
+        origin <<EOF
+            git branch -D feature
+        EOF
+        "#,
+    )?;
+
+    let (success, stderr) = run(&guard)?;
+    assert!(!success);
+    assert!(stderr.contains("Found stray local branches: feature"));
+    Ok(())
+}
@@ -0,0 +1,74 @@
+mod fixture;
+
+use std::process::Command;
+
+use anyhow::Result;
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            git checkout -b feature
+            touch awesome-patch
+            git add awesome-patch
+            git commit -m "Awesome patch"
+            git push -u origin feature
+            git checkout master
+        EOF
+        "#,
+    )
+}
+
+fn merged_locals(working_directory: &std::path::Path, fetch: bool) -> Result<String> {
+    let mut args = vec![
+        "--no-update",
+        "--no-confirm",
+        "--delete",
+        "merged:origin",
+        "--porcelain",
+        "local",
+    ];
+    if fetch {
+        args.push("--fetch");
+    }
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&args)
+        .current_dir(working_directory)
+        .output()?;
+    assert!(output.status.success());
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+#[test]
+fn test_fetch() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    // Merge after the fixture's `git remote update --prune`, so the local base is behind.
+    let status = Command::new("git")
+        .args(&["merge", "feature", "--no-ff", "--no-edit"])
+        .current_dir(guard.working_directory().join("../origin"))
+        .status()?;
+    assert!(status.success());
+
+    assert_eq!(merged_locals(&guard.working_directory(), false)?, "");
+    assert_eq!(
+        merged_locals(&guard.working_directory(), true)?,
+        "feature\n"
+    );
+    Ok(())
+}
@@ -204,7 +204,11 @@ pub fn test_default_param() -> PlanParam<'static> {
     use DeleteRange::*;
     PlanParam {
         bases: vec!["master"],
+        merged_into_tags: Vec::new(),
         protected_patterns: Vec::new(),
+        protected_commits: Vec::new(),
+        protect_merged_into: Vec::new(),
+        exclude_patterns: Vec::new(),
         delete: DeleteFilter::from_iter(vec![
             MergedLocal,
             MergedRemote(Scope::All),
@@ -212,6 +216,35 @@ pub fn test_default_param() -> PlanParam<'static> {
             Diverged(Scope::All),
         ]),
         detach: true,
+        head: None,
+        pull_refs: false,
+        gerrit: false,
+        keep_if_protected_upstream: false,
+        cautious_push_only: false,
+        no_stray_remote_delete: false,
+        keep_remote_ahead: false,
+        keep_stashed: false,
+        keep_ancestors: false,
+        keep_recent: None,
+        keep_recent_checkouts: None,
+        merged_older_than: None,
+        stack_prefix: false,
+        offline: false,
+        skip_defaults: false,
+        limit_to_tracked: false,
+        assume_unmerged_safe: false,
+        cross_check_gone: false,
+        remote_default_only: false,
+        timeout: None,
+        squash_detect: true,
+        strict_squash: false,
+        cache: false,
+        incremental: false,
+        detect_reverted: false,
+        detect_renamed_upstream: false,
+        remote_ahead_guard: None,
+        base_resolver: None,
+        merged_branches: Vec::new(),
     }
 }
 
@@ -0,0 +1,76 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{get_trim_plan, ClassifiedBranch, Git, LocalBranch, RemoteTrackingBranch};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            git checkout -b feature master
+            touch feature
+            git add feature
+            git commit -m "Add feature"
+            git push -u origin feature
+            touch unpushed
+            git add unpushed
+            git commit -m "Add unpushed"
+            git checkout master
+        EOF
+        origin <<EOF
+            # Someone else force-pushes the feature, and it is merged.
+            git checkout -B feature master
+            touch rewritten
+            git add rewritten
+            git commit -m "Add rewritten"
+            git checkout master
+            git merge feature --no-ff
+        EOF
+        local <<EOF
+            git fetch origin
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_force_updated_upstream_is_kept() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    assert_eq!(plan.to_delete, set! {});
+
+    let local = ClassifiedBranch::Stray(LocalBranch::new("refs/heads/feature"));
+    let upstream = ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new(
+        "refs/remotes/origin/feature",
+    ));
+    for branch in &[local, upstream] {
+        let preserved = plan
+            .preserved
+            .iter()
+            .find(|preserved| &preserved.branch == branch)
+            .expect("the branch is kept back");
+        assert_eq!(preserved.reason, "upstream force-updated; manual review");
+    }
+    Ok(())
+}
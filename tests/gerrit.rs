@@ -0,0 +1,103 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{
+    get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam, RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config --add remote.origin.fetch "+refs/changes/*:refs/remotes/origin/changes/*"
+            git checkout -b change master
+            touch change
+            git add change
+            git commit -m "Add change"
+            # Gerrit creates the change ref from a push to refs/for/master
+            git push origin change:refs/changes/01/1/1
+            git config branch.change.remote origin
+            git config branch.change.merge refs/changes/01/1/1
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge refs/changes/01/1/1 --no-ff
+        EOF
+        local <<EOF
+            git fetch origin
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_change_ref_is_kept_by_default() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/change")),
+        },
+    );
+    let preserved = plan
+        .preserved
+        .iter()
+        .find(|preserved| {
+            preserved.branch
+                == ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new(
+                    "refs/remotes/origin/changes/01/1/1",
+                ))
+        })
+        .expect("the change ref is kept back");
+    assert_eq!(preserved.reason, "a non-heads remote");
+    Ok(())
+}
+
+#[test]
+fn test_change_ref_is_deleted_locally_with_gerrit() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            gerrit: true,
+            ..test_default_param()
+        },
+    )?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/change")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new(
+                "refs/remotes/origin/changes/01/1/1"
+            )),
+        },
+    );
+    assert_eq!(
+        plan.local_only,
+        set! {RemoteTrackingBranch::new("refs/remotes/origin/changes/01/1/1")},
+    );
+    assert!(plan.remotes_to_delete(&git.repo)?.is_empty());
+    Ok(())
+}
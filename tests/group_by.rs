@@ -0,0 +1,93 @@
+mod fixture;
+
+use std::process::Command;
+
+use anyhow::Result;
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init upstream
+        upstream <<EOF
+            git config user.name "UpstreamTest"
+            git config user.email "upstream@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone upstream origin -o upstream
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            git config remote.pushdefault upstream
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            git remote add upstream ../upstream
+            git fetch upstream
+            git branch -u upstream/master master
+
+            git checkout -b feature
+            touch awesome-patch
+            git add awesome-patch
+            git commit -m "Awesome patch"
+            git push -u origin feature
+            git checkout master
+        EOF
+        # A maintainer's branch on the upstream
+        upstream <<EOF
+            git checkout -b fix
+            touch fix-patch
+            git add fix-patch
+            git commit -m "Fix patch"
+            git checkout master
+            git merge fix --no-ff
+        EOF
+        origin <<EOF
+            git push upstream feature:refs/pull/1/head
+        EOF
+        upstream <<EOF
+            git merge refs/pull/1/head
+        EOF
+        local <<EOF
+            git fetch upstream
+            git branch --track fix upstream/fix
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_group_by_remote() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm", "--dry-run"])
+        .args(&["--delete", "merged:*", "--group-by", "remote"])
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(
+        stdout.contains(
+            "Delete from local:
+  - feature
+  - fix
+Delete from remote `origin`:
+  - feature
+Delete from remote `upstream`:
+  - fix
+"
+        ),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("Delete merged local branches:"));
+    Ok(())
+}
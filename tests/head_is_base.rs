@@ -0,0 +1,83 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{get_trim_plan, ClassifiedBranch, Git, LocalBranch, RemoteTrackingBranch};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+
+            git checkout -b feature
+            touch awesome-patch
+            git add awesome-patch
+            git commit -m "Awesome patch"
+            git push -u origin feature
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge feature --no-ff
+        EOF
+        local <<EOF
+            git pull --ff-only
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_head_is_base() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    assert_eq!(git.repo.head()?.name(), Some("refs/heads/master"));
+
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/feature")),
+        },
+    );
+    assert!(plan
+        .classifications
+        .iter()
+        .all(|classification| classification.branch != "refs/heads/master"));
+
+    let master = LocalBranch::new("refs/heads/master");
+    let preserved = plan.get_preserved_local(&master).expect("preserved");
+    assert!(preserved.base);
+    assert_eq!(preserved.reason, "base");
+    // It isn't classified, so it isn't reported as merged either.
+    assert_eq!(preserved.branch, ClassifiedBranch::BaseLocal(master));
+    let origin_master = RemoteTrackingBranch::new("refs/remotes/origin/master");
+    let preserved = plan
+        .get_preserved_upstream(&origin_master)
+        .expect("preserved");
+    assert!(preserved.base);
+    assert_eq!(
+        preserved.branch,
+        ClassifiedBranch::BaseRemoteTracking(origin_master)
+    );
+    Ok(())
+}
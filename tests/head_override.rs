@@ -0,0 +1,124 @@
+mod fixture;
+
+use std::convert::TryFrom;
+use std::process::Command;
+
+use anyhow::Result;
+use git2::{BranchType, Repository};
+
+use git_trim::{get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            for NAME in feature/x feature/y; do
+                git checkout -b \$NAME master
+                touch \$(basename \$NAME)
+                git add \$(basename \$NAME)
+                git commit -m "Add \$NAME"
+                git push -u origin \$NAME
+            done
+            # CI checks out the commit, not the branch.
+            git checkout --detach feature/x
+        EOF
+        origin <<EOF
+            git merge feature/x --no-ff
+            git merge feature/y --no-ff
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_head_is_kept_back_when_detached() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            head: Some("feature/x"),
+            ..test_default_param()
+        },
+    )?;
+    let feature_x = LocalBranch::new("refs/heads/feature/x");
+    assert!(!plan
+        .to_delete
+        .iter()
+        .any(|branch| branch.local() == Some(&feature_x)));
+    assert!(plan
+        .to_delete
+        .contains(&ClassifiedBranch::MergedLocal(LocalBranch::new(
+            "refs/heads/feature/y"
+        ))));
+    let preserved = plan
+        .get_preserved_local(&feature_x)
+        .expect("feature/x is kept back");
+    assert_eq!(preserved.reason, "HEAD");
+    Ok(())
+}
+
+#[test]
+fn test_checked_out_branch_is_kept_back_with_head() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        local <<EOF
+            git checkout feature/y
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            detach: false,
+            head: Some("feature/x"),
+            ..test_default_param()
+        },
+    )?;
+    for name in &["refs/heads/feature/x", "refs/heads/feature/y"] {
+        let local = LocalBranch::new(name);
+        assert!(!plan
+            .to_delete
+            .iter()
+            .any(|branch| branch.local() == Some(&local)));
+        let preserved = plan.get_preserved_local(&local).expect("kept back");
+        assert_eq!(preserved.reason, "HEAD");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_head_cli() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm", "--delete", "merged:origin"])
+        .args(&["--head", "feature/x"])
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(output.status.success());
+
+    let repo = Repository::open(guard.working_directory())?;
+    assert!(repo.find_branch("feature/x", BranchType::Local).is_ok());
+    assert!(repo.find_branch("feature/y", BranchType::Local).is_err());
+    Ok(())
+}
@@ -0,0 +1,133 @@
+mod fixture;
+
+use std::convert::TryFrom;
+use std::iter::FromIterator;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::args::{DeleteFilter, DeleteRange};
+use git_trim::{
+    get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam, RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            git checkout -b Feature
+            touch awesome-patch
+            git add awesome-patch
+            git commit -m "Awesome patch"
+            git push -u origin Feature
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge Feature --no-ff
+        EOF
+        "#,
+    )
+}
+
+fn set_ignore_case(repo: &Repository, ignore_case: bool) -> Result<Git> {
+    repo.config()?.set_bool("core.ignorecase", ignore_case)?;
+    Ok(Git::try_from(Repository::open(repo.path())?)?)
+}
+
+#[test]
+fn test_protected_case_sensitive() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = set_ignore_case(&Repository::open(guard.working_directory())?, false)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            protected_patterns: vec!["feature"],
+            ..test_default_param()
+        },
+    )?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/Feature")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/Feature")),
+        },
+    );
+    Ok(())
+}
+
+#[test]
+fn test_protected_ignore_case() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = set_ignore_case(&Repository::open(guard.working_directory())?, true)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            protected_patterns: vec!["feature", "ORIGIN/FEAT*"],
+            ..test_default_param()
+        },
+    )?;
+    assert_eq!(plan.to_delete, set! {});
+    Ok(())
+}
+
+fn base_fixture() -> Fixture {
+    fixture().append_fixture_trace(
+        r#"
+        local <<EOF
+            git branch Master master
+        EOF
+        "#,
+    )
+}
+
+fn local_param() -> PlanParam<'static> {
+    PlanParam {
+        delete: DeleteFilter::from_iter(vec![DeleteRange::MergedLocal, DeleteRange::Local]),
+        ..test_default_param()
+    }
+}
+
+#[test]
+fn test_base_case_sensitive() -> Result<()> {
+    let guard = base_fixture().prepare("local", r#""#)?;
+
+    let git = set_ignore_case(&Repository::open(guard.working_directory())?, false)?;
+    let plan = get_trim_plan(&git, &local_param())?;
+    assert!(plan
+        .to_delete
+        .contains(&ClassifiedBranch::MergedNonTrackingLocal(LocalBranch::new(
+            "refs/heads/Master"
+        ))));
+    Ok(())
+}
+
+#[test]
+fn test_base_ignore_case() -> Result<()> {
+    let guard = base_fixture().prepare("local", r#""#)?;
+
+    let git = set_ignore_case(&Repository::open(guard.working_directory())?, true)?;
+    let plan = get_trim_plan(&git, &local_param())?;
+    assert!(!plan
+        .to_delete
+        .contains(&ClassifiedBranch::MergedNonTrackingLocal(LocalBranch::new(
+            "refs/heads/Master"
+        ))));
+    Ok(())
+}
@@ -0,0 +1,112 @@
+mod fixture;
+
+use std::convert::TryFrom;
+use std::process::Command;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{get_trim_plan, Git, PlanParam, TrimPlan};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            for NAME in merged alive changed; do
+                git checkout -b \$NAME master
+                touch \$NAME
+                git add \$NAME
+                git commit -m "Add \$NAME"
+                git push -u origin \$NAME
+            done
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge merged --no-ff
+            git merge changed --no-ff
+        EOF
+        local <<EOF
+            git fetch origin
+        EOF
+        "#,
+    )
+}
+
+fn param(incremental: bool) -> PlanParam<'static> {
+    PlanParam {
+        incremental,
+        ..test_default_param()
+    }
+}
+
+fn reclassified(plan: &TrimPlan) -> Vec<&str> {
+    let mut result: Vec<_> = plan
+        .classifications
+        .iter()
+        .filter(|classification| !classification.reused)
+        .map(|classification| classification.branch.as_str())
+        .collect();
+    result.dedup();
+    result
+}
+
+#[test]
+fn test_incremental() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let first = get_trim_plan(&git, &param(true))?;
+    assert_eq!(
+        reclassified(&first),
+        vec![
+            "refs/heads/alive",
+            "refs/heads/changed",
+            "refs/heads/merged",
+        ],
+    );
+
+    let second = get_trim_plan(&git, &param(true))?;
+    assert_eq!(reclassified(&second), Vec::<&str>::new());
+    assert_eq!(second.to_delete, first.to_delete);
+    Ok(())
+}
+
+#[test]
+fn test_only_moved_branch_is_reclassified() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    get_trim_plan(&git, &param(true))?;
+
+    let status = Command::new("bash")
+        .args(&[
+            "-c",
+            "git checkout -q changed && git commit -q --allow-empty -m 'More' && git checkout -q master",
+        ])
+        .current_dir(guard.working_directory())
+        .status()?;
+    assert!(status.success());
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let incremental = get_trim_plan(&git, &param(true))?;
+    assert_eq!(reclassified(&incremental), vec!["refs/heads/changed"]);
+
+    let full = get_trim_plan(&git, &param(false))?;
+    assert_eq!(incremental.to_delete, full.to_delete);
+    Ok(())
+}
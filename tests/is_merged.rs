@@ -0,0 +1,81 @@
+mod fixture;
+
+use std::process::Command;
+
+use anyhow::Result;
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+
+            git checkout -b merged
+            touch merged-patch
+            git add merged-patch
+            git commit -m "Merged patch"
+
+            git checkout -b squashed master
+            touch squashed-patch
+            git add squashed-patch
+            git commit -m "Squashed patch"
+
+            git checkout -b unmerged master
+            touch unmerged-patch
+            git add unmerged-patch
+            git commit -m "Unmerged patch"
+
+            git checkout master
+            git merge merged --no-ff
+            git merge squashed --squash && git commit --no-edit
+        EOF
+        "#,
+    )
+}
+
+fn is_merged(guard: &fixture::FixtureGuard, branch: &str) -> Result<(Option<i32>, String)> {
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["is-merged", branch, "master"])
+        .current_dir(guard.working_directory())
+        .output()?;
+    let stdout = std::str::from_utf8(&output.stdout)?.trim().to_owned();
+    Ok((output.status.code(), stdout))
+}
+
+#[test]
+fn test_is_merged() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    assert_eq!(is_merged(&guard, "merged")?, (Some(0), "merged".to_owned()));
+    assert_eq!(
+        is_merged(&guard, "squashed")?,
+        (Some(0), "merged".to_owned())
+    );
+    assert_eq!(
+        is_merged(&guard, "unmerged")?,
+        (Some(2), "not merged".to_owned())
+    );
+    Ok(())
+}
+
+#[test]
+fn test_is_merged_error() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let (code, stdout) = is_merged(&guard, "nonexistent")?;
+    assert_eq!(code, Some(1));
+    assert_eq!(stdout, "");
+    Ok(())
+}
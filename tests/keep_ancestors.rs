@@ -0,0 +1,84 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            for ENTRY in feature/base:master feature/child:feature/base merged:master; do
+                NAME=\${ENTRY%:*}
+                START=\${ENTRY#*:}
+                git checkout -b \$NAME \$START
+                touch \${NAME#*/}
+                git add \${NAME#*/}
+                git commit -m "Add \$NAME"
+                git push -u origin \$NAME
+            done
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge feature/base --no-ff
+            git merge merged --no-ff
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_ancestor_is_deleted_by_default() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    assert!(plan
+        .to_delete
+        .contains(&ClassifiedBranch::MergedLocal(LocalBranch::new(
+            "refs/heads/feature/base"
+        ))));
+    Ok(())
+}
+
+#[test]
+fn test_keep_ancestors() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            keep_ancestors: true,
+            ..test_default_param()
+        },
+    )?;
+    assert_eq!(
+        plan.locals_to_delete(),
+        vec![&LocalBranch::new("refs/heads/merged")],
+    );
+    assert_eq!(
+        plan.get_preserved_local(&LocalBranch::new("refs/heads/feature/base"))
+            .map(|preserved| preserved.reason.as_str()),
+        Some("ancestor of active branch"),
+    );
+    Ok(())
+}
@@ -0,0 +1,91 @@
+mod fixture;
+
+use std::collections::HashSet;
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{
+    get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam, RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            for ENTRY in charlie:3 alpha:1 echo:5 bravo:2 delta:4; do
+                NAME=\${ENTRY%:*}
+                DAY=\${ENTRY#*:}
+                git checkout -b \$NAME master
+                touch \$NAME
+                git add \$NAME
+                GIT_COMMITTER_DATE="2020-01-0\$DAY 00:00:00 +0000" git commit -m "Add \$NAME"
+                git push -u origin \$NAME
+            done
+            git checkout master
+        EOF
+        origin <<EOF
+            for NAME in alpha bravo charlie delta echo; do
+                git merge \$NAME --no-ff
+            done
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_keep_recent() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            keep_recent: Some(2),
+            ..test_default_param()
+        },
+    )?;
+
+    let mut expected = HashSet::new();
+    for name in &["alpha", "bravo", "charlie"] {
+        expected.insert(ClassifiedBranch::MergedLocal(LocalBranch::new(&format!(
+            "refs/heads/{}",
+            name
+        ))));
+        expected.insert(ClassifiedBranch::MergedRemoteTracking(
+            RemoteTrackingBranch::new(&format!("refs/remotes/origin/{}", name)),
+        ));
+    }
+    assert_eq!(plan.to_delete, expected);
+
+    for name in &["delta", "echo"] {
+        let preserved = plan
+            .get_preserved_local(&LocalBranch::new(&format!("refs/heads/{}", name)))
+            .expect("preserved");
+        assert_eq!(preserved.reason, "among 2 most recent branches");
+        let preserved = plan
+            .get_preserved_upstream(&RemoteTrackingBranch::new(&format!(
+                "refs/remotes/origin/{}",
+                name
+            )))
+            .expect("preserved");
+        assert_eq!(preserved.reason, "among 2 most recent branches");
+    }
+    Ok(())
+}
@@ -0,0 +1,103 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{
+    get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam, RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            for NAME in feature-1 feature-2 feature-3; do
+                git checkout -b \$NAME master
+                touch \$NAME
+                git add \$NAME
+                git commit -m "Add \$NAME"
+                git push -u origin \$NAME
+            done
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge feature-1 --no-ff
+            git merge feature-2 --no-ff
+            git merge feature-3 --no-ff
+        EOF
+        local <<EOF
+            git checkout feature-2
+            git checkout feature-3
+            git checkout master
+        EOF
+        "#,
+    )
+}
+
+fn param(keep_recent_checkouts: usize) -> PlanParam<'static> {
+    PlanParam {
+        keep_recent_checkouts: Some(keep_recent_checkouts),
+        ..test_default_param()
+    }
+}
+
+#[test]
+fn test_keep_recent_checkouts() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    // `master`, `feature-3` and `feature-2`.
+    let plan = get_trim_plan(&git, &param(3))?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature-1")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new(
+                "refs/remotes/origin/feature-1"
+            )),
+        },
+    );
+    for name in &["feature-2", "feature-3"] {
+        let local = LocalBranch::new(&format!("refs/heads/{}", name));
+        let preserved = plan.get_preserved_local(&local).expect("preserved");
+        assert_eq!(preserved.reason, "recently checked out");
+        let upstream = RemoteTrackingBranch::new(&format!("refs/remotes/origin/{}", name));
+        let preserved = plan.get_preserved_upstream(&upstream).expect("preserved");
+        assert_eq!(preserved.reason, "recently checked out");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_keep_recent_checkouts_fewer() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &param(2))?;
+    assert!(plan
+        .get_preserved_local(&LocalBranch::new("refs/heads/feature-3"))
+        .is_some());
+    assert!(plan
+        .to_delete
+        .contains(&ClassifiedBranch::MergedLocal(LocalBranch::new(
+            "refs/heads/feature-2"
+        ))));
+    Ok(())
+}
@@ -0,0 +1,95 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            for NAME in stashed clean; do
+                git checkout -b \$NAME master
+                touch \$NAME
+                git add \$NAME
+                git commit -m "Add \$NAME"
+                git push -u origin \$NAME
+            done
+            git checkout stashed
+            echo "Work in progress" >> README.md
+            git stash
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge stashed --no-ff
+            git merge clean --no-ff
+        EOF
+        "#,
+    )
+}
+
+fn param() -> PlanParam<'static> {
+    PlanParam {
+        protected_patterns: vec!["*/master"],
+        ..test_default_param()
+    }
+}
+
+#[test]
+fn test_default() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &param())?;
+    assert!(plan
+        .to_delete
+        .contains(&ClassifiedBranch::MergedLocal(LocalBranch::new(
+            "refs/heads/stashed"
+        ))));
+    Ok(())
+}
+
+#[test]
+fn test_keep_stashed() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            keep_stashed: true,
+            ..param()
+        },
+    )?;
+    let locals: Vec<_> = plan.locals_to_delete().into_iter().cloned().collect();
+    assert_eq!(locals, vec![LocalBranch::new("refs/heads/clean")]);
+
+    let preserved = plan
+        .preserved
+        .iter()
+        .find(|preserved| {
+            preserved.branch
+                == ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/stashed"))
+        })
+        .expect("stashed branch is kept");
+    assert_eq!(preserved.reason, "has associated stash");
+    Ok(())
+}
@@ -0,0 +1,93 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam, TrimPlan};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git checkout -b merged master
+            touch merged
+            git add merged
+            git commit -m "Add merged"
+            git push -u origin merged
+
+            for NAME in push-only scratch; do
+                git checkout -b \$NAME master
+                touch \$NAME
+                git add \$NAME
+                git commit -m "Add \$NAME"
+            done
+            git config branch.push-only.pushRemote origin
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge merged --no-ff
+        EOF
+        "#,
+    )
+}
+
+fn param(limit_to_tracked: bool) -> PlanParam<'static> {
+    PlanParam {
+        limit_to_tracked,
+        ..test_default_param()
+    }
+}
+
+fn mentions(plan: &TrimPlan, refname: &str) -> bool {
+    let is_local = |branch: &ClassifiedBranch| branch.local() == Some(&LocalBranch::new(refname));
+    plan.to_delete.iter().any(is_local)
+        || plan.preserved.iter().any(|p| is_local(&p.branch))
+        || plan.skipped.contains_key(refname)
+}
+
+#[test]
+fn test_untracked_branches_are_classified_by_default() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &param(false))?;
+    assert!(plan
+        .to_delete
+        .contains(&ClassifiedBranch::MergedLocal(LocalBranch::new(
+            "refs/heads/merged"
+        ))));
+    assert!(mentions(&plan, "refs/heads/push-only"));
+    assert!(mentions(&plan, "refs/heads/scratch"));
+    Ok(())
+}
+
+#[test]
+fn test_untracked_branches_are_skipped_entirely() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &param(true))?;
+    assert!(plan
+        .to_delete
+        .contains(&ClassifiedBranch::MergedLocal(LocalBranch::new(
+            "refs/heads/merged"
+        ))));
+    assert!(mentions(&plan, "refs/heads/push-only"));
+    assert!(!mentions(&plan, "refs/heads/scratch"));
+    Ok(())
+}
@@ -0,0 +1,93 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{get_trim_plan, ClassifiedBranch, Git, LocalBranch};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+
+            git checkout -b topic
+            git checkout -b feature --track topic
+            touch awesome-patch
+            git add awesome-patch
+            git commit -m "Awesome patch"
+            git checkout master
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_local_upstream_not_merged() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    assert!(!plan
+        .to_delete
+        .iter()
+        .any(|branch| branch.local() == Some(&LocalBranch::new("refs/heads/feature"))));
+    Ok(())
+}
+
+#[test]
+fn test_local_upstream_merged() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        local <<EOF
+            git merge feature
+            git push origin master
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    assert!(plan
+        .to_delete
+        .contains(&ClassifiedBranch::MergedLocal(LocalBranch::new(
+            "refs/heads/feature"
+        ))));
+    Ok(())
+}
+
+#[test]
+fn test_local_upstream_gone() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        local <<EOF
+            git branch -D topic
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    assert!(plan
+        .to_delete
+        .contains(&ClassifiedBranch::Stray(LocalBranch::new(
+            "refs/heads/feature"
+        ))));
+    Ok(())
+}
@@ -0,0 +1,50 @@
+mod fixture;
+
+use std::process::Command;
+
+use anyhow::Result;
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        "#,
+    )
+}
+
+#[test]
+fn test_json_log_lines() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--log-format", "json", "--no-update", "--no-confirm"])
+        .env("RUST_LOG", "debug")
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(output.status.success());
+
+    let stderr = std::str::from_utf8(&output.stderr)?;
+    let mut records = 0;
+    for line in stderr.lines() {
+        let record: serde_json::Value = serde_json::from_str(line)?;
+        for key in &["level", "target", "message", "timestamp"] {
+            assert!(record.get(key).is_some(), "{} is missing in {}", key, line);
+        }
+        records += 1;
+    }
+    assert!(records > 0);
+
+    let stdout = std::str::from_utf8(&output.stdout)?;
+    assert!(stdout.contains("Branches that will remain:"));
+    Ok(())
+}
@@ -0,0 +1,94 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{
+    get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam, RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+
+            git checkout -b feature-early
+            touch early && git add early && git commit -m "Early"
+            git push -u origin feature-early
+
+            git checkout master -b feature-late
+            touch late && git add late && git commit -m "Late"
+            git push -u origin feature-late
+        EOF
+        origin <<EOF
+            git merge feature-early --no-ff
+        EOF
+        # The topic branches off before feature-late is merged.
+        local <<EOF
+            git fetch origin
+            git checkout origin/master -b topic
+            touch topic && git add topic && git commit -m "Topic"
+        EOF
+        origin <<EOF
+            git merge feature-late --no-ff
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_tip() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature-early")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/feature-early")),
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature-late")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/feature-late")),
+        },
+    );
+    Ok(())
+}
+
+#[test]
+fn test_merge_base() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            bases: vec!["merge-base:master"],
+            ..test_default_param()
+        },
+    )?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature-early")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/feature-early")),
+        },
+    );
+    Ok(())
+}
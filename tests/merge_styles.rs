@@ -5,7 +5,9 @@ use std::convert::TryFrom;
 use anyhow::Result;
 use git2::Repository;
 
-use git_trim::{get_trim_plan, ClassifiedBranch, Git, LocalBranch};
+use git_trim::{
+    get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam, RemoteTrackingBranch,
+};
 
 use fixture::{rc, test_default_param, Fixture};
 
@@ -92,6 +94,38 @@ fn test_rebase() -> Result<()> {
     Ok(())
 }
 
+/// GitHub's "Rebase and merge" replays the commits with new ids, and the remote branch might be left alive.
+#[test]
+fn test_rebase_and_merge_remote_alive() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        origin <<EOF
+            git checkout master
+            touch diverged
+            git add diverged
+            git commit -m "Diverge master"
+            git checkout -b rebase-tmp feature
+            git rebase master
+            git checkout master
+            git merge rebase-tmp --ff-only
+            git branch -D rebase-tmp
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/feature")),
+        },
+    );
+    Ok(())
+}
+
 #[test]
 fn test_squash() -> Result<()> {
     let guard = fixture().prepare(
@@ -116,6 +150,97 @@ fn test_squash() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_no_squash_detect() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        origin <<EOF
+            git checkout master
+            git merge feature --squash && git commit --no-edit
+            git branch -D feature
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            squash_detect: false,
+            ..test_default_param()
+        },
+    )?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::Stray(LocalBranch::new("refs/heads/feature")),
+        },
+    );
+    Ok(())
+}
+
+#[test]
+fn test_squash_does_not_run_hooks() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        local <<EOF
+            mkdir -p ../hooks
+            for HOOK in pre-commit prepare-commit-msg commit-msg post-commit; do
+                printf '#!/bin/sh\ntouch hook-invoked\nexit 1\n' > ../hooks/\$HOOK
+                chmod +x ../hooks/\$HOOK
+            done
+            git config core.hooksPath ../hooks
+        EOF
+        origin <<EOF
+            git checkout master
+            git merge feature --squash && git commit --no-edit
+            git branch -D feature
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature")),
+        },
+    );
+    assert!(!guard.working_directory().join("hook-invoked").exists());
+    Ok(())
+}
+
+#[test]
+fn test_squash_with_failing_pre_commit_hook() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        local <<EOF
+            printf '#!/bin/sh\nexit 1\n' > .git/hooks/pre-commit
+            chmod +x .git/hooks/pre-commit
+        EOF
+        origin <<EOF
+            git checkout master
+            git merge feature --squash && git commit --no-edit
+            git branch -D feature
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature")),
+        },
+    );
+    Ok(())
+}
+
 #[test]
 fn test_mixed() -> Result<()> {
     let fixture = rc().append_fixture_trace(
@@ -0,0 +1,99 @@
+mod fixture;
+
+use std::convert::TryFrom;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use anyhow::Result;
+use git2::{BranchType, Repository};
+
+use git_trim::{
+    get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam, RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            for NAME in first second alive; do
+                git checkout -b \$NAME master
+                touch \$NAME
+                git add \$NAME
+                git commit -m "Add \$NAME"
+                git push -u origin \$NAME
+            done
+            git checkout master
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_merged_from_stdin() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm", "--merged-from-stdin"])
+        .current_dir(guard.working_directory())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()?;
+    child.stdin.take().unwrap().write_all(b"first\nsecond\n")?;
+    assert!(child.wait()?.success());
+
+    let repo = Repository::open(guard.working_directory())?;
+    let mut locals = Vec::new();
+    for branch in repo.branches(Some(BranchType::Local))? {
+        let (branch, _) = branch?;
+        locals.push(branch.name()?.unwrap().to_owned());
+    }
+    locals.sort();
+    assert_eq!(locals, vec!["alive", "master"]);
+    for name in &["first", "second"] {
+        assert!(repo
+            .find_branch(&format!("origin/{}", name), BranchType::Remote)
+            .is_err());
+    }
+    assert!(repo.find_branch("origin/alive", BranchType::Remote).is_ok());
+    Ok(())
+}
+
+#[test]
+fn test_listed_base_is_kept_back() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            merged_branches: vec!["master", "refs/heads/first"],
+            ..test_default_param()
+        },
+    )?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/first")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/first")),
+        },
+    );
+    assert!(plan
+        .get_preserved_local(&LocalBranch::new("refs/heads/master"))
+        .is_some());
+    Ok(())
+}
@@ -0,0 +1,104 @@
+mod fixture;
+
+use std::convert::TryFrom;
+use std::process::Command;
+use std::time::Duration;
+
+use anyhow::Result;
+use git2::{BranchType, Repository};
+
+use git_trim::{
+    get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam, RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            git checkout -b old master
+            touch old
+            git add old
+            GIT_COMMITTER_DATE="2000-01-01T00:00:00" git commit -m "Add old"
+            git push -u origin old
+            git checkout -b fresh master
+            touch fresh
+            git add fresh
+            git commit -m "Add fresh"
+            git push -u origin fresh
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge old --no-ff
+            git merge fresh --no-ff
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_merged_older_than() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            merged_older_than: Some(Duration::from_secs(7 * 24 * 60 * 60)),
+            ..test_default_param()
+        },
+    )?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/old")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/old")),
+        },
+    );
+    let preserved = plan
+        .get_preserved_local(&LocalBranch::new("refs/heads/fresh"))
+        .expect("fresh is kept back");
+    assert_eq!(preserved.reason, "merged recently");
+    let preserved = plan
+        .get_preserved_upstream(&RemoteTrackingBranch::new("refs/remotes/origin/fresh"))
+        .expect("the upstream of fresh is kept back");
+    assert_eq!(preserved.reason, "merged recently");
+    Ok(())
+}
+
+#[test]
+fn test_merged_older_than_cli() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm", "--delete", "merged:origin"])
+        .args(&["--merged-older-than", "1w"])
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(output.status.success());
+
+    let repo = Repository::open(guard.working_directory())?;
+    assert!(repo.find_branch("old", BranchType::Local).is_err());
+    assert!(repo.find_branch("fresh", BranchType::Local).is_ok());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm", "--merged-older-than", "1y"])
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(!output.status.success());
+    Ok(())
+}
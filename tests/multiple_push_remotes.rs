@@ -0,0 +1,217 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{
+    get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam, RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin fork1
+        git clone origin fork2
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git remote add fork1 ../fork1
+            git remote add fork2 ../fork2
+            git fetch --all
+            git checkout -b feature
+            touch awesome-patch
+            git add awesome-patch
+            git commit -m "Awesome patch"
+            git push -u origin feature
+            git push fork1 feature
+            git push fork2 feature
+            git config --add branch.feature.pushRemote fork1
+            git config --add branch.feature.pushRemote fork2
+            git checkout master
+        EOF
+        "#,
+    )
+}
+
+fn push_url_fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin fork1
+        git clone origin fork2
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git remote add fork1 ../fork1
+            git remote add fork2 ../fork2
+            git fetch --all
+            git checkout -b feature
+            touch awesome-patch
+            git add awesome-patch
+            git commit -m "Awesome patch"
+            git push -u origin feature
+            git push fork1 feature
+            git push fork2 feature
+            git config --add remote.origin.pushurl ../fork1
+            git config --add remote.origin.pushurl ../fork2
+            git checkout master
+        EOF
+        "#,
+    )
+}
+
+fn param() -> PlanParam<'static> {
+    PlanParam {
+        protected_patterns: vec!["*/master"],
+        ..test_default_param()
+    }
+}
+
+#[test]
+fn test_merged_push_remotes() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        origin <<EOF
+            git merge feature --no-ff
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &param())?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/feature")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/fork1/feature")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/fork2/feature")),
+        },
+    );
+    Ok(())
+}
+
+#[test]
+fn test_diverged_push_remote() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        within fork2 <<EOF
+            git config user.name "Fork Test"
+            git config user.email "fork@test"
+            git checkout feature
+            touch another-patch
+            git add another-patch
+            git commit -m "Another patch"
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge feature --no-ff
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &param())?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/feature")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/fork1/feature")),
+            ClassifiedBranch::DivergedRemoteTracking {
+                local: LocalBranch::new("refs/heads/feature"),
+                upstream: RemoteTrackingBranch::new("refs/remotes/fork2/feature"),
+            },
+        },
+    );
+    Ok(())
+}
+
+#[test]
+fn test_merged_push_urls() -> Result<()> {
+    let guard = push_url_fixture().prepare(
+        "local",
+        r#"
+        origin <<EOF
+            git merge feature --no-ff
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &param())?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/feature")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/fork1/feature")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/fork2/feature")),
+        },
+    );
+    Ok(())
+}
+
+#[test]
+fn test_disagreeing_upstreams() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        origin <<EOF
+            git merge feature --no-ff
+            git checkout feature
+            touch stale-patch
+            git add stale-patch
+            git commit -m "Stale patch"
+            git checkout master
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &param())?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/fork1/feature")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/fork2/feature")),
+        },
+    );
+    // The fetch upstream has a commit that isn't merged, while the push upstreams are merged.
+    let preserved = plan
+        .get_preserved_upstream(&RemoteTrackingBranch::new("refs/remotes/origin/feature"))
+        .expect("preserved");
+    assert_eq!(
+        preserved.branch,
+        ClassifiedBranch::DivergedRemoteTracking {
+            local: LocalBranch::new("refs/heads/feature"),
+            upstream: RemoteTrackingBranch::new("refs/remotes/origin/feature"),
+        }
+    );
+    assert_eq!(preserved.reason, "not merged, while a push upstream is");
+    Ok(())
+}
@@ -0,0 +1,88 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{get_trim_plan, ClassifiedBranch, Git, RemoteTrackingBranch};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+
+            git checkout -b feature
+            touch awesome-patch
+            git add awesome-patch
+            git commit -m "Awesome patch"
+            git push -u origin feature
+            git checkout master
+            git branch -D feature
+        EOF
+        origin <<EOF
+            git merge feature --no-ff
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_outside_fetch_refspec_is_kept() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        local <<EOF
+            git config remote.origin.fetch +refs/heads/master:refs/remotes/origin/master
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+
+    assert_eq!(plan.to_delete, set! {});
+    assert!(plan.preserved.iter().any(|preserved| {
+        preserved.branch
+            == ClassifiedBranch::MergedNonUpstreamRemoteTracking(RemoteTrackingBranch::new(
+                "refs/remotes/origin/feature",
+            ))
+            && preserved.reason == "outside fetch refspec"
+    }));
+    Ok(())
+}
+
+#[test]
+fn test_multiple_fetch_refspecs() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        local <<EOF
+            git config remote.origin.fetch +refs/heads/master:refs/remotes/origin/master
+            git config --add remote.origin.fetch +refs/heads/feature:refs/remotes/origin/feature
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+
+    // Covered by the second refspec, so it is handled as an ordinary non-upstream branch.
+    assert!(plan.skipped.contains_key("refs/remotes/origin/feature"));
+    Ok(())
+}
@@ -0,0 +1,141 @@
+mod fixture;
+
+use std::convert::TryFrom;
+use std::process::Command;
+
+use anyhow::Result;
+use git2::{BranchType, Repository};
+
+use git_trim::{
+    get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam, RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            git checkout -b merged master
+            touch merged
+            git add merged
+            git commit -m "Add merged"
+            git push -u origin merged
+
+            git checkout -b diverged master
+            touch diverged
+            git add diverged
+            git commit -m "Add diverged"
+            git push -u origin diverged
+            # The rest is pushed for a review only, and squash merged.
+            touch reviewed
+            git add reviewed
+            git commit -m "Add reviewed"
+            git push origin diverged:review
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge merged --no-ff
+            git merge review --squash && git commit -m "Squash review"
+            git branch -D review
+        EOF
+        local <<EOF
+            git fetch --prune
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_stray_remote_is_kept() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let diverged = ClassifiedBranch::DivergedRemoteTracking {
+        local: LocalBranch::new("refs/heads/diverged"),
+        upstream: RemoteTrackingBranch::new("refs/remotes/origin/diverged"),
+    };
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    assert!(plan.to_delete.contains(&diverged));
+
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            no_stray_remote_delete: true,
+            ..test_default_param()
+        },
+    )?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/merged")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/merged")),
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/diverged")),
+        },
+    );
+    let preserved = plan
+        .preserved
+        .iter()
+        .find(|preserved| preserved.branch == diverged)
+        .expect("the stray remote is kept");
+    assert_eq!(preserved.reason, "stray remote deletion disabled");
+    // Only the remote side is kept back.
+    assert!(preserved.remote_only);
+    assert!(plan
+        .get_preserved_local(&LocalBranch::new("refs/heads/diverged"))
+        .is_none());
+    Ok(())
+}
+
+#[test]
+fn test_no_stray_remote_delete_cli() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm"])
+        .args(&["--delete", "merged:origin,diverged:origin"])
+        .arg("--no-stray-remote-delete")
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(output.status.success());
+
+    let repo = Repository::open(guard.working_directory())?;
+    assert!(repo.find_branch("merged", BranchType::Local).is_err());
+    assert!(repo.find_branch("diverged", BranchType::Local).is_err());
+    let origin = Repository::open(guard.working_directory().join("../origin"))?;
+    assert!(origin.find_branch("merged", BranchType::Local).is_err());
+    assert!(origin.find_branch("diverged", BranchType::Local).is_ok());
+    Ok(())
+}
+
+#[test]
+fn test_no_stray_remote_delete_without_diverged_filter() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm"])
+        .args(&["--delete", "merged:origin"])
+        .arg("--no-stray-remote-delete")
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(output.status.success());
+
+    // The diverged local is filtered as diverged, not as merged.
+    let repo = Repository::open(guard.working_directory())?;
+    assert!(repo.find_branch("merged", BranchType::Local).is_err());
+    assert!(repo.find_branch("diverged", BranchType::Local).is_ok());
+    Ok(())
+}
@@ -0,0 +1,80 @@
+mod fixture;
+
+use std::process::Command;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::report::NOTES_REF;
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            for NAME in first second; do
+                git checkout -b \$NAME master
+                touch \$NAME
+                git add \$NAME
+                git commit -m "Add \$NAME"
+                git push -u origin \$NAME
+            done
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge first --no-ff
+            git merge second --no-ff
+        EOF
+        local <<EOF
+            git fetch origin
+        EOF
+        "#,
+    )
+}
+
+fn run(working_directory: &std::path::Path, extra: &[&str]) -> Result<bool> {
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm", "--note-deletions"])
+        .args(extra)
+        .current_dir(working_directory)
+        .output()?;
+    Ok(output.status.success())
+}
+
+#[test]
+fn test_notes_accumulate() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+    let repo = Repository::open(guard.working_directory())?;
+    let base = repo.refname_to_id("refs/heads/master")?;
+    let first = repo.refname_to_id("refs/heads/first")?.to_string();
+    let second = repo.refname_to_id("refs/heads/second")?.to_string();
+
+    assert!(run(&guard.working_directory(), &["--exclude", "second"])?);
+    let note = repo.find_note(Some(NOTES_REF), base)?;
+    let message = note.message().unwrap();
+    assert!(message.contains(&format!(" local refs/heads/first {}\n", first)));
+    assert!(!message.contains("refs/heads/second"));
+
+    assert!(run(&guard.working_directory(), &[])?);
+    let note = repo.find_note(Some(NOTES_REF), base)?;
+    let message = note.message().unwrap();
+    assert!(message.contains(&format!(" local refs/heads/first {}\n", first)));
+    assert!(message.contains(&format!(" local refs/heads/second {}\n", second)));
+    assert!(message.contains(" origin refs/heads/first "));
+    assert!(message.contains(" origin refs/heads/second "));
+    Ok(())
+}
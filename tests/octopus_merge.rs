@@ -0,0 +1,94 @@
+mod fixture;
+
+use std::collections::HashSet;
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{
+    get_trim_plan, is_merged, is_merged_by_rev_list, is_merged_by_revwalk, ClassifiedBranch, Git,
+    LocalBranch, RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            for NAME in feature-1 feature-2 feature-3; do
+                git checkout -b \$NAME master
+                touch \$NAME
+                git add \$NAME
+                git commit -m "Add \$NAME"
+                git push -u origin \$NAME
+            done
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge feature-1 feature-2 feature-3 -m "Octopus merge"
+        EOF
+        local <<EOF
+            git fetch
+        EOF
+        "#,
+    )
+}
+
+const NAMES: &[&str] = &["feature-1", "feature-2", "feature-3"];
+
+#[test]
+fn test_octopus_merge() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    let mut expected = HashSet::new();
+    for name in NAMES {
+        expected.insert(ClassifiedBranch::MergedLocal(LocalBranch::new(&format!(
+            "refs/heads/{}",
+            name
+        ))));
+        expected.insert(ClassifiedBranch::MergedRemoteTracking(
+            RemoteTrackingBranch::new(&format!("refs/remotes/origin/{}", name)),
+        ));
+    }
+    assert_eq!(plan.to_delete, expected);
+    Ok(())
+}
+
+#[test]
+fn test_octopus_merge_each_parent() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    for name in NAMES {
+        let refname = format!("refs/heads/{}", name);
+        assert!(is_merged(&git, name, "origin/master")?, "{}", name);
+        assert!(
+            is_merged_by_rev_list(&git.repo, "refs/remotes/origin/master", &refname)?,
+            "{}",
+            name
+        );
+        assert!(
+            is_merged_by_revwalk(&git.repo, "refs/remotes/origin/master", &refname)?,
+            "{}",
+            name
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,111 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{
+    get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam, RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            git checkout -b synced master
+            git push -u origin synced
+            git checkout -b feature master
+            touch feature
+            git add feature
+            git commit -m "Add feature"
+            git push -u origin feature
+            git checkout master
+        EOF
+        origin <<EOF
+            touch upgrade
+            git add upgrade
+            git commit -m "Add upgrade"
+        EOF
+        local <<EOF
+            git fetch origin
+            for NAME in synced feature; do
+                git checkout \$NAME
+                git merge origin/master --no-ff -m "Merge master into \$NAME"
+                git push
+            done
+            git checkout master
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_only_base_merges() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/synced")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new(
+                "refs/remotes/origin/synced"
+            )),
+        },
+    );
+    assert_eq!(
+        plan.reason(&ClassifiedBranch::MergedLocal(LocalBranch::new(
+            "refs/heads/synced"
+        ))),
+        "no unique content, only base merges",
+    );
+    Ok(())
+}
+
+#[test]
+fn test_only_base_merges_without_squash_detect() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            squash_detect: false,
+            ..test_default_param()
+        },
+    )?;
+    // The merge tracker finds the merge of the base unmerged without the squash detection.
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/synced")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new(
+                "refs/remotes/origin/synced"
+            )),
+        },
+    );
+    assert_eq!(
+        plan.reason(&ClassifiedBranch::MergedLocal(LocalBranch::new(
+            "refs/heads/synced"
+        ))),
+        "no unique content, only base merges",
+    );
+    Ok(())
+}
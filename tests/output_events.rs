@@ -0,0 +1,103 @@
+mod fixture;
+
+use std::process::Command;
+
+use anyhow::Result;
+use git2::{BranchType, Repository};
+use serde_json::Value;
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            for NAME in merged protected; do
+                git checkout -b \$NAME master
+                touch \$NAME
+                git add \$NAME
+                git commit -m "Add \$NAME"
+                git push -u origin \$NAME
+            done
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge merged --no-ff
+            git merge protected --no-ff
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_ndjson_events() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm", "--delete", "merged:origin"])
+        .args(&["--protected", "protected,origin/protected"])
+        .args(&["--output", "ndjson-events"])
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(output.status.success());
+
+    let mut events = Vec::new();
+    for line in std::str::from_utf8(&output.stdout)?.lines() {
+        let event: Value = serde_json::from_str(line)?;
+        events.push(event);
+    }
+    let kinds: Vec<_> = events
+        .iter()
+        .map(|event| event["event"].as_str().unwrap())
+        .collect();
+    // The base and the protected branch, each with its upstream.
+    let kept_back = 4;
+    let mut expected = vec!["classified", "classified"];
+    expected.extend(vec!["kept_back"; kept_back]);
+    expected.extend(vec!["deleted", "deleted", "summary"]);
+    assert_eq!(kinds, expected);
+
+    let branch = |event: &Value| {
+        (
+            event["branch"].as_str().unwrap().to_owned(),
+            event["remote"].as_str().map(str::to_owned),
+        )
+    };
+    let merged = ("merged".to_owned(), None);
+    let origin_merged = ("merged".to_owned(), Some("origin".to_owned()));
+    assert_eq!(branch(&events[0]), merged);
+    assert_eq!(branch(&events[1]), origin_merged);
+    assert!(events[2..2 + kept_back].iter().any(|event| {
+        branch(event) == ("protected".to_owned(), None)
+            && event["reason"] == "protected by a pattern `protected`"
+    }));
+    // Remote branches are deleted first.
+    assert_eq!(branch(&events[2 + kept_back]), origin_merged);
+    assert_eq!(branch(&events[3 + kept_back]), merged);
+    assert_eq!(
+        events.last().unwrap()["counts"],
+        serde_json::json!({
+            "classified": 2,
+            "kept_back": kept_back,
+            "deleted": 2,
+        }),
+    );
+
+    let repo = Repository::open(guard.working_directory())?;
+    assert!(repo.find_branch("merged", BranchType::Local).is_err());
+    assert!(repo.find_branch("protected", BranchType::Local).is_ok());
+    Ok(())
+}
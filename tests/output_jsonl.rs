@@ -0,0 +1,117 @@
+mod fixture;
+
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::iter::FromIterator;
+use std::process::Command;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::args::{DeleteFilter, DeleteRange};
+use git_trim::{get_trim_plan, Git, PlanParam};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            for NAME in merged protected; do
+                git checkout -b \$NAME master
+                touch \$NAME
+                git add \$NAME
+                git commit -m "Add \$NAME"
+                git push -u origin \$NAME
+            done
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge merged --no-ff
+            git merge protected --no-ff
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_jsonl() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&[
+            "--no-update",
+            "--no-confirm",
+            "--delete",
+            "merged:origin",
+            "--protected",
+            "protected",
+            "--output",
+            "jsonl",
+        ])
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(output.status.success());
+
+    let mut deleted_locals = HashSet::new();
+    let mut deleted_remotes = HashSet::new();
+    let mut kept = HashSet::new();
+    for line in std::str::from_utf8(&output.stdout)?.lines() {
+        let record: serde_json::Value = serde_json::from_str(line)?;
+        for key in &["action", "name", "remote", "classification"] {
+            assert!(record.get(key).is_some(), "{} is missing in {}", key, line);
+        }
+        let name = record["name"].as_str().unwrap().to_owned();
+        match (
+            record["action"].as_str().unwrap(),
+            record["remote"].as_str(),
+        ) {
+            ("delete", None) => assert!(deleted_locals.insert(name)),
+            ("delete", Some(remote)) => {
+                assert!(deleted_remotes.insert(format!("{}/{}", remote, name)))
+            }
+            ("keep", _) => {
+                kept.insert(name);
+            }
+            (action, _) => panic!("unknown action {}", action),
+        }
+    }
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            protected_patterns: vec!["protected"],
+            delete: DeleteFilter::from_iter(vec!["merged:origin".parse::<DeleteRange>()?]),
+            ..test_default_param()
+        },
+    )?;
+    let expected_locals: HashSet<_> = plan
+        .locals_to_delete()
+        .into_iter()
+        .map(|local| local.short_name().to_owned())
+        .collect();
+    let expected_remotes: HashSet<_> = plan
+        .remotes_to_delete(&git.repo)?
+        .into_iter()
+        .map(|remote| format!("{}/{}", remote.remote, remote.short_name()))
+        .collect();
+    assert_eq!(deleted_locals, expected_locals);
+    assert_eq!(deleted_remotes, expected_remotes);
+    assert_eq!(deleted_locals, set! {"merged".to_owned()});
+    assert!(kept.contains("protected"));
+    Ok(())
+}
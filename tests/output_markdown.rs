@@ -0,0 +1,106 @@
+mod fixture;
+
+use std::process::Command;
+
+use anyhow::Result;
+use git2::{BranchType, Repository};
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            for NAME in "merged|fixed" protected; do
+                git checkout -b "\$NAME" master
+                touch "\$NAME"
+                git add "\$NAME"
+                git commit -m "Add \$NAME"
+                git push -u origin "\$NAME"
+            done
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge "merged|fixed" --no-ff
+            git merge protected --no-ff
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_markdown() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm", "--delete", "merged:origin"])
+        .args(&["--protected", "protected", "--output", "markdown"])
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(output.status.success());
+
+    let markdown = String::from_utf8(output.stdout)?;
+    // The pipe would end the table cell otherwise.
+    assert!(markdown.starts_with(
+        r#"| Branch | Type | Classification |
+| --- | --- | --- |
+| `merged\|fixed` | local | merged local |
+| `merged\|fixed` | remote `origin` | merged remote tracking |
+
+<details>
+<summary>Kept back branches ("#
+    ));
+    assert!(markdown.contains("- `protected` (local): protected by a pattern `protected`\n"));
+    assert!(
+        markdown.contains("- `protected` (remote `origin`): protected by a pattern `protected`\n")
+    );
+    assert!(markdown.ends_with("\n</details>\n"));
+    Ok(())
+}
+
+#[test]
+fn test_markdown_stale_tracking() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        local <<EOF
+            git push origin master:stale
+            git fetch
+        EOF
+        "#,
+    )?;
+    let origin = Repository::open(guard.working_directory().join("../origin"))?;
+    origin.find_branch("stale", BranchType::Local)?.delete()?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&[
+            "--no-update",
+            "--no-confirm",
+            "--delete",
+            "stale-tracking:origin",
+        ])
+        .args(&["--output", "markdown"])
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(output.status.success());
+
+    // Only the remote tracking branch is deleted, so it isn't a deletion on the remote.
+    let markdown = String::from_utf8(output.stdout)?;
+    assert!(
+        markdown.contains("| `stale` | remote `origin` (local only) | stale remote tracking |\n")
+    );
+    Ok(())
+}
@@ -0,0 +1,124 @@
+mod fixture;
+
+use std::process::Command;
+
+use anyhow::Result;
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            for NAME in "merged&fixed" protected; do
+                git checkout -b "\$NAME" master
+                touch "\$NAME"
+                git add "\$NAME"
+                git commit -m "Add \$NAME"
+                git push -u origin "\$NAME"
+            done
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge "merged&fixed" --no-ff
+            git merge protected --no-ff
+        EOF
+        "#,
+    )
+}
+
+/// Checks that the tags are balanced and every `&` starts an entity.
+fn assert_well_formed(xml: &str) {
+    let mut stack = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        assert_text(&rest[..start]);
+        let end = rest[start..].find('>').expect("unclosed tag") + start;
+        let tag = &rest[start + 1..end];
+        rest = &rest[end + 1..];
+
+        if tag.starts_with('?') {
+            assert!(tag.ends_with('?'), "{}", tag);
+        } else if let Some(name) = tag.strip_prefix('/') {
+            assert_eq!(stack.pop(), Some(name.to_owned()));
+        } else {
+            let mut parts = tag.splitn(2, ' ');
+            let name = parts.next().unwrap();
+            assert_text(parts.next().unwrap_or(""));
+            if !tag.ends_with('/') {
+                stack.push(name.to_owned());
+            }
+        }
+    }
+    assert_text(rest);
+    assert!(stack.is_empty(), "unclosed {:?}", stack);
+}
+
+fn assert_text(text: &str) {
+    assert!(!text.contains('<') && !text.contains('>'), "{}", text);
+    for (i, _) in text.match_indices('&') {
+        let entity = &text[i..];
+        assert!(
+            ["&amp;", "&lt;", "&gt;", "&quot;", "&apos;"]
+                .iter()
+                .any(|e| entity.starts_with(e)),
+            "{}",
+            text
+        );
+    }
+}
+
+#[test]
+fn test_xml() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&[
+            "--no-update",
+            "--no-confirm",
+            "--delete",
+            "merged:origin",
+            "--protected",
+            "protected",
+            "--output",
+            "xml",
+        ])
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(output.status.success());
+
+    let xml = String::from_utf8(output.stdout)?;
+    assert_well_formed(&xml);
+    assert!(xml.starts_with(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<trim-plan>
+  <delete>
+    <local classification="merged local">merged&amp;fixed</local>
+    <remote remote="origin" classification="merged remote tracking">merged&amp;fixed</remote>
+  </delete>
+  <kept-back>
+"#
+    ));
+    assert!(xml.contains(
+        r#"    <local reason="protected by a pattern `protected`">protected</local>
+"#
+    ));
+    assert!(xml.contains(
+        r#"    <remote remote="origin" reason="protected by a pattern `protected`">protected</remote>
+"#
+    ));
+    Ok(())
+}
@@ -0,0 +1,95 @@
+mod fixture;
+
+use anyhow::Result;
+use git2::{ErrorCode, Repository};
+
+use git_trim::{delete_local_branches, delete_remote_tracking_branches};
+use git_trim::{LocalBranch, RemoteTrackingBranch};
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            for NAME in feature-1 feature-2; do
+                git checkout -b \$NAME master
+                touch \$NAME
+                git add \$NAME
+                git commit -m "Add \$NAME"
+                git push -u origin \$NAME
+            done
+            git checkout master
+            git pack-refs --all
+        EOF
+        "#,
+    )
+}
+
+fn assert_deleted(repo: &Repository, refname: &str) {
+    match repo.find_reference(refname) {
+        Err(err) if err.code() == ErrorCode::NotFound => {}
+        Err(err) => panic!("{}", err),
+        Ok(_) => panic!("{} is not deleted", refname),
+    }
+}
+
+#[test]
+fn test_delete_packed_refs() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let repo = Repository::open(guard.working_directory())?;
+    let git_dir = repo.path().to_owned();
+    assert!(!git_dir.join("refs/heads/feature-1").exists());
+    assert!(!git_dir.join("refs/remotes/origin/feature-1").exists());
+
+    delete_local_branches(&repo, &[&LocalBranch::new("refs/heads/feature-1")], false)?;
+    delete_remote_tracking_branches(
+        &repo,
+        &[&RemoteTrackingBranch::new("refs/remotes/origin/feature-1")],
+        false,
+    )?;
+
+    assert_deleted(&repo, "refs/heads/feature-1");
+    assert_deleted(&repo, "refs/remotes/origin/feature-1");
+    assert!(!git_dir.join("refs/heads/feature-1").exists());
+    assert!(!git_dir.join("refs/remotes/origin/feature-1").exists());
+
+    let packed_refs = std::fs::read_to_string(git_dir.join("packed-refs"))?;
+    assert!(!packed_refs.contains("refs/heads/feature-1"));
+    assert!(!packed_refs.contains("refs/remotes/origin/feature-1"));
+    assert!(packed_refs.contains("refs/heads/feature-2"));
+    assert!(repo.find_reference("refs/heads/feature-2").is_ok());
+    Ok(())
+}
+
+#[test]
+fn test_delete_non_branch() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let repo = Repository::open(guard.working_directory())?;
+    let commit = repo.refname_to_id("refs/heads/feature-1")?;
+    repo.reference("refs/tags/v1", commit, false, "test")?;
+
+    // The fields are public, so nothing stops a ref outside of `refs/heads/` from getting here.
+    let tag = LocalBranch {
+        refname: "refs/tags/v1".to_owned(),
+    };
+    let err = delete_local_branches(&repo, &[&tag], false)
+        .err()
+        .expect("not a branch");
+    assert_eq!(format!("{}", err), "refs/tags/v1 is not a branch");
+    assert!(repo.find_reference("refs/tags/v1").is_ok());
+    Ok(())
+}
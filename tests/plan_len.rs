@@ -0,0 +1,110 @@
+mod fixture;
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::iter::FromIterator;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{get_trim_plan, Git, PlanParam};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+
+            git checkout -b feature
+            touch awesome-patch
+            git add awesome-patch
+            git commit -m "Awesome patch"
+            git push -u origin feature
+            git checkout master
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_empty() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    assert!(plan.is_empty());
+    assert_eq!(plan.len(), 0);
+    assert_eq!(plan.local_len(), 0);
+    assert_eq!(plan.remote_len(), 0);
+    assert_eq!(plan.len_by_category(), BTreeMap::new());
+    Ok(())
+}
+
+#[test]
+fn test_nonempty() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        origin <<EOF
+            git merge feature --no-ff
+        EOF
+        local <<EOF
+            git fetch origin
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    assert!(!plan.is_empty());
+    assert_eq!(plan.len(), 2);
+    assert_eq!(plan.local_len(), 1);
+    assert_eq!(plan.remote_len(), 1);
+    assert_eq!(
+        plan.len_by_category(),
+        BTreeMap::from_iter(vec![("merged local", 1), ("merged remote tracking", 1)]),
+    );
+    Ok(())
+}
+
+#[test]
+fn test_empty_after_keep_back() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        origin <<EOF
+            git merge feature --no-ff
+        EOF
+        local <<EOF
+            git fetch origin
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            protected_patterns: vec!["feature"],
+            ..test_default_param()
+        },
+    )?;
+    assert!(plan.is_empty());
+    assert_eq!(plan.len(), 0);
+    assert!(!plan.preserved.is_empty());
+    Ok(())
+}
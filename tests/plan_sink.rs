@@ -0,0 +1,123 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::porcelain_outputs::{emit, PlanSink};
+use git_trim::{
+    get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam, Preserved, RemoteBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            for NAME in merged another protected; do
+                git checkout -b \$NAME master
+                touch \$NAME
+                git add \$NAME
+                git commit -m "Add \$NAME"
+                git push -u origin \$NAME
+            done
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge merged --no-ff
+            git merge another --no-ff
+            git merge protected --no-ff
+        EOF
+        "#,
+    )
+}
+
+#[derive(Default)]
+struct MockSink {
+    events: Vec<String>,
+}
+
+impl PlanSink for MockSink {
+    fn on_delete_local(&mut self, branch: &ClassifiedBranch, local: &LocalBranch) -> Result<()> {
+        self.events.push(format!(
+            "delete local {} ({})",
+            local.short_name(),
+            branch.category()
+        ));
+        Ok(())
+    }
+
+    fn on_delete_remote(&mut self, branch: &ClassifiedBranch, remote: &RemoteBranch) -> Result<()> {
+        self.events.push(format!(
+            "delete remote {}/{} ({})",
+            remote.remote,
+            remote.short_name(),
+            branch.category()
+        ));
+        Ok(())
+    }
+
+    fn on_kept_back(
+        &mut self,
+        preserved: &Preserved,
+        name: &str,
+        remote: Option<&str>,
+    ) -> Result<()> {
+        let name = match remote {
+            Some(remote) => format!("{}/{}", remote, name),
+            None => name.to_owned(),
+        };
+        self.events
+            .push(format!("keep {} ({})", name, preserved.reason));
+        Ok(())
+    }
+}
+
+#[test]
+fn test_plan_sink() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            protected_patterns: vec!["protected"],
+            ..test_default_param()
+        },
+    )?;
+
+    let mut sink = MockSink::default();
+    emit(&plan, &git.repo, &mut sink)?;
+
+    assert_eq!(
+        &sink.events[..4],
+        &[
+            "delete local another (merged local)",
+            "delete local merged (merged local)",
+            "delete remote origin/another (merged remote tracking)",
+            "delete remote origin/merged (merged remote tracking)",
+        ][..],
+    );
+    let kept = &sink.events[4..];
+    assert!(kept.contains(&"keep master (base)".to_owned()));
+    assert!(kept.contains(&"keep protected (protected by a pattern `protected`)".to_owned()));
+    assert!(kept
+        .iter()
+        .all(|event| event.starts_with("keep ") && !event.contains("merged")));
+    Ok(())
+}
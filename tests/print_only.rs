@@ -0,0 +1,92 @@
+mod fixture;
+
+use std::collections::BTreeMap;
+use std::process::Command;
+
+use anyhow::Result;
+use git2::Repository;
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            for NAME in merged gone; do
+                git checkout -b \$NAME master
+                touch \$NAME
+                git add \$NAME
+                git commit -m "Add \$NAME"
+                git push -u origin \$NAME
+            done
+            git checkout master
+        EOF
+        # `git remote update --prune` would remove `origin/gone`
+        origin <<EOF
+            git merge merged --no-ff
+            git merge gone --no-ff
+            git branch -D gone
+        EOF
+        local <<EOF
+            git fetch origin master
+        EOF
+        "#,
+    )
+}
+
+fn refs(repo: &Repository) -> Result<BTreeMap<String, String>> {
+    let mut result = BTreeMap::new();
+    for reference in repo.references()? {
+        let reference = reference?;
+        if let (Some(name), Some(target)) = (reference.name(), reference.target()) {
+            result.insert(name.to_owned(), target.to_string());
+        }
+    }
+    Ok(result)
+}
+
+#[test]
+fn test_print_modes_keep_refs() -> Result<()> {
+    let modes: &[&[&str]] = &[
+        &["--porcelain", "local"],
+        &["--porcelain", "remote"],
+        &["--porcelain", "json"],
+        &["--output", "jsonl"],
+        &["--output", "xml"],
+        &["--dump-classifications"],
+        &["--compare-base", "master"],
+    ];
+    for mode in modes {
+        let guard = fixture().prepare("local", r#""#)?;
+        let repo = Repository::open(guard.working_directory())?;
+        let before = refs(&repo)?;
+        assert!(before.contains_key("refs/remotes/origin/gone"));
+
+        let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+            .args(&["--no-confirm", "--delete", "merged:*,stale-tracking"])
+            .args(*mode)
+            .current_dir(guard.working_directory())
+            .output()?;
+        assert!(
+            output.status.success(),
+            "{:?}: {}",
+            mode,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(refs(&repo)?, before, "{:?}", mode);
+    }
+    Ok(())
+}
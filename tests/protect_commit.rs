@@ -0,0 +1,97 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{
+    get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam, RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            for NAME in deploy-42 feature; do
+                git checkout -b \$NAME master
+                touch \$NAME
+                git add \$NAME
+                git commit -m "Add \$NAME"
+                git push -u origin \$NAME
+            done
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge deploy-42 --no-ff
+            git merge feature --no-ff
+        EOF
+        local <<EOF
+            git fetch origin
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_protect_commit() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let repo = Repository::open(guard.working_directory())?;
+    let deployed = repo.refname_to_id("refs/heads/deploy-42")?.to_string();
+    let git = Git::try_from(repo)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            protected_commits: vec![&deployed[..7]],
+            ..test_default_param()
+        },
+    )?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/feature")),
+        },
+    );
+    let preserved = plan
+        .get_preserved_local(&LocalBranch::new("refs/heads/deploy-42"))
+        .expect("preserved");
+    assert_eq!(preserved.reason, "tip matches protected commit");
+    let preserved = plan
+        .get_preserved_upstream(&RemoteTrackingBranch::new("refs/remotes/origin/deploy-42"))
+        .expect("preserved");
+    assert_eq!(preserved.reason, "tip matches protected commit");
+    Ok(())
+}
+
+#[test]
+fn test_unresolved_commit() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let result = get_trim_plan(
+        &git,
+        &PlanParam {
+            protected_commits: vec!["0000000000000000000000000000000000000000"],
+            ..test_default_param()
+        },
+    );
+    assert!(result.is_err());
+    Ok(())
+}
@@ -0,0 +1,80 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{
+    get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam, RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+            git branch staging
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            for NAME in staged shipped; do
+                git checkout -b \$NAME master
+                touch \$NAME
+                git add \$NAME
+                git commit -m "Add \$NAME"
+                git push -u origin \$NAME
+            done
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge staged --no-ff
+            git merge shipped --no-ff
+            git checkout staging
+            git merge staged --no-ff
+            git checkout master
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_protect_merged_into() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            protect_merged_into: vec!["origin/staging"],
+            ..test_default_param()
+        },
+    )?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/shipped")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/shipped")),
+        },
+    );
+    let preserved = plan
+        .get_preserved_local(&LocalBranch::new("refs/heads/staged"))
+        .expect("preserved");
+    assert_eq!(preserved.reason, "still merged into origin/staging");
+    let preserved = plan
+        .get_preserved_upstream(&RemoteTrackingBranch::new("refs/remotes/origin/staged"))
+        .expect("preserved");
+    assert_eq!(preserved.reason, "still merged into origin/staging");
+    Ok(())
+}
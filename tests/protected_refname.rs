@@ -0,0 +1,95 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{
+    get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam, RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            git checkout -b main
+            touch awesome-patch
+            git add awesome-patch
+            git commit -m "Awesome patch"
+            git push -u origin main
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge main --no-ff
+        EOF
+        local <<EOF
+            git fetch origin
+        EOF
+        "#,
+    )
+}
+
+fn param(protected: &'static str) -> PlanParam<'static> {
+    PlanParam {
+        protected_patterns: vec![protected],
+        ..test_default_param()
+    }
+}
+
+#[test]
+fn test_unprotected() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/main")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/main")),
+        },
+    );
+    Ok(())
+}
+
+#[test]
+fn test_short_and_full_names_protect_the_same() -> Result<()> {
+    for protected in &["main", "refs/heads/main"] {
+        let guard = fixture().prepare("local", r#""#)?;
+
+        let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+        let plan = get_trim_plan(&git, &param(protected))?;
+        assert_eq!(
+            plan.to_delete,
+            set! {
+                ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/main")),
+            },
+            "protected by {}",
+            protected,
+        );
+        let preserved = plan
+            .get_preserved_local(&LocalBranch::new("refs/heads/main"))
+            .expect("preserved");
+        assert_eq!(
+            preserved.reason,
+            format!("protected by a pattern `{}`", protected)
+        );
+    }
+    Ok(())
+}
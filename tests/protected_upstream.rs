@@ -0,0 +1,81 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+            git checkout -b release
+            touch release-note
+            git add release-note
+            git commit -m "Release note"
+            git checkout master
+            git merge release --no-ff
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            git checkout -b my-release origin/release
+            git checkout master
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_without_keep_if_protected_upstream() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            protected_patterns: vec!["origin/release"],
+            ..test_default_param()
+        },
+    )?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/my-release")),
+        },
+    );
+    Ok(())
+}
+
+#[test]
+fn test_keep_if_protected_upstream() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            protected_patterns: vec!["origin/release"],
+            keep_if_protected_upstream: true,
+            ..test_default_param()
+        },
+    )?;
+    assert_eq!(plan.to_delete, set! {});
+    assert!(plan.preserved.iter().any(|preserved| preserved.branch
+        == ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/my-release"))
+        && preserved.reason == "upstream is protected"));
+    Ok(())
+}
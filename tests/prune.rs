@@ -0,0 +1,80 @@
+mod fixture;
+
+use std::process::Command;
+
+use anyhow::Result;
+use git2::{BranchType, Repository};
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            git checkout -b feature
+            touch awesome-patch
+            git add awesome-patch
+            git commit -m "Awesome patch"
+            git push -u origin feature
+            git push origin master:other
+            git checkout master
+        EOF
+        "#,
+    )
+}
+
+fn stray_locals(working_directory: &std::path::Path, prune: bool) -> Result<String> {
+    let mut args = vec![
+        "--no-update",
+        "--no-confirm",
+        "--delete",
+        "stray",
+        "--porcelain",
+        "local",
+    ];
+    if prune {
+        args.push("--prune");
+    }
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&args)
+        .current_dir(working_directory)
+        .output()?;
+    assert!(output.status.success());
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+#[test]
+fn test_prune() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    // Delete the remote branch after the fixture's `git remote update --prune`
+    // to leave a stale remote tracking branch.
+    let origin = Repository::open(guard.working_directory().join("../origin"))?;
+    origin.find_branch("feature", BranchType::Local)?.delete()?;
+
+    assert_eq!(stray_locals(&guard.working_directory(), false)?, "");
+    assert_eq!(stray_locals(&guard.working_directory(), true)?, "feature\n");
+
+    let local = Repository::open(guard.working_directory())?;
+    assert!(local
+        .find_branch("origin/feature", BranchType::Remote)
+        .is_err());
+    assert!(local
+        .find_branch("origin/other", BranchType::Remote)
+        .is_ok());
+    assert!(origin.find_branch("other", BranchType::Local).is_ok());
+    Ok(())
+}
@@ -0,0 +1,134 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{
+    get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam, RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone --bare origin fork
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git remote add fork ../fork
+            git fetch fork
+            git config remote.pushdefault fork
+            git checkout -b feature master
+            touch feature
+            git add feature
+            git commit -m "Add feature"
+            git push -u origin feature
+            git push fork feature
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge feature --no-ff
+            git branch -D feature
+        EOF
+        local <<EOF
+            git fetch --all --prune
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_push_only_upstream_message() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    let local = ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature"));
+    let push = ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new(
+        "refs/remotes/fork/feature",
+    ));
+    assert!(plan.to_delete.contains(&local));
+    assert!(plan.to_delete.contains(&push));
+    assert_eq!(
+        plan.reason(&local),
+        "upstream is gone; merged status is inferred from the push upstream only",
+    );
+    Ok(())
+}
+
+#[test]
+fn test_cautious_push_only() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            cautious_push_only: true,
+            ..test_default_param()
+        },
+    )?;
+    assert!(plan
+        .to_delete
+        .contains(&ClassifiedBranch::MergedLocal(LocalBranch::new(
+            "refs/heads/feature"
+        ))));
+    let preserved = plan
+        .get_preserved_upstream(&RemoteTrackingBranch::new("refs/remotes/fork/feature"))
+        .expect("the push upstream is kept back");
+    assert_eq!(
+        preserved.reason,
+        "merged status is inferred from the push upstream only"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_cautious_push_only_diverged() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        local <<EOF
+            git checkout -b extra feature
+            touch extra
+            git add extra
+            git commit -m "Extra"
+            git push fork extra:feature
+            git checkout master
+            git branch -D extra
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            cautious_push_only: true,
+            ..test_default_param()
+        },
+    )?;
+    // The diverged push upstream is kept back, but the merged local is still deleted.
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature")),
+        },
+    );
+    assert!(plan
+        .get_preserved_upstream(&RemoteTrackingBranch::new("refs/remotes/fork/feature"))
+        .is_some());
+    Ok(())
+}
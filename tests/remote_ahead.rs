@@ -0,0 +1,91 @@
+mod fixture;
+
+use std::convert::TryFrom;
+use std::iter::FromIterator;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::args::{DeleteFilter, DeleteRange};
+use git_trim::{
+    get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam, RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            git checkout -b feature
+            touch awesome-patch
+            git add awesome-patch
+            git commit -m "Awesome patch"
+            git push -u origin feature
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge feature --no-ff
+
+            # Someone pushed directly after the merge
+            git checkout feature
+            touch another-patch
+            git add another-patch
+            git commit -m "Another patch"
+            git checkout master
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_remote_ahead_is_kept() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            protected_patterns: vec!["*/master"],
+            delete: DeleteFilter::from_iter(vec![
+                "merged:origin".parse::<DeleteRange>()?,
+                "diverged:origin".parse::<DeleteRange>()?,
+            ]),
+            keep_remote_ahead: true,
+            ..test_default_param()
+        },
+    )?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature")),
+        },
+    );
+
+    let preserved = plan
+        .preserved
+        .iter()
+        .find(|preserved| {
+            preserved.branch
+                == ClassifiedBranch::DivergedRemoteTracking {
+                    local: LocalBranch::new("refs/heads/feature"),
+                    upstream: RemoteTrackingBranch::new("refs/remotes/origin/feature"),
+                }
+        })
+        .expect("the upstream is kept");
+    assert_eq!(preserved.reason, "remote has commits not in local or base");
+    Ok(())
+}
@@ -0,0 +1,124 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{
+    get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam, RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            git checkout -b feature
+            for NAME in first second third; do
+                touch \$NAME
+                git add \$NAME
+                git commit -m "Add \$NAME"
+            done
+            git push -u origin feature
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge feature --squash && git commit --no-edit
+        EOF
+        local <<EOF
+            git fetch origin
+        EOF
+        "#,
+    )
+}
+
+fn param(remote_ahead_guard: Option<usize>) -> PlanParam<'static> {
+    PlanParam {
+        remote_ahead_guard,
+        ..test_default_param()
+    }
+}
+
+// The remote branch has 3 commits after the squash merge, which are in neither the base nor the local.
+const PUSHED_AFTER_MERGE: &str = r#"
+    origin <<EOF
+        git checkout feature
+        for NAME in fourth fifth sixth; do
+            touch \$NAME
+            git add \$NAME
+            git commit -m "Add \$NAME"
+        done
+        git checkout master
+    EOF
+    local <<EOF
+        git fetch origin
+    EOF
+"#;
+
+#[test]
+fn test_squash_merged_commits_are_not_counted() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &param(Some(0)))?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/feature")),
+        },
+    );
+    Ok(())
+}
+
+#[test]
+fn test_within_guard() -> Result<()> {
+    let guard = fixture().prepare("local", PUSHED_AFTER_MERGE)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &param(Some(3)))?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::DivergedRemoteTracking {
+                local: LocalBranch::new("refs/heads/feature"),
+                upstream: RemoteTrackingBranch::new("refs/remotes/origin/feature"),
+            },
+        },
+    );
+    Ok(())
+}
+
+#[test]
+fn test_ahead_of_guard() -> Result<()> {
+    let guard = fixture().prepare("local", PUSHED_AFTER_MERGE)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &param(Some(2)))?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature")),
+        },
+    );
+    let preserved = plan
+        .get_preserved_upstream(&RemoteTrackingBranch::new("refs/remotes/origin/feature"))
+        .expect("preserved");
+    assert_eq!(preserved.reason, "remote is 3 commit(s) ahead of the base");
+    Ok(())
+}
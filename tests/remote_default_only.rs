@@ -0,0 +1,115 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{
+    get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam, RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init upstream
+        upstream <<EOF
+            git config user.name "UpstreamTest"
+            git config user.email "upstream@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone upstream origin -o upstream
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            git config remote.pushdefault upstream
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            git remote add upstream ../upstream
+            git fetch upstream
+            git branch -u upstream/master master
+
+            git checkout -b feature
+            touch awesome-patch
+            git add awesome-patch
+            git commit -m "Awesome patch"
+            git push -u origin feature
+            git checkout master
+        EOF
+        # A maintainer's branch on the upstream
+        upstream <<EOF
+            git checkout -b fix
+            touch fix-patch
+            git add fix-patch
+            git commit -m "Fix patch"
+            git checkout master
+            git merge fix --no-ff
+        EOF
+        origin <<EOF
+            git push upstream feature:refs/pull/1/head
+        EOF
+        upstream <<EOF
+            git merge refs/pull/1/head
+        EOF
+        local <<EOF
+            git fetch upstream
+            git branch --track fix upstream/fix
+        EOF
+        "#,
+    )
+}
+
+fn param(remote_default_only: bool) -> PlanParam<'static> {
+    PlanParam {
+        remote_default_only,
+        ..test_default_param()
+    }
+}
+
+#[test]
+fn test_all_remotes() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &param(false))?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/feature")),
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/fix")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/upstream/fix")),
+        },
+    );
+    Ok(())
+}
+
+#[test]
+fn test_remote_default_only() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &param(true))?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/feature")),
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/fix")),
+        },
+    );
+    let preserved = plan
+        .get_preserved_upstream(&RemoteTrackingBranch::new("refs/remotes/upstream/fix"))
+        .expect("preserved");
+    assert_eq!(preserved.reason, "fetch-only remote");
+    Ok(())
+}
@@ -0,0 +1,67 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{Git, LocalBranch, RemoteRefspecs};
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+            for i in \$(seq 10); do
+                git branch feature-\$i
+            done
+        EOF
+        git clone origin local
+        local <<EOF
+            for i in \$(seq 10); do
+                git branch --track feature-\$i origin/feature-\$i
+            done
+            git config branch.gone.remote ../nowhere
+            git config branch.gone.merge refs/heads/gone
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_looked_up_once_per_remote() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let refspecs = RemoteRefspecs::default();
+    for i in 1..=10 {
+        let local = LocalBranch::new(&format!("refs/heads/feature-{}", i));
+        assert_eq!(
+            local.fetch_upstream_with(&git.repo, &git.config, &refspecs)?,
+            local.fetch_upstream(&git.repo, &git.config)?,
+        );
+        assert_eq!(
+            local.push_upstreams_with(&git.repo, &git.config, &refspecs)?,
+            local.push_upstreams(&git.repo, &git.config)?,
+        );
+    }
+    assert_eq!(refspecs.lookups(), 1);
+
+    // A missing remote is cached as well.
+    let gone = LocalBranch::new("refs/heads/gone");
+    for _ in 0..2 {
+        assert_eq!(
+            gone.fetch_upstream_with(&git.repo, &git.config, &refspecs)?,
+            gone.fetch_upstream(&git.repo, &git.config)?,
+        );
+    }
+    assert_eq!(refspecs.lookups(), 2);
+    Ok(())
+}
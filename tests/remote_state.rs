@@ -0,0 +1,87 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::porcelain_outputs::print_json_lines;
+use git_trim::{get_trim_plan, Git, LocalBranch, RemoteState};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            for NAME in deleted present; do
+                git checkout -b \$NAME master
+                touch \$NAME
+                git add \$NAME
+                git commit -m "Add \$NAME"
+                git push -u origin \$NAME
+            done
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge deleted --no-ff
+            git merge present --no-ff
+            git branch -d deleted
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_remote_state() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    assert_eq!(
+        plan.remote_state(&LocalBranch::new("refs/heads/deleted")),
+        Some(RemoteState::Deleted),
+    );
+    assert_eq!(
+        plan.remote_state(&LocalBranch::new("refs/heads/present")),
+        Some(RemoteState::Present),
+    );
+    assert_eq!(
+        plan.remote_state(&LocalBranch::new("refs/heads/master")),
+        None
+    );
+
+    let mut output = Vec::new();
+    print_json_lines(&plan, &git.repo, &mut output)?;
+    let mut states = Vec::new();
+    for line in String::from_utf8(output)?.lines() {
+        let line: serde_json::Value = serde_json::from_str(line)?;
+        if line["action"] == "delete" && line["remote"].is_null() {
+            states.push((
+                line["name"].as_str().unwrap().to_owned(),
+                line["remote_state"].as_str().unwrap().to_owned(),
+            ));
+        }
+    }
+    assert_eq!(
+        states,
+        vec![
+            ("deleted".to_owned(), "deleted".to_owned()),
+            ("present".to_owned(), "present".to_owned()),
+        ],
+    );
+    Ok(())
+}
@@ -0,0 +1,74 @@
+mod fixture;
+
+use std::process::Command;
+
+use anyhow::Result;
+use git2::Repository;
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            for NAME in feature collision; do
+                git checkout -b \$NAME master
+                touch \$NAME
+                git add \$NAME
+                git commit -m "Add \$NAME"
+                git push -u origin \$NAME
+            done
+            git checkout master
+            git branch archive/collision master
+        EOF
+        origin <<EOF
+            git merge feature --no-ff
+            git merge collision --no-ff
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_rename_archive() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let repo = Repository::open(guard.working_directory())?;
+    let feature = repo.refname_to_id("refs/heads/feature")?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm", "--rename-archive", "archive"])
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(output.status.success());
+
+    assert!(repo.find_reference("refs/heads/feature").is_err());
+    assert_eq!(repo.refname_to_id("refs/heads/archive/feature")?, feature);
+    assert!(repo.reflog("refs/heads/archive/feature")?.len() > 0);
+    // The branch config moves along, so it still tracks its upstream.
+    let config = repo.config()?;
+    assert_eq!(
+        config.get_string("branch.archive/feature.merge")?,
+        "refs/heads/feature"
+    );
+    assert!(config.get_string("branch.feature.merge").is_err());
+
+    // The archive of `collision` is taken, so it is skipped without failing the run.
+    assert!(repo.find_reference("refs/heads/collision").is_ok());
+    assert!(String::from_utf8(output.stderr)?
+        .contains("Skip renaming branch collision since archive/collision already exists."));
+    Ok(())
+}
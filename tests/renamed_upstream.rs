@@ -0,0 +1,101 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            for NAME in renamed rebased gone; do
+                git checkout -b \$NAME master
+                touch \$NAME
+                git add \$NAME
+                git commit -m "Add \$NAME"
+                git push -u origin \$NAME
+            done
+            git checkout master
+        EOF
+        origin <<EOF
+            touch other
+            git add other
+            git commit -m "Add other"
+            git branch -m renamed renamed-v2
+            git checkout -b rebased-v2 master
+            git cherry-pick rebased
+            git checkout master
+            git branch -D rebased gone
+        EOF
+        local <<EOF
+            git fetch --prune
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_renamed_upstream_is_stray_by_default() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::Stray(LocalBranch::new("refs/heads/renamed")),
+            ClassifiedBranch::Stray(LocalBranch::new("refs/heads/rebased")),
+            ClassifiedBranch::Stray(LocalBranch::new("refs/heads/gone")),
+        },
+    );
+    Ok(())
+}
+
+#[test]
+fn test_detect_renamed_upstream() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            detect_renamed_upstream: true,
+            ..test_default_param()
+        },
+    )?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::Stray(LocalBranch::new("refs/heads/gone")),
+        },
+    );
+    assert_eq!(
+        plan.get_preserved_local(&LocalBranch::new("refs/heads/renamed"))
+            .map(|preserved| preserved.reason.as_str()),
+        Some("upstream renamed to origin/renamed-v2"),
+    );
+    assert_eq!(
+        plan.get_preserved_local(&LocalBranch::new("refs/heads/rebased"))
+            .map(|preserved| preserved.reason.as_str()),
+        Some("upstream renamed to origin/rebased-v2"),
+    );
+    Ok(())
+}
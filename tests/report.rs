@@ -0,0 +1,118 @@
+mod fixture;
+
+use std::process::Command;
+
+use anyhow::Result;
+use git2::Repository;
+use serde_json::Value;
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            for NAME in merged protected; do
+                git checkout -b \$NAME master
+                touch \$NAME
+                git add \$NAME
+                git commit -m "Add \$NAME"
+                git push -u origin \$NAME
+            done
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge merged --no-ff
+            git merge protected --no-ff
+        EOF
+        "#,
+    )
+}
+
+fn run(working_directory: &std::path::Path) -> Result<(bool, Value)> {
+    let report = working_directory.join("../report.json");
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm", "--protected", "protected"])
+        .arg("--report")
+        .arg(&report)
+        .current_dir(working_directory)
+        .output()?;
+    let report = serde_json::from_str(&std::fs::read_to_string(report)?)?;
+    Ok((output.status.success(), report))
+}
+
+fn find<'a>(entries: &'a Value, refname: &str) -> &'a Value {
+    entries
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|entry| entry["refname"] == refname)
+        .unwrap_or_else(|| panic!("{} is not in {}", refname, entries))
+}
+
+#[test]
+fn test_report() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+    let merged_oid = Repository::open(guard.working_directory())?
+        .refname_to_id("refs/heads/merged")?
+        .to_string();
+
+    let (success, report) = run(&guard.working_directory())?;
+    assert!(success);
+    assert!(report["timestamp"].as_u64().is_some());
+    assert_eq!(report["bases"], serde_json::json!(["master"]));
+    assert_eq!(report["filter"], "merged-local,merged-remote:origin");
+
+    let deleted = find(&report["deleted"], "refs/heads/merged");
+    assert_eq!(deleted["name"], "merged");
+    assert_eq!(deleted["remote"], Value::Null);
+    assert_eq!(deleted["oid"], merged_oid.as_str());
+    assert_eq!(deleted["classification"], "merged local");
+    assert_eq!(deleted["status"], "deleted");
+
+    let kept = find(&report["kept"], "refs/heads/protected");
+    assert_eq!(kept["classification"], "merged local");
+    assert_eq!(kept["reason"], "protected by a pattern `protected`");
+    Ok(())
+}
+
+#[test]
+fn test_report_failed_deletions() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        origin <<EOF
+            git config receive.denyDeletes true
+        EOF
+        "#,
+    )?;
+
+    let (success, report) = run(&guard.working_directory())?;
+    assert!(!success);
+
+    let remote = report["deleted"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|entry| entry["remote"] == "origin" && entry["name"] == "merged")
+        .expect("remote branch is in the report");
+    assert_eq!(remote["status"], "failed");
+    assert!(remote["error"].is_string());
+
+    let local = find(&report["deleted"], "refs/heads/merged");
+    assert_eq!(local["status"], "not-attempted");
+    Ok(())
+}
@@ -0,0 +1,73 @@
+mod fixture;
+
+use std::convert::TryFrom;
+use std::process::Command;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{get_duplicate_local_branches, Git, LocalBranch};
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+
+            git checkout -b feature
+            touch feature
+            git add feature
+            git commit -m "Add feature"
+            git branch feature-copy
+
+            git checkout -b unique master
+            touch unique
+            git add unique
+            git commit -m "Add unique"
+            git checkout master
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_report_duplicates() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let repo = Repository::open(guard.working_directory())?;
+    let feature = repo.refname_to_id("refs/heads/feature")?;
+    let git = Git::try_from(repo)?;
+    assert_eq!(
+        get_duplicate_local_branches(&git)?,
+        vec![(
+            feature,
+            vec![
+                LocalBranch::new("refs/heads/feature"),
+                LocalBranch::new("refs/heads/feature-copy"),
+            ],
+        )],
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--report-duplicates"])
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        format!("{} feature feature-copy\n", &feature.to_string()[..7]),
+    );
+    Ok(())
+}
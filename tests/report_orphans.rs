@@ -0,0 +1,62 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{get_orphan_local_branches, Git, LocalBranch};
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_only_branches_without_upstream_are_reported() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        local <<EOF
+            git checkout -b tracked
+            git push -u origin tracked
+
+            git checkout -b push-only master
+            git config branch.push-only.pushRemote origin
+
+            git checkout -b scratch master
+            git checkout -b another-scratch master
+            git checkout master
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let orphans = get_orphan_local_branches(&git)?;
+
+    assert_eq!(
+        orphans,
+        vec![
+            LocalBranch::new("refs/heads/another-scratch"),
+            LocalBranch::new("refs/heads/scratch"),
+        ],
+    );
+    Ok(())
+}
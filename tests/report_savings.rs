@@ -0,0 +1,96 @@
+mod fixture;
+
+use std::convert::TryFrom;
+use std::process::Command;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{get_trim_plan, Git, LocalBranch, PlanParam};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            git checkout -b merged master
+            touch merged
+            git add merged
+            git commit -m "Add merged"
+            git push -u origin merged
+
+            git checkout -b squashed master
+            touch squashed-1
+            git add squashed-1
+            git commit -m "Add squashed-1"
+            touch squashed-2
+            git add squashed-2
+            git commit -m "Add squashed-2"
+            git push -u origin squashed
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge merged --no-ff
+            git merge squashed --squash && git commit --no-edit
+            git branch -D squashed
+        EOF
+        "#,
+    )
+}
+
+fn param() -> PlanParam<'static> {
+    PlanParam {
+        protected_patterns: vec!["*/master"],
+        ..test_default_param()
+    }
+}
+
+#[test]
+fn test_unique_commits() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &param())?;
+    let unique_commits = plan.unique_commits(&git.repo)?;
+    assert_eq!(
+        unique_commits.per_branch,
+        vec![
+            (LocalBranch::new("refs/heads/merged"), 0),
+            (LocalBranch::new("refs/heads/squashed"), 2),
+        ],
+    );
+    assert_eq!(unique_commits.total, 2);
+    Ok(())
+}
+
+#[test]
+fn test_report_after_deletion() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm", "--report-savings"])
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(output.status.success());
+    let stdout = std::str::from_utf8(&output.stdout)?;
+    assert!(stdout.contains("  - squashed: 2\n"));
+    assert!(stdout.contains("Total: 2 unique commits\n"));
+
+    let repo = Repository::open(guard.working_directory())?;
+    assert!(repo.find_reference("refs/heads/squashed").is_err());
+    Ok(())
+}
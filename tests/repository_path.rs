@@ -0,0 +1,66 @@
+mod fixture;
+
+use std::process::Command;
+
+use anyhow::Result;
+use git2::{BranchType, Repository};
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            git checkout -b merged master
+            touch merged
+            git add merged
+            git commit -m "Add merged"
+            git push -u origin merged
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge merged --no-ff
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_repository_path() -> Result<()> {
+    let target = fixture().prepare("local", r#""#)?;
+    let cwd = fixture().prepare("local", r#""#)?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm", "--delete", "merged:origin"])
+        .arg("-C")
+        .arg(target.working_directory())
+        .current_dir(cwd.working_directory())
+        .output()?;
+    assert!(output.status.success());
+
+    let target_repo = Repository::open(target.working_directory())?;
+    assert!(target_repo
+        .find_branch("merged", BranchType::Local)
+        .is_err());
+    let origin = Repository::open(target.working_directory().join("../origin"))?;
+    assert!(origin.find_branch("merged", BranchType::Local).is_err());
+
+    let cwd_repo = Repository::open(cwd.working_directory())?;
+    assert!(cwd_repo.find_branch("merged", BranchType::Local).is_ok());
+    let origin = Repository::open(cwd.working_directory().join("../origin"))?;
+    assert!(origin.find_branch("merged", BranchType::Local).is_ok());
+    Ok(())
+}
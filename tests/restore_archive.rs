@@ -0,0 +1,164 @@
+mod fixture;
+
+use std::convert::TryFrom;
+use std::process::Command;
+
+use anyhow::Result;
+use git2::{BranchType, Repository};
+
+use git_trim::{
+    apply_trim_plan, get_trim_plan, list_archives, restore_archive, DeleteOptions, Git, PlanParam,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            git checkout -b feature
+            touch awesome-patch
+            git add awesome-patch
+            git commit -m "Awesome patch"
+            git push -u origin feature
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge feature --no-ff
+        EOF
+        "#,
+    )
+}
+
+const NAMESPACE: &str = "refs/trim-archive";
+
+fn archive(git: &Git) -> Result<()> {
+    let plan = get_trim_plan(
+        git,
+        &PlanParam {
+            protected_patterns: vec!["*/master"],
+            ..test_default_param()
+        },
+    )?;
+    let report = apply_trim_plan(
+        git,
+        &plan,
+        &DeleteOptions {
+            archive: Some(NAMESPACE.to_owned()),
+            ..DeleteOptions::default()
+        },
+    )?;
+    assert!(report.failures().is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_list_archives() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let local_oid = git.repo.refname_to_id("refs/heads/feature")?;
+    let remote_oid = git.repo.refname_to_id("refs/remotes/origin/feature")?;
+    archive(&git)?;
+
+    let archives = list_archives(&git.repo, NAMESPACE)?;
+    let listed: Vec<_> = archives
+        .iter()
+        .map(|archive| {
+            (
+                archive.refname.as_str(),
+                archive.branch.as_str(),
+                archive.oid,
+            )
+        })
+        .collect();
+    assert_eq!(
+        listed,
+        vec![
+            ("refs/trim-archive/feature", "refs/heads/feature", local_oid),
+            (
+                "refs/trim-archive/remotes/origin/feature",
+                "refs/remotes/origin/feature",
+                remote_oid
+            ),
+        ],
+    );
+    assert!(archives.iter().all(|archive| archive.timestamp.is_some()));
+    Ok(())
+}
+
+#[test]
+fn test_restore_archive() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let local_oid = git.repo.refname_to_id("refs/heads/feature")?;
+    archive(&git)?;
+    assert!(git.repo.find_branch("feature", BranchType::Local).is_err());
+
+    assert_eq!(
+        restore_archive(&git.repo, NAMESPACE, "feature", false, false)?,
+        local_oid
+    );
+    assert_eq!(git.repo.refname_to_id("refs/heads/feature")?, local_oid);
+
+    // It already exists now.
+    assert!(restore_archive(&git.repo, NAMESPACE, "feature", false, false).is_err());
+    restore_archive(&git.repo, NAMESPACE, "feature", true, true)?;
+    assert_eq!(git.repo.refname_to_id("refs/heads/feature")?, local_oid);
+    assert!(git
+        .repo
+        .find_reference("refs/trim-archive/feature")
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn test_restore_cli() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let local_oid = git.repo.refname_to_id("refs/heads/feature")?;
+    archive(&git)?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--restore", "feature"])
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(output.status.success());
+    assert_eq!(git.repo.refname_to_id("refs/heads/feature")?, local_oid);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--restore", "feature"])
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(!output.status.success());
+    Ok(())
+}
+
+#[test]
+fn test_restore_flags_require_restore() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    for flag in &["--force", "--drop-archive"] {
+        let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+            .args(&["--dry-run", "--no-update", "--no-confirm", flag])
+            .current_dir(guard.working_directory())
+            .output()?;
+        assert!(!output.status.success());
+    }
+    Ok(())
+}
@@ -0,0 +1,124 @@
+mod fixture;
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use anyhow::Result;
+use git2::{BranchType, Repository};
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            git checkout -b feature
+            touch awesome-patch
+            git add awesome-patch
+            git commit -m "Awesome patch"
+            git push -u origin feature
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge feature --no-ff
+        EOF
+        "#,
+    )
+}
+
+/// Install a fake `git` that fails the first `failures` pushes with `message`,
+/// and runs the real `git` otherwise.
+fn mock_git(dir: &Path, failures: usize, message: &str) -> Result<PathBuf> {
+    let real_git = Command::new("sh")
+        .args(&["-c", "command -v git"])
+        .output()?;
+    let real_git = std::str::from_utf8(&real_git.stdout)?.trim();
+
+    let bin = dir.join("bin");
+    fs::create_dir_all(&bin)?;
+    let script = bin.join("git");
+    fs::write(
+        &script,
+        format!(
+            r#"#!/bin/sh
+case " $* " in
+    *" push "*)
+        COUNT=$(cat "{count}" 2>/dev/null || echo 0)
+        echo $((COUNT + 1)) > "{count}"
+        if [ "$COUNT" -lt {failures} ]; then
+            echo "fatal: unable to access 'https://example.com/origin/': {message}" >&2
+            exit 128
+        fi
+        ;;
+esac
+exec "{real_git}" "$@"
+"#,
+            count = dir.join("push-count").display(),
+            failures = failures,
+            message = message,
+            real_git = real_git,
+        ),
+    )?;
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755))?;
+    Ok(bin)
+}
+
+fn run(working_directory: &Path, bin: &Path) -> Result<Output> {
+    let path = format!("{}:{}", bin.display(), std::env::var("PATH")?);
+    Ok(Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm", "--delete", "merged:origin"])
+        .env("PATH", path)
+        .current_dir(working_directory)
+        .output()?)
+}
+
+fn push_count(dir: &Path) -> Result<usize> {
+    Ok(fs::read_to_string(dir.join("push-count"))?.trim().parse()?)
+}
+
+#[test]
+fn test_retry_transient_error() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+    let dir = guard.working_directory().join("..");
+    let bin = mock_git(&dir, 1, "Could not resolve host: example.com")?;
+
+    let output = run(&guard.working_directory(), &bin)?;
+    assert!(output.status.success());
+    assert_eq!(push_count(&dir)?, 2);
+    let stderr = std::str::from_utf8(&output.stderr)?;
+    assert!(stderr.contains("Deleted after 1 retries: origin/feature"));
+
+    let origin = Repository::open(dir.join("origin"))?;
+    assert!(origin.find_branch("feature", BranchType::Local).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_no_retry_on_authentication_error() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+    let dir = guard.working_directory().join("..");
+    let bin = mock_git(&dir, 1, "Authentication failed")?;
+
+    let output = run(&guard.working_directory(), &bin)?;
+    assert!(!output.status.success());
+    assert_eq!(push_count(&dir)?, 1);
+
+    let origin = Repository::open(dir.join("origin"))?;
+    assert!(origin.find_branch("feature", BranchType::Local).is_ok());
+    Ok(())
+}
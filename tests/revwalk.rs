@@ -0,0 +1,80 @@
+mod fixture;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{is_merged_by_rev_list, is_merged_by_revwalk};
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+
+            git checkout -b rebased
+            touch rebased-1 && git add rebased-1 && git commit -m "Rebased 1"
+            touch rebased-2 && git add rebased-2 && git commit -m "Rebased 2"
+
+            git checkout master -b squashed
+            touch squashed-1 && git add squashed-1 && git commit -m "Squashed 1"
+            touch squashed-2 && git add squashed-2 && git commit -m "Squashed 2"
+
+            git checkout master -b merged
+            touch merged && git add merged && git commit -m "Merged"
+
+            git checkout master -b partially-picked
+            touch picked && git add picked && git commit -m "Picked"
+            touch not-picked && git add not-picked && git commit -m "Not picked"
+
+            git checkout master -b unmerged
+            touch unmerged && git add unmerged && git commit -m "Unmerged"
+
+            git checkout master
+            touch master && git add master && git commit -m "Diverge master"
+            git cherry-pick rebased~1 rebased
+            git merge --squash squashed && git commit -m "Squashed"
+            git merge --no-ff merged -m "Merge"
+            git cherry-pick partially-picked~1
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_same_as_rev_list() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+    let repo = Repository::open(guard.working_directory())?;
+
+    let branches = [
+        "rebased",
+        "squashed",
+        "merged",
+        "partially-picked",
+        "unmerged",
+    ];
+    for branch in &branches {
+        for (base, commit) in &[("master", *branch), (*branch, "master")] {
+            assert_eq!(
+                is_merged_by_revwalk(&repo, base, commit)?,
+                is_merged_by_rev_list(&repo, base, commit)?,
+                "base={} commit={}",
+                base,
+                commit,
+            );
+        }
+    }
+
+    assert!(is_merged_by_revwalk(&repo, "master", "rebased")?);
+    assert!(is_merged_by_revwalk(&repo, "master", "merged")?);
+    assert!(!is_merged_by_revwalk(&repo, "master", "squashed")?);
+    assert!(!is_merged_by_revwalk(&repo, "master", "partially-picked")?);
+    assert!(!is_merged_by_revwalk(&repo, "master", "unmerged")?);
+    Ok(())
+}
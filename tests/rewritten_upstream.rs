@@ -0,0 +1,83 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{
+    get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam, RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            printf "1\n2\n3\n4\n5\n6\n7\n8\n9\n" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            git checkout -b feature
+            sed -i -e "s/^5$/five/" README.md
+            git commit -am "Awesome patch"
+            git push -u origin feature
+            git checkout master
+        EOF
+        origin <<EOF
+            # Someone pushed to the feature branch
+            git checkout feature
+            touch another-patch
+            git add another-patch
+            git commit -m "Another patch"
+            git checkout master
+        EOF
+        local <<EOF
+            # The local branch is behind its upstream
+            git fetch origin
+        EOF
+        origin <<EOF
+            # It changes the context lines of the awesome patch, so the patch id changes on rebase.
+            sed -i -e "s/^3$/three/" README.md
+            git commit -am "Change the context"
+
+            # Rebase merge that rewrites the feature branch on the server
+            git rebase master feature
+            git checkout master
+            git merge feature --ff-only
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_rewritten_upstream() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            protected_patterns: vec!["*/master"],
+            ..test_default_param()
+        },
+    )?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/feature")),
+        },
+    );
+    Ok(())
+}
@@ -0,0 +1,59 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{
+    get_trim_plan, is_merged, ClassifiedBranch, Git, LocalBranch, RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+            git branch feature
+        EOF
+        git clone origin local
+        local <<EOF
+            git branch --track feature origin/feature
+        EOF
+        git clone --bare origin bare
+        "#,
+    )
+}
+
+#[test]
+fn test_same_as_base_is_merged() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/feature")),
+        },
+    );
+    Ok(())
+}
+
+#[test]
+fn test_same_as_base_skips_rev_list() -> Result<()> {
+    let guard = fixture().prepare("bare", r#""#)?;
+
+    // `git rev-list` can't run in a bare repository, so it fails if the check reaches there.
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    assert!(is_merged(&git, "feature", "master")?);
+    Ok(())
+}
@@ -0,0 +1,89 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{
+    get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam, RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init -b main origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+
+            git checkout -b feature
+            touch awesome-patch
+            git add awesome-patch
+            git commit -m "Awesome patch"
+            git push -u origin feature
+            git checkout main
+        EOF
+        origin <<EOF
+            git merge feature --no-ff
+        EOF
+        "#,
+    )
+}
+
+fn param(skip_defaults: bool) -> PlanParam<'static> {
+    PlanParam {
+        bases: vec!["main"],
+        skip_defaults,
+        ..test_default_param()
+    }
+}
+
+#[test]
+fn test_defaults_are_kept_back() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &param(false))?;
+    assert!(plan
+        .get_preserved_local(&LocalBranch::new("refs/heads/main"))
+        .is_some());
+    Ok(())
+}
+
+#[test]
+fn test_skip_defaults() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &param(true))?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/feature")),
+        },
+    );
+    let main = LocalBranch::new("refs/heads/main");
+    let origin_main = RemoteTrackingBranch::new("refs/remotes/origin/main");
+    assert!(plan.get_preserved_local(&main).is_none());
+    assert!(plan.get_preserved_upstream(&origin_main).is_none());
+    assert!(plan
+        .preserved
+        .iter()
+        .all(|preserved| preserved.branch.local() != Some(&main)
+            && preserved.branch.upstream() != Some(&origin_main)));
+    Ok(())
+}
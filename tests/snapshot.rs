@@ -0,0 +1,151 @@
+mod fixture;
+
+use std::convert::TryFrom;
+use std::process::Command;
+
+use anyhow::Result;
+use git2::{BranchType, Repository};
+use tempfile::tempdir;
+
+use git_trim::snapshot::Snapshot;
+use git_trim::{get_trim_plan, Git};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            for NAME in merged stray alive; do
+                git checkout -b \$NAME master
+                touch \$NAME
+                git add \$NAME
+                git commit -m "Add \$NAME"
+                git push -u origin \$NAME
+            done
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge merged --no-ff
+            git branch -D stray
+        EOF
+        local <<EOF
+            git fetch --prune
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_replay_after_mutation() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+    let scratch = tempdir()?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let original = get_trim_plan(&git, &test_default_param())?;
+    assert!(!original.to_delete.is_empty());
+    let snapshot = Snapshot::capture(&git)?;
+    snapshot.save(&scratch.path().join("snapshot.json"))?;
+
+    for name in &["merged", "stray"] {
+        git.repo.find_branch(name, BranchType::Local)?.delete()?;
+    }
+    let origin = Repository::open(guard.working_directory().join("../origin"))?;
+    origin.find_branch("alive", BranchType::Local)?.delete()?;
+    assert_ne!(
+        get_trim_plan(&git, &test_default_param())?.to_delete,
+        original.to_delete
+    );
+
+    let loaded = Snapshot::load(&scratch.path().join("snapshot.json"))?;
+    assert_eq!(loaded, snapshot);
+    let replayed = loaded.replay(&scratch.path().join("replay"))?;
+    let plan = get_trim_plan(&replayed, &test_default_param())?;
+    assert_eq!(plan.to_delete, original.to_delete);
+    assert_eq!(plan.skipped, original.skipped);
+    Ok(())
+}
+
+#[test]
+fn test_replay_cli() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+    let scratch = tempdir()?;
+    let snapshot = scratch.path().join("snapshot.json");
+
+    let trim = |args: &[&str]| -> Result<String> {
+        let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+            .args(&["--no-update", "--no-confirm", "--porcelain", "local"])
+            .args(&["--delete", "merged:origin,stray"])
+            .args(args)
+            .current_dir(guard.working_directory())
+            .output()?;
+        assert!(output.status.success());
+        Ok(String::from_utf8(output.stdout)?)
+    };
+    let original = trim(&[])?;
+    assert_eq!(original, "merged\nstray\n");
+    trim(&["--snapshot", snapshot.to_str().unwrap()])?;
+
+    let repo = Repository::open(guard.working_directory())?;
+    repo.find_branch("merged", BranchType::Local)?.delete()?;
+    assert_eq!(trim(&[])?, "stray\n");
+
+    assert_eq!(trim(&["--replay", snapshot.to_str().unwrap()])?, original);
+    // The replay doesn't delete anything.
+    assert!(repo.find_branch("stray", BranchType::Local).is_ok());
+    Ok(())
+}
+
+#[test]
+fn test_capture_linked_worktree() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        local <<EOF
+            git worktree add ../linked
+        EOF
+        "#,
+    )?;
+    let scratch = tempdir()?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let linked = Git::try_from(Repository::open(
+        guard.working_directory().join("../linked"),
+    )?)?;
+    // The objects are shared with the main repository.
+    assert_eq!(
+        Snapshot::capture(&linked)?.objects,
+        Snapshot::capture(&git)?.objects
+    );
+    Snapshot::capture(&linked)?.replay(&scratch.path().join("replay"))?;
+    Ok(())
+}
+
+#[test]
+fn test_replay_without_objects() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+    let scratch = tempdir()?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let mut snapshot = Snapshot::capture(&git)?;
+    snapshot.objects = scratch.path().join("gone");
+    let err = snapshot
+        .replay(&scratch.path().join("replay"))
+        .err()
+        .expect("the objects are gone");
+    assert!(format!("{}", err).contains("must still be there"));
+    Ok(())
+}
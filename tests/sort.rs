@@ -0,0 +1,83 @@
+mod fixture;
+
+use std::convert::TryFrom;
+use std::process::Command;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::args::SortKey;
+use git_trim::{get_trim_plan, Git, LocalBranch};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            for ENTRY in bravo:3 charlie:1 alpha:2; do
+                NAME=\${ENTRY%:*}
+                DAY=\${ENTRY#*:}
+                git checkout -b \$NAME master
+                touch \$NAME
+                git add \$NAME
+                GIT_COMMITTER_DATE="2020-01-0\$DAY 00:00:00 +0000" git commit -m "Add \$NAME"
+                git push -u origin \$NAME
+            done
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge alpha --no-ff
+            git merge bravo --no-ff
+            git merge charlie --no-ff
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_sort_by_age() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    assert_eq!(
+        plan.sorted_locals_to_delete_by(&git.repo, SortKey::Age)?
+            .into_iter()
+            .cloned()
+            .collect::<Vec<_>>(),
+        vec![
+            LocalBranch::new("refs/heads/charlie"),
+            LocalBranch::new("refs/heads/alpha"),
+            LocalBranch::new("refs/heads/bravo"),
+        ],
+    );
+
+    for (format, expected) in &[
+        ("local", "charlie\nalpha\nbravo\n"),
+        ("remote", "origin/charlie\norigin/alpha\norigin/bravo\n"),
+    ] {
+        let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+            .args(&["--no-update", "--no-confirm", "--delete", "merged:origin"])
+            .args(&["--sort", "age"])
+            .args(&["--porcelain", format])
+            .current_dir(guard.working_directory())
+            .output()?;
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8(output.stdout)?, *expected);
+    }
+    Ok(())
+}
@@ -0,0 +1,98 @@
+mod fixture;
+
+use std::convert::TryFrom;
+use std::process::Command;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{get_trim_plan, Git, LocalBranch, RemoteBranch};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            for NAME in delta alpha echo charlie bravo; do
+                git branch \$NAME master
+                git push -u origin \$NAME
+            done
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_sorted_accessors() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    let names = ["alpha", "bravo", "charlie", "delta", "echo"];
+    assert_eq!(
+        plan.sorted_locals_to_delete()
+            .into_iter()
+            .cloned()
+            .collect::<Vec<_>>(),
+        names
+            .iter()
+            .map(|name| LocalBranch::new(&format!("refs/heads/{}", name)))
+            .collect::<Vec<_>>(),
+    );
+    assert_eq!(
+        plan.sorted_remotes_to_delete(&git.repo)?,
+        names
+            .iter()
+            .map(|name| RemoteBranch {
+                remote: "origin".to_owned(),
+                refname: format!("refs/heads/{}", name),
+            })
+            .collect::<Vec<_>>(),
+    );
+    Ok(())
+}
+
+#[test]
+fn test_porcelain_output_is_stable() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    for format in &["local", "remote"] {
+        let mut outputs = Vec::new();
+        for _ in 0..5 {
+            let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+                .args(&[
+                    "--no-update",
+                    "--no-confirm",
+                    "--delete",
+                    "merged:origin",
+                    "--porcelain",
+                    format,
+                ])
+                .current_dir(guard.working_directory())
+                .output()?;
+            assert!(output.status.success());
+            outputs.push(String::from_utf8(output.stdout)?);
+        }
+        assert!(outputs.iter().all(|output| output == &outputs[0]));
+
+        let mut sorted: Vec<_> = outputs[0].lines().collect();
+        sorted.sort();
+        assert_eq!(outputs[0].lines().collect::<Vec<_>>(), sorted);
+        assert_eq!(sorted.len(), 5);
+    }
+    Ok(())
+}
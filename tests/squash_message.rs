@@ -0,0 +1,125 @@
+mod fixture;
+
+use std::convert::TryFrom;
+use std::process::Command;
+
+use anyhow::Result;
+use git2::{ObjectType, Repository};
+
+use git_trim::{get_trim_plan, Git, PlanParam, SQUASH_TEST_MESSAGE};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            git checkout -b squashed
+            touch squashed-1
+            git add squashed-1
+            git commit -m "Add squashed-1"
+            touch squashed-2
+            git add squashed-2
+            git commit -m "Add squashed-2"
+            git push -u origin squashed
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge squashed --squash && git commit --no-edit
+            git branch -D squashed
+        EOF
+        "#,
+    )
+}
+
+fn param() -> PlanParam<'static> {
+    PlanParam {
+        protected_patterns: vec!["*/master"],
+        ..test_default_param()
+    }
+}
+
+fn commit_messages(repo: &Repository) -> Result<Vec<String>> {
+    let mut oids = Vec::new();
+    repo.odb()?.foreach(|oid| {
+        oids.push(*oid);
+        true
+    })?;
+    let mut result = Vec::new();
+    for oid in oids {
+        let object = repo.find_object(oid, None)?;
+        if object.kind() == Some(ObjectType::Commit) {
+            let commit = object.peel_to_commit()?;
+            result.push(commit.message().unwrap_or_default().to_owned());
+        }
+    }
+    Ok(result)
+}
+
+#[test]
+fn test_default_message() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    get_trim_plan(&git, &param())?;
+    let messages = commit_messages(&git.repo)?;
+    assert!(messages
+        .iter()
+        .any(|message| message == SQUASH_TEST_MESSAGE));
+    Ok(())
+}
+
+#[test]
+fn test_configured_message() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        local <<EOF
+            git config trim.squashMessage "policy: throwaway"
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    get_trim_plan(&git, &param())?;
+    let messages = commit_messages(&git.repo)?;
+    assert!(messages
+        .iter()
+        .any(|message| message == "policy: throwaway"));
+    assert!(!messages
+        .iter()
+        .any(|message| message == SQUASH_TEST_MESSAGE));
+    Ok(())
+}
+
+#[test]
+fn test_message_from_env() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm", "--dry-run"])
+        .env("GIT_TRIM_SQUASH_MESSAGE", "policy: from env")
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(output.status.success());
+
+    let messages = commit_messages(&Repository::open(guard.working_directory())?)?;
+    assert!(messages.iter().any(|message| message == "policy: from env"));
+    assert!(!messages
+        .iter()
+        .any(|message| message == SQUASH_TEST_MESSAGE));
+    Ok(())
+}
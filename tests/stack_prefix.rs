@@ -0,0 +1,103 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{
+    get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam, RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            for NAME in other user/feature-1 user/feature-2 user/feature-3; do
+                git checkout -b \$NAME master
+                touch \$(basename \$NAME)
+                git add \$(basename \$NAME)
+                git commit -m "Add \$NAME"
+                git push -u origin \$NAME
+            done
+        EOF
+        origin <<EOF
+            git merge user/feature-1 --no-ff
+            git merge user/feature-2 --no-ff
+            git merge other --no-ff
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_stack_prefix() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            protected_patterns: vec!["*/master"],
+            stack_prefix: true,
+            ..test_default_param()
+        },
+    )?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/user/feature-1")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/user/feature-1")),
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/user/feature-2")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/user/feature-2")),
+        },
+    );
+
+    let preserved = plan
+        .preserved
+        .iter()
+        .find(|preserved| {
+            preserved.branch == ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/other"))
+        })
+        .expect("a branch outside the stack is kept");
+    assert_eq!(preserved.reason, "outside the stack `user/feature-`");
+    Ok(())
+}
+
+#[test]
+fn test_stack_prefix_requires_separator() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        local <<EOF
+            git checkout master
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let result = get_trim_plan(
+        &git,
+        &PlanParam {
+            stack_prefix: true,
+            ..test_default_param()
+        },
+    );
+    assert!(result.is_err());
+    Ok(())
+}
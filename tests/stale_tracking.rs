@@ -0,0 +1,112 @@
+mod fixture;
+
+use std::convert::TryFrom;
+use std::iter::FromIterator;
+use std::process::Command;
+
+use anyhow::Result;
+use git2::{BranchType, Repository};
+
+use git_trim::args::{DeleteFilter, DeleteRange};
+use git_trim::{get_trim_plan, ClassifiedBranch, Git, PlanParam, RemoteTrackingBranch};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            git checkout -b feature
+            touch awesome-patch
+            git add awesome-patch
+            git commit -m "Awesome patch"
+            git push origin feature
+            git push origin feature:other
+            git checkout master
+            git branch -D feature
+        EOF
+        "#,
+    )
+}
+
+/// Delete the remote branch after the fixture's `git remote update --prune`
+/// to leave a stale remote tracking branch.
+fn delete_remote_branch(guard: &fixture::FixtureGuard) -> Result<Repository> {
+    let origin = Repository::open(guard.working_directory().join("../origin"))?;
+    origin.find_branch("feature", BranchType::Local)?.delete()?;
+    Ok(origin)
+}
+
+#[test]
+fn test_plan() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+    delete_remote_branch(&guard)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            delete: DeleteFilter::from_iter(vec![DeleteRange::StaleTracking("origin".parse()?)]),
+            ..test_default_param()
+        },
+    )?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::StaleRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/feature")),
+        },
+    );
+
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    assert!(!plan
+        .to_delete
+        .iter()
+        .any(|branch| matches!(branch, ClassifiedBranch::StaleRemoteTracking(_))));
+    Ok(())
+}
+
+#[test]
+fn test_delete_only_under_filter() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+    let origin = delete_remote_branch(&guard)?;
+    let local = Repository::open(guard.working_directory())?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm"])
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(output.status.success());
+    assert!(local
+        .find_branch("origin/feature", BranchType::Remote)
+        .is_ok());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm", "--delete", "stale-tracking"])
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(output.status.success());
+    let stdout = std::str::from_utf8(&output.stdout)?;
+    assert!(stdout.contains("Delete stale remote tracking branches:\n  - origin/feature\n"));
+
+    assert!(local
+        .find_branch("origin/feature", BranchType::Remote)
+        .is_err());
+    assert!(local
+        .find_branch("origin/other", BranchType::Remote)
+        .is_ok());
+    assert!(origin.find_branch("other", BranchType::Local).is_ok());
+    Ok(())
+}
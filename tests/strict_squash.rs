@@ -0,0 +1,103 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{
+    get_trim_plan, ClassifiedBranch, Git, LocalBranch, PlanParam, RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+
+            git checkout -b side master
+            touch side
+            git add side
+            git commit -m "Add side"
+
+            git checkout -b feature master
+            touch feature
+            git add feature
+            git commit -m "Add feature"
+            git merge side --no-ff -m "Merge side"
+            git push -u origin feature
+            git branch -D side
+            git checkout master
+        EOF
+        "#,
+    )
+}
+
+const SQUASH_MERGED: &str = r#"
+    origin <<EOF
+        git merge --squash feature
+        git commit -m "Squash feature"
+    EOF
+    local <<EOF
+        git fetch origin
+    EOF
+"#;
+
+// The base gets the same changes independently, so its tree matches the branch by coincidence.
+// Squashing the branch would make the same patch, as it squashes the merged changes as well.
+const SAME_TREE_BY_COINCIDENCE: &str = r#"
+    origin <<EOF
+        touch feature side
+        git add feature side
+        git commit -m "Add feature and side on their own"
+    EOF
+    local <<EOF
+        git fetch origin
+    EOF
+"#;
+
+#[test]
+fn test_merge_commits_skip_squash_detection() -> Result<()> {
+    let guard = fixture().prepare("local", SAME_TREE_BY_COINCIDENCE)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    assert_eq!(plan.to_delete, set! {});
+    Ok(())
+}
+
+#[test]
+fn test_strict_squash() -> Result<()> {
+    let guard = fixture().prepare("local", SQUASH_MERGED)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(
+        &git,
+        &PlanParam {
+            strict_squash: true,
+            ..test_default_param()
+        },
+    )?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/feature")),
+        },
+    );
+    Ok(())
+}
@@ -0,0 +1,80 @@
+mod fixture;
+
+use std::process::Command;
+
+use anyhow::Result;
+use git2::{BranchType, Repository};
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init sub-origin
+        within sub-origin <<EOF
+            git config user.name "Sub Origin Test"
+            git config user.email "sub-origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+            git -c protocol.file.allow=always submodule add ../sub-origin sub
+            git commit -m "Add a submodule"
+        EOF
+        git -c protocol.file.allow=always clone --recurse-submodules origin local
+        within local/sub <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+
+            git checkout -B master origin/master
+            git checkout -b feature
+            touch awesome-patch
+            git add awesome-patch
+            git commit -m "Awesome patch"
+            git push -u origin feature
+            git checkout master
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_recurse_submodules() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        within sub-origin <<EOF
+            git merge feature --no-ff
+        EOF
+        within local/sub <<EOF
+            git fetch origin
+        EOF
+        "#,
+    )?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm", "--recurse-submodules"])
+        .current_dir(guard.working_directory())
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(output.status.success(), "{}", stdout);
+
+    // The superproject has nothing to trim, so the deletion is reported only under the submodule.
+    let (superproject, submodule) =
+        stdout.split_at(stdout.find("Submodule `sub`:").expect("submodule report"));
+    assert!(!superproject.contains("Delete merged local branches:"));
+    assert!(submodule.contains("Delete merged local branches:\n  - feature\n"));
+
+    let sub = Repository::open(guard.working_directory().join("sub"))?;
+    assert!(sub.find_branch("feature", BranchType::Local).is_err());
+    Ok(())
+}
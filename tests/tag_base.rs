@@ -0,0 +1,114 @@
+mod fixture;
+
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{
+    get_trim_plan, resolve_base, ClassifiedBranch, Git, LocalBranch, PlanParam,
+    RemoteTrackingBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+
+            git checkout -b release
+            touch release-note
+            git add release-note
+            git commit -m "Release note"
+            git checkout master
+            git merge release --no-ff
+
+            git checkout -b feature
+            touch awesome-patch
+            git add awesome-patch
+            git commit -m "Awesome patch"
+            git checkout master
+            git merge feature --no-ff
+
+            touch post-release
+            git add post-release
+            git commit -m "Post release"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git branch --track release origin/release
+            git branch --track feature origin/feature
+        EOF
+        "#,
+    )
+}
+
+fn param() -> PlanParam<'static> {
+    PlanParam {
+        bases: vec!["release"],
+        ..test_default_param()
+    }
+}
+
+fn assert_kept_back_by_tag(git: &Git) -> Result<()> {
+    let plan = get_trim_plan(git, &param())?;
+    assert_eq!(
+        plan.to_delete,
+        set! {
+            ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/feature")),
+            ClassifiedBranch::MergedRemoteTracking(RemoteTrackingBranch::new("refs/remotes/origin/feature")),
+        },
+    );
+    let preserved = plan
+        .get_preserved_local(&LocalBranch::new("refs/heads/release"))
+        .expect("preserved");
+    assert!(preserved.base);
+    let preserved = plan
+        .get_preserved_upstream(&RemoteTrackingBranch::new("refs/remotes/origin/release"))
+        .expect("preserved");
+    assert!(preserved.base);
+    Ok(())
+}
+
+#[test]
+fn test_annotated_tag_base() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        local <<EOF
+            git tag -a release -m "Release" origin/master~1
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let tagged = git.repo.revparse_single("origin/master~1")?.id();
+    assert_eq!(resolve_base(&git, "release")?.oid, tagged);
+    assert_kept_back_by_tag(&git)
+}
+
+#[test]
+fn test_lightweight_tag_base() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        local <<EOF
+            git tag release origin/master~1
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let tagged = git.repo.revparse_single("origin/master~1")?.id();
+    assert_eq!(resolve_base(&git, "release")?.oid, tagged);
+    assert_kept_back_by_tag(&git)
+}
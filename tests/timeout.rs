@@ -0,0 +1,65 @@
+mod fixture;
+
+use std::convert::TryFrom;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use git2::Repository;
+
+use git_trim::{get_trim_plan, Git, LocalBranch, PlanParam};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+            git checkout -b feature
+            touch awesome-patch
+            git add awesome-patch
+            git commit -m "Awesome patch"
+            git checkout master
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config protocol.ext.allow always
+            git fetch ../origin feature:feature
+            # The remote never responds
+            git config branch.feature.remote "ext::sleep 60"
+            git config branch.feature.merge "refs/heads/feature"
+        EOF
+        "#,
+    )
+}
+
+fn param() -> PlanParam<'static> {
+    PlanParam {
+        timeout: Some(Duration::from_secs(1)),
+        ..test_default_param()
+    }
+}
+
+#[test]
+fn test_timed_out_remote_is_unknown() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let started = Instant::now();
+    let plan = get_trim_plan(&git, &param())?;
+    assert!(started.elapsed() < Duration::from_secs(30));
+
+    assert_eq!(plan.to_delete, set! {});
+    let preserved = plan
+        .get_preserved_local(&LocalBranch::new("refs/heads/feature"))
+        .expect("preserved");
+    assert_eq!(preserved.reason, "remote state unknown (timed out)");
+    Ok(())
+}
@@ -0,0 +1,78 @@
+mod fixture;
+
+use std::process::{Command, Output};
+
+use anyhow::Result;
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            # A corrupted cache is reported with `warn!`
+            mkdir -p .git/git-trim
+            echo "corrupted" > .git/git-trim/cache.json
+        EOF
+        "#,
+    )
+}
+
+fn run(working_directory: &std::path::Path, extra_args: &[&str]) -> Result<Output> {
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm", "--cache"])
+        .args(extra_args)
+        .env_remove("RUST_LOG")
+        .current_dir(working_directory)
+        .output()?;
+    assert!(output.status.success());
+    Ok(output)
+}
+
+#[test]
+fn test_warnings_by_default() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let output = run(&guard.working_directory(), &[])?;
+    let stderr = std::str::from_utf8(&output.stderr)?;
+    assert!(stderr.contains("Ignore the corrupted cache"));
+    assert!(!stderr.contains("SEMVER"));
+    Ok(())
+}
+
+#[test]
+fn test_quiet() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let output = run(&guard.working_directory(), &["--quiet"])?;
+    let stderr = std::str::from_utf8(&output.stderr)?;
+    assert!(!stderr.contains("Ignore the corrupted cache"));
+
+    let stdout = std::str::from_utf8(&output.stdout)?;
+    assert!(stdout.contains("Branches that will remain:"));
+    Ok(())
+}
+
+#[test]
+fn test_verbose() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let output = run(&guard.working_directory(), &["-v"])?;
+    let stderr = std::str::from_utf8(&output.stderr)?;
+    assert!(stderr.contains("SEMVER"));
+    assert!(!stderr.contains("> git rev-list"));
+
+    let output = run(&guard.working_directory(), &["-vvv"])?;
+    let stderr = std::str::from_utf8(&output.stderr)?;
+    assert!(stderr.contains("> git branch"));
+    Ok(())
+}
@@ -0,0 +1,97 @@
+mod fixture;
+
+use std::convert::TryFrom;
+use std::process::Command;
+
+use anyhow::Result;
+use git2::{BranchType, Repository};
+
+use git_trim::{
+    apply_trim_plan, get_trim_plan, verify_deletions, DeleteOptions, DeletionTarget, Git,
+    RemoteBranch,
+};
+
+use fixture::{rc, test_default_param, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        git init origin
+        origin <<EOF
+            git config user.name "Origin Test"
+            git config user.email "origin@test"
+            echo "Hello World!" > README.md
+            git add README.md
+            git commit -m "Initial commit"
+        EOF
+        git clone origin local
+        local <<EOF
+            git config user.name "Local Test"
+            git config user.email "local@test"
+            git config remote.pushdefault origin
+            git config push.default simple
+            git checkout -b merged master
+            touch merged
+            git add merged
+            git commit -m "Add merged"
+            git push -u origin merged
+            git checkout master
+        EOF
+        origin <<EOF
+            git merge merged --no-ff
+            # Recreates the branch right after it is deleted, like a concurrent process would.
+            echo '#!/bin/sh' > .git/hooks/post-receive
+            echo 'git update-ref refs/heads/merged HEAD' >> .git/hooks/post-receive
+            chmod +x .git/hooks/post-receive
+        EOF
+        "#,
+    )
+}
+
+#[test]
+fn test_verify_deletions() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    let report = apply_trim_plan(&git, &plan, &DeleteOptions::default())?;
+    assert!(report.failures().is_empty());
+    assert_eq!(
+        verify_deletions(&git, &report, false, None)?,
+        vec![DeletionTarget::Remote(RemoteBranch {
+            remote: "origin".to_owned(),
+            refname: "refs/heads/merged".to_owned(),
+        })],
+    );
+    Ok(())
+}
+
+#[test]
+fn test_survived_deletion_fails_the_trim() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm", "--delete", "merged:origin"])
+        .current_dir(guard.working_directory())
+        .output()?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.contains("Still exists after the deletion: origin/merged"));
+
+    let repo = Repository::open(guard.working_directory())?;
+    assert!(repo.find_branch("merged", BranchType::Local).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_verify_deletions_offline() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    let report = apply_trim_plan(&git, &plan, &DeleteOptions::default())?;
+    assert!(report.failures().is_empty());
+    // The remote branches are not checked without accessing the remotes.
+    assert_eq!(verify_deletions(&git, &report, true, None)?, vec![]);
+    Ok(())
+}
@@ -0,0 +1,85 @@
+mod fixture;
+
+use std::process::Command;
+
+use anyhow::Result;
+use git2::{BranchType, Repository};
+
+use fixture::{rc, Fixture};
+
+fn fixture() -> Fixture {
+    rc().append_fixture_trace(
+        r#"
+        mkdir remotes notes
+        for NAME in local other; do
+            git init remotes/$NAME
+            within remotes/$NAME <<EOF
+                git config user.name "Origin Test"
+                git config user.email "origin@test"
+                echo "Hello World!" > README.md
+                git add README.md
+                git commit -m "Initial commit"
+        EOF
+            git clone remotes/$NAME $NAME
+            within $NAME <<EOF
+                git config user.name "Local Test"
+                git config user.email "local@test"
+                git checkout -b merged-$NAME master
+                touch $NAME
+                git add $NAME
+                git commit -m "Add $NAME"
+                git push -u origin merged-$NAME
+                git checkout master
+        EOF
+            within remotes/$NAME <<EOF
+                git merge merged-$NAME --no-ff
+        EOF
+        done
+        # No remotes to trim with.
+        git init lonely
+        "#,
+    )
+}
+
+#[test]
+fn test_workspace() -> Result<()> {
+    let guard = fixture().prepare("local", r#""#)?;
+    let workspace = guard.working_directory().join("..");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_git-trim"))
+        .args(&["--no-update", "--no-confirm", "--delete", "merged:origin"])
+        .args(&["--workspace-depth", "1", "--workspace"])
+        .arg(&workspace)
+        .current_dir(&workspace)
+        .output()?;
+    // `lonely` fails, but the others are trimmed.
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    let headers: Vec<_> = stdout
+        .lines()
+        .filter(|line| line.starts_with("Repository "))
+        .collect();
+    assert_eq!(
+        headers,
+        vec![
+            "Repository `local`:",
+            "Repository `lonely`:",
+            "Repository `other`:",
+        ],
+    );
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(
+        stderr.contains("Failed to trim repository `lonely`"),
+        "{}",
+        stderr
+    );
+
+    for name in &["local", "other"] {
+        let repo = Repository::open(workspace.join(name))?;
+        let branch = format!("merged-{}", name);
+        assert!(repo.find_branch(&branch, BranchType::Local).is_err());
+        let origin = Repository::open(workspace.join("remotes").join(name))?;
+        assert!(origin.find_branch(&branch, BranchType::Local).is_err());
+    }
+    Ok(())
+}
@@ -3,9 +3,9 @@ mod fixture;
 use std::convert::TryFrom;
 
 use anyhow::Result;
-use git2::Repository;
+use git2::{BranchType, Repository};
 
-use git_trim::{get_trim_plan, ClassifiedBranch, Git, LocalBranch};
+use git_trim::{apply_trim_plan, get_trim_plan, ClassifiedBranch, DeleteOptions, Git, LocalBranch};
 
 use fixture::{rc, test_default_param, Fixture};
 
@@ -57,3 +57,135 @@ fn test_bases_implicit_value() -> Result<()> {
     }));
     Ok(())
 }
+
+#[test]
+fn test_removed_but_unpruned_worktree() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        rm -rf worktree
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+
+    assert!(plan
+        .to_delete
+        .contains(&ClassifiedBranch::MergedLocal(LocalBranch::new(
+            "refs/heads/worktree"
+        ))));
+    assert!(plan
+        .get_preserved_local(&LocalBranch::new("refs/heads/worktree"))
+        .is_none());
+    Ok(())
+}
+
+#[test]
+fn test_delete_removed_but_unpruned_worktree() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        rm -rf worktree
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    apply_trim_plan(&git, &plan, &DeleteOptions::default())?;
+
+    assert!(git.repo.find_branch("worktree", BranchType::Local).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_keep_unrelated_removed_worktree() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        local <<EOF
+            git worktree add -b other ../other
+        EOF
+        rm -rf worktree other
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+    apply_trim_plan(&git, &plan, &DeleteOptions::default())?;
+
+    assert!(git.repo.find_branch("worktree", BranchType::Local).is_err());
+    // Only the worktree of the deleted branch is pruned.
+    assert!(!git.repo.path().join("worktrees").join("worktree").exists());
+    assert!(git.repo.path().join("worktrees").join("other").exists());
+    Ok(())
+}
+
+#[test]
+fn test_removed_but_locked_worktree() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        local <<EOF
+            git worktree lock ../worktree
+        EOF
+        rm -rf worktree
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+
+    assert!(plan.preserved.iter().any(|w| {
+        w.branch == ClassifiedBranch::MergedLocal(LocalBranch::new("refs/heads/worktree"))
+            && w.reason.contains("worktree")
+    }));
+    Ok(())
+}
+
+#[test]
+fn test_staged_changes_in_worktree() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        within worktree <<EOF
+            echo "Staged" >> README.md
+            git add README.md
+        EOF
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+
+    let preserved = plan
+        .get_preserved_local(&LocalBranch::new("refs/heads/worktree"))
+        .expect("preserved");
+    assert_eq!(preserved.reason, "staged changes present");
+    assert!(!plan
+        .to_delete
+        .contains(&ClassifiedBranch::MergedLocal(LocalBranch::new(
+            "refs/heads/worktree"
+        ))));
+    Ok(())
+}
+
+#[test]
+fn test_unreadable_index_in_worktree() -> Result<()> {
+    let guard = fixture().prepare(
+        "local",
+        r#"
+        echo "not an index" > local/.git/worktrees/worktree/index
+        "#,
+    )?;
+
+    let git = Git::try_from(Repository::open(guard.working_directory())?)?;
+    let plan = get_trim_plan(&git, &test_default_param())?;
+
+    // It is still kept back for the worktree.
+    let preserved = plan
+        .get_preserved_local(&LocalBranch::new("refs/heads/worktree"))
+        .expect("preserved");
+    assert!(preserved.reason.starts_with("worktree at "));
+    Ok(())
+}